@@ -0,0 +1,243 @@
+// Bilingual documents - map French labels and values onto the canonical English fields
+
+use crate::documents::field;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Appended to the prompt when OCR finds English/French label pairs
+pub const PROMPT_HINT: &str = " This document is bilingual (English/French). Always use the English field names above and give values in English where the document prints both.";
+
+// French labels (accents stripped, spaces as underscores) and the field they mean
+const FRENCH_LABELS: &[(&str, &str)] = &[
+    ("niv", "vin"),
+    ("numero_d_identification", "vin"),
+    ("numero_de_serie", "vin"),
+    ("annee", "year"),
+    ("annee_modele", "year"),
+    ("marque", "make"),
+    ("modele", "model"),
+    ("couleur", "color"),
+    ("kilometrage", "mileage"),
+    ("odometre", "mileage"),
+    ("proprietaire", "owner_name"),
+    ("nom_du_proprietaire", "owner_name"),
+    ("plaque", "plate_number"),
+    ("numero_de_plaque", "plate_number"),
+    ("immatriculation", "plate_number"),
+    ("prix", "price"),
+];
+
+// English label and its French counterparts, as printed side by side on the form
+const LABEL_PAIRS: &[(&str, &[&str])] = &[
+    ("make", &["marque"]),
+    ("model", &["modèle", "modele"]),
+    ("year", &["année", "annee"]),
+    ("colo", &["couleur"]),
+    ("owner", &["propriétaire", "proprietaire"]),
+    ("plate", &["plaque"]),
+    ("vin", &["niv"]),
+];
+
+const FRENCH_COLORS: &[(&str, &str)] = &[
+    ("rouge", "Red"),
+    ("bleu", "Blue"),
+    ("noir", "Black"),
+    ("blanc", "White"),
+    ("gris", "Gray"),
+    ("vert", "Green"),
+    ("jaune", "Yellow"),
+    ("argent", "Silver"),
+    ("brun", "Brown"),
+    ("marron", "Brown"),
+    ("or", "Gold"),
+    ("mauve", "Purple"),
+    ("violet", "Purple"),
+];
+
+/// OCR text with several English/French label pairs, as on Canadian registrations
+pub fn is_bilingual(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let has_word = |word: &str| {
+        lower
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|w| w.starts_with(word))
+    };
+    LABEL_PAIRS
+        .iter()
+        .filter(|(english, french)| has_word(english) && french.iter().any(|f| has_word(f)))
+        .count()
+        >= 3
+}
+
+/// Rewrite a model response onto English keys, returning the French originals by field.
+/// English values win when the model gave both.
+pub fn canonicalize(value: &Value) -> (Value, BTreeMap<String, String>) {
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return (value.clone(), BTreeMap::new()),
+    };
+
+    let mut canonical = Value::Object(Map::new());
+    let mut french = Vec::new();
+    for (key, v) in object {
+        let normalized = normalize_label(key);
+        // "Make/Marque" style keys: take whichever half we recognize
+        let halves: Vec<&str> = normalized.split('/').map(|h| h.trim_matches('_')).collect();
+        if let Some(target) = halves.iter().find_map(|h| french_label(h)) {
+            french.push((target, key));
+        } else if let Some(english) = halves.iter().find(|h| !h.is_empty()) {
+            canonical[*english] = v.clone();
+        }
+    }
+
+    let mut originals = BTreeMap::new();
+    for (target, key) in french {
+        if let Some(text) = field(value, key) {
+            if field(&canonical, target).is_none() {
+                canonical[target] = Value::String(text.clone());
+            }
+            originals.insert(target.to_string(), text);
+        }
+    }
+
+    // A French color under the English key still reads differently from the English form
+    if let Some(color) = field(&canonical, "color") {
+        if let Some(english) = english_color(&color) {
+            originals.entry("color".to_string()).or_insert(color);
+            canonical["color"] = Value::String(english);
+        }
+    }
+
+    (canonical, originals)
+}
+
+fn french_label(normalized: &str) -> Option<&'static str> {
+    FRENCH_LABELS
+        .iter()
+        .find(|(label, _)| *label == normalized)
+        .map(|(_, target)| *target)
+}
+
+/// "Numéro de série" -> "numero_de_serie"
+fn normalize_label(label: &str) -> String {
+    label
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            ' ' | '-' | '\'' | '’' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// "Rouge" -> "Red"; also "Rouge / Red" style bilingual values
+fn english_color(color: &str) -> Option<String> {
+    let parts: Vec<&str> = color.split('/').map(|p| p.trim()).collect();
+    let translate = |part: &str| {
+        FRENCH_COLORS
+            .iter()
+            .find(|(french, _)| normalize_label(part) == *french)
+            .map(|(_, english)| english.to_string())
+    };
+    if parts.len() > 1 {
+        // Keep the half that isn't French
+        if let Some(english) = parts
+            .iter()
+            .find(|p| translate(p).is_none() && !p.is_empty())
+        {
+            return Some(english.to_string());
+        }
+    }
+    parts.first().and_then(|p| translate(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::documents::{extracted_from_value, ExtractedData};
+    use serde_json::json;
+
+    const REGISTRATION_OCR: &str = "VEHICLE PERMIT / CERTIFICAT D'IMMATRICULATION
+Make/Marque: FORD  Model/Modèle: F150
+Year/Année: 2015  Colour/Couleur: RED/ROUGE
+VIN/NIV: 1FTFW1EF5FFA12345
+Owner/Propriétaire: Jean Tremblay";
+
+    fn captured(response: Value) -> (ExtractedData, BTreeMap<String, String>) {
+        let (canonical, originals) = canonicalize(&response);
+        (extracted_from_value(&canonical), originals)
+    }
+
+    #[test]
+    fn recognizes_bilingual_registrations() {
+        assert!(is_bilingual(REGISTRATION_OCR));
+        assert!(!is_bilingual("Make: Ford Model: F150 Year: 2015"));
+        // One stray French word isn't a bilingual layout
+        assert!(!is_bilingual("Make: Ford Model: F150 Couleur: Rouge"));
+    }
+
+    #[test]
+    fn either_language_captures_the_same_fields() {
+        let (english, english_originals) = captured(json!({
+            "vin": "1FTFW1EF5FFA12345",
+            "year": "2015",
+            "make": "Ford",
+            "model": "F150",
+            "color": "Red",
+            "owner_name": "Jean Tremblay",
+        }));
+        let (french, french_originals) = captured(json!({
+            "NIV": "1FTFW1EF5FFA12345",
+            "Année": "2015",
+            "Marque": "Ford",
+            "Modèle": "F150",
+            "Couleur": "Rouge",
+            "Propriétaire": "Jean Tremblay",
+        }));
+        let (mixed, mixed_originals) = captured(json!({
+            "vin": "1FTFW1EF5FFA12345",
+            "year": 2015,
+            "Make/Marque": "Ford",
+            "model": "F150",
+            "color": "Rouge / Red",
+            "owner_name": "Jean Tremblay",
+        }));
+
+        for data in [&french, &mixed] {
+            assert_eq!(data.vin, english.vin);
+            assert_eq!(data.year, english.year);
+            assert_eq!(data.make, english.make);
+            assert_eq!(data.model, english.model);
+            assert_eq!(data.color, english.color);
+            assert_eq!(data.owner_name, english.owner_name);
+        }
+        assert_eq!(english.color.as_deref(), Some("Red"));
+
+        // The French originals are kept alongside
+        assert!(english_originals.is_empty());
+        assert_eq!(french_originals["color"], "Rouge");
+        assert_eq!(french_originals["vin"], "1FTFW1EF5FFA12345");
+        assert_eq!(mixed_originals["make"], "Ford");
+        assert_eq!(mixed_originals["color"], "Rouge / Red");
+    }
+
+    #[test]
+    fn english_values_win_when_both_are_given() {
+        let (data, originals) =
+            captured(json!({ "color": "Blue", "couleur": "Rouge", "marque": "Ford" }));
+        assert_eq!(data.color.as_deref(), Some("Blue"));
+        assert_eq!(data.make.as_deref(), Some("Ford"));
+        assert_eq!(originals["color"], "Rouge");
+        assert_eq!(
+            canonicalize(&json!("not an object")).0,
+            json!("not an object")
+        );
+    }
+}
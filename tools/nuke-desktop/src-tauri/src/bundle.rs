@@ -0,0 +1,251 @@
+// Results bundles - validate, migrate and import previously exported extraction results
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::floors::ConfidenceFloors;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Bump when ExtractionResult changes shape in a way old bundles need migrating for
+pub const BUNDLE_SCHEMA_VERSION: u64 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleIssue {
+    pub index: usize,
+    /// The record's path, when it had one
+    pub path: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleReport {
+    /// Schema version the file was written with
+    pub schema_version: u64,
+    pub migrated: bool,
+    pub total: usize,
+    pub valid: usize,
+    pub malformed: Vec<BundleIssue>,
+    pub missing_files: Vec<BundleIssue>,
+    /// Valid records whose confidence is below their document type's floor; they won't sync
+    #[serde(default)]
+    pub below_floor: Vec<BundleIssue>,
+    /// The valid records, when the caller asked to import them
+    pub results: Option<Vec<ExtractionResult>>,
+}
+
+/// Check a results bundle before import, optionally returning only its valid records
+#[tauri::command]
+pub async fn validate_bundle(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    import_valid: Option<bool>,
+) -> Result<BundleReport, NukeError> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| NukeError::FileRead(format!("Failed to read bundle: {}", e)))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| NukeError::JsonParse(format!("Bundle is not valid JSON: {}", e)))?;
+
+    let floors = state.confidence_floors.lock().unwrap().clone();
+    let mut report = check_bundle(value, &floors).map_err(NukeError::JsonParse)?;
+    if !import_valid.unwrap_or(false) {
+        report.results = None;
+    }
+    Ok(report)
+}
+
+/// Migrate a parsed bundle to the current schema and validate every record
+pub fn check_bundle(
+    value: serde_json::Value,
+    floors: &ConfidenceFloors,
+) -> Result<BundleReport, String> {
+    let (schema_version, records) = migrate(value)?;
+
+    let total = records.len();
+    let mut malformed = Vec::new();
+    let mut missing_files = Vec::new();
+    let mut below_floor = Vec::new();
+    let mut results = Vec::new();
+
+    for (index, record) in records.into_iter().enumerate() {
+        let path = record
+            .get("path")
+            .and_then(|p| p.as_str())
+            .map(|p| p.to_string());
+        match serde_json::from_value::<ExtractionResult>(record) {
+            Ok(result) if !Path::new(&result.path).exists() => missing_files.push(BundleIssue {
+                index,
+                path,
+                error: "File no longer exists".to_string(),
+            }),
+            Ok(result) => {
+                if let Some(below) = floors.check(&result) {
+                    below_floor.push(BundleIssue {
+                        index,
+                        path,
+                        error: below.message(),
+                    });
+                }
+                results.push(result);
+            }
+            Err(e) => malformed.push(BundleIssue {
+                index,
+                path,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(BundleReport {
+        schema_version,
+        migrated: schema_version < BUNDLE_SCHEMA_VERSION,
+        total,
+        valid: results.len(),
+        malformed,
+        missing_files,
+        below_floor,
+        results: Some(results),
+    })
+}
+
+/// Bring any known bundle layout up to the current schema, returning its original version
+fn migrate(value: serde_json::Value) -> Result<(u64, Vec<serde_json::Value>), String> {
+    // Version 1 bundles were a bare array of results, before routing existed
+    if let serde_json::Value::Array(records) = value {
+        let records = records.into_iter().map(migrate_v1_record).collect();
+        return Ok((1, records));
+    }
+
+    let version = value
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .ok_or("Bundle has no schema_version")?;
+    if version > BUNDLE_SCHEMA_VERSION {
+        return Err(format!(
+            "Bundle schema version {} is newer than this app supports ({})",
+            version, BUNDLE_SCHEMA_VERSION
+        ));
+    }
+
+    let records = match value.get("results") {
+        Some(serde_json::Value::Array(records)) => records.clone(),
+        _ => return Err("Bundle has no results array".to_string()),
+    };
+    if version == 1 {
+        return Ok((1, records.into_iter().map(migrate_v1_record).collect()));
+    }
+    Ok((version, records))
+}
+
+/// Version 1 records predate the route and image kind; everything went through vision
+fn migrate_v1_record(mut record: serde_json::Value) -> serde_json::Value {
+    if let Some(object) = record.as_object_mut() {
+        object
+            .entry("route")
+            .or_insert_with(|| serde_json::json!("vision"));
+        object
+            .entry("image_kind")
+            .or_insert_with(|| serde_json::json!("document"));
+        object
+            .entry("raw_response")
+            .or_insert_with(|| serde_json::json!(""));
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// An existing file for records to point at
+    fn temp_file(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("nuke-{}-{}.jpg", name, std::process::id()));
+        std::fs::write(&path, b"photo").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn record(path: &str, confidence: f32) -> serde_json::Value {
+        json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "photo",
+            "data": { "vin": "1HGCM82633A004352" },
+            "confidence": confidence,
+            "raw_response": "",
+        })
+    }
+
+    #[test]
+    fn valid_bundle_reports_missing_files_and_low_confidence() {
+        let path = temp_file("bundle-valid");
+        let bundle = json!({
+            "schema_version": BUNDLE_SCHEMA_VERSION,
+            "results": [record(&path, 0.9), record("/nonexistent/x.jpg", 0.9), record(&path, 0.1)],
+        });
+        let report = check_bundle(bundle, &ConfidenceFloors::default()).unwrap();
+
+        assert_eq!(report.schema_version, BUNDLE_SCHEMA_VERSION);
+        assert!(!report.migrated);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.valid, 2);
+        assert!(report.malformed.is_empty());
+        assert_eq!(report.missing_files.len(), 1);
+        assert_eq!(report.missing_files[0].index, 1);
+        assert_eq!(
+            report.missing_files[0].path.as_deref(),
+            Some("/nonexistent/x.jpg")
+        );
+        // Below the photo floor, but still a valid record
+        assert_eq!(report.below_floor.len(), 1);
+        assert_eq!(report.below_floor[0].index, 2);
+        assert_eq!(report.results.unwrap().len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn older_schema_bundles_are_migrated() {
+        let path = temp_file("bundle-v1");
+        // Version 1: a bare array, with no route, image kind or raw response
+        let bare =
+            json!([{ "path": path, "model": "llava", "data": { "vin": "X" }, "confidence": 0.6 }]);
+        let report = check_bundle(bare, &ConfidenceFloors::default()).unwrap();
+        assert!(report.migrated);
+        assert_eq!(report.schema_version, 1);
+        assert_eq!(report.valid, 1);
+        let result = &report.results.unwrap()[0];
+        assert_eq!(result.route, crate::documents::ProcessingRoute::Vision);
+        assert_eq!(result.image_kind, crate::imaging::ImageKind::Document);
+
+        let wrapped = json!({
+            "schema_version": 1,
+            "results": [{ "path": path, "model": "llava", "data": {}, "confidence": 0.6 }],
+        });
+        let report = check_bundle(wrapped, &ConfidenceFloors::default()).unwrap();
+        assert!(report.migrated);
+        assert_eq!(report.valid, 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_bundles_report_malformed_records_or_fail() {
+        let path = temp_file("bundle-corrupt");
+        let bundle = json!({
+            "schema_version": 2,
+            "results": [record(&path, 0.9), { "path": path, "confidence": "high" }, 7],
+        });
+        let report = check_bundle(bundle, &ConfidenceFloors::default()).unwrap();
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.malformed.len(), 2);
+        assert_eq!(report.malformed[0].path.as_deref(), Some(path.as_str()));
+        assert_eq!(report.malformed[1].index, 2);
+        assert_eq!(report.malformed[1].path, None);
+
+        let floors = ConfidenceFloors::default();
+        assert!(check_bundle(json!({ "schema_version": 9, "results": [] }), &floors).is_err());
+        assert!(check_bundle(json!({ "results": [] }), &floors).is_err());
+        assert!(check_bundle(json!({ "schema_version": 2 }), &floors).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,337 @@
+// Persistent caches that survive app restarts
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::ocr::OcrOutput;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Files already processed, keyed by content hash + model + prompt version. On disk it's a
+/// log with one entry per line, so recording a result appends instead of rewriting the store.
+pub struct ProcessedStore {
+    path: PathBuf,
+    entries: HashMap<String, ExtractionResult>,
+    // The file holds superseded or unreadable lines; the next insert rewrites it
+    needs_compaction: bool,
+}
+
+impl ProcessedStore {
+    /// Load the store from disk, starting empty if the file is missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let contents = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut entries = HashMap::new();
+        let mut lines = 0;
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            lines += 1;
+            // A crash mid-append leaves a partial last line; it's dropped at compaction
+            if let Ok((key, result)) = serde_json::from_str::<(String, ExtractionResult)>(line) {
+                entries.insert(key, result);
+            }
+        }
+        ProcessedStore {
+            path,
+            needs_compaction: lines > entries.len(),
+            entries,
+        }
+    }
+
+    pub fn key(content_hash: &str, model: &str, prompt_version: &str) -> String {
+        format!("{}:{}:{}", content_hash, model, prompt_version)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ExtractionResult> {
+        self.entries.get(key)
+    }
+
+    /// Every VIN extracted so far, including inventory rows
+    pub fn known_vins(&self) -> Vec<String> {
+        self.entries
+            .values()
+            .flat_map(|r| std::iter::once(&r.data).chain(&r.inventory))
+            .filter_map(|d| d.vin.clone())
+            .collect()
+    }
+
+    /// Record a result, appending it to the file on disk
+    pub fn insert(&mut self, key: String, result: ExtractionResult) -> Result<(), String> {
+        let line = entry_line(&key, &result)?;
+        self.entries.insert(key, result);
+        if self.needs_compaction {
+            return self.compact();
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open processed store: {}", e))?;
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write processed store: {}", e))
+    }
+
+    /// Rewrite the file with one line per current entry
+    fn compact(&mut self) -> Result<(), String> {
+        let mut contents = String::new();
+        for (key, result) in &self.entries {
+            contents.push_str(&entry_line(key, result)?);
+        }
+        // Written aside and renamed, so a crash can't leave a half-written store
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, contents)
+            .map_err(|e| format!("Failed to write processed store: {}", e))?;
+        std::fs::rename(&temp, &self.path)
+            .map_err(|e| format!("Failed to write processed store: {}", e))?;
+        self.needs_compaction = false;
+        Ok(())
+    }
+}
+
+/// A `[key, result]` line of the store's log
+fn entry_line(key: &str, result: &ExtractionResult) -> Result<String, String> {
+    let entry = serde_json::to_string(&(key, result))
+        .map_err(|e| format!("Failed to serialize processed store: {}", e))?;
+    Ok(entry + "\n")
+}
+
+/// Raw Ollama responses keyed by (images, model, prompt, options), one file per entry.
+/// Sits below the processed store: a parser fix still applies to a cached response.
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    model: String,
+    response: String,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ResponseCache { dir }
+    }
+
+    /// Images are hashed as the base64 we send, which maps one-to-one to their bytes;
+    /// `options` is the serialized sampling options
+    pub fn key(model: &str, prompt: &str, options: &str, images: &[String]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for part in [model, prompt, options]
+            .into_iter()
+            .chain(images.iter().map(|i| i.as_str()))
+        {
+            // Length-prefix each part so ("ab", "c") and ("a", "bc") differ
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let json = std::fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str::<CachedResponse>(&json)
+            .ok()
+            .map(|c| c.response)
+    }
+
+    pub fn insert(&self, key: &str, model: &str, response: &str) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create response cache: {}", e))?;
+        let json = serde_json::to_string(&CachedResponse {
+            model: model.to_string(),
+            response: response.to_string(),
+        })
+        .map_err(|e| format!("Failed to serialize cached response: {}", e))?;
+        std::fs::write(self.entry_path(key), json)
+            .map_err(|e| format!("Failed to write cached response: {}", e))
+    }
+
+    /// Remove cached responses, all of them or only one model's; returns how many went
+    pub fn clear(&self, model: Option<&str>) -> Result<usize, String> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(0),
+        };
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(model) = model {
+                let cached = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<CachedResponse>(&json).ok());
+                if cached.map(|c| c.model != model).unwrap_or(false) {
+                    continue;
+                }
+            }
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove cached response: {}", e))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// OCR output keyed by file content hash, one file per entry
+#[derive(Clone)]
+pub struct OcrCache {
+    dir: PathBuf,
+}
+
+impl OcrCache {
+    pub fn new(dir: PathBuf) -> Self {
+        OcrCache { dir }
+    }
+
+    pub fn get(&self, content_hash: &str) -> Option<OcrOutput> {
+        let json = std::fs::read_to_string(self.entry_path(content_hash)).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn insert(&self, content_hash: &str, output: &OcrOutput) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create OCR cache: {}", e))?;
+        let json = serde_json::to_string(output)
+            .map_err(|e| format!("Failed to serialize OCR output: {}", e))?;
+        std::fs::write(self.entry_path(content_hash), json)
+            .map_err(|e| format!("Failed to write OCR cache: {}", e))
+    }
+
+    fn entry_path(&self, content_hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", content_hash))
+    }
+}
+
+/// Drop cached Ollama responses so the next run asks the model again
+#[tauri::command]
+pub async fn clear_response_cache(
+    state: tauri::State<'_, AppState>,
+    model: Option<String>,
+) -> Result<usize, NukeError> {
+    state
+        .responses
+        .clear(model.as_deref())
+        .map_err(NukeError::FileWrite)
+}
+
+// Bytes `quick_hash` reads from the start of a file
+const QUICK_HASH_PREFIX: u64 = 64 * 1024;
+
+/// BLAKE3 of a file's first 64KB plus its size; cheap enough to run on every scanned file
+pub fn quick_hash(path: &Path, size: u64) -> Result<String, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut prefix = Vec::new();
+    file.take(QUICK_HASH_PREFIX)
+        .read_to_end(&mut prefix)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&size.to_le_bytes());
+    hasher.update(&prefix);
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hash a file's full contents with BLAKE3
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, vin: &str) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": { "vin": vin },
+            "confidence": 0.9,
+            "raw_response": "{}",
+        }))
+        .unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn processed_store_hits_after_a_reload_and_misses_other_keys() {
+        let path = temp_path("processed-reload");
+        let key = ProcessedStore::key("abc", "llava", "17");
+        let mut store = ProcessedStore::load(path.clone());
+        assert!(store.get(&key).is_none());
+        store
+            .insert(key.clone(), result("/a.jpg", "1HGCM82633A004352"))
+            .unwrap();
+
+        let store = ProcessedStore::load(path.clone());
+        assert_eq!(store.get(&key).unwrap().path, "/a.jpg");
+        assert!(store
+            .get(&ProcessedStore::key("abc", "llava", "18"))
+            .is_none());
+        assert!(store
+            .get(&ProcessedStore::key("abd", "llava", "17"))
+            .is_none());
+        assert_eq!(store.known_vins(), vec!["1HGCM82633A004352"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn inserts_append_one_line_each() {
+        let path = temp_path("processed-append");
+        let mut store = ProcessedStore::load(path.clone());
+        for i in 0..3 {
+            store
+                .insert(format!("k{}", i), result("/a.jpg", "V"))
+                .unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert!(contents.lines().next().unwrap().starts_with("[\"k0\","));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn superseded_and_partial_lines_are_compacted_on_the_next_insert() {
+        let path = temp_path("processed-compact");
+        let mut store = ProcessedStore::load(path.clone());
+        store
+            .insert("k".to_string(), result("/old.jpg", "V"))
+            .unwrap();
+        store
+            .insert("k".to_string(), result("/new.jpg", "V"))
+            .unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"[\"half").unwrap();
+
+        let mut store = ProcessedStore::load(path.clone());
+        assert_eq!(store.get("k").unwrap().path, "/new.jpg");
+        store
+            .insert("k2".to_string(), result("/b.jpg", "V"))
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+// Condition assessment - a conservative, model-estimated read of a vehicle's condition
+
+use crate::documents::field;
+use crate::error::NukeError;
+use crate::ollama::{self, Generation, OllamaOptions};
+use crate::{modeljson, AppState};
+use serde::{Deserialize, Serialize};
+
+const CONDITION_PROMPT: &str = "Assess the condition of the vehicle in this photo. Only report what is clearly visible; if unsure, pick the lower rating. Return ONLY JSON with fields: rating (one of project, driver, good, excellent), notes (one or two sentences), damage_flags (list of visible issues such as rust, dents, scratches, missing_parts, cracked_glass, faded_paint, interior_wear). Use an empty list if nothing is clearly wrong.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConditionRating {
+    Project,
+    Driver,
+    Good,
+    Excellent,
+}
+
+/// Condition as estimated by a vision model from photos; never an inspection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Condition {
+    /// None when the model's answer wasn't one of the four ratings
+    pub rating: Option<ConditionRating>,
+    pub notes: Option<String>,
+    pub damage_flags: Vec<String>,
+    /// Model that made the estimate, so nobody mistakes it for a human assessment
+    pub estimated_by: String,
+}
+
+/// Ask a vision model for a condition assessment of a vehicle photo
+#[tauri::command]
+pub async fn assess_condition(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    model: String,
+) -> Result<Condition, NukeError> {
+    let image_data = std::fs::read(&path)
+        .map_err(|e| NukeError::FileRead(format!("Failed to read image: {}", e)))?;
+    let client = state.ollama_client();
+    let generation = Generation {
+        model: &model,
+        prompt: CONDITION_PROMPT,
+        images: vec![ollama::encode_image_b64(&image_data)],
+        options: &OllamaOptions::default(),
+    };
+    let response = ollama::generate_cached(&client, &state.responses, generation, None).await?;
+
+    parse_condition(&response, &model)
+        .ok_or_else(|| NukeError::JsonParse("Model returned no condition assessment".to_string()))
+}
+
+/// Parse a condition-assessment response into a Condition
+pub fn parse_condition(raw: &str, model: &str) -> Option<Condition> {
+    let value = modeljson::extract_json(raw)?;
+
+    let rating = field(&value, "rating").and_then(|r| match r.to_lowercase().as_str() {
+        "project" => Some(ConditionRating::Project),
+        "driver" => Some(ConditionRating::Driver),
+        "good" => Some(ConditionRating::Good),
+        "excellent" => Some(ConditionRating::Excellent),
+        _ => None,
+    });
+
+    let mut damage_flags: Vec<String> = Vec::new();
+    if let Some(flags) = value.get("damage_flags").and_then(|f| f.as_array()) {
+        for flag in flags.iter().filter_map(|f| f.as_str()) {
+            let flag = flag.trim().to_lowercase().replace([' ', '-'], "_");
+            if !flag.is_empty() && !damage_flags.contains(&flag) {
+                damage_flags.push(flag);
+            }
+        }
+    }
+
+    Some(Condition {
+        rating,
+        notes: field(&value, "notes"),
+        damage_flags,
+        estimated_by: model.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_condition_response_wrapped_in_prose() {
+        let raw = r#"Here you go: {"rating": "Driver", "notes": "Surface rust on rockers.", "damage_flags": ["Rust", "missing parts", "rust", "cracked-glass", " "]}"#;
+        let condition = parse_condition(raw, "llava").unwrap();
+        assert_eq!(condition.rating, Some(ConditionRating::Driver));
+        assert_eq!(condition.notes.as_deref(), Some("Surface rust on rockers."));
+        // Normalized to snake_case, de-duplicated, blanks dropped
+        assert_eq!(
+            condition.damage_flags,
+            vec!["rust", "missing_parts", "cracked_glass"]
+        );
+        assert_eq!(condition.estimated_by, "llava");
+    }
+
+    #[test]
+    fn unknown_ratings_and_missing_fields_stay_empty() {
+        let condition = parse_condition(r#"{"rating": "mint", "damage_flags": []}"#, "m").unwrap();
+        assert_eq!(condition.rating, None);
+        assert_eq!(condition.notes, None);
+        assert!(condition.damage_flags.is_empty());
+
+        assert!(parse_condition("The car looks fine.", "m").is_none());
+    }
+
+    #[test]
+    fn serializes_ratings_in_lowercase() {
+        let condition = parse_condition(r#"{"rating": "EXCELLENT"}"#, "m").unwrap();
+        let value = serde_json::to_value(&condition).unwrap();
+        assert_eq!(value["rating"], "excellent");
+        assert!(ConditionRating::Project < ConditionRating::Excellent);
+    }
+}
@@ -0,0 +1,188 @@
+// Consistency scoring - agreement across a vehicle's documents as a stand-in for accuracy
+
+use crate::documents::{ExtractedData, ExtractionResult};
+use crate::error::NukeError;
+use crate::{standard, vin};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Below this a vehicle goes to manual review
+const REVIEW_THRESHOLD: f32 = 0.8;
+
+type FieldValue = fn(&ExtractedData) -> Option<String>;
+
+// Fields compared across documents, normalized so formatting differences don't count
+const CHECKED_FIELDS: &[(&str, FieldValue)] = &[
+    ("vin", |d| {
+        let raw = d.vin.as_deref()?;
+        Some(
+            vin::normalize_vin(raw)
+                .map(|v| v.match_key())
+                .unwrap_or_else(|| raw.trim().to_uppercase()),
+        )
+    }),
+    ("year", |d| {
+        let digits: String = d
+            .year
+            .as_deref()?
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect();
+        (!digits.is_empty()).then_some(digits)
+    }),
+    ("make", |d| {
+        Some(standard::vpic_make(d.make.as_deref()?).to_lowercase())
+    }),
+    ("model", |d| {
+        let model = d
+            .model
+            .as_deref()?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!model.is_empty()).then(|| model.to_lowercase())
+    }),
+];
+
+/// A field whose value differs between documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Disagreement {
+    pub field: String,
+    /// Normalized value -> paths that reported it
+    pub values: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyScore {
+    /// Share of reported values agreeing with the majority, averaged over fields (0-1)
+    pub score: f32,
+    pub needs_review: bool,
+    pub disagreements: Vec<Disagreement>,
+}
+
+/// Score how well a clustered vehicle's documents and photos agree on VIN, year, make and model
+#[tauri::command]
+pub async fn consistency_score(
+    results_for_vehicle: Vec<ExtractionResult>,
+) -> Result<ConsistencyScore, NukeError> {
+    Ok(score_consistency(&results_for_vehicle))
+}
+
+pub fn score_consistency(results: &[ExtractionResult]) -> ConsistencyScore {
+    let mut agreements = Vec::new();
+    let mut disagreements = Vec::new();
+
+    for (field, value) in CHECKED_FIELDS {
+        let mut values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for result in results {
+            if let Some(v) = value(&result.data) {
+                values.entry(v).or_default().push(result.path.clone());
+            }
+        }
+
+        let reported: usize = values.values().map(|paths| paths.len()).sum();
+        if reported == 0 {
+            continue;
+        }
+        let majority = values.values().map(|paths| paths.len()).max().unwrap_or(0);
+        agreements.push(majority as f32 / reported as f32);
+
+        if values.len() > 1 {
+            disagreements.push(Disagreement {
+                field: field.to_string(),
+                values,
+            });
+        }
+    }
+
+    // Nothing to compare is no evidence either way
+    let score = if agreements.is_empty() {
+        1.0
+    } else {
+        agreements.iter().sum::<f32>() / agreements.len() as f32
+    };
+
+    ConsistencyScore {
+        score,
+        needs_review: score < REVIEW_THRESHOLD,
+        disagreements,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(path: &str, data: serde_json::Value) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": data,
+            "confidence": 0.8,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn consistent_cluster_scores_high_despite_formatting() {
+        let score = score_consistency(&[
+            result(
+                "/title.jpg",
+                json!({ "vin": "1HGCM82633A004352", "year": "2003", "make": "Honda", "model": "Accord" }),
+            ),
+            result(
+                "/registration.jpg",
+                json!({ "vin": "1hgcm 82633a004352", "year": "MY 2003", "make": "HONDA", "model": "accord " }),
+            ),
+            // A photo with no VIN or model doesn't count against the rest
+            result("/front.jpg", json!({ "make": "Honda" })),
+        ]);
+        assert_eq!(score.score, 1.0);
+        assert!(!score.needs_review);
+        assert!(score.disagreements.is_empty());
+    }
+
+    #[test]
+    fn conflicting_cluster_scores_low_and_lists_disagreements() {
+        let score = score_consistency(&[
+            result(
+                "/title.jpg",
+                json!({ "vin": "1HGCM82633A004352", "year": "2003", "make": "Honda", "model": "Accord" }),
+            ),
+            result(
+                "/bill.jpg",
+                json!({ "vin": "1FMEU15H5FLA12345", "year": "1985", "make": "Ford", "model": "Bronco II" }),
+            ),
+        ]);
+        assert!(score.score < REVIEW_THRESHOLD);
+        assert!(score.needs_review);
+        let fields: Vec<&str> = score
+            .disagreements
+            .iter()
+            .map(|d| d.field.as_str())
+            .collect();
+        assert_eq!(fields, vec!["vin", "year", "make", "model"]);
+        assert_eq!(score.disagreements[1].values["1985"], vec!["/bill.jpg"]);
+        assert_eq!(score.disagreements[1].values["2003"], vec!["/title.jpg"]);
+    }
+
+    #[test]
+    fn one_dissenting_document_lowers_the_score_proportionally() {
+        let accord = json!({ "vin": "1HGCM82633A004352", "year": "2003", "make": "Honda", "model": "Accord" });
+        let score = score_consistency(&[
+            result("/a.jpg", accord.clone()),
+            result("/b.jpg", accord.clone()),
+            result("/c.jpg", accord),
+            result("/d.jpg", json!({ "year": "2008" })),
+        ]);
+        // Year agrees 3 of 4; the other fields fully agree
+        assert!((score.score - (3.0 + 0.75) / 4.0).abs() < 1e-6);
+        assert!(!score.needs_review);
+        assert_eq!(score.disagreements.len(), 1);
+        assert_eq!(score_consistency(&[]).score, 1.0);
+    }
+}
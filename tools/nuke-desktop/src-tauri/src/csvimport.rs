@@ -0,0 +1,583 @@
+// Streaming CSV import - parse and sync large inventory files one batch at a time
+
+use crate::documents::ExtractedData;
+use crate::error::NukeError;
+use crate::events::{self, AppEvent};
+use crate::progress::{self, Stage};
+use crate::spreadsheet::{self, CsvParseOptions};
+use crate::{vin, AppState, OnDuplicate};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io::Read;
+use std::path::Path;
+use tauri::Manager;
+
+// Enough errors to diagnose a bad file without the report growing with it
+const MAX_REPORTED_ERRORS: usize = 50;
+
+// Vehicle fields and the column headers that mean them, compared after normalize_header
+const COLUMN_ALIASES: &[(&str, &[&str])] = &[
+    (
+        "vin",
+        &[
+            "vin",
+            "vin number",
+            "vin no",
+            "serial",
+            "serial number",
+            "chassis",
+            "chassis number",
+            "chassis no",
+        ],
+    ),
+    ("year", &["year", "model year", "yr", "my"]),
+    ("make", &["make", "manufacturer", "mfr", "brand"]),
+    ("model", &["model", "model name"]),
+    (
+        "color",
+        &[
+            "color",
+            "colour",
+            "exterior color",
+            "exterior colour",
+            "ext color",
+        ],
+    ),
+    (
+        "mileage",
+        &["mileage", "miles", "odometer", "odo", "kilometers", "km"],
+    ),
+    (
+        "price",
+        &[
+            "price",
+            "asking price",
+            "list price",
+            "sale price",
+            "asking",
+        ],
+    ),
+    (
+        "stock_number",
+        &["stock", "stock no", "stock number", "stk", "stk no"],
+    ),
+    ("owner_name", &["owner", "owner name", "registered owner"]),
+    (
+        "plate_number",
+        &[
+            "plate",
+            "plate number",
+            "license plate",
+            "licence plate",
+            "tag",
+        ],
+    ),
+    ("date", &["date", "purchase date", "sale date"]),
+];
+
+/// Vehicles read from a CSV, keyed by the fields their headers matched
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvVehicleMapping {
+    pub vehicles: Vec<ExtractedData>,
+    /// Header and the vehicle field it mapped to, in file order
+    pub mapped_columns: Vec<(String, String)>,
+    /// Headers that didn't map to a vehicle field
+    pub unmapped_columns: Vec<String>,
+    /// Rows with none of VIN, year, make or model
+    pub skipped: usize,
+}
+
+/// Final validation report for a streamed import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvImportReport {
+    pub rows_read: usize,
+    pub synced: usize,
+    pub failed: usize,
+    /// Rows with none of VIN, year, make or model
+    pub skipped: usize,
+    /// Rows synced with a VIN whose check digit doesn't validate
+    pub invalid_vins: usize,
+    /// Headers that didn't map to a vehicle field
+    pub unmapped_columns: Vec<String>,
+    /// First errors only; see MAX_REPORTED_ERRORS
+    pub errors: Vec<String>,
+}
+
+impl CsvImportReport {
+    fn error(&mut self, message: String) {
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(message);
+        }
+    }
+}
+
+/// Import a CSV of vehicles to Nuke cloud without loading the whole file. Its encoding is
+/// detected, and its delimiter too unless `delimiter` is given.
+#[tauri::command]
+pub async fn stream_csv_import(
+    app: tauri::AppHandle,
+    path: String,
+    api_key: String,
+    batch_size: usize,
+    delimiter: Option<String>,
+    options: Option<CsvParseOptions>,
+    on_duplicate: Option<OnDuplicate>,
+) -> Result<CsvImportReport, NukeError> {
+    let state = app.state::<AppState>();
+    let delimiter = spreadsheet::parse_delimiter(delimiter.as_deref())?;
+    let file = std::fs::File::open(&path)
+        .map_err(|e| NukeError::FileRead(format!("Failed to open file: {}", e)))?;
+    let source = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+    let on_duplicate = on_duplicate.unwrap_or_default();
+    let client = state.http.clone();
+
+    state.progress.reset();
+    stream_import(
+        file,
+        &source,
+        delimiter,
+        &options.unwrap_or_default(),
+        batch_size,
+        |batch| {
+            let request = crate::batch_request(&batch, on_duplicate);
+            let sent = client
+                .post(format!("{}/api-v1-batch", crate::CLOUD_API_URL))
+                .header("X-API-Key", &api_key)
+                .json(&request)
+                .send();
+            async move {
+                match sent.await {
+                    Ok(resp) if resp.status().is_success() => Ok(()),
+                    Ok(resp) => Err(format!("Batch failed: {}", resp.status())),
+                    Err(e) => Err(format!("Request error: {}", e)),
+                }
+            }
+        },
+        |stage, count| report_progress(Some(&app), &state, stage, count),
+    )
+    .await
+    .map_err(NukeError::FileRead)
+}
+
+/// Advance the pipeline progress for a streamed import, emitting each batch's outcome
+pub fn report_progress(
+    app: Option<&tauri::AppHandle>,
+    state: &AppState,
+    stage: Stage,
+    count: usize,
+) {
+    progress::advance(app, &state.progress, stage, count);
+    let (synced, failed) = match stage {
+        Stage::Synced => (count, 0),
+        Stage::Failed => (0, count),
+        _ => return,
+    };
+    events::emit(
+        app,
+        &state.events,
+        AppEvent::SyncBatchDone {
+            target: "cloud".to_string(),
+            synced,
+            failed,
+        },
+    );
+}
+
+/// Read a CSV of vehicles, matching its headers against the vehicle fields
+#[tauri::command]
+pub async fn map_csv_to_vehicles(
+    path: String,
+    delimiter: Option<String>,
+) -> Result<CsvVehicleMapping, NukeError> {
+    let delimiter = spreadsheet::parse_delimiter(delimiter.as_deref())?;
+    let (headers, rows) = spreadsheet::read_csv_rows(Path::new(&path), delimiter)?;
+    Ok(map_vehicles(&headers, &rows))
+}
+
+/// Turn header-keyed rows into vehicle records; when two columns map to the same field
+/// the first non-empty one wins
+pub fn map_vehicles(headers: &[String], rows: &[Vec<String>]) -> CsvVehicleMapping {
+    let mut mapping = CsvVehicleMapping::default();
+    let columns: Vec<Option<&str>> = headers
+        .iter()
+        .map(|header| {
+            let field = vehicle_field(header);
+            match field {
+                Some(field) => mapping
+                    .mapped_columns
+                    .push((header.to_string(), field.to_string())),
+                None => mapping.unmapped_columns.push(header.to_string()),
+            }
+            field
+        })
+        .collect();
+
+    for row in rows {
+        let mut vehicle = ExtractedData::default();
+        for (column, value) in columns.iter().zip(row) {
+            if let (Some(field), false) = (column, value.trim().is_empty()) {
+                set_field(&mut vehicle, field, value.trim());
+            }
+        }
+        let identified = [&vehicle.vin, &vehicle.year, &vehicle.make, &vehicle.model]
+            .iter()
+            .any(|f| f.is_some());
+        if identified {
+            mapping.vehicles.push(vehicle);
+        } else {
+            mapping.skipped += 1;
+        }
+    }
+    mapping
+}
+
+fn set_field(vehicle: &mut ExtractedData, field: &str, value: &str) {
+    let slot = match field {
+        "vin" => &mut vehicle.vin,
+        "year" => &mut vehicle.year,
+        "make" => &mut vehicle.make,
+        "model" => &mut vehicle.model,
+        "color" => &mut vehicle.color,
+        "mileage" => &mut vehicle.mileage,
+        "price" => &mut vehicle.price,
+        "stock_number" => &mut vehicle.stock_number,
+        "owner_name" => &mut vehicle.owner_name,
+        "plate_number" => &mut vehicle.plate_number,
+        "date" => &mut vehicle.date,
+        _ => return,
+    };
+    if slot.is_none() {
+        let value = match field {
+            "vin" => value.replace([' ', '-'], "").to_uppercase(),
+            _ => value.to_string(),
+        };
+        *slot = Some(value);
+    }
+}
+
+/// Parse rows into vehicle payloads and hand them to `send` in batches of `batch_size`;
+/// at most one batch is held in memory
+pub async fn stream_import<R, F, Fut>(
+    reader: R,
+    source: &str,
+    delimiter: Option<u8>,
+    options: &CsvParseOptions,
+    batch_size: usize,
+    mut send: F,
+    mut on_progress: impl FnMut(Stage, usize),
+) -> Result<CsvImportReport, String>
+where
+    R: Read,
+    F: FnMut(Vec<serde_json::Value>) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let batch_size = batch_size.max(1);
+    let (decoded, delimiter) = spreadsheet::decode_csv_stream(reader, delimiter)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(decoded);
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read headers: {}", e))?
+        .clone();
+
+    let mut report = CsvImportReport::default();
+    let columns: Vec<Option<&str>> = headers
+        .iter()
+        .map(|header| {
+            let field = vehicle_field(header);
+            if field.is_none() {
+                report.unmapped_columns.push(header.to_string());
+            }
+            field
+        })
+        .collect();
+
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut record = csv::StringRecord::new();
+    loop {
+        match reader.read_record(&mut record) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                report.rows_read += 1;
+                report.skipped += 1;
+                report.error(format!("Row {}: {}", report.rows_read, e));
+                continue;
+            }
+        }
+        report.rows_read += 1;
+        on_progress(Stage::Processed, 1);
+
+        let mut vehicle = serde_json::Map::new();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            if let (Some(field), false) = (column, value.trim().is_empty()) {
+                vehicle.insert(
+                    field.to_string(),
+                    spreadsheet::coerce_cell(value.trim(), options),
+                );
+            }
+        }
+        if !["vin", "year", "make", "model"]
+            .iter()
+            .any(|f| vehicle.contains_key(*f))
+        {
+            report.skipped += 1;
+            continue;
+        }
+        if let Some(vin) = vehicle.get("vin").and_then(|v| v.as_str()) {
+            if !vin::validate_vin(&vin.to_uppercase()) {
+                report.invalid_vins += 1;
+            }
+        }
+        vehicle.insert(
+            "description".to_string(),
+            serde_json::Value::String(format!("Imported from {} row {}", source, report.rows_read)),
+        );
+        batch.push(serde_json::Value::Object(vehicle));
+
+        if batch.len() >= batch_size {
+            let full = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+            send_batch(full, &mut send, &mut report, &mut on_progress).await;
+        }
+    }
+    if !batch.is_empty() {
+        send_batch(batch, &mut send, &mut report, &mut on_progress).await;
+    }
+
+    Ok(report)
+}
+
+async fn send_batch<F, Fut>(
+    batch: Vec<serde_json::Value>,
+    send: &mut F,
+    report: &mut CsvImportReport,
+    on_progress: &mut impl FnMut(Stage, usize),
+) where
+    F: FnMut(Vec<serde_json::Value>) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let count = batch.len();
+    match send(batch).await {
+        Ok(()) => {
+            report.synced += count;
+            on_progress(Stage::Synced, count);
+        }
+        Err(e) => {
+            report.failed += count;
+            report.error(e);
+            on_progress(Stage::Failed, count);
+        }
+    }
+}
+
+/// Vehicle field a column header maps to
+pub fn vehicle_field(header: &str) -> Option<&'static str> {
+    let header = normalize_header(header);
+    COLUMN_ALIASES
+        .iter()
+        .find(|(_, aliases)| aliases.iter().any(|a| normalize_header(a) == header))
+        .map(|(field, _)| *field)
+}
+
+/// Lowercase letters and digits only, so "VIN #", "vin_number" and "Vin Number" agree
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A CSV generated row by row as it's read, so the whole file never exists at once.
+    /// Every 1000th row has no vehicle fields; `generated` counts rows produced so far.
+    struct GeneratedCsv {
+        rows: usize,
+        generated: Rc<Cell<usize>>,
+        line: Vec<u8>,
+        pos: usize,
+    }
+
+    impl GeneratedCsv {
+        fn new(rows: usize) -> (Self, Rc<Cell<usize>>) {
+            let generated = Rc::new(Cell::new(0));
+            let csv = GeneratedCsv {
+                rows,
+                generated: generated.clone(),
+                line: b"Stock #,Year,Make,Model,VIN,Notes\n".to_vec(),
+                pos: 0,
+            };
+            (csv, generated)
+        }
+    }
+
+    impl Read for GeneratedCsv {
+        fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+            if self.pos == self.line.len() {
+                let row = self.generated.get() + 1;
+                if row > self.rows {
+                    return Ok(0);
+                }
+                self.generated.set(row);
+                self.line = if row.is_multiple_of(1000) {
+                    format!("S{},,,,,blank\n", row).into_bytes()
+                } else {
+                    format!("S{},2015,Ford,F150,1FTFW1EF5FFA{:05},x\n", row, row).into_bytes()
+                };
+                self.pos = 0;
+            }
+            let n = out.len().min(self.line.len() - self.pos);
+            out[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[tokio::test]
+    async fn large_csv_streams_in_bounded_batches_with_correct_counts() {
+        let (csv, generated) = GeneratedCsv::new(100_000);
+        let mut batches = 0;
+        let mut largest_batch = 0;
+        let mut read_ahead_at_first_batch = 0;
+        let mut processed = 0;
+        let mut synced = 0;
+
+        let report = stream_import(
+            csv,
+            "inventory.csv",
+            None,
+            &CsvParseOptions::default(),
+            500,
+            |batch| {
+                batches += 1;
+                largest_batch = largest_batch.max(batch.len());
+                if batches == 1 {
+                    read_ahead_at_first_batch = generated.get();
+                    assert_eq!(batch[0]["stock_number"], "S1");
+                    assert_eq!(batch[0]["description"], "Imported from inventory.csv row 1");
+                }
+                let failed = batches == 3;
+                async move {
+                    if failed {
+                        Err("Batch failed: 500 Internal Server Error".to_string())
+                    } else {
+                        Ok(())
+                    }
+                }
+            },
+            |stage, count| match stage {
+                Stage::Processed => processed += count,
+                Stage::Synced => synced += count,
+                _ => {}
+            },
+        )
+        .await
+        .unwrap();
+
+        // Only a buffer's worth of rows had been generated when the first batch went out
+        assert_eq!(largest_batch, 500);
+        assert!(
+            read_ahead_at_first_batch < 1_000,
+            "{}",
+            read_ahead_at_first_batch
+        );
+
+        assert_eq!(report.rows_read, 100_000);
+        assert_eq!(processed, 100_000);
+        assert_eq!(report.skipped, 100);
+        assert_eq!(report.failed, 500);
+        assert_eq!(report.synced, 99_400);
+        assert_eq!(synced, report.synced);
+        assert_eq!(batches, 200);
+        assert_eq!(report.unmapped_columns, vec!["Notes"]);
+        assert_eq!(
+            report.errors,
+            vec!["Batch failed: 500 Internal Server Error"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_partial_last_batch_is_still_sent() {
+        let (csv, _) = GeneratedCsv::new(1_234);
+        let mut sizes = Vec::new();
+        let report = stream_import(
+            csv,
+            "inventory.csv",
+            None,
+            &CsvParseOptions::default(),
+            500,
+            |batch| {
+                sizes.push(batch.len());
+                async { Ok(()) }
+            },
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        assert_eq!(sizes, vec![500, 500, 233]);
+        assert_eq!(report.synced, 1_233);
+        assert_eq!(report.skipped, 1);
+    }
+
+    #[test]
+    fn headers_map_to_vehicle_fields_regardless_of_spelling() {
+        assert_eq!(vehicle_field("VIN #"), Some("vin"));
+        assert_eq!(vehicle_field("Exterior Colour"), Some("color"));
+        assert_eq!(vehicle_field("stk_no"), Some("stock_number"));
+        assert_eq!(vehicle_field("Notes"), None);
+    }
+
+    /// Import `csv` in one batch, returning the report and the payloads sent
+    async fn import_all(
+        csv: &[u8],
+        delimiter: Option<u8>,
+    ) -> (CsvImportReport, Vec<serde_json::Value>) {
+        let mut sent = Vec::new();
+        let report = stream_import(
+            csv,
+            "inventory.csv",
+            delimiter,
+            &CsvParseOptions::default(),
+            500,
+            |batch| {
+                sent.extend(batch);
+                async { Ok(()) }
+            },
+            |_, _| {},
+        )
+        .await
+        .unwrap();
+        (report, sent)
+    }
+
+    #[tokio::test]
+    async fn latin1_semicolon_exports_are_decoded_and_sniffed() {
+        let csv = b"vin;make;model\n1HGCM82633A004352;Citro\xebn;DS\n";
+        let (report, sent) = import_all(csv, None).await;
+        assert_eq!(report.rows_read, 1);
+        assert!(report.unmapped_columns.is_empty());
+        assert_eq!(sent[0]["make"], "Citroën");
+        assert_eq!(sent[0]["vin"], "1HGCM82633A004352");
+    }
+
+    #[tokio::test]
+    async fn a_given_delimiter_overrides_the_sniffed_one() {
+        let csv = b"vin;make;model\n1HGCM82633A004352;Honda;Accord\n";
+        let (report, sent) = import_all(csv, Some(b',')).await;
+        assert_eq!(report.unmapped_columns, ["vin;make;model"]);
+        assert_eq!(report.skipped, 1);
+        assert!(sent.is_empty());
+    }
+}
@@ -0,0 +1,344 @@
+// Date reconciliation - pick one canonical capture date from EXIF, filename and mtime
+
+use crate::error::NukeError;
+use crate::photos;
+use crate::ScanResult;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const ISO_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const ISO_OFFSET_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateSource {
+    Exif,
+    Filename,
+    Mtime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    /// A calendar day with no time of day
+    Date,
+    DateTime,
+}
+
+/// A date normalized to ISO 8601 without moving it to a different day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedDate {
+    /// "2023-05-14", "2023-05-14T10:15:02" (local, zone unknown) or "2023-05-14T10:15:02-05:00"
+    pub iso: String,
+    /// The day as written, in the date's own time zone
+    pub date: NaiveDate,
+    pub precision: DatePrecision,
+    /// Seconds east of UTC, when the source carried a zone
+    pub offset_seconds: Option<i32>,
+}
+
+/// Every date we found for a file, and the one chosen as canonical
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDates {
+    /// Most reliable of the sources: EXIF capture > filename-embedded date > mtime
+    pub captured_at: Option<String>,
+    pub source: Option<DateSource>,
+    /// Whether `captured_at` has a time of day
+    #[serde(default)]
+    pub precision: Option<DatePrecision>,
+    pub exif: Option<String>,
+    pub filename: Option<String>,
+    pub mtime: Option<String>,
+}
+
+/// Fill in `dates` on scan results with a canonical `captured_at` and all date sources
+#[tauri::command]
+pub async fn reconcile_dates(mut results: Vec<ScanResult>) -> Result<Vec<ScanResult>, NukeError> {
+    for result in &mut results {
+        let exif = match result.category.as_str() {
+            "image" => result
+                .captured_at
+                .clone()
+                .or_else(|| photos::read_capture_time(Path::new(&result.path))),
+            _ => None,
+        };
+        result.dates = Some(reconcile(
+            exif,
+            filename_date(&result.filename),
+            mtime_iso(&result.modified),
+        ));
+    }
+    Ok(results)
+}
+
+/// Choose the canonical date from the available sources
+pub fn reconcile(
+    exif: Option<String>,
+    filename: Option<String>,
+    mtime: Option<String>,
+) -> FileDates {
+    let (captured_at, source) = if let Some(date) = &exif {
+        (Some(date.clone()), Some(DateSource::Exif))
+    } else if let Some(date) = &filename {
+        (Some(date.clone()), Some(DateSource::Filename))
+    } else if let Some(date) = &mtime {
+        (Some(date.clone()), Some(DateSource::Mtime))
+    } else {
+        (None, None)
+    };
+
+    let precision = captured_at
+        .as_deref()
+        .and_then(parse_date)
+        .map(|d| d.precision);
+
+    FileDates {
+        captured_at,
+        source,
+        precision,
+        exif,
+        filename,
+        mtime,
+    }
+}
+
+/// Date embedded in a filename: IMG_20230514_101502.jpg, 2023-05-14 title.pdf, PXL_20230514...
+pub fn filename_date(filename: &str) -> Option<String> {
+    let date_regex = Regex::new(
+        r"(?:^|\D)((?:19|20)\d{2})[-_.]?(\d{2})[-_.]?(\d{2})(?:[-_T ]?(\d{2})[-_.:]?(\d{2})[-_.:]?(\d{2}))?(?:\D|$)",
+    )
+    .ok()?;
+
+    for caps in date_regex.captures_iter(filename) {
+        let part = |i: usize| caps.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+        let date = match NaiveDate::from_ymd_opt(part(1)? as i32, part(2)?, part(3)?) {
+            Some(d) => d,
+            None => continue,
+        };
+        // A date alone stays date-only rather than midnight, which a UTC conversion could
+        // move to the day before; a bad time falls back to the date
+        let datetime = match (part(4), part(5), part(6)) {
+            (Some(h), Some(m), Some(s)) => date.and_hms_opt(h, m, s),
+            _ => None,
+        };
+        return Some(match datetime {
+            Some(datetime) => datetime.format(ISO_FORMAT).to_string(),
+            None => date.format(ISO_DATE_FORMAT).to_string(),
+        });
+    }
+    None
+}
+
+/// `ScanResult.modified` (Unix seconds) as ISO 8601 in local time with its offset, so the
+/// day matches the EXIF and filename dates it's compared against
+fn mtime_iso(modified: &str) -> Option<String> {
+    let secs: i64 = modified.parse().ok()?;
+    let datetime = DateTime::from_timestamp(secs, 0)?.with_timezone(&chrono::Local);
+    Some(datetime.format(ISO_OFFSET_FORMAT).to_string())
+}
+
+/// Parse a document, EXIF or filename date, keeping its offset when it has one and noting
+/// whether it's date-only; the calendar day is never shifted by converting zones
+pub fn parse_date(text: &str) -> Option<ParsedDate> {
+    let text = text.trim().trim_end_matches('.');
+    // EXIF writes "2023:05:14 10:15:02"
+    const OFFSET_FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S%:z",
+        "%Y-%m-%d %H:%M:%S %z",
+        "%Y:%m:%d %H:%M:%S%:z",
+    ];
+    const NAIVE_FORMATS: &[&str] = &[
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y:%m:%d %H:%M:%S",
+    ];
+
+    let with_offset = DateTime::parse_from_rfc3339(text).ok().or_else(|| {
+        OFFSET_FORMATS
+            .iter()
+            .find_map(|format| DateTime::parse_from_str(text, format).ok())
+    });
+    if let Some(datetime) = with_offset {
+        return Some(offset_date(datetime));
+    }
+
+    if let Some(datetime) = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        return Some(ParsedDate {
+            iso: datetime.format(ISO_FORMAT).to_string(),
+            date: datetime.date(),
+            precision: DatePrecision::DateTime,
+            offset_seconds: None,
+        });
+    }
+
+    parse_document_date(text).map(|date| ParsedDate {
+        iso: date.format(ISO_DATE_FORMAT).to_string(),
+        date,
+        precision: DatePrecision::Date,
+        offset_seconds: None,
+    })
+}
+
+fn offset_date(datetime: DateTime<FixedOffset>) -> ParsedDate {
+    ParsedDate {
+        iso: datetime.format(ISO_OFFSET_FORMAT).to_string(),
+        date: datetime.date_naive(),
+        precision: DatePrecision::DateTime,
+        offset_seconds: Some(datetime.offset().local_minus_utc()),
+    }
+}
+
+/// Parse a date as printed on a document: 2023-05-14, 05/14/2023, 5-14-23, May 14, 2023
+pub fn parse_document_date(text: &str) -> Option<NaiveDate> {
+    let text = text.trim().trim_end_matches('.');
+    // Two-digit years first: chrono's %Y would read "24" as the year 24
+    const FORMATS: &[&str] = &[
+        "%Y-%m-%d",
+        "%m/%d/%y",
+        "%m-%d-%y",
+        "%m/%d/%Y",
+        "%m-%d-%Y",
+        "%B %d, %Y",
+        "%b %d, %Y",
+        "%d %B %Y",
+        "%d %b %Y",
+    ];
+    FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        // An ISO timestamp: keep the date part
+        .or_else(|| NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exif_wins_when_all_three_dates_differ() {
+        let filename = filename_date("IMG_20230514_101502.jpg");
+        assert_eq!(filename.as_deref(), Some("2023-05-14T10:15:02"));
+        let dates = reconcile(
+            Some("2021-01-02T03:04:05".to_string()),
+            filename,
+            Some("2024-01-01T00:00:00-05:00".to_string()),
+        );
+        assert_eq!(dates.source, Some(DateSource::Exif));
+        assert_eq!(dates.captured_at.as_deref(), Some("2021-01-02T03:04:05"));
+        assert_eq!(dates.precision, Some(DatePrecision::DateTime));
+        // Every source is kept alongside the canonical date
+        assert_eq!(dates.filename.as_deref(), Some("2023-05-14T10:15:02"));
+        assert_eq!(dates.mtime.as_deref(), Some("2024-01-01T00:00:00-05:00"));
+    }
+
+    #[test]
+    fn falls_back_to_filename_then_mtime() {
+        let dates = reconcile(
+            None,
+            Some("2019-07-04".to_string()),
+            Some("2024-01-01T00:00:00Z".to_string()),
+        );
+        assert_eq!(dates.source, Some(DateSource::Filename));
+        assert_eq!(dates.precision, Some(DatePrecision::Date));
+
+        let dates = reconcile(None, None, Some("2024-01-01T00:00:00Z".to_string()));
+        assert_eq!(dates.source, Some(DateSource::Mtime));
+
+        let dates = reconcile(None, None, None);
+        assert_eq!(dates.captured_at, None);
+        assert_eq!(dates.source, None);
+    }
+
+    #[test]
+    fn reads_dates_embedded_in_filenames() {
+        assert_eq!(
+            filename_date("scan 2019-07-04.pdf").as_deref(),
+            Some("2019-07-04")
+        );
+        assert_eq!(
+            filename_date("PXL_20230514_101502123.jpg").as_deref(),
+            Some("2023-05-14")
+        );
+        assert_eq!(filename_date("IMG_0042.jpg"), None);
+        // Not a real day, so it's skipped
+        assert_eq!(filename_date("20231399.jpg"), None);
+    }
+
+    #[test]
+    fn zoned_dates_keep_their_own_day_and_offset() {
+        let date = parse_date("2023-05-14T23:30:00-05:00").unwrap();
+        assert_eq!(date.iso, "2023-05-14T23:30:00-05:00");
+        assert_eq!(date.date.to_string(), "2023-05-14");
+        assert_eq!(date.precision, DatePrecision::DateTime);
+        assert_eq!(date.offset_seconds, Some(-18000));
+
+        // Converting either of these to UTC would move it to a neighbouring day
+        let utc = parse_date("2023-05-14T00:30:00Z").unwrap();
+        assert_eq!(utc.date.to_string(), "2023-05-14");
+        assert_eq!(utc.offset_seconds, Some(0));
+        let tokyo = parse_date("2023-05-14 08:00:00 +0900").unwrap();
+        assert_eq!(tokyo.date.to_string(), "2023-05-14");
+        assert_eq!(tokyo.iso, "2023-05-14T08:00:00+09:00");
+    }
+
+    #[test]
+    fn unzoned_dates_stay_local_and_date_only_values_have_no_time() {
+        let exif = parse_date("2023:05:14 10:15:02").unwrap();
+        assert_eq!(exif.iso, "2023-05-14T10:15:02");
+        assert_eq!(exif.precision, DatePrecision::DateTime);
+        assert_eq!(exif.offset_seconds, None);
+
+        for text in ["05/14/2023", "2023-05-14", "May 14, 2023."] {
+            let printed = parse_date(text).unwrap();
+            assert_eq!(printed.iso, "2023-05-14", "{}", text);
+            assert_eq!(printed.precision, DatePrecision::Date, "{}", text);
+            assert_eq!(printed.offset_seconds, None, "{}", text);
+        }
+        assert!(parse_date("sometime").is_none());
+    }
+
+    #[test]
+    fn parses_printed_document_dates() {
+        let may_14 = NaiveDate::from_ymd_opt(2023, 5, 14);
+        for text in [
+            "2023-05-14",
+            "05/14/23",
+            "5-14-23",
+            "May 14, 2023",
+            "14 May 2023",
+            "2023-05-14T10:00:00",
+        ] {
+            assert_eq!(parse_document_date(text), may_14, "{}", text);
+        }
+    }
+
+    #[tokio::test]
+    async fn reconcile_dates_fills_in_scan_results() {
+        let result: ScanResult = serde_json::from_value(serde_json::json!({
+            "path": "/nonexistent/title 2019-07-04.pdf",
+            "filename": "title 2019-07-04.pdf",
+            "file_type": "pdf",
+            "category": "document",
+            "size": 1,
+            "modified": "1700000000",
+        }))
+        .unwrap();
+        let results = reconcile_dates(vec![result]).await.unwrap();
+        let dates = results[0].dates.as_ref().unwrap();
+        assert_eq!(dates.source, Some(DateSource::Filename));
+        assert_eq!(dates.captured_at.as_deref(), Some("2019-07-04"));
+        assert_eq!(dates.exif, None);
+        assert!(dates
+            .mtime
+            .as_deref()
+            .is_some_and(|m| m.starts_with("2023-11-1")));
+    }
+}
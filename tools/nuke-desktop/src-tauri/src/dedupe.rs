@@ -0,0 +1,529 @@
+// Duplicate detection across extraction results
+
+use crate::documents::{ExtractedData, ExtractionResult};
+use crate::error::NukeError;
+use crate::imaging::{self, ScanFingerprint};
+use crate::standard;
+use crate::vin;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinDuplicate {
+    pub path: String,
+    pub vin: String,
+    pub pre_1981: bool,
+    /// The existing VIN as given, or the path of an earlier result with the same VIN
+    pub duplicate_of: String,
+}
+
+/// Find results whose VIN matches a known vehicle once formatting differences are removed
+#[tauri::command]
+pub async fn find_vin_duplicates(
+    results: Vec<ExtractionResult>,
+    existing_vins: Vec<String>,
+) -> Result<Vec<VinDuplicate>, NukeError> {
+    Ok(vin_duplicates(&results, &existing_vins))
+}
+
+fn vin_duplicates(results: &[ExtractionResult], existing_vins: &[String]) -> Vec<VinDuplicate> {
+    let mut known: HashMap<String, String> = HashMap::new();
+    for existing in existing_vins {
+        if let Some(normalized) = vin::normalize_vin(existing) {
+            known
+                .entry(normalized.match_key())
+                .or_insert_with(|| existing.clone());
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for result in results {
+        let normalized = match result.data.vin.as_deref().and_then(vin::normalize_vin) {
+            Some(n) => n,
+            None => continue,
+        };
+
+        let key = normalized.match_key();
+        match known.get(&key) {
+            Some(original) => duplicates.push(VinDuplicate {
+                path: result.path.clone(),
+                vin: normalized.vin,
+                pre_1981: normalized.pre_1981,
+                duplicate_of: original.clone(),
+            }),
+            None => {
+                known.insert(key, result.path.clone());
+            }
+        }
+    }
+
+    duplicates
+}
+
+// Perceptual-hash bits two copies of one page may differ by
+const SAME_SCAN_MAX_DISTANCE: u32 = 10;
+// A matching VIN vouches for the pair, so allow for crops and angles
+const SAME_VIN_MAX_DISTANCE: u32 = 18;
+
+/// Copies of one document, with the best one chosen as primary
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanGroup {
+    pub primary: String,
+    pub duplicates: Vec<String>,
+    pub vin: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanDedupe {
+    /// One result per document: the primary, with the other copies' paths attached
+    pub results: Vec<ExtractionResult>,
+    /// Only documents that had more than one copy
+    pub groups: Vec<ScanGroup>,
+}
+
+/// Group phone snaps and flatbed scans of the same document, keeping the highest-resolution copy
+#[tauri::command]
+pub async fn dedupe_scans(results: Vec<ExtractionResult>) -> Result<ScanDedupe, NukeError> {
+    let fingerprints: Vec<Option<ScanFingerprint>> = results
+        .iter()
+        .map(|r| imaging::scan_fingerprint(Path::new(&r.path)))
+        .collect();
+    Ok(group_scans(results, &fingerprints))
+}
+
+pub fn group_scans(
+    results: Vec<ExtractionResult>,
+    fingerprints: &[Option<ScanFingerprint>],
+) -> ScanDedupe {
+    let vins: Vec<Option<String>> = results
+        .iter()
+        .map(|r| {
+            r.data
+                .vin
+                .as_deref()
+                .and_then(vin::normalize_vin)
+                .map(|n| n.match_key())
+        })
+        .collect();
+
+    // Union-find over pairs that look like the same page
+    let mut parent: Vec<usize> = (0..results.len()).collect();
+    for a in 0..results.len() {
+        for b in a + 1..results.len() {
+            let (Some(fa), Some(fb)) = (fingerprints[a], fingerprints[b]) else {
+                continue;
+            };
+            let max_distance = match (&vins[a], &vins[b]) {
+                (Some(x), Some(y)) if x != y => continue,
+                (Some(_), Some(_)) => SAME_VIN_MAX_DISTANCE,
+                _ => SAME_SCAN_MAX_DISTANCE,
+            };
+            if imaging::hash_distance(fa.hash, fb.hash) <= max_distance {
+                let (ra, rb) = (root(&mut parent, a), root(&mut parent, b));
+                parent[rb] = ra;
+            }
+        }
+    }
+
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); results.len()];
+    for i in 0..results.len() {
+        let r = root(&mut parent, i);
+        members[r].push(i);
+    }
+
+    // Most pixels wins; extraction confidence breaks ties
+    let quality: Vec<(u64, f32)> = results
+        .iter()
+        .zip(fingerprints)
+        .map(|(r, f)| (f.map(|f| f.pixels).unwrap_or(0), r.confidence))
+        .collect();
+    let mut slots: Vec<Option<ExtractionResult>> = results.into_iter().map(Some).collect();
+    let mut kept = Vec::new();
+    let mut groups = Vec::new();
+    for group in members.into_iter().filter(|m| !m.is_empty()) {
+        let best = *group
+            .iter()
+            .max_by(|a, b| {
+                quality[**a]
+                    .partial_cmp(&quality[**b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(&group[0]);
+        let duplicates: Vec<String> = group
+            .iter()
+            .filter(|i| **i != best)
+            .filter_map(|i| slots[*i].as_ref().map(|r| r.path.clone()))
+            .collect();
+        let Some(mut primary) = slots[best].take() else {
+            continue;
+        };
+        if !duplicates.is_empty() {
+            groups.push(ScanGroup {
+                primary: primary.path.clone(),
+                duplicates: duplicates.clone(),
+                vin: group.iter().find_map(|i| vins[*i].clone()),
+            });
+        }
+        primary.duplicate_scans = duplicates;
+        kept.push(primary);
+    }
+
+    ScanDedupe {
+        results: kept,
+        groups,
+    }
+}
+
+/// Union-find root with path halving
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// How much each field counts toward `vehicle_similarity`, and the score that counts as a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarityWeights {
+    pub year: f32,
+    pub make_model: f32,
+    pub mileage: f32,
+    pub color: f32,
+    /// Records scoring at or above this are treated as the same vehicle
+    pub threshold: f32,
+}
+
+impl Default for SimilarityWeights {
+    fn default() -> Self {
+        SimilarityWeights {
+            year: 0.3,
+            make_model: 0.4,
+            mileage: 0.2,
+            color: 0.1,
+            threshold: 0.8,
+        }
+    }
+}
+
+/// Similarity score with the per-field components that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Similarity {
+    pub score: f32,
+    pub is_match: bool,
+    /// Only fields present on both records are scored
+    pub year: Option<f32>,
+    pub make_model: Option<f32>,
+    pub mileage: Option<f32>,
+    pub color: Option<f32>,
+}
+
+/// Score how likely two VIN-less records describe the same vehicle (0-1)
+#[tauri::command]
+pub async fn vehicle_similarity(
+    a: ExtractedData,
+    b: ExtractedData,
+    weights: Option<SimilarityWeights>,
+) -> Result<Similarity, NukeError> {
+    Ok(score_similarity(&a, &b, &weights.unwrap_or_default()))
+}
+
+pub fn score_similarity(
+    a: &ExtractedData,
+    b: &ExtractedData,
+    weights: &SimilarityWeights,
+) -> Similarity {
+    let number = |s: &Option<String>| {
+        s.as_ref()?
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect::<String>()
+            .parse::<f64>()
+            .ok()
+    };
+
+    // Years a couple apart are often a misread or model-year vs. build-year
+    let year = match (number(&a.year), number(&b.year)) {
+        (Some(x), Some(y)) => Some((1.0 - (x - y).abs() as f32 / 3.0).max(0.0)),
+        _ => None,
+    };
+
+    let make_model = match (make_model_key(a), make_model_key(b)) {
+        (Some(x), Some(y)) => Some(strsim::jaro_winkler(&x, &y) as f32),
+        _ => None,
+    };
+
+    let mileage = match (number(&a.mileage), number(&b.mileage)) {
+        (Some(x), Some(y)) if x.max(y) > 0.0 => {
+            Some((1.0 - ((x - y).abs() / x.max(y)) as f32).max(0.0))
+        }
+        (Some(_), Some(_)) => Some(1.0),
+        _ => None,
+    };
+
+    let color = match (&a.color, &b.color) {
+        (Some(x), Some(y)) => {
+            Some(strsim::jaro_winkler(&x.trim().to_lowercase(), &y.trim().to_lowercase()) as f32)
+        }
+        _ => None,
+    };
+
+    // Weight only the fields both records have
+    let parts = [
+        (year, weights.year),
+        (make_model, weights.make_model),
+        (mileage, weights.mileage),
+        (color, weights.color),
+    ];
+    let total_weight: f32 = parts
+        .iter()
+        .filter(|(v, _)| v.is_some())
+        .map(|(_, w)| w)
+        .sum();
+    let score = if total_weight > 0.0 {
+        parts
+            .iter()
+            .filter_map(|(v, w)| v.map(|v| v * w))
+            .sum::<f32>()
+            / total_weight
+    } else {
+        0.0
+    };
+
+    Similarity {
+        score,
+        is_match: total_weight > 0.0 && score >= weights.threshold,
+        year,
+        make_model,
+        mileage,
+        color,
+    }
+}
+
+/// "CHEVROLET c10" style key with the make normalized to its vPIC name
+fn make_model_key(data: &ExtractedData) -> Option<String> {
+    let make = data.make.as_deref().map(standard::vpic_make);
+    let model = data.model.as_deref().map(|m| m.trim().to_lowercase());
+    match (make, model) {
+        (None, None) => None,
+        (make, model) => Some(format!(
+            "{} {}",
+            make.unwrap_or_default().to_lowercase(),
+            model.unwrap_or_default()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, vin: &str) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": { "vin": vin },
+            "confidence": 0.9,
+            "raw_response": "{}",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn formatted_vins_match_existing_vehicles() {
+        let results = vec![result("/title.jpg", "VIN: 1HGCM-82633-A004352")];
+        let duplicates = vin_duplicates(&results, &["1hgcm82633a004352".to_string()]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].vin, "1HGCM82633A004352");
+        assert_eq!(duplicates[0].duplicate_of, "1hgcm82633a004352");
+        assert!(!duplicates[0].pre_1981);
+    }
+
+    #[test]
+    fn short_pre_1981_serials_match_without_leading_zeros() {
+        let results = vec![
+            result("/registration.jpg", "00F8512345"),
+            result("/bill of sale.jpg", "F8512345"),
+            result("/other.jpg", "F8512346"),
+        ];
+        let duplicates = vin_duplicates(&results, &[]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].path, "/bill of sale.jpg");
+        assert_eq!(duplicates[0].duplicate_of, "/registration.jpg");
+        assert!(duplicates[0].pre_1981);
+    }
+
+    fn vehicle(year: &str, make: &str, model: &str, mileage: &str, color: &str) -> ExtractedData {
+        ExtractedData {
+            year: Some(year.to_string()),
+            make: Some(make.to_string()),
+            model: Some(model.to_string()),
+            mileage: Some(mileage.to_string()),
+            color: Some(color.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn near_identical_records_score_high() {
+        let weights = SimilarityWeights::default();
+        let a = vehicle("1972", "Chevy", "C10", "45,120", "Red");
+        let b = vehicle("1972", "Chevrolet", "C-10", "45200 mi", "red");
+        let similarity = score_similarity(&a, &b, &weights);
+        assert!(
+            similarity.is_match && similarity.score > 0.9,
+            "{:?}",
+            similarity
+        );
+        assert_eq!(similarity.year, Some(1.0));
+        assert_eq!(similarity.color, Some(1.0));
+    }
+
+    #[test]
+    fn clearly_different_records_score_low() {
+        let weights = SimilarityWeights::default();
+        let a = vehicle("1972", "Chevy", "C10", "45120", "Red");
+        let b = vehicle("1995", "Toyota", "Tacoma", "210000", "White");
+        let similarity = score_similarity(&a, &b, &weights);
+        assert!(
+            !similarity.is_match && similarity.score < 0.4,
+            "{:?}",
+            similarity
+        );
+        assert_eq!(similarity.year, Some(0.0));
+    }
+
+    #[test]
+    fn only_shared_fields_are_weighted() {
+        let weights = SimilarityWeights::default();
+        let empty = score_similarity(
+            &ExtractedData::default(),
+            &ExtractedData::default(),
+            &weights,
+        );
+        assert_eq!(empty.score, 0.0);
+        assert!(!empty.is_match);
+
+        // Same year and make/model, nothing else to compare
+        let a = ExtractedData {
+            color: None,
+            mileage: None,
+            ..vehicle("1969", "Ford", "Bronco", "", "")
+        };
+        let b = vehicle("1969", "Ford", "Bronco", "88000", "Blue");
+        let similarity = score_similarity(&a, &b, &weights);
+        assert_eq!(similarity.mileage, None);
+        assert_eq!(similarity.color, None);
+        assert!((similarity.score - 1.0).abs() < 1e-6, "{:?}", similarity);
+    }
+
+    #[test]
+    fn weights_and_threshold_are_configurable() {
+        let a = vehicle("1972", "Chevy", "C10", "45000", "Red");
+        let b = vehicle("1973", "Chevy", "C10", "90000", "Blue");
+        assert!(!score_similarity(&a, &b, &SimilarityWeights::default()).is_match);
+
+        let ignore_wear = SimilarityWeights {
+            mileage: 0.0,
+            color: 0.0,
+            ..Default::default()
+        };
+        assert!(score_similarity(&a, &b, &ignore_wear).is_match);
+        let strict = SimilarityWeights {
+            threshold: 1.01,
+            ..ignore_wear
+        };
+        assert!(!score_similarity(&a, &b, &strict).is_match);
+    }
+
+    /// A document page: text-like bars in `seed`'s pattern and a dark header block
+    fn page(width: u32, height: u32, seed: u32) -> image::GrayImage {
+        image::GrayImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x as f32 / width as f32, y as f32 / height as f32);
+            let row = (fy * 12.0) as u32;
+            let bar_end = 0.1 + 0.8 * ((row * 7 + seed) % 10) as f32 / 10.0;
+            let bar = (row + seed).is_multiple_of(3) && fx > 0.1 && fx < bar_end;
+            let header = fx > 0.6 && fy < 0.25;
+            image::Luma([if bar || header { 20 } else { 235 }])
+        })
+    }
+
+    #[test]
+    fn high_resolution_copy_is_kept_as_primary() {
+        let dir = std::env::temp_dir().join(format!("nuke-scan-dupes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let flatbed = dir.join("flatbed.png");
+        page(1600, 2000, 1).save(&flatbed).unwrap();
+        // The phone snap: the same page at a quarter of the size, as a JPEG
+        let snap = dir.join("snap.jpg");
+        image::imageops::resize(
+            &page(1600, 2000, 1),
+            400,
+            500,
+            image::imageops::FilterType::Triangle,
+        )
+        .save(&snap)
+        .unwrap();
+        let other = dir.join("bill.png");
+        page(800, 1000, 2).save(&other).unwrap();
+
+        let path = |p: &Path| p.to_string_lossy().to_string();
+        let mut snap_result = result(&path(&snap), "1HGCM82633A004352");
+        snap_result.confidence = 0.95;
+        let mut flatbed_result = result(&path(&flatbed), "");
+        flatbed_result.data.vin = None;
+        let mut other_result = result(&path(&other), "");
+        other_result.data.vin = None;
+        let results = vec![snap_result, flatbed_result, other_result];
+        let fingerprints: Vec<_> = results
+            .iter()
+            .map(|r| imaging::scan_fingerprint(Path::new(&r.path)))
+            .collect();
+        let dedupe = group_scans(results, &fingerprints);
+
+        assert_eq!(dedupe.results.len(), 2);
+        assert_eq!(dedupe.groups.len(), 1);
+        let group = &dedupe.groups[0];
+        assert_eq!(group.primary, path(&flatbed));
+        assert_eq!(group.duplicates, vec![path(&snap)]);
+        assert_eq!(group.vin.as_deref(), Some("1HGCM82633A004352"));
+        let primary = dedupe
+            .results
+            .iter()
+            .find(|r| r.path == path(&flatbed))
+            .unwrap();
+        assert_eq!(primary.duplicate_scans, vec![path(&snap)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn different_vins_are_never_the_same_scan() {
+        let fingerprint = |pixels| {
+            Some(ScanFingerprint {
+                hash: 0xF0F0,
+                pixels,
+            })
+        };
+        let results = vec![
+            result("/a.jpg", "1HGCM82633A004352"),
+            result("/b.jpg", "1FMEU15H5FLA12345"),
+        ];
+        let dedupe = group_scans(results, &[fingerprint(100), fingerprint(400)]);
+        assert_eq!(dedupe.results.len(), 2);
+        assert!(dedupe.groups.is_empty());
+
+        // Unreadable files can't be matched to anything
+        let results = vec![
+            result("/a.jpg", "1HGCM82633A004352"),
+            result("/b.jpg", "1HGCM82633A004352"),
+        ];
+        assert!(group_scans(results, &[None, fingerprint(100)])
+            .groups
+            .is_empty());
+    }
+}
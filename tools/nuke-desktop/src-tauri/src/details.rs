@@ -0,0 +1,348 @@
+// Detail shots - tire sidewalls, engine-bay stamps, build tags and glass etching that generic
+// prompts skip over
+
+use crate::documents::{field, ExtractedData};
+use crate::error::NukeError;
+use crate::{ocr, vin};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DetailKind {
+    /// Tire sidewall, usually shot for the DOT date code
+    Tire,
+    /// Engine, frame or block with stamped numbers
+    EngineBay,
+    /// Cowl tag, trim tag or door data plate
+    BuildTag,
+    /// VIN etched into a window as an anti-theft mark
+    GlassEtch,
+}
+
+/// Week and year a tire was made, from the last group of its DOT code
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TireDate {
+    pub week: u32,
+    /// None for pre-2000 three-digit codes, which only give the year within a decade
+    pub year: Option<u32>,
+    pub raw: String,
+}
+
+/// Codes decoded from a GM-style cowl tag; raw is kept for tags we can't decode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildTag {
+    pub raw: String,
+    pub model_year: Option<u32>,
+    pub body_style: Option<String>,
+    pub trim_code: Option<String>,
+    pub paint_code: Option<String>,
+    pub build_month: Option<u32>,
+    /// A-E: week within the build month
+    pub build_week: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailShot {
+    pub kind: DetailKind,
+    pub tire_date: Option<TireDate>,
+    /// Numbers stamped on the engine, frame or body, as read
+    pub stamped_numbers: Vec<String>,
+    /// A stamped number that is a full, valid VIN
+    pub stamped_vin: Option<String>,
+    pub build_tag: Option<BuildTag>,
+    /// VIN etched into the glass; set from tuned OCR when the model couldn't read it
+    #[serde(default)]
+    pub etched_vin: Option<String>,
+}
+
+/// A glass-etched VIN compared against the car's other VINs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EtchCheck {
+    pub etched_vin: Option<String>,
+    pub checksum_valid: bool,
+    /// Other VINs for the car that match the etching
+    pub corroborates: Vec<String>,
+    /// Other VINs for the car that don't
+    pub conflicts: Vec<String>,
+    /// The etching doesn't match the title: swapped glass at best, a stolen car at worst
+    pub title_mismatch: bool,
+}
+
+/// Read the VIN etched into a window and cross-check it against the title and other VINs
+#[tauri::command]
+pub async fn check_glass_etch(
+    path: String,
+    title_vin: Option<String>,
+    other_vins: Option<Vec<String>>,
+) -> Result<EtchCheck, NukeError> {
+    let reads = ocr::read_etched_vins(Path::new(&path)).await;
+    Ok(cross_check_etch(
+        reads.first().map(|v| v.as_str()),
+        title_vin.as_deref(),
+        &other_vins.unwrap_or_default(),
+    ))
+}
+
+pub fn cross_check_etch(
+    etched: Option<&str>,
+    title_vin: Option<&str>,
+    others: &[String],
+) -> EtchCheck {
+    let key = |raw: &str| vin::normalize_vin(raw).map(|v| v.match_key());
+    let etched_key = match etched.and_then(key) {
+        Some(k) => k,
+        None => return EtchCheck::default(),
+    };
+
+    let (corroborates, conflicts): (Vec<String>, Vec<String>) = title_vin
+        .into_iter()
+        .map(str::to_string)
+        .chain(others.iter().cloned())
+        .filter(|other| key(other).is_some())
+        .partition(|other| key(other).as_deref() == Some(etched_key.as_str()));
+
+    EtchCheck {
+        checksum_valid: vin::validate_vin(&etched_key),
+        title_mismatch: title_vin
+            .and_then(key)
+            .map(|title| title != etched_key)
+            .unwrap_or(false),
+        etched_vin: Some(etched_key),
+        corroborates,
+        conflicts,
+    }
+}
+
+/// Parse the detail fields of a photo response; None unless the model called it a detail shot
+pub fn parse_detail_shot(value: &serde_json::Value) -> Option<DetailShot> {
+    let kind = match field(value, "shot_type")?
+        .to_lowercase()
+        .replace([' ', '-'], "_")
+        .as_str()
+    {
+        "tire" | "tyre" | "wheel" => DetailKind::Tire,
+        "engine_bay" | "engine" | "engine_stamp" | "frame_stamp" => DetailKind::EngineBay,
+        "build_tag" | "cowl_tag" | "trim_tag" | "data_plate" => DetailKind::BuildTag,
+        "glass_etch" | "window_etch" | "etched_vin" => DetailKind::GlassEtch,
+        _ => return None,
+    };
+
+    let stamped_numbers: Vec<String> = match value.get("stamped_numbers") {
+        Some(serde_json::Value::Array(numbers)) => numbers
+            .iter()
+            .filter_map(|n| match n {
+                serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => field(value, "stamped_numbers").into_iter().collect(),
+    };
+    let stamped_vin = stamped_numbers
+        .iter()
+        .flat_map(|n| vin::find_vins(&n.to_uppercase()))
+        .find(|v| vin::validate_vin(v));
+
+    Some(DetailShot {
+        kind,
+        tire_date: field(value, "dot_code").and_then(|code| parse_dot_date(&code)),
+        stamped_numbers,
+        stamped_vin,
+        build_tag: field(value, "build_tag").map(|tag| parse_build_tag(&tag)),
+        etched_vin: field(value, "etched_vin").and_then(|v| {
+            vin::find_vins(&v.replace([' ', '-'], ""))
+                .into_iter()
+                .next()
+        }),
+    })
+}
+
+/// Fill general fields a detail shot can vouch for
+pub fn apply_detail(detail: DetailShot, data: &mut ExtractedData) {
+    if data.vin.is_none() {
+        data.vin = detail
+            .stamped_vin
+            .clone()
+            .or_else(|| detail.etched_vin.clone());
+    }
+    if data.year.is_none() {
+        data.year = detail
+            .build_tag
+            .as_ref()
+            .and_then(|t| t.model_year)
+            .map(|y| y.to_string());
+    }
+    data.detail = Some(detail);
+}
+
+/// "DOT U2LL LMLR 5107" -> week 51 of 2007; "DOT ... 427" -> week 42 of some year ending in 7
+pub fn parse_dot_date(code: &str) -> Option<TireDate> {
+    // The date is the last group of digits in the code
+    let date = Regex::new(r"(\d{3,4})\D*$")
+        .ok()?
+        .captures(code.trim())?
+        .get(1)?
+        .as_str();
+    let week: u32 = date[..2].parse().ok()?;
+    if !(1..=53).contains(&week) {
+        return None;
+    }
+    let year = match date.len() {
+        4 => Some(2000 + date[2..].parse::<u32>().ok()?),
+        _ => None,
+    };
+    Some(TireDate {
+        week,
+        year,
+        raw: date.to_string(),
+    })
+}
+
+/// Decode "ST 69-12437 BDY 1234 / TR 711 / PNT 76 76 / 03B"-style tags
+pub fn parse_build_tag(text: &str) -> BuildTag {
+    let upper = text.to_uppercase();
+    let capture = |pattern: &str| {
+        Regex::new(pattern)
+            .ok()?
+            .captures(&upper)?
+            .get(1)
+            .map(|m| m.as_str().to_string())
+    };
+
+    let style = capture(r"\bST\s+(\d{2}-?\s?\d{4,5})");
+    let model_year = style
+        .as_deref()
+        .and_then(|s| s.get(..2))
+        .and_then(|yy| yy.parse::<u32>().ok())
+        .map(|yy| 1900 + yy);
+    let build_date = Regex::new(r"\b(0[1-9]|1[0-2])([A-E])\b")
+        .ok()
+        .and_then(|r| r.captures(&upper));
+
+    BuildTag {
+        raw: text.trim().to_string(),
+        model_year,
+        body_style: style
+            .as_deref()
+            .and_then(|s| s.get(2..))
+            .map(|s| s.trim_start_matches(['-', ' ']).to_string()),
+        trim_code: capture(r"\b(?:TR|TRIM)\s*[:#]?\s*([0-9A-Z-]+)"),
+        paint_code: capture(r"\b(?:PNT|PAINT)\s*[:#]?\s*([0-9A-Z]+(?:\s+[0-9A-Z]{1,3}\b)?)"),
+        build_month: build_date
+            .as_ref()
+            .and_then(|c| c.get(1)?.as_str().parse().ok()),
+        build_week: build_date
+            .as_ref()
+            .and_then(|c| c.get(2)?.as_str().bytes().next())
+            .map(|b| (b - b'A') as u32 + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tire_response_gives_the_dot_date() {
+        let detail = parse_detail_shot(&json!({
+            "shot_type": "tire",
+            "dot_code": "DOT U2LL LMLR 5107",
+            "stamped_numbers": [],
+        }))
+        .unwrap();
+        assert_eq!(detail.kind, DetailKind::Tire);
+        let date = detail.tire_date.unwrap();
+        assert_eq!((date.week, date.year), (51, Some(2007)));
+        assert_eq!(date.raw, "5107");
+        assert!(detail.stamped_vin.is_none());
+    }
+
+    #[test]
+    fn dot_codes_before_2000_give_no_year() {
+        let date = parse_dot_date("DOT AB12 CDE 427").unwrap();
+        assert_eq!((date.week, date.year), (42, None));
+        // Week 61 doesn't exist, and a code without a date group has none to give
+        assert!(parse_dot_date("DOT U2LL LMLR 6107").is_none());
+        assert!(parse_dot_date("DOT U2LL").is_none());
+    }
+
+    #[test]
+    fn engine_stamp_response_finds_the_stamped_vin() {
+        let detail = parse_detail_shot(&json!({
+            "shot_type": "engine bay",
+            "stamped_numbers": ["V0123CE", "1hgcm82633a004352", 350],
+        }))
+        .unwrap();
+        assert_eq!(detail.kind, DetailKind::EngineBay);
+        assert_eq!(
+            detail.stamped_numbers,
+            vec!["V0123CE", "1hgcm82633a004352", "350"]
+        );
+        assert_eq!(detail.stamped_vin.as_deref(), Some("1HGCM82633A004352"));
+
+        let mut data = ExtractedData::default();
+        apply_detail(detail, &mut data);
+        assert_eq!(data.vin.as_deref(), Some("1HGCM82633A004352"));
+        assert!(data.detail.is_some());
+    }
+
+    #[test]
+    fn build_tags_decode_year_style_trim_paint_and_date() {
+        let tag = parse_build_tag("ST 69-12437 NOR 123456 BDY\nTR 711 PNT 76 76\n03B");
+        assert_eq!(tag.model_year, Some(1969));
+        assert_eq!(tag.body_style.as_deref(), Some("12437"));
+        assert_eq!(tag.trim_code.as_deref(), Some("711"));
+        assert_eq!(tag.paint_code.as_deref(), Some("76 76"));
+        assert_eq!((tag.build_month, tag.build_week), (Some(3), Some(2)));
+
+        let detail =
+            parse_detail_shot(&json!({ "shot_type": "cowl tag", "build_tag": "ST 69-12437" }))
+                .unwrap();
+        let mut data = ExtractedData::default();
+        apply_detail(detail, &mut data);
+        assert_eq!(data.year.as_deref(), Some("1969"));
+    }
+
+    #[test]
+    fn general_photos_are_not_detail_shots() {
+        assert!(parse_detail_shot(&json!({ "shot_type": "exterior" })).is_none());
+        assert!(parse_detail_shot(&json!({ "make": "Ford" })).is_none());
+    }
+
+    #[test]
+    fn etched_vin_is_cross_checked_against_the_title_and_other_vins() {
+        let matching = cross_check_etch(
+            Some("1HGCM82633A004352"),
+            Some("1HGCM82633A004352"),
+            &["1hgcm82633a004352".to_string()],
+        );
+        assert!(matching.checksum_valid);
+        assert!(!matching.title_mismatch);
+        assert_eq!(matching.corroborates.len(), 2);
+        assert!(matching.conflicts.is_empty());
+
+        let swapped = cross_check_etch(Some("1HGCM82633A004352"), Some("1FTEF14N0SLA00001"), &[]);
+        assert!(swapped.title_mismatch);
+        assert_eq!(swapped.conflicts, vec!["1FTEF14N0SLA00001"]);
+
+        // Nothing readable etched means nothing to compare
+        let unread = cross_check_etch(Some("~ ,."), Some("1HGCM82633A004352"), &[]);
+        assert!(unread.etched_vin.is_none() && !unread.title_mismatch);
+    }
+
+    #[test]
+    fn glass_etch_response_gives_the_etched_vin() {
+        let detail = parse_detail_shot(&json!({
+            "shot_type": "glass etch",
+            "etched_vin": "1HGCM 82633 A004352",
+        }))
+        .unwrap();
+        assert_eq!(detail.kind, DetailKind::GlassEtch);
+        assert_eq!(detail.etched_vin.as_deref(), Some("1HGCM82633A004352"));
+    }
+}
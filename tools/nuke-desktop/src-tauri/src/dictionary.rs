@@ -0,0 +1,332 @@
+// Vehicle dictionary - the makes, aliases and models recognized in paths and document text
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+// (make, aliases, models that need the make named too, models that identify the make alone)
+type BuiltInMake = (&'static str, &'static [&'static str], &'static [&'static str], &'static [&'static str]);
+
+const BUILT_IN_MAKES: &[BuiltInMake] = &[
+    ("Chevrolet", &["chevy"], &["blazer", "impala", "nova", "chevelle", "bel air"], &["c10", "c20", "k10", "k20", "k5", "suburban", "silverado", "camaro", "corvette", "el camino"]),
+    ("Ford", &[], &["ranger", "falcon", "galaxie"], &["mustang", "f100", "f-100", "f150", "f-150", "f250", "f-250", "bronco", "thunderbird", "model a", "model t"]),
+    ("Dodge", &[], &["dart", "coronet"], &["challenger", "charger", "power wagon"]),
+    ("Plymouth", &[], &["fury"], &["barracuda", "cuda", "road runner", "gtx"]),
+    ("Pontiac", &[], &["firebird", "trans am"], &["gto"]),
+    ("GMC", &[], &["sierra", "jimmy"], &[]),
+    ("Jeep", &[], &["cherokee", "cj5", "cj7"], &["wrangler", "wagoneer"]),
+    ("Ram", &[], &["1500", "2500"], &[]),
+    ("Toyota", &[], &["celica", "supra", "corolla", "pickup"], &["tacoma", "4runner", "land cruiser", "fj40", "fj60", "fj80"]),
+    ("Honda", &[], &["prelude", "s2000"], &["civic", "accord"]),
+    ("Acura", &[], &["integra", "nsx"], &[]),
+    ("Nissan", &["datsun"], &["240z", "260z", "280z", "300zx", "skyline", "silvia"], &[]),
+    ("Mazda", &[], &["rx-7", "rx7", "miata", "mx-5"], &[]),
+    ("Subaru", &[], &["impreza", "wrx", "brat"], &[]),
+    ("Mitsubishi", &[], &["lancer", "evo", "3000gt"], &[]),
+    ("BMW", &[], &["2002", "m3", "m5", "e30", "e36", "e46"], &[]),
+    ("Mercedes-Benz", &["mercedes", "benz"], &["280sl", "300sl", "190e", "sl", "g-wagen"], &[]),
+    ("Porsche", &[], &["911", "912", "914", "930", "944", "928", "carrera", "targa"], &[]),
+    ("Volkswagen", &["vw"], &["beetle", "bus", "golf", "gti", "karmann ghia", "vanagon"], &[]),
+    ("Audi", &[], &["quattro", "tt", "a4"], &[]),
+    ("Volvo", &[], &["240", "p1800", "amazon"], &[]),
+    ("Jaguar", &[], &["xke", "e-type", "xj6"], &[]),
+    ("Land Rover", &["landrover"], &["defender", "series", "range rover"], &[]),
+    ("Triumph", &[], &["tr6", "tr4", "spitfire"], &[]),
+    ("MG", &[], &["mgb", "midget"], &[]),
+    ("Alfa Romeo", &["alfa"], &["spider", "giulia", "gtv"], &[]),
+    ("Ferrari", &[], &["308", "328", "testarossa", "dino"], &[]),
+    ("Lamborghini", &[], &["countach", "diablo", "miura"], &[]),
+];
+
+const BUILT_IN_TRIMS: &[&str] = &["SS", "RS", "GT", "Turbo", "Turbo S", "Z28", "Denali", "Trail Boss"];
+
+// Engine codes that aren't a displacement, e.g. "camaro_ls3"
+const BUILT_IN_ENGINE_CODES: &[&str] = &["LS1", "LS2", "LS3", "LS6", "LS7", "LSA", "LT1", "LT4", "L88", "Hemi", "Coyote", "2JZ", "RB26", "13B"];
+
+// Words after the model searched for trim and engine, so "camaro 1969 ss 396" still counts
+const DESIGNATOR_WINDOW: usize = 4;
+
+// Three-digit numbers read as cubic inches; smaller ones are more often model numbers
+const ENGINE_CUBIC_INCHES: std::ops::RangeInclusive<u32> = 170..=572;
+
+// Adjacent words joined when matching terms, so "land cruiser" finds "landcruiser" and back
+const MAX_RUN_WORDS: usize = 3;
+
+// Term lengths (separators dropped) for one and two typos in a fuzzy match; shorter terms
+// like "dodge" are one letter from ordinary words ("lodge")
+const FUZZY_ONE_EDIT_LEN: usize = 6;
+const FUZZY_TWO_EDITS_LEN: usize = 9;
+
+/// A make, the other names it goes by, and its models
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MakeEntry {
+    /// Canonical name reported in hints, e.g. "Chevrolet"
+    pub name: String,
+    /// Other spellings: "chevy"
+    pub aliases: Vec<String>,
+    /// Only matched when the make is also named; "911" alone is too often a number
+    pub models: Vec<String>,
+    /// Distinctive enough to match alone, and imply the make
+    pub signature_models: Vec<String>,
+}
+
+/// A make and model found in some text, and whether either was only a near match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Found<'a> {
+    pub make: Option<&'a str>,
+    pub model: Option<String>,
+    pub fuzzy_make: bool,
+    pub fuzzy_model: bool,
+}
+
+/// Makes and models recognized by `extract_hints_from_text`.
+/// Loaded from a JSON file whose entries extend the built-in list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VehicleDictionary {
+    pub makes: Vec<MakeEntry>,
+    /// Trim designators read after a model: "SS", "Trail Boss"
+    pub trims: Vec<String>,
+    /// Engine names read after a model besides displacements: "LS3"
+    pub engine_codes: Vec<String>,
+}
+
+impl VehicleDictionary {
+    pub fn built_in() -> Self {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        VehicleDictionary {
+            makes: BUILT_IN_MAKES
+                .iter()
+                .map(|(name, aliases, models, signature_models)| MakeEntry {
+                    name: name.to_string(),
+                    aliases: list(aliases),
+                    models: list(models),
+                    signature_models: list(signature_models),
+                })
+                .collect(),
+            trims: list(BUILT_IN_TRIMS),
+            engine_codes: list(BUILT_IN_ENGINE_CODES),
+        }
+    }
+
+    /// The built-in dictionary extended with the user's file, if it exists and parses
+    pub fn load(path: &Path) -> Self {
+        let mut dictionary = Self::built_in();
+        let user: Option<VehicleDictionary> = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok());
+        if let Some(user) = user {
+            dictionary.extend(user);
+        }
+        dictionary
+    }
+
+    /// Add another dictionary's makes, merging into makes already listed under the same name
+    pub fn extend(&mut self, other: VehicleDictionary) {
+        self.trims.extend(other.trims);
+        self.engine_codes.extend(other.engine_codes);
+        for entry in other.makes {
+            match self
+                .makes
+                .iter_mut()
+                .find(|m| m.name.eq_ignore_ascii_case(&entry.name))
+            {
+                Some(existing) => {
+                    existing.aliases.extend(entry.aliases);
+                    existing.models.extend(entry.models);
+                    existing.signature_models.extend(entry.signature_models);
+                }
+                None => self.makes.push(entry),
+            }
+        }
+    }
+
+    /// (make, model) named in `text`; a model only counts under its make unless it's a signature model
+    pub fn find(&self, text: &str) -> (Option<&str>, Option<String>) {
+        let found = self.find_with(text, false);
+        (found.make, found.model)
+    }
+
+    /// `find`, also accepting near misses ("corvete") when `fuzzy` is set. Exact matches are
+    /// tried everywhere first, so a typo never beats a term that's spelled right.
+    pub fn find_with(&self, text: &str, fuzzy: bool) -> Found<'_> {
+        let runs = word_runs(&split_words(text));
+        let passes: &[bool] = if fuzzy { &[false, true] } else { &[false] };
+        let named = |term: &str, near: bool| {
+            let edits = if near { max_edits(term) } else { 0 };
+            (!near || edits > 0) && term_runs(&runs, term, edits).next().is_some()
+        };
+
+        for &near in passes {
+            let make = self.makes.iter().find(|m| {
+                std::iter::once(&m.name)
+                    .chain(&m.aliases)
+                    .any(|name| named(name, near))
+            });
+            if let Some(make) = make {
+                let model = passes.iter().find_map(|&near| {
+                    let mut models = make.signature_models.iter().chain(&make.models);
+                    models.find(|model| named(model, near)).map(|model| (model, near))
+                });
+                return Found {
+                    make: Some(&make.name),
+                    model: model.map(|(model, _)| model.to_uppercase()),
+                    fuzzy_make: near,
+                    fuzzy_model: model.is_some_and(|(_, near)| near),
+                };
+            }
+
+            let signature = self.makes.iter().find_map(|make| {
+                let model = make.signature_models.iter().find(|model| named(model, near))?;
+                Some(Found {
+                    make: Some(&make.name),
+                    model: Some(model.to_uppercase()),
+                    fuzzy_make: near,
+                    fuzzy_model: near,
+                })
+            });
+            if let Some(found) = signature {
+                return found;
+            }
+        }
+        Found::default()
+    }
+
+    /// (trim, engine) in the words right after `model` in `text`; "camaro_ss_396" gives SS and 396.
+    /// Bare tokens like "GT" or "350" mean too many things to trust without a model before them.
+    pub fn find_designators(&self, text: &str, model: &str) -> (Option<String>, Option<String>) {
+        let words = split_words(text);
+        let runs = word_runs(&words);
+        // Where the model was named; a fuzzy match's spelling is only close to it
+        let mut found: Vec<(usize, usize)> = term_runs(&runs, model, 0).collect();
+        if found.is_empty() {
+            found = term_runs(&runs, model, max_edits(model)).collect();
+        }
+
+        // Longest first, so "turbo s" wins over "turbo"
+        let mut trims: Vec<(&String, Vec<String>)> = self
+            .trims
+            .iter()
+            .map(|t| (t, split_words(t)))
+            .filter(|(_, w)| !w.is_empty())
+            .collect();
+        trims.sort_by_key(|(_, w)| std::cmp::Reverse(w.len()));
+
+        let (mut trim, mut engine) = (None, None);
+        // A path can name the model more than once: "camaro/1969_camaro_ss.jpg"
+        for after in found.into_iter().map(|(start, len)| start + len) {
+            let window = &words[after..words.len().min(after + DESIGNATOR_WINDOW)];
+            trim = trim.or_else(|| {
+                trims
+                    .iter()
+                    .find(|(_, words)| window.windows(words.len()).any(|w| w == words))
+                    .map(|(trim, _)| trim.to_string())
+            });
+            engine = engine.or_else(|| window.iter().find_map(|word| self.engine(word)));
+        }
+        (trim, engine)
+    }
+
+    /// A listed engine code, in its listed spelling, or a displacement
+    fn engine(&self, word: &str) -> Option<String> {
+        self.engine_codes
+            .iter()
+            .find(|code| code.eq_ignore_ascii_case(word))
+            .map(|code| code.to_string())
+            .or_else(|| displacement(word))
+    }
+}
+
+/// "5.7", "6.2l" -> "5.7L"; "396", "454ci" -> "396" (cubic inches)
+fn displacement(word: &str) -> Option<String> {
+    if let Some((whole, tenths)) = word.trim_end_matches('l').split_once('.') {
+        let digit = |part: &str| part.len() == 1 && part.bytes().all(|b| b.is_ascii_digit());
+        let liters = digit(whole) && digit(tenths) && whole != "0";
+        return liters.then(|| format!("{}.{}L", whole, tenths));
+    }
+    let digits = word.strip_suffix("ci").unwrap_or(word);
+    let inches: u32 = digits.parse().ok()?;
+    (digits.len() == 3 && ENGINE_CUBIC_INCHES.contains(&inches)).then(|| digits.to_string())
+}
+
+/// Edits allowed in a fuzzy match of `term`. None for terms with digits, where one
+/// character is the difference between models ("f150", "f250").
+fn max_edits(term: &str) -> usize {
+    let term = compact(term);
+    if term.chars().any(|c| c.is_ascii_digit()) {
+        0
+    } else if term.len() >= FUZZY_TWO_EDITS_LEN {
+        2
+    } else if term.len() >= FUZZY_ONE_EDIT_LEN {
+        1
+    } else {
+        0
+    }
+}
+
+/// Lowercase letters and digits only: "F-150" -> "f150"
+fn compact(term: &str) -> String {
+    term.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+/// Every run of up to MAX_RUN_WORDS adjacent words as (start, words, joined)
+fn word_runs(words: &[String]) -> Vec<(usize, usize, String)> {
+    let mut runs = Vec::new();
+    for start in 0..words.len() {
+        let mut joined = String::new();
+        for (len, word) in words[start..].iter().take(MAX_RUN_WORDS).enumerate() {
+            joined.push_str(word);
+            runs.push((start, len + 1, joined.clone()));
+        }
+    }
+    runs
+}
+
+/// Where `term` appears as (start, words), whatever separates its parts: "f 150", "f-150" and
+/// "f150" are one term. Runs within `edits` typos of it count too.
+fn term_runs<'a>(
+    runs: &'a [(usize, usize, String)],
+    term: &str,
+    edits: usize,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let term = compact(term);
+    runs.iter()
+        .filter(move |(_, _, joined)| {
+            !term.is_empty()
+                && joined.len().abs_diff(term.len()) <= edits
+                && (joined == &term || (edits > 0 && strsim::levenshtein(joined, &term) <= edits))
+        })
+        .map(|(start, len, _)| (*start, *len))
+}
+
+/// Lowercase words split on anything but letters and digits; a dot between digits stays,
+/// so "5.7" is one word
+fn split_words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        let decimal_point = c == '.'
+            && i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(|next| next.is_ascii_digit());
+        if c.is_alphanumeric() || decimal_point {
+            word.push(c);
+        } else if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// The built-in dictionary, for callers without access to AppState
+pub fn built_in() -> &'static VehicleDictionary {
+    static BUILT_IN: OnceLock<VehicleDictionary> = OnceLock::new();
+    BUILT_IN.get_or_init(VehicleDictionary::built_in)
+}
+
+
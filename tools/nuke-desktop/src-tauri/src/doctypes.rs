@@ -0,0 +1,1086 @@
+// Specialized document types - layout detection, targeted prompts, and parsers
+
+use crate::documents::{extracted_from_value, field, ExtractedData};
+use crate::error::NukeError;
+use crate::{dates, vin};
+use chrono::{Months, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// Fields every targeted prompt still asks for
+const BASE_FIELDS: &str = "vin, year, make, model, color, mileage, owner_name, plate_number, document_type, date, price, confidence (0-1)";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentKind {
+    #[default]
+    General,
+    Auction,
+    /// Dealer inventory sheet: one vehicle per row
+    Inventory,
+    /// Monroney label or used-car buyer's guide
+    WindowSticker,
+    /// Certificate of title
+    Title,
+    /// Loan agreement, payoff letter or other finance paperwork
+    Finance,
+    /// Emissions or safety inspection certificate
+    Inspection,
+    /// Concours judging sheet, restoration build book or award certificate
+    Concours,
+    /// Shop whiteboard or work order: build notes, parts lists and costs
+    ShopNotes,
+}
+
+/// Auction lot details from a bill of sale or consignment paperwork
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuctionLot {
+    pub lot_number: Option<String>,
+    pub auction_house: Option<String>,
+    pub sale_date: Option<String>,
+    pub hammer_price: Option<f64>,
+}
+
+// Auction houses we normalize names for
+const AUCTION_HOUSES: &[(&str, &str)] = &[
+    ("mecum", "Mecum"),
+    ("barrett-jackson", "Barrett-Jackson"),
+    ("barrett jackson", "Barrett-Jackson"),
+    ("rm sotheby", "RM Sotheby's"),
+    ("bonhams", "Bonhams"),
+    ("gooding", "Gooding & Company"),
+    ("bring a trailer", "Bring a Trailer"),
+    ("russo and steele", "Russo and Steele"),
+];
+
+/// Pricing and equipment from a window sticker
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSticker {
+    pub msrp: Option<f64>,
+    pub trim: Option<String>,
+    pub options: Vec<String>,
+}
+
+/// Title brands we accept; anything else goes to review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleBrand {
+    Clean,
+    Salvage,
+    Rebuilt,
+    Flood,
+    Lemon,
+    Junk,
+    Fire,
+    Hail,
+    OdometerRollback,
+}
+
+// Brand wording as printed on titles -> brand; more specific phrases first
+const TITLE_BRANDS: &[(&str, TitleBrand)] = &[
+    ("rebuilt salvage", TitleBrand::Rebuilt),
+    ("prior salvage", TitleBrand::Rebuilt),
+    ("reconstructed", TitleBrand::Rebuilt),
+    ("rebuilt", TitleBrand::Rebuilt),
+    ("salvage", TitleBrand::Salvage),
+    ("flood", TitleBrand::Flood),
+    ("water damage", TitleBrand::Flood),
+    ("lemon", TitleBrand::Lemon),
+    ("manufacturer buyback", TitleBrand::Lemon),
+    ("junk", TitleBrand::Junk),
+    ("non-repairable", TitleBrand::Junk),
+    ("nonrepairable", TitleBrand::Junk),
+    ("fire", TitleBrand::Fire),
+    ("hail", TitleBrand::Hail),
+    ("not actual mileage", TitleBrand::OdometerRollback),
+    ("odometer", TitleBrand::OdometerRollback),
+    ("clean", TitleBrand::Clean),
+    ("clear", TitleBrand::Clean),
+];
+
+/// Brand and status read from a certificate of title
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleStatus {
+    /// None when the brand field was missing or didn't match a known brand
+    pub title_brand: Option<TitleBrand>,
+    /// The brand text as the model read it
+    pub raw_brand: Option<String>,
+    pub title_number: Option<String>,
+    pub issuing_state: Option<String>,
+    pub needs_review: bool,
+}
+
+/// Lien and loan details from finance paperwork
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FinanceInfo {
+    pub lienholder: Option<String>,
+    pub loan_balance: Option<f64>,
+    /// Masked to the last four digits unless redaction is turned off
+    pub account_number: Option<String>,
+    pub account_redacted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InspectionResult {
+    Pass,
+    Fail,
+}
+
+/// Outcome of an emissions or safety inspection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InspectionRecord {
+    /// ISO date (YYYY-MM-DD)
+    pub inspection_date: Option<String>,
+    pub result: Option<InspectionResult>,
+    pub station: Option<String>,
+    pub odometer: Option<String>,
+    /// Two-letter state code
+    pub jurisdiction: Option<String>,
+    /// Printed expiration, or inspection date plus the state's interval
+    pub expires_on: Option<String>,
+    /// Passed and not yet expired as of extraction; None when we can't tell
+    pub current: Option<bool>,
+}
+
+// Months an inspection stays valid, for states where the interval is uniform
+const INSPECTION_INTERVALS: &[(&str, u32)] = &[
+    ("AZ", 24),
+    ("CA", 24),
+    ("CO", 24),
+    ("CT", 24),
+    ("DE", 24),
+    ("GA", 12),
+    ("IL", 24),
+    ("MA", 12),
+    ("MD", 24),
+    ("ME", 12),
+    ("MO", 24),
+    ("NC", 12),
+    ("NH", 12),
+    ("NJ", 24),
+    ("NY", 12),
+    ("PA", 12),
+    ("RI", 24),
+    ("TX", 12),
+    ("UT", 24),
+    ("VA", 12),
+    ("VT", 12),
+    ("WV", 12),
+];
+
+/// Collector provenance from concours and restoration documentation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConcoursRecord {
+    /// None when the documentation doesn't say either way
+    pub matching_numbers: Option<bool>,
+    pub restoration_shop: Option<String>,
+    pub awards: Vec<String>,
+    /// Engine, transmission and build-sheet details as written
+    pub build_details: Vec<String>,
+}
+
+/// Notes from a restoration shop's whiteboard or work order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShopNotes {
+    /// Everything written, line by line
+    pub notes: String,
+    pub items: Vec<LineItem>,
+}
+
+/// A part or job from shop notes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineItem {
+    pub description: String,
+    pub quantity: Option<u32>,
+    pub cost: Option<f64>,
+}
+
+/// Parse shop notes from text, or None if it doesn't read as shop notes
+#[tauri::command]
+pub async fn extract_shop_notes(text: String) -> Result<Option<ShopNotes>, NukeError> {
+    if detect_document_kind(&text) != DocumentKind::ShopNotes {
+        return Ok(None);
+    }
+    Ok(Some(ShopNotes {
+        items: parse_line_items(&text),
+        notes: text,
+    }))
+}
+
+/// Detect a specialized document layout from OCR text
+pub fn detect_document_kind(text: &str) -> DocumentKind {
+    let lower = text.to_lowercase();
+
+    // Several distinct VINs on one page is a list, whatever else it says
+    if vin::find_vins(text).len() >= 3 {
+        return DocumentKind::Inventory;
+    }
+
+    let auction_keywords = ["lot #", "lot no", "lot number", "hammer price", "buyer's premium", "auction"];
+    let auction_hits = auction_keywords.iter().filter(|k| lower.contains(*k)).count();
+    let names_house = AUCTION_HOUSES.iter().any(|(key, _)| lower.contains(key));
+    if auction_hits >= 2 || (names_house && auction_hits >= 1) {
+        return DocumentKind::Auction;
+    }
+
+    // Monroney labels and FTC buyer's guides use fixed wording
+    let sticker_keywords = [
+        "msrp",
+        "manufacturer's suggested retail price",
+        "standard equipment",
+        "optional equipment",
+        "total vehicle price",
+        "fuel economy",
+        "buyers guide",
+        "buyer's guide",
+        "as is - no dealer warranty",
+    ];
+    if sticker_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::WindowSticker;
+    }
+
+    let title_keywords = [
+        "certificate of title",
+        "title number",
+        "title no",
+        "title brand",
+        "lienholder",
+        "odometer brand",
+    ];
+    if title_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::Title;
+    }
+
+    let inspection_keywords = [
+        "emissions test",
+        "emission test",
+        "emissions inspection",
+        "smog check",
+        "smog certificate",
+        "safety inspection",
+        "vehicle inspection",
+        "inspection station",
+        "inspection date",
+        "certificate of inspection",
+        "inspector",
+    ];
+    if inspection_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::Inspection;
+    }
+
+    let concours_keywords = [
+        "concours",
+        "judging",
+        "judged",
+        "matching numbers",
+        "numbers matching",
+        "numbers-matching",
+        "best in class",
+        "best of show",
+        "build book",
+        "build sheet",
+        "restoration by",
+        "restored by",
+        "ncrs",
+        "aaca",
+        "bloomington gold",
+        "top flight",
+    ];
+    if concours_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::Concours;
+    }
+
+    let shop_keywords = [
+        "whiteboard",
+        "work order",
+        "parts list",
+        "parts:",
+        "labor",
+        "to do",
+        "todo",
+        "qty",
+        "ordered",
+        "r&r",
+        "bodywork",
+        "install",
+    ];
+    if shop_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::ShopNotes;
+    }
+
+    let finance_keywords = [
+        "payoff",
+        "loan agreement",
+        "retail installment",
+        "amount financed",
+        "finance charge",
+        "annual percentage rate",
+        "loan balance",
+        "account number",
+    ];
+    if finance_keywords.iter().filter(|k| lower.contains(*k)).count() >= 2 {
+        return DocumentKind::Finance;
+    }
+
+    let inventory_keywords = ["inventory", "stock #", "stock no", "stk #", "in stock"];
+    let names_inventory = inventory_keywords.iter().any(|k| lower.contains(k));
+    if names_inventory && vehicle_rows(text).len() >= 3 {
+        return DocumentKind::Inventory;
+    }
+
+    DocumentKind::General
+}
+
+/// Targeted prompt for a specialized document, or None to use the general prompt
+pub fn prompt_for(kind: DocumentKind) -> Option<String> {
+    match kind {
+        DocumentKind::General => None,
+        DocumentKind::Auction => Some(format!(
+            "This is auction paperwork (bill of sale, consignment or lot sheet from an auction house like Mecum or Barrett-Jackson). Return ONLY JSON with fields: {}, lot_number, auction_house, sale_date, hammer_price (number, no currency symbols). Use null for anything you can't read.",
+            BASE_FIELDS
+        )),
+        DocumentKind::Inventory => Some(format!(
+            "This is a dealer inventory sheet listing several vehicles, one per row. Read every row. Return ONLY JSON of the form {{\"vehicles\": [...], \"confidence\": 0-1}} where each vehicle has fields: {}, stock_number. Use null for cells you can't read; don't skip rows.",
+            BASE_FIELDS
+        )),
+        DocumentKind::Title => Some(format!(
+            "This is a vehicle certificate of title. Return ONLY JSON with fields: {}, title_number, issuing_state, title_brand. title_brand must be exactly one of: clean, salvage, rebuilt, flood, lemon, junk, fire, hail, odometer_rollback, or null if the brand area is missing or unreadable. Only answer clean if the title explicitly shows no brand.",
+            BASE_FIELDS
+        )),
+        DocumentKind::Finance => Some(format!(
+            "This is vehicle finance paperwork (loan agreement, retail installment contract or payoff letter). Return ONLY JSON with fields: {}, lienholder (the lender's name), loan_balance (current balance or payoff amount as a number, no currency symbols), account_number. Use null for anything you can't read.",
+            BASE_FIELDS
+        )),
+        DocumentKind::Inspection => Some(format!(
+            "This is an emissions or safety inspection certificate. Return ONLY JSON with fields: {}, inspection_date, result (exactly \"pass\" or \"fail\"), station (station name and number), odometer, inspection_state (two-letter state code), expiration_date (only if printed). Use null for anything you can't read.",
+            BASE_FIELDS
+        )),
+        DocumentKind::Concours => Some(format!(
+            "This is classic-car concours or restoration documentation (judging sheet, build book, award certificate). Return ONLY JSON with fields: {}, matching_numbers (true only if the document states the engine/drivetrain numbers match the car, false if it says they don't, null if not stated), restoration_shop, awards (list of award or class results as written, e.g. \"NCRS Top Flight 2019\"), build_details (list of engine, transmission, build-sheet or option details as written). Use null for anything you can't read and empty lists when none are listed.",
+            BASE_FIELDS
+        )),
+        DocumentKind::ShopNotes => Some(format!(
+            "This is a restoration shop whiteboard or work order with build notes, parts and costs, often handwritten. Return ONLY JSON with fields: {}, notes (all the text as written, one line per line), items (list of objects with description, quantity (number or null) and cost (number, no currency symbols, or null) for each part or job listed). Use null for anything you can't read and an empty list if nothing is itemized.",
+            BASE_FIELDS
+        )),
+        DocumentKind::WindowSticker => Some(format!(
+            "This is a new-car window sticker (Monroney label) or a used-car buyer's guide. Return ONLY JSON with fields: {}, trim, msrp (total MSRP as a number, no currency symbols), options (list of optional equipment names as printed, without prices). Use null for anything you can't read and an empty list if no options are listed.",
+            BASE_FIELDS
+        )),
+    }
+}
+
+/// Fill the kind-specific fields of ExtractedData from the model's JSON
+pub fn apply_kind_fields(kind: DocumentKind, value: &serde_json::Value, data: &mut ExtractedData) {
+    match kind {
+        DocumentKind::General => {}
+        DocumentKind::Auction => data.auction = parse_auction(value),
+        DocumentKind::Inventory => data.document_type = Some("inventory".to_string()),
+        DocumentKind::WindowSticker => data.window_sticker = parse_window_sticker(value),
+        DocumentKind::Title => data.title = Some(parse_title(value)),
+        DocumentKind::Finance => data.finance = parse_finance(value),
+        DocumentKind::Concours => data.concours = Some(parse_concours(value)),
+        DocumentKind::ShopNotes => {
+            data.document_type = Some("shop_notes".to_string());
+            data.shop_notes = Some(parse_shop_notes(value));
+        }
+        DocumentKind::Inspection => {
+            let inspection = parse_inspection(value, chrono::Local::now().date_naive());
+            if data.mileage.is_none() {
+                data.mileage = inspection.odometer.clone();
+            }
+            data.inspection = Some(inspection);
+        }
+    }
+}
+
+/// Parse inspection fields and work out whether the inspection is still current on `today`
+pub fn parse_inspection(value: &serde_json::Value, today: NaiveDate) -> InspectionRecord {
+    let inspection_date = field(value, "inspection_date").and_then(|d| dates::parse_date(&d)).map(|d| d.date);
+    let result = field(value, "result").and_then(|r| {
+        let lower = r.to_lowercase();
+        if lower.contains("fail") || lower.contains("reject") {
+            Some(InspectionResult::Fail)
+        } else if lower.contains("pass") {
+            Some(InspectionResult::Pass)
+        } else {
+            None
+        }
+    });
+    let jurisdiction = field(value, "inspection_state")
+        .map(|s| s.trim().to_uppercase())
+        .filter(|s| s.len() == 2);
+
+    // A printed expiration beats the state's usual interval
+    let expires_on = field(value, "expiration_date")
+        .and_then(|d| dates::parse_date(&d))
+        .map(|d| d.date)
+        .or_else(|| {
+            let months = INSPECTION_INTERVALS
+                .iter()
+                .find(|(state, _)| Some(*state) == jurisdiction.as_deref())?
+                .1;
+            inspection_date?.checked_add_months(Months::new(months))
+        });
+
+    let current = match (result, expires_on) {
+        (Some(InspectionResult::Fail), _) => Some(false),
+        (Some(InspectionResult::Pass), Some(expires)) => Some(today <= expires),
+        _ => None,
+    };
+
+    InspectionRecord {
+        inspection_date: inspection_date.map(|d| d.to_string()),
+        result,
+        station: field(value, "station"),
+        odometer: field(value, "odometer").map(|o| o.chars().filter(|c| c.is_ascii_digit()).collect()),
+        jurisdiction,
+        expires_on: expires_on.map(|d| d.to_string()),
+        current,
+    }
+}
+
+/// Parse concours fields; matching_numbers stays None unless the model gave a clear answer
+pub fn parse_concours(value: &serde_json::Value) -> ConcoursRecord {
+    let list = |key: &str| -> Vec<String> {
+        match value.get(key) {
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .filter_map(|i| i.as_str())
+                .map(|i| i.trim().to_string())
+                .filter(|i| !i.is_empty())
+                .collect(),
+            _ => field(value, key).into_iter().collect(),
+        }
+    };
+    let matching_numbers = match value.get("matching_numbers") {
+        Some(serde_json::Value::Bool(b)) => Some(*b),
+        _ => field(value, "matching_numbers").and_then(|m| match m.to_lowercase().as_str() {
+            "true" | "yes" | "matching" => Some(true),
+            "false" | "no" | "non-matching" | "not matching" => Some(false),
+            _ => None,
+        }),
+    };
+
+    ConcoursRecord {
+        matching_numbers,
+        restoration_shop: field(value, "restoration_shop"),
+        awards: list("awards"),
+        build_details: list("build_details"),
+    }
+}
+
+/// Parse shop notes; when the model doesn't itemize, line items are read from the notes
+pub fn parse_shop_notes(value: &serde_json::Value) -> ShopNotes {
+    let notes = match value.get("notes") {
+        Some(serde_json::Value::Array(lines)) => lines
+            .iter()
+            .filter_map(|l| l.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => field(value, "notes").unwrap_or_default(),
+    };
+    let items: Vec<LineItem> = match value.get("items") {
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| {
+                let description = field(item, "description")?;
+                Some(LineItem {
+                    description,
+                    quantity: field(item, "quantity").and_then(|q| q.trim().parse().ok()),
+                    cost: field(item, "cost").and_then(|c| parse_money(&c)),
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let items = if items.is_empty() { parse_line_items(&notes) } else { items };
+    ShopNotes { notes, items }
+}
+
+/// Lines that list a quantity or a cost: "2x valve cover gasket $38", "Paint - 4,500"
+pub fn parse_line_items(text: &str) -> Vec<LineItem> {
+    let quantity_regex = Regex::new(r"(?i)^(?:(\d{1,3})\s*(?:x|pcs|ea)\b\.?|qty:?\s*(\d{1,3}))\s*").ok();
+    let cost_regex = Regex::new(r"(?:[-:=]\s*)?\$\s*([\d,]+(?:\.\d{2})?)\s*$|\s[-:=]\s*([\d,]+(?:\.\d{2})?)\s*$").ok();
+
+    text.lines()
+        .filter_map(|line| {
+            let mut rest = line.trim().trim_start_matches(['-', '*', '•']).trim();
+            let mut quantity = None;
+            if let Some(caps) = quantity_regex.as_ref().and_then(|r| r.captures(rest)) {
+                quantity = caps.get(1).or_else(|| caps.get(2)).and_then(|q| q.as_str().parse().ok());
+                rest = &rest[caps[0].len()..];
+            }
+            let mut cost = None;
+            if let Some(caps) = cost_regex.as_ref().and_then(|r| r.captures(rest)) {
+                cost = caps.get(1).or_else(|| caps.get(2)).and_then(|c| parse_money(c.as_str()));
+                rest = &rest[..caps.get(0)?.start()];
+            }
+            let description = rest.trim().trim_end_matches([':', '-', '=']).trim();
+            if description.is_empty() || (quantity.is_none() && cost.is_none()) {
+                return None;
+            }
+            Some(LineItem {
+                description: description.to_string(),
+                quantity,
+                cost,
+            })
+        })
+        .collect()
+}
+
+/// Parse finance fields, returning None when the response has none of them
+pub fn parse_finance(value: &serde_json::Value) -> Option<FinanceInfo> {
+    let finance = FinanceInfo {
+        lienholder: field(value, "lienholder"),
+        loan_balance: field(value, "loan_balance").and_then(|b| parse_money(&b)),
+        account_number: field(value, "account_number"),
+        account_redacted: false,
+    };
+    if finance.lienholder.is_none() && finance.loan_balance.is_none() && finance.account_number.is_none() {
+        return None;
+    }
+    Some(finance)
+}
+
+/// Parse title fields; an unreadable or unknown brand is flagged, never assumed clean
+pub fn parse_title(value: &serde_json::Value) -> TitleStatus {
+    let raw_brand = field(value, "title_brand");
+    let title_brand = raw_brand.as_deref().and_then(validate_title_brand);
+    TitleStatus {
+        needs_review: title_brand.is_none(),
+        title_brand,
+        raw_brand,
+        title_number: field(value, "title_number"),
+        issuing_state: field(value, "issuing_state"),
+    }
+}
+
+/// Match brand text against the known brand list
+pub fn validate_title_brand(raw: &str) -> Option<TitleBrand> {
+    let lower = raw.trim().to_lowercase().replace('_', " ");
+    TITLE_BRANDS
+        .iter()
+        .find(|(phrase, _)| lower.contains(phrase))
+        .map(|(_, brand)| *brand)
+}
+
+/// Parse window sticker fields, returning None when the response has none of them
+pub fn parse_window_sticker(value: &serde_json::Value) -> Option<WindowSticker> {
+    let options: Vec<String> = value
+        .get("options")
+        .and_then(|o| o.as_array())
+        .map(|options| {
+            options
+                .iter()
+                .filter_map(|o| o.as_str())
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let sticker = WindowSticker {
+        msrp: field(value, "msrp").and_then(|p| parse_money(&p)),
+        trim: field(value, "trim"),
+        options,
+    };
+    if sticker.msrp.is_none() && sticker.trim.is_none() && sticker.options.is_empty() {
+        return None;
+    }
+    Some(sticker)
+}
+
+/// Parse each row of an inventory sheet response into its own record
+pub fn parse_inventory(value: &serde_json::Value) -> Vec<ExtractedData> {
+    // Models sometimes return the bare array, or name it "rows"
+    let rows = match value {
+        serde_json::Value::Array(rows) => rows,
+        _ => match value.get("vehicles").or_else(|| value.get("rows")) {
+            Some(serde_json::Value::Array(rows)) => rows,
+            _ => return Vec::new(),
+        },
+    };
+
+    rows.iter()
+        .map(|row| {
+            let mut data = extracted_from_value(row);
+            data.stock_number = field(row, "stock_number");
+            data
+        })
+        .filter(|data| {
+            data.vin.is_some() || data.year.is_some() || data.make.is_some() || data.model.is_some()
+        })
+        .collect()
+}
+
+/// OCR fallback: treat each line that names a vehicle as an inventory row
+pub fn parse_inventory_text(text: &str) -> Vec<ExtractedData> {
+    let price_regex = Regex::new(r"\$\s*([\d,]+(?:\.\d{2})?)").ok();
+    let mileage_regex = Regex::new(r"(?i)\b([\d,]{3,9})\s*(?:mi|miles|km)\b").ok();
+    let stock_regex = Regex::new(r"(?i)\b(?:stock|stk)\s*(?:#|no\.?)?\s*([A-Z0-9-]+)").ok();
+
+    vehicle_rows(text)
+        .into_iter()
+        .map(|line| {
+            let hint = crate::extract_hints_from_text(line);
+            let capture = |regex: &Option<Regex>| {
+                regex
+                    .as_ref()?
+                    .captures(line)
+                    .map(|c| c[1].replace(',', ""))
+            };
+            ExtractedData {
+                vin: vin::find_vins(line).into_iter().next(),
+                year: hint.as_ref().and_then(|h| h.year.clone()),
+                make: hint.as_ref().and_then(|h| h.make.clone()),
+                model: hint.as_ref().and_then(|h| h.model.clone()),
+                mileage: capture(&mileage_regex),
+                price: capture(&price_regex),
+                stock_number: capture(&stock_regex),
+                document_type: Some("inventory".to_string()),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Lines that carry a VIN or a model year plus a known make
+fn vehicle_rows(text: &str) -> Vec<&str> {
+    text.lines()
+        .filter(|line| {
+            if !vin::find_vins(line).is_empty() {
+                return true;
+            }
+            crate::extract_hints_from_text(line)
+                .map(|h| h.year.is_some() && h.make.is_some())
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Parse auction lot fields, returning None when the response has none of them
+pub fn parse_auction(value: &serde_json::Value) -> Option<AuctionLot> {
+    let lot = AuctionLot {
+        lot_number: field(value, "lot_number").map(|l| l.trim_start_matches('#').trim().to_string()),
+        auction_house: field(value, "auction_house").map(|h| normalize_auction_house(&h)),
+        sale_date: field(value, "sale_date"),
+        hammer_price: field(value, "hammer_price").and_then(|p| parse_money(&p)),
+    };
+
+    if lot.lot_number.is_none()
+        && lot.auction_house.is_none()
+        && lot.sale_date.is_none()
+        && lot.hammer_price.is_none()
+    {
+        return None;
+    }
+    Some(lot)
+}
+
+fn normalize_auction_house(name: &str) -> String {
+    let lower = name.to_lowercase();
+    AUCTION_HOUSES
+        .iter()
+        .find(|(key, _)| lower.contains(key))
+        .map(|(_, canonical)| canonical.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Parse "$45,000" / "45000.00" / "USD 45,000" into a number
+pub fn parse_money(s: &str) -> Option<f64> {
+    let cleaned: String = s
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    cleaned.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detects_auction_paperwork() {
+        let text = "MECUM AUCTIONS\nBill of Sale\nLot # S112\nHammer Price: $48,500";
+        assert_eq!(detect_document_kind(text), DocumentKind::Auction);
+        // One auction word alone, from a house we don't know, isn't enough
+        assert_eq!(
+            detect_document_kind("Estate auction flyer"),
+            DocumentKind::General
+        );
+    }
+
+    #[test]
+    fn parses_an_auction_response_into_lot_fields() {
+        let response = json!({
+            "vin": "124379N612345",
+            "year": 1969,
+            "make": "Chevrolet",
+            "lot_number": "#S112",
+            "auction_house": "Mecum Auctions Kissimmee",
+            "sale_date": "2024-01-13",
+            "hammer_price": "$48,500.00",
+            "confidence": 0.9,
+        });
+        let mut data = extracted_from_value(&response);
+        apply_kind_fields(DocumentKind::Auction, &response, &mut data);
+
+        let lot = data.auction.unwrap();
+        assert_eq!(lot.lot_number.as_deref(), Some("S112"));
+        assert_eq!(lot.auction_house.as_deref(), Some("Mecum"));
+        assert_eq!(lot.sale_date.as_deref(), Some("2024-01-13"));
+        assert_eq!(lot.hammer_price, Some(48500.0));
+        assert_eq!(data.year.as_deref(), Some("1969"));
+    }
+
+    #[test]
+    fn auction_lot_is_none_without_lot_fields() {
+        assert!(parse_auction(&json!({ "lot_number": null, "vin": "X" })).is_none());
+        let lot = parse_auction(&json!({ "auction_house": "Smith Family Auctions" })).unwrap();
+        assert_eq!(lot.auction_house.as_deref(), Some("Smith Family Auctions"));
+    }
+
+    #[test]
+    fn parses_money_in_printed_formats() {
+        assert_eq!(parse_money("$45,000"), Some(45000.0));
+        assert_eq!(parse_money("USD 45,000.50"), Some(45000.5));
+        assert_eq!(parse_money("n/a"), None);
+    }
+
+    #[test]
+    fn inventory_response_gives_one_record_per_vehicle_row() {
+        let response = json!({
+            "vehicles": [
+                { "year": 1972, "make": "Chevrolet", "model": "C10", "stock_number": "A12", "price": "$18,500" },
+                { "year": "1985", "make": "Ford", "model": "Bronco", "vin": "1fmeu15h5fla12345" },
+                { "year": null, "make": null, "stock_number": "A14" },
+            ],
+            "confidence": 0.8,
+        });
+        let rows = parse_inventory(&response);
+        // The unreadable third row carries nothing about a vehicle
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].stock_number.as_deref(), Some("A12"));
+        assert_eq!(rows[0].make.as_deref(), Some("Chevrolet"));
+        assert_eq!(rows[1].vin.as_deref(), Some("1FMEU15H5FLA12345"));
+        assert_eq!(rows[1].stock_number, None);
+
+        // Bare arrays and "rows" are accepted too
+        assert_eq!(parse_inventory(&json!([{ "make": "Ford" }])).len(), 1);
+        assert_eq!(
+            parse_inventory(&json!({ "rows": [{ "model": "C10" }] })).len(),
+            1
+        );
+        assert!(parse_inventory(&json!({ "vin": "1FMEU15H5FLA12345" })).is_empty());
+    }
+
+    #[test]
+    fn inventory_text_is_detected_and_split_into_rows() {
+        let text = "SMITH MOTORS INVENTORY\nStock # Year Make Model Price\n\
+                    STK 101 1972 Chevrolet C10 45,000 mi $18,500\n\
+                    STK 102 1985 Ford Bronco $12,000\n\
+                    STK 103 1999 Toyota Tacoma\nThank you";
+        assert_eq!(detect_document_kind(text), DocumentKind::Inventory);
+
+        let rows = parse_inventory_text(text);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].year.as_deref(), Some("1972"));
+        assert_eq!(rows[0].price.as_deref(), Some("18500"));
+        assert_eq!(rows[0].mileage.as_deref(), Some("45000"));
+        assert_eq!(rows[0].stock_number.as_deref(), Some("101"));
+        assert_eq!(rows[1].make.as_deref(), Some("Ford"));
+        assert_eq!(rows[2].price, None);
+        assert!(rows
+            .iter()
+            .all(|r| r.document_type.as_deref() == Some("inventory")));
+    }
+
+    #[test]
+    fn one_vehicle_on_a_stock_sheet_is_not_an_inventory() {
+        let text = "Stock # 101\n1972 Chevrolet C10\nThank you for your business";
+        assert_ne!(detect_document_kind(text), DocumentKind::Inventory);
+    }
+
+    #[test]
+    fn window_sticker_response_fills_msrp_trim_and_options() {
+        let response = json!({
+            "vin": "1FA6P8CF5L5100001",
+            "year": 2020,
+            "make": "Ford",
+            "model": "Mustang",
+            "trim": "GT Premium",
+            "msrp": "$47,305",
+            "options": ["Equipment Group 401A ", "Performance Package", "", 7],
+            "confidence": 0.9,
+        });
+        let mut data = extracted_from_value(&response);
+        apply_kind_fields(DocumentKind::WindowSticker, &response, &mut data);
+
+        let sticker = data.window_sticker.unwrap();
+        assert_eq!(sticker.msrp, Some(47305.0));
+        assert_eq!(sticker.trim.as_deref(), Some("GT Premium"));
+        assert_eq!(
+            sticker.options,
+            vec!["Equipment Group 401A", "Performance Package"]
+        );
+        assert_eq!(data.vin.as_deref(), Some("1FA6P8CF5L5100001"));
+        assert!(parse_window_sticker(&json!({ "options": [], "vin": "X" })).is_none());
+    }
+
+    #[test]
+    fn detects_monroney_labels_and_buyers_guides() {
+        let label = "STANDARD EQUIPMENT ... OPTIONAL EQUIPMENT ... TOTAL VEHICLE PRICE";
+        assert_eq!(detect_document_kind(label), DocumentKind::WindowSticker);
+        let guide = "BUYERS GUIDE\nAS IS - NO DEALER WARRANTY";
+        assert_eq!(detect_document_kind(guide), DocumentKind::WindowSticker);
+        // A price alone could be anything
+        assert_ne!(
+            detect_document_kind("MSRP $47,305"),
+            DocumentKind::WindowSticker
+        );
+    }
+
+    #[test]
+    fn salvage_title_response_captures_a_validated_brand() {
+        let response = json!({
+            "vin": "1FMEU15H5FLA12345",
+            "title_brand": "SALVAGE",
+            "title_number": "T123",
+            "issuing_state": "TX",
+            "confidence": 0.9,
+        });
+        let mut data = extracted_from_value(&response);
+        apply_kind_fields(DocumentKind::Title, &response, &mut data);
+
+        let title = data.title.as_ref().unwrap();
+        assert_eq!(title.title_brand, Some(TitleBrand::Salvage));
+        assert_eq!(title.raw_brand.as_deref(), Some("SALVAGE"));
+        assert_eq!(title.title_number.as_deref(), Some("T123"));
+        assert!(!title.needs_review);
+        // The brand rides along in the synced data
+        let synced = serde_json::to_value(&data).unwrap();
+        assert_eq!(synced["title"]["title_brand"], "salvage");
+    }
+
+    #[test]
+    fn brand_wording_maps_to_the_most_specific_brand() {
+        assert_eq!(
+            validate_title_brand("Rebuilt Salvage"),
+            Some(TitleBrand::Rebuilt)
+        );
+        assert_eq!(
+            validate_title_brand("odometer_rollback"),
+            Some(TitleBrand::OdometerRollback)
+        );
+        assert_eq!(
+            validate_title_brand(" Water Damage "),
+            Some(TitleBrand::Flood)
+        );
+        assert_eq!(validate_title_brand("smudged"), None);
+    }
+
+    #[test]
+    fn missing_or_unknown_brands_go_to_review_instead_of_clean() {
+        for response in [
+            json!({ "title_brand": null }),
+            json!({ "title_brand": "smudged" }),
+            json!({}),
+        ] {
+            let title = parse_title(&response);
+            assert!(title.needs_review);
+            assert_eq!(title.title_brand, None);
+        }
+        assert_eq!(
+            detect_document_kind("CERTIFICATE OF TITLE\nTITLE NUMBER 12345"),
+            DocumentKind::Title
+        );
+    }
+
+    fn inspection_certificate() -> serde_json::Value {
+        json!({
+            "vin": "1FMEU15H5FLA12345",
+            "inspection_date": "03/15/2024",
+            "result": "PASS",
+            "station": "Joe's Garage #A123",
+            "odometer": "84,210",
+            "inspection_state": "ny",
+        })
+    }
+
+    #[test]
+    fn inspection_response_fills_date_result_station_and_odometer() {
+        let today = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap();
+        let inspection = parse_inspection(&inspection_certificate(), today);
+        assert_eq!(inspection.inspection_date.as_deref(), Some("2024-03-15"));
+        assert_eq!(inspection.result, Some(InspectionResult::Pass));
+        assert_eq!(inspection.station.as_deref(), Some("Joe's Garage #A123"));
+        assert_eq!(inspection.odometer.as_deref(), Some("84210"));
+        assert_eq!(inspection.jurisdiction.as_deref(), Some("NY"));
+        // New York inspections run twelve months
+        assert_eq!(inspection.expires_on.as_deref(), Some("2025-03-15"));
+        assert_eq!(inspection.current, Some(true));
+
+        let mut data = extracted_from_value(&inspection_certificate());
+        apply_kind_fields(
+            DocumentKind::Inspection,
+            &inspection_certificate(),
+            &mut data,
+        );
+        assert_eq!(data.vin.as_deref(), Some("1FMEU15H5FLA12345"));
+        assert_eq!(data.mileage.as_deref(), Some("84210"));
+        assert!(data.inspection.is_some());
+    }
+
+    #[test]
+    fn inspection_currency_follows_expiry_result_and_known_states() {
+        let later = NaiveDate::from_ymd_opt(2025, 4, 1).unwrap();
+        assert_eq!(
+            parse_inspection(&inspection_certificate(), later).current,
+            Some(false)
+        );
+
+        let today = NaiveDate::from_ymd_opt(2024, 10, 1).unwrap();
+        let failed = json!({ "inspection_date": "2024-09-01", "result": "Rejected", "inspection_state": "NY" });
+        assert_eq!(parse_inspection(&failed, today).current, Some(false));
+
+        // No interval on file for the state and no printed expiration: can't tell
+        let unknown =
+            json!({ "inspection_date": "2024-03-15", "result": "pass", "inspection_state": "FL" });
+        assert_eq!(parse_inspection(&unknown, today).current, None);
+        let printed = json!({ "inspection_date": "2024-03-15", "result": "pass", "inspection_state": "FL", "expiration_date": "2024-12-31" });
+        let printed = parse_inspection(&printed, today);
+        assert_eq!(printed.expires_on.as_deref(), Some("2024-12-31"));
+        assert_eq!(printed.current, Some(true));
+
+        assert_eq!(
+            detect_document_kind("SMOG CHECK\nVehicle Inspection Report\nInspector: 12"),
+            DocumentKind::Inspection
+        );
+    }
+
+    #[test]
+    fn concours_response_fills_provenance_fields() {
+        let response = json!({
+            "vin": "194377S100001",
+            "year": 1967,
+            "make": "Chevrolet",
+            "model": "Corvette",
+            "matching_numbers": "yes",
+            "restoration_shop": "Smith Restorations",
+            "awards": ["NCRS Top Flight 2019", " Bloomington Gold ", ""],
+            "build_details": ["L71 427/435", "M21 4-speed"],
+        });
+        let mut data = extracted_from_value(&response);
+        apply_kind_fields(DocumentKind::Concours, &response, &mut data);
+
+        let concours = data.concours.as_ref().unwrap();
+        assert_eq!(concours.matching_numbers, Some(true));
+        assert_eq!(
+            concours.restoration_shop.as_deref(),
+            Some("Smith Restorations")
+        );
+        assert_eq!(
+            concours.awards,
+            vec!["NCRS Top Flight 2019", "Bloomington Gold"]
+        );
+        assert_eq!(concours.build_details, vec!["L71 427/435", "M21 4-speed"]);
+        assert_eq!(data.vin.as_deref(), Some("194377S100001"));
+        let synced = serde_json::to_value(&data).unwrap();
+        assert_eq!(synced["concours"]["matching_numbers"], true);
+    }
+
+    #[test]
+    fn matching_numbers_needs_a_clear_answer() {
+        let matching = |value: serde_json::Value| {
+            parse_concours(&json!({ "matching_numbers": value })).matching_numbers
+        };
+        assert_eq!(matching(json!(false)), Some(false));
+        assert_eq!(matching(json!("Non-matching")), Some(false));
+        assert_eq!(matching(json!(null)), None);
+        assert_eq!(matching(json!("see notes")), None);
+        // A single award given as a string is still a list
+        assert_eq!(
+            parse_concours(&json!({ "awards": "AACA Senior" })).awards,
+            vec!["AACA Senior"]
+        );
+        assert_eq!(
+            detect_document_kind("NCRS JUDGING SHEET\nTop Flight award"),
+            DocumentKind::Concours
+        );
+    }
+
+    const WHITEBOARD: &str = "1967 Camaro - WORK ORDER
+To do: finish bodywork
+- 2x valve cover gasket $38.50
+- Paint & materials - 4,500
+Qty 4 wheel bearings
+waiting on glass
+";
+
+    #[tokio::test]
+    async fn whiteboard_notes_keep_the_text_and_detected_items() {
+        assert_eq!(detect_document_kind(WHITEBOARD), DocumentKind::ShopNotes);
+        let notes = extract_shop_notes(WHITEBOARD.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(notes.notes, WHITEBOARD);
+        assert_eq!(
+            notes.items,
+            vec![
+                LineItem {
+                    description: "valve cover gasket".to_string(),
+                    quantity: Some(2),
+                    cost: Some(38.5),
+                },
+                LineItem {
+                    description: "Paint & materials".to_string(),
+                    quantity: None,
+                    cost: Some(4500.0),
+                },
+                LineItem {
+                    description: "wheel bearings".to_string(),
+                    quantity: Some(4),
+                    cost: None,
+                },
+            ]
+        );
+        assert!(extract_shop_notes("CERTIFICATE OF TITLE".to_string())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn shop_notes_response_is_itemized_from_its_notes_when_the_model_did_not() {
+        let response = json!({ "notes": ["Brakes: $300", "new carpet"], "items": [] });
+        let mut data = extracted_from_value(&response);
+        apply_kind_fields(DocumentKind::ShopNotes, &response, &mut data);
+        assert_eq!(data.document_type.as_deref(), Some("shop_notes"));
+        let notes = data.shop_notes.unwrap();
+        assert_eq!(notes.notes, "Brakes: $300\nnew carpet");
+        assert_eq!(notes.items.len(), 1);
+        assert_eq!(notes.items[0].description, "Brakes");
+        assert_eq!(notes.items[0].cost, Some(300.0));
+
+        // Items the model listed are kept as given
+        let itemized = parse_shop_notes(&json!({
+            "notes": "parts list",
+            "items": [{ "description": "Carb rebuild kit", "quantity": "1", "cost": "$64.99" }, { "cost": 5 }],
+        }));
+        assert_eq!(itemized.items.len(), 1);
+        assert_eq!(itemized.items[0].quantity, Some(1));
+        assert_eq!(itemized.items[0].cost, Some(64.99));
+    }
+}
@@ -0,0 +1,1654 @@
+// Document processing - extract structured vehicle data with local models
+
+use crate::bilingual;
+use crate::cache::{self, ProcessedStore, ResponseCache};
+use crate::condition::Condition;
+use crate::dates;
+use crate::details::{self, DetailKind, DetailShot};
+use crate::error::NukeError;
+use crate::events::{self, AppEvent};
+use crate::doctypes::{
+    self, AuctionLot, ConcoursRecord, DocumentKind, FinanceInfo, InspectionRecord, ShopNotes, TitleStatus,
+    WindowSticker,
+};
+use crate::plates::{self, PlateType};
+use crate::vin::{self, PartialVin, VinSource, VinSourceKind, VinVerification};
+use crate::imaging::{self, CollageLayout, ImageKind, ScanCorrection, StitchedScan};
+use crate::modeljson;
+use crate::privacy;
+use crate::progress::{self, Stage};
+use crate::ocr::{self, OcrOutput};
+use crate::ollama::{self, Generation, OllamaOptions, TokenSink};
+use crate::AppState;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+// Bump when prompts change so cached extractions are redone
+pub const PROMPT_VERSION: &str = "17";
+
+// OCR thresholds for treating an image as a text document
+const TEXT_DENSE_MIN_WORDS: usize = 40;
+const TEXT_DENSE_MIN_CONFIDENCE: f32 = 60.0;
+// Above this, the image is visually busy enough to need a vision model
+const PHOTO_MIN_COMPLEXITY: f32 = 0.6;
+
+/// Re-prompts for JSON after an unparseable answer, unless `set_json_retries` changes it
+pub const DEFAULT_JSON_RETRIES: u32 = 2;
+
+// Documents `process_documents` works on at once. Ollama runs one generation at a time on the
+// GPU, so more than a few only queues up; two or three still hide file reads, OCR and resizing.
+const DEFAULT_CONCURRENCY: usize = 2;
+const MAX_CONCURRENCY: usize = 4;
+
+const DOCUMENT_PROMPT: &str = "Analyze this vehicle-related image. If it shows a vehicle, identify it. If it's a document (title, registration, receipt, bill of sale), read it. Return ONLY JSON with fields: vin, year, make, model, color, mileage, owner_name, plate_number, document_type, date, price, confidence (0-1). Use null for anything you can't read.";
+
+const PHOTO_PROMPT: &str = "This is a photo of a vehicle or part of one. Identify the year, make, model and color, and read any visible license plate, VIN plate or odometer. If only part of the VIN is visible, write ? for each hidden character. Also say what the shot shows: shot_type is one of exterior, interior, tire, engine_bay, build_tag, glass_etch, odometer, other. For a tire, give dot_code (the full DOT code from the sidewall). For an engine bay or frame, give stamped_numbers (list of stamped VINs or part numbers exactly as stamped). For a cowl tag, trim tag or door data plate, give build_tag (all text on the tag). For a VIN etched into a window, give etched_vin. Return ONLY JSON with fields: vin, year, make, model, color, mileage, plate_number, shot_type, dot_code, stamped_numbers, build_tag, etched_vin, confidence (0-1). Use null for anything you can't determine.";
+
+const HANDWRITING_PROMPT: &str = "This is a handwritten vehicle document (bill of sale, receipt or note), often from a private sale. Read the handwriting carefully; VINs and amounts may be written with ambiguous characters, so only report what you can read. Return ONLY JSON with fields: vin, year, make, model, color, mileage, owner_name, plate_number, document_type, date, price, confidence (0-1). Use null for anything you can't read.";
+
+// Handwritten extractions are never reported above this confidence
+const HANDWRITING_MAX_CONFIDENCE: f32 = 0.6;
+
+const TEXT_DOCUMENT_PROMPT: &str = "The following text was read by OCR from a vehicle document (title, registration, receipt, bill of sale). Extract the vehicle information. Return ONLY JSON with fields: vin, year, make, model, color, mileage, owner_name, plate_number, document_type, date, price, confidence (0-1). Use null for anything not present.";
+
+/// Vehicle fields extracted from a document or photo
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtractedData {
+    pub vin: Option<String>,
+    /// Part of a VIN when the rest was hidden or unreadable
+    pub partial_vin: Option<PartialVin>,
+    pub year: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub color: Option<String>,
+    pub mileage: Option<String>,
+    pub owner_name: Option<String>,
+    pub plate_number: Option<String>,
+    pub plate_type: Option<PlateType>,
+    pub document_type: Option<String>,
+    pub date: Option<String>,
+    /// Whether `date` has a time of day
+    pub date_precision: Option<dates::DatePrecision>,
+    pub price: Option<String>,
+    pub auction: Option<AuctionLot>,
+    /// Dealer stock number, from inventory sheets
+    pub stock_number: Option<String>,
+    pub window_sticker: Option<WindowSticker>,
+    pub title: Option<TitleStatus>,
+    pub finance: Option<FinanceInfo>,
+    pub inspection: Option<InspectionRecord>,
+    pub concours: Option<ConcoursRecord>,
+    pub shop_notes: Option<ShopNotes>,
+    /// French values from bilingual documents, by canonical field
+    pub french_originals: BTreeMap<String, String>,
+    /// Tire, engine-bay and build-tag specifics from detail photos
+    pub detail: Option<DetailShot>,
+}
+
+impl ExtractedData {
+    /// Take every field this record is missing from `other`
+    pub fn fill_missing(&mut self, other: &ExtractedData) {
+        fn fill<T: Clone>(field: &mut Option<T>, other: &Option<T>) {
+            if field.is_none() {
+                *field = other.clone();
+            }
+        }
+        fill(&mut self.vin, &other.vin);
+        fill(&mut self.partial_vin, &other.partial_vin);
+        fill(&mut self.year, &other.year);
+        fill(&mut self.make, &other.make);
+        fill(&mut self.model, &other.model);
+        fill(&mut self.color, &other.color);
+        fill(&mut self.mileage, &other.mileage);
+        fill(&mut self.owner_name, &other.owner_name);
+        fill(&mut self.plate_number, &other.plate_number);
+        fill(&mut self.plate_type, &other.plate_type);
+        fill(&mut self.document_type, &other.document_type);
+        if self.date.is_none() {
+            self.date = other.date.clone();
+            self.date_precision = other.date_precision;
+        }
+        fill(&mut self.price, &other.price);
+        fill(&mut self.auction, &other.auction);
+        fill(&mut self.stock_number, &other.stock_number);
+        fill(&mut self.window_sticker, &other.window_sticker);
+        fill(&mut self.title, &other.title);
+        fill(&mut self.finance, &other.finance);
+        fill(&mut self.inspection, &other.inspection);
+        fill(&mut self.concours, &other.concours);
+        fill(&mut self.shop_notes, &other.shop_notes);
+        fill(&mut self.detail, &other.detail);
+        for (field, original) in &other.french_originals {
+            self.french_originals
+                .entry(field.clone())
+                .or_insert_with(|| original.clone());
+        }
+    }
+}
+
+/// Which model family a document was sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessingRoute {
+    Vision,
+    Text,
+    /// No model involved; regex extraction over document text
+    Offline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionResult {
+    pub path: String,
+    pub model: String,
+    pub route: ProcessingRoute,
+    /// Pre-classification that picked the prompt family
+    pub image_kind: ImageKind,
+    /// Set when the photo is a grid of several photos; split it with `split_collage`
+    #[serde(default)]
+    pub collage: Option<CollageLayout>,
+    /// The image was a fax or low-DPI scan and went through the enhancement path
+    #[serde(default)]
+    pub enhanced: bool,
+    /// Negative or mirrored scans that were corrected before extraction
+    #[serde(default)]
+    pub corrections: Vec<ScanCorrection>,
+    /// Predominantly handwritten; read with the handwriting prompt and capped confidence
+    #[serde(default)]
+    pub handwritten: bool,
+    #[serde(default)]
+    pub document_kind: DocumentKind,
+    pub data: ExtractedData,
+    /// One record per row when the document is an inventory sheet
+    #[serde(default)]
+    pub inventory: Vec<ExtractedData>,
+    pub confidence: f32,
+    pub raw_response: String,
+    #[serde(default)]
+    pub vin_verification: Option<VinVerification>,
+    /// Position within the vehicle's photo set, set by `order_photo_set`
+    #[serde(default)]
+    pub order_index: Option<usize>,
+    /// Model-estimated condition, set from `assess_condition`
+    #[serde(default)]
+    pub condition: Option<Condition>,
+    /// Raw OCR text and word confidences, set from `attach_ocr`
+    #[serde(default)]
+    pub ocr: Option<OcrOutput>,
+    /// Lower-quality copies of this document, set from `dedupe_scans`
+    #[serde(default)]
+    pub duplicate_scans: Vec<String>,
+    /// Section scans read as this one document, top to bottom, when `process_documents`
+    /// stitched a split scan; `path` is the top section
+    #[serde(default)]
+    pub stitched_sections: Vec<String>,
+    /// Some seam of the stitch matched weakly; check the sections belong together
+    #[serde(default)]
+    pub stitch_needs_review: bool,
+    /// Width / height of the source image
+    #[serde(default)]
+    pub aspect_ratio: Option<f32>,
+    #[serde(default)]
+    pub cached: bool,
+    /// Model calls it took to get JSON back, counting re-prompts; 0 when no model was used
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// Model output after JSON parsing
+#[derive(Debug, Default)]
+struct ParsedResponse {
+    data: ExtractedData,
+    rows: Vec<ExtractedData>,
+    confidence: f32,
+}
+
+/// Extract vehicle data from a document image, routing text-dense scans to a text model.
+/// With `stream` the model's output arrives token by token as `ollama-token` events.
+/// `model` must accept images; a text-only one fails with `NonVisionModel` up front.
+#[tauri::command]
+pub async fn process_document(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+    stream: Option<bool>,
+    options: Option<OllamaOptions>,
+) -> Result<ExtractionResult, NukeError> {
+    ollama::require_vision(&state.ollama_client(), ollama::OLLAMA_URL, &model).await?;
+    let emitter = ollama::token_emitter(&app, &path);
+    let call = ModelCall {
+        options: &options.unwrap_or_default(),
+        on_token: match stream {
+            Some(true) => Some(&emitter),
+            _ => None,
+        },
+    };
+    process_document_cached(&state, None, path.clone(), model, text_model, call).await
+}
+
+/// Process a list of documents, serving unchanged files from the processed store. Up to
+/// `concurrency` documents (default 2, at most MAX_CONCURRENCY) are in flight at once;
+/// results come back in input order. A document that fails is reported as an error event
+/// and left out; the batch fails only when no document could be processed. Consecutive
+/// overlapping scans of one document are stitched first and read as that document.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(files = paths.len(), model = %model))]
+pub async fn process_documents(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    model: String,
+    text_model: Option<String>,
+    stream: Option<bool>,
+    options: Option<OllamaOptions>,
+    concurrency: Option<usize>,
+) -> Result<Vec<ExtractionResult>, NukeError> {
+    let client = app.state::<AppState>().ollama_client();
+    ollama::require_vision(&client, ollama::OLLAMA_URL, &model).await?;
+    // Stitched images live until the batch is done with them
+    let stitch_dir = tempfile::Builder::new()
+        .prefix("nuke-stitch-")
+        .tempdir()
+        .map_err(|e| NukeError::FileWrite(format!("Failed to create stitch folder: {}", e)))?;
+    let output_dir = stitch_dir.path().to_path_buf();
+    let stitched = imaging::stitch_batch_sections(paths.clone(), output_dir).await?;
+    let started = std::time::Instant::now();
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .clamp(1, MAX_CONCURRENCY);
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let options = Arc::new(options.unwrap_or_default());
+    let stream = stream.unwrap_or(false);
+
+    let tasks: Vec<_> = stitch_inputs(paths, stitched)
+        .into_iter()
+        .map(|(path, stitch)| {
+            let (app, permits, options) = (app.clone(), permits.clone(), options.clone());
+            let (model, text_model) = (model.clone(), text_model.clone());
+            let task_path = path.clone();
+            let task = tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let state = app.state::<AppState>();
+                let result = {
+                    let emitter = ollama::token_emitter(&app, &task_path);
+                    let call = ModelCall {
+                        options: &options,
+                        on_token: if stream { Some(&emitter) } else { None },
+                    };
+                    // Batches wait for a busy Ollama server instead of piling on
+                    let path = task_path.clone();
+                    process_document_cached(&state, Some(&app), path, model, text_model, call).await
+                };
+                let stage = if result.is_ok() {
+                    Stage::Processed
+                } else {
+                    Stage::Failed
+                };
+                progress::advance(Some(&app), &state.progress, stage, 1);
+                match stitch {
+                    Some(scan) => result.map(|result| from_sections(result, scan)),
+                    None => result,
+                }
+            });
+            (path, task)
+        })
+        .collect();
+
+    let files = tasks.len();
+    let results = collect_documents(tasks).await?;
+    tracing::info!(
+        processed = results.len(),
+        cached = results.iter().filter(|r| r.cached).count(),
+        failed = files - results.len(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "document batch finished"
+    );
+    Ok(results)
+}
+
+/// What a batch extracts, in order: each stitched scan in place of its top section, and its
+/// other sections dropped
+fn stitch_inputs(
+    paths: Vec<String>,
+    stitched: Vec<StitchedScan>,
+) -> Vec<(String, Option<StitchedScan>)> {
+    let lower_sections: HashSet<String> = stitched
+        .iter()
+        .flat_map(|scan| scan.sources.iter().skip(1).cloned())
+        .collect();
+    let mut by_top: HashMap<String, StitchedScan> = stitched
+        .into_iter()
+        .filter_map(|scan| Some((scan.sources.first()?.clone(), scan)))
+        .collect();
+    paths
+        .into_iter()
+        .filter(|path| !lower_sections.contains(path))
+        .map(|path| match by_top.remove(&path) {
+            Some(scan) => (scan.path.clone(), Some(scan)),
+            None => (path, None),
+        })
+        .collect()
+}
+
+/// A stitched image's result, reported against the sections it was stitched from
+fn from_sections(result: ExtractionResult, scan: StitchedScan) -> ExtractionResult {
+    ExtractionResult {
+        path: scan.sources[0].clone(),
+        stitched_sections: scan.sources,
+        stitch_needs_review: scan.needs_review,
+        ..result
+    }
+}
+
+/// Await each document's task in spawn order, so results stay in input order whatever
+/// order they finish in. Failed documents are left out; with no result at all, the first
+/// failure is returned instead.
+async fn collect_documents(
+    tasks: Vec<(String, JoinHandle<Result<ExtractionResult, NukeError>>)>,
+) -> Result<Vec<ExtractionResult>, NukeError> {
+    let mut results = Vec::new();
+    let mut first_error = None;
+    for (path, task) in tasks {
+        let error = match task.await {
+            Ok(Ok(result)) => {
+                results.push(result);
+                continue;
+            }
+            Ok(Err(e)) => e,
+            Err(e) => {
+                tracing::warn!(%path, error = %e, "document task failed");
+                NukeError::Other(format!("{}: document task failed: {}", path, e))
+            }
+        };
+        first_error.get_or_insert(error);
+    }
+    match first_error {
+        Some(error) if results.is_empty() => Err(error),
+        _ => Ok(results),
+    }
+}
+
+/// Extract every vehicle row from a dealer inventory sheet (photo or printout)
+#[tauri::command]
+pub async fn extract_inventory(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    model: String,
+) -> Result<Vec<ExtractedData>, NukeError> {
+    let call = ModelCall {
+        options: &OllamaOptions::default(),
+        on_token: None,
+    };
+    let result = process_document_cached(&state, None, path, model, None, call).await?;
+    if result.document_kind != DocumentKind::Inventory {
+        return Err(NukeError::InvalidInput("Document doesn't look like an inventory sheet".to_string()));
+    }
+    Ok(result.inventory)
+}
+
+/// Processed-store key for a file under the current models, options and prompt version
+fn cache_key(
+    path: &str,
+    model: &str,
+    text_model: Option<&str>,
+    options: &OllamaOptions,
+) -> Result<String, String> {
+    let content_hash = cache::hash_file(Path::new(path))?;
+    let model_key = match text_model {
+        Some(text_model) => format!("{}+{}", model, text_model),
+        None => model.to_string(),
+    };
+    let options = serde_json::to_string(options).map_err(|e| e.to_string())?;
+    let model_key = format!("{}+{}", model_key, options);
+    Ok(ProcessedStore::key(&content_hash, &model_key, PROMPT_VERSION))
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FolderRun {
+    pub processed: usize,
+    /// Already in the processed store under these models and prompts
+    pub skipped: usize,
+    /// Left for the next run, after a cancel or the `max_files` limit
+    pub remaining: usize,
+    pub failed: usize,
+    pub errors: Vec<String>,
+}
+
+/// Work through a folder's images, skipping what's already processed; safe to stop and re-run
+#[tauri::command]
+pub async fn process_folder_resumable(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+    max_files: Option<usize>,
+) -> Result<FolderRun, NukeError> {
+    state.cancel_processing.store(false, Ordering::SeqCst);
+
+    let mut files: Vec<String> = walkdir::WalkDir::new(&path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let extension = e
+                .path()
+                .extension()
+                .map(|x| x.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            crate::IMAGE_EXTENSIONS.contains(&extension.as_str())
+        })
+        .map(|e| e.path().to_string_lossy().to_string())
+        .collect();
+    // Same order every run, so progress is predictable
+    files.sort();
+
+    let options = OllamaOptions::default();
+    let key_for = |file: &str| {
+        let key = cache_key(file, &model, text_model.as_deref(), &options);
+        match &key {
+            Ok(_) => progress::advance(Some(&app), &state.progress, Stage::Hashed, 1),
+            Err(e) => file_failed(&state, Some(&app), file, e),
+        }
+        key
+    };
+    // Each result is persisted as it lands, so an interrupted run loses at most one file
+    let extract = |key: String, file: String| {
+        let (state, app) = (&state, &app);
+        let (model, text_model, options) = (model.clone(), text_model.clone(), &options);
+        async move {
+            let call = ModelCall {
+                options,
+                on_token: None,
+            };
+            let extracted =
+                extract_and_store(state, Some(app), key, file, model, text_model, call).await;
+            let stage = if extracted.is_ok() { Stage::Processed } else { Stage::Failed };
+            progress::advance(Some(app), &state.progress, stage, 1);
+            extracted.map(|_| ())
+        }
+    };
+
+    Ok(resume_folder(
+        files,
+        &state.processed,
+        &state.cancel_processing,
+        max_files,
+        key_for,
+        extract,
+    )
+    .await)
+}
+
+/// The loop behind `process_folder_resumable`: files whose key is already in `processed` are
+/// skipped, and once `cancel` is set or `max_files` have been tried the rest are left for the
+/// next run. `extract` runs one file and stores its result under the key.
+async fn resume_folder<F, Fut>(
+    files: Vec<String>,
+    processed: &Mutex<ProcessedStore>,
+    cancel: &AtomicBool,
+    max_files: Option<usize>,
+    key_for: impl Fn(&str) -> Result<String, String>,
+    mut extract: F,
+) -> FolderRun
+where
+    F: FnMut(String, String) -> Fut,
+    Fut: Future<Output = Result<(), NukeError>>,
+{
+    let mut run = FolderRun::default();
+    for file in files {
+        let key = match key_for(&file) {
+            Ok(key) => key,
+            Err(e) => {
+                run.failed += 1;
+                run.errors.push(format!("{}: {}", file, e));
+                continue;
+            }
+        };
+        if processed.lock().unwrap().get(&key).is_some() {
+            run.skipped += 1;
+            continue;
+        }
+
+        let stopped = cancel.load(Ordering::SeqCst)
+            || max_files.map(|max| run.processed + run.failed >= max).unwrap_or(false);
+        if stopped {
+            run.remaining += 1;
+            continue;
+        }
+
+        match extract(key, file.clone()).await {
+            Ok(()) => run.processed += 1,
+            Err(e) => {
+                run.failed += 1;
+                run.errors.push(format!("{}: {}", file, e));
+            }
+        }
+    }
+    run
+}
+
+/// Ask a running `process_folder_resumable` to stop after the current file
+#[tauri::command]
+pub async fn cancel_processing(state: tauri::State<'_, AppState>) -> Result<(), NukeError> {
+    state.cancel_processing.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Set how many times a model is re-prompted when its answer has no JSON; 0 turns retries off
+#[tauri::command]
+pub async fn set_json_retries(state: tauri::State<'_, AppState>, retries: u32) -> Result<(), NukeError> {
+    state.json_retries.store(retries, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Look up the processed store before running extraction, and record new results in it
+#[tracing::instrument(skip_all, fields(path = %path, model = %model))]
+async fn process_document_cached(
+    state: &AppState,
+    app: Option<&tauri::AppHandle>,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+    call: ModelCall<'_>,
+) -> Result<ExtractionResult, NukeError> {
+    let key = match cache_key(&path, &model, text_model.as_deref(), call.options) {
+        Ok(key) => key,
+        Err(e) => {
+            tracing::warn!(error = %e, "can't read document");
+            file_failed(state, app, &path, &e);
+            return Err(NukeError::FileRead(e));
+        }
+    };
+    progress::advance(app, &state.progress, Stage::Hashed, 1);
+
+    let hit = state.processed.lock().unwrap().get(&key).cloned();
+    if let Some(hit) = hit {
+        // Same bytes may have moved; report the path the caller asked about
+        let mut hit = ExtractionResult {
+            path,
+            cached: true,
+            ..hit
+        };
+        privacy::redact_result(&state.privacy.lock().unwrap(), &mut hit);
+        tracing::debug!("served from the processed store");
+        events::emit(app, &state.events, AppEvent::FileProcessed {
+            path: hit.path.clone(),
+            cached: true,
+        });
+        return Ok(hit);
+    }
+
+    extract_and_store(state, app, key, path, model, text_model, call).await
+}
+
+/// Run extraction and record the result in the processed store under `key`
+async fn extract_and_store(
+    state: &AppState,
+    app: Option<&tauri::AppHandle>,
+    key: String,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+    call: ModelCall<'_>,
+) -> Result<ExtractionResult, NukeError> {
+    let client = state.ollama_client();
+    let started = std::time::Instant::now();
+    let extracted = async {
+        if let Some(app) = app {
+            // Our own text model being loaded isn't another workload
+            let models: Vec<&str> = std::iter::once(model.as_str())
+                .chain(text_model.as_deref())
+                .collect();
+            ollama::wait_for_capacity(app, &client, &models).await?;
+        }
+        let extractor = Extractor {
+            client: &client,
+            responses: &state.responses,
+            max_dimension: state.max_image_dimension.load(Ordering::Relaxed),
+            json_retries: state.json_retries.load(Ordering::Relaxed),
+            call,
+        };
+        let result = extract_document(&extractor, path.clone(), model, text_model).await?;
+        state
+            .processed
+            .lock()
+            .unwrap()
+            .insert(key, result.clone())
+            .map_err(NukeError::FileWrite)?;
+        Ok::<_, NukeError>(result)
+    }
+    .await;
+
+    match extracted {
+        Ok(mut result) => {
+            tracing::info!(
+                kind = ?result.document_kind,
+                confidence = result.confidence,
+                has_vin = result.data.vin.is_some(),
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "document extracted"
+            );
+            // The local store keeps the full record; what leaves this module honors privacy settings
+            privacy::redact_result(&state.privacy.lock().unwrap(), &mut result);
+            events::emit(app, &state.events, AppEvent::FileProcessed {
+                path: result.path.clone(),
+                cached: false,
+            });
+            Ok(result)
+        }
+        Err(e) => {
+            tracing::warn!(
+                error = %e,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "extraction failed"
+            );
+            file_failed(state, app, &path, &e.to_string());
+            Err(e)
+        }
+    }
+}
+
+fn file_failed(state: &AppState, app: Option<&tauri::AppHandle>, path: &str, message: &str) {
+    events::emit(app, &state.events, AppEvent::Error {
+        operation: "process_document".to_string(),
+        path: Some(path.to_string()),
+        message: message.to_string(),
+    });
+}
+
+/// How each model call is made: sampling options, and where streamed tokens go
+#[derive(Clone, Copy)]
+struct ModelCall<'a> {
+    options: &'a OllamaOptions,
+    on_token: Option<&'a TokenSink<'a>>,
+}
+
+/// What extraction needs besides the file and the models
+struct Extractor<'a> {
+    client: &'a ollama::OllamaClient,
+    responses: &'a ResponseCache,
+    /// Longest image edge sent to the model
+    max_dimension: u32,
+    /// Re-prompts allowed when the model's answer has no JSON in it
+    json_retries: u32,
+    call: ModelCall<'a>,
+}
+
+#[tracing::instrument(skip_all, fields(text_model = ?text_model))]
+async fn extract_document(
+    extractor: &Extractor<'_>,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+) -> Result<ExtractionResult, NukeError> {
+    let is_pdf = Path::new(&path)
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+
+    // iPhone HEIC photos are unreadable to OCR, the image crate and Ollama: work from a JPEG copy
+    if imaging::is_heif(Path::new(&path)) {
+        let jpeg = imaging::convert_heif(Path::new(&path)).await.map_err(NukeError::ImageDecode)?;
+        let jpeg_path = jpeg.to_string_lossy().to_string();
+        let result = extract_image(extractor, jpeg_path, model, text_model).await;
+        let _ = std::fs::remove_file(&jpeg);
+        return result.map(|result| ExtractionResult { path, ..result });
+    }
+    if !is_pdf {
+        return extract_image(extractor, path, model, text_model).await;
+    }
+
+    // Vision models read images, not PDF bytes: render each page and extract from those
+    let pages = imaging::render_pdf_pages(Path::new(&path)).await.map_err(NukeError::ImageDecode)?;
+    let mut page_results = Vec::new();
+    let mut errors = Vec::new();
+    for page in &pages {
+        let page_path = page.to_string_lossy().to_string();
+        match extract_image(extractor, page_path, model.clone(), text_model.clone()).await {
+            Ok(result) => page_results.push(result),
+            Err(e) => {
+                tracing::debug!(page = %page.display(), error = %e, "PDF page failed");
+                errors.push(e);
+            }
+        }
+    }
+    if let Some(dir) = pages.first().and_then(|p| p.parent()) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    match (merge_pages(page_results), errors.first()) {
+        (Some(merged), _) => Ok(ExtractionResult { path, ..merged }),
+        (None, Some(NukeError::OllamaUnreachable)) => Err(NukeError::OllamaUnreachable),
+        (None, _) => Err(NukeError::Other(format!(
+            "No page of the PDF could be read: {}",
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; ")
+        ))),
+    }
+}
+
+/// Combine a PDF's per-page results: the page with the most trustworthy VIN leads, and
+/// the other pages fill in what it's missing, most confident first
+pub fn merge_pages(mut pages: Vec<ExtractionResult>) -> Option<ExtractionResult> {
+    let vin_rank = |r: &ExtractionResult| {
+        let confirmed = r
+            .vin_verification
+            .as_ref()
+            .map(|v| v.status == vin::VinStatus::Confirmed)
+            .unwrap_or(false);
+        let valid = r.data.vin.as_deref().map(vin::validate_vin).unwrap_or(false);
+        (r.data.vin.is_some(), confirmed, valid)
+    };
+    pages.sort_by(|a, b| {
+        vin_rank(b)
+            .cmp(&vin_rank(a))
+            .then(b.confidence.total_cmp(&a.confidence))
+    });
+
+    let mut pages = pages.into_iter();
+    let mut merged = pages.next()?;
+    let mut rest: Vec<ExtractionResult> = pages.collect();
+    rest.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    for page in rest {
+        merged.data.fill_missing(&page.data);
+        merged.inventory.extend(page.inventory);
+        merged.handwritten |= page.handwritten;
+        merged.enhanced |= page.enhanced;
+        merged.raw_response = format!("{}\n\n{}", merged.raw_response, page.raw_response);
+    }
+    merged.image_kind = ImageKind::Document;
+    merged.collage = None;
+    merged.aspect_ratio = None;
+    Some(merged)
+}
+
+/// Extract from one image (or one rendered PDF page)
+async fn extract_image(
+    extractor: &Extractor<'_>,
+    path: String,
+    model: String,
+    text_model: Option<String>,
+) -> Result<ExtractionResult, NukeError> {
+    let original_path = Path::new(&path);
+
+    // Faxes and low-DPI scans are cleaned up first; OCR and the model see the enhanced copy
+    let enhanced_path = imaging::enhance_if_low_dpi(original_path);
+    let enhanced = enhanced_path.is_some();
+    let scan_path = enhanced_path.as_deref().unwrap_or(original_path);
+
+    // Cheap OCR pass first; a missing tesseract just means we always use vision
+    let ocr_output = ocr::run_ocr(scan_path).await.ok();
+
+    // Negative and mirrored scans are unreadable until flipped back
+    let corrected = imaging::correct_scan(scan_path, ocr_output).await;
+    let ocr_output = corrected.ocr;
+    let temp_files: Vec<PathBuf> = enhanced_path.iter().chain(&corrected.path).cloned().collect();
+    let file_path = corrected.path.as_deref().unwrap_or(scan_path);
+    let grayscale = imaging::load_grayscale(file_path);
+    let complexity = grayscale.as_ref().map(imaging::visual_complexity);
+
+    // Handwriting defeats OCR, so OCR word counts can't classify it
+    let handwritten = imaging::is_handwritten(grayscale.as_ref(), ocr_output.as_ref());
+
+    // Receipt strips and panoramas read badly through a vehicle prompt
+    let aspect_ratio = imaging::aspect_ratio(original_path);
+    let strip = aspect_ratio
+        .map(|r| !imaging::AspectRange::default().contains(r))
+        .unwrap_or(false);
+
+    // Paper documents and car photos need different prompts
+    let image_kind = if handwritten || strip {
+        ImageKind::Document
+    } else {
+        imaging::classify_image(
+            grayscale.as_ref(),
+            ocr_output.as_ref().map(|o| o.word_count).unwrap_or(0),
+        )
+    };
+
+    // Collages confuse the vision model; flag them so they can be split
+    let collage = match image_kind {
+        ImageKind::Photo => imaging::detect_collage(file_path),
+        ImageKind::Document => None,
+    };
+
+    let route = route_for(
+        text_model.as_deref(),
+        image_kind,
+        handwritten,
+        strip,
+        ocr_output.as_ref(),
+        complexity,
+    );
+
+    // Recognizable layouts (auction paperwork, ...) get a targeted prompt
+    let document_kind = match (image_kind, &ocr_output) {
+        (ImageKind::Document, Some(o)) => doctypes::detect_document_kind(&o.text),
+        _ => DocumentKind::General,
+    };
+    let targeted_prompt = doctypes::prompt_for(document_kind);
+    let ocr_vins = ocr_output
+        .as_ref()
+        .map(|o| vin::find_vins(&o.text))
+        .unwrap_or_default();
+    let ocr_text = ocr_output.as_ref().map(|o| o.text.clone()).unwrap_or_default();
+    let language_hint = if bilingual::is_bilingual(&ocr_text) {
+        bilingual::PROMPT_HINT
+    } else {
+        ""
+    };
+
+    let request = match (route, text_model, ocr_output) {
+        (ProcessingRoute::Text, Some(text_model), Some(ocr_output)) => {
+            let prompt = match &targeted_prompt {
+                Some(p) => format!("{}{}\n\nText read by OCR:\n{}", p, language_hint, ocr_output.text),
+                None => format!("{}{}\n\nText:\n{}", TEXT_DOCUMENT_PROMPT, language_hint, ocr_output.text),
+            };
+            Ok((text_model, prompt, Vec::new()))
+        }
+        _ => match std::fs::read(file_path) {
+            Ok(image_data) => {
+                let image_data = imaging::prepare_for_model(&image_data, extractor.max_dimension);
+                let base64_image = ollama::encode_image_b64(&image_data);
+                let prompt = vision_prompt(image_kind, handwritten, targeted_prompt.as_deref());
+                let prompt = format!("{}{}", prompt, language_hint);
+                Ok((model, prompt, vec![base64_image]))
+            }
+            Err(e) => Err(NukeError::FileRead(format!("Failed to read image: {}", e))),
+        },
+    };
+    let generated = async {
+        let (used_model, prompt, images) = request?;
+        let (client, responses, on_token) = (extractor.client, extractor.responses, extractor.call.on_token);
+        let generation = Generation {
+            model: &used_model,
+            prompt: &prompt,
+            images: images.clone(),
+            options: extractor.call.options,
+        };
+        let mut raw_response = ollama::generate_cached(client, responses, generation, on_token).await?;
+        let mut parsed = parse_response(&raw_response, document_kind);
+        let mut attempts = 1;
+        // Models sometimes answer in prose; ask again, more firmly. Each retry's prompt differs,
+        // so the response cache doesn't hand back the answer that just failed.
+        while parsed.is_none() && attempts <= extractor.json_retries {
+            let strict_prompt = format!("{}{}", prompt, strict_json_suffix(attempts));
+            let generation = Generation {
+                model: &used_model,
+                prompt: &strict_prompt,
+                images: images.clone(),
+                options: extractor.call.options,
+            };
+            raw_response = ollama::generate_cached(client, responses, generation, on_token).await?;
+            parsed = parse_response(&raw_response, document_kind);
+            attempts += 1;
+        }
+        Ok::<_, NukeError>((used_model, raw_response, parsed, attempts))
+    }
+    .await;
+
+    // Enhanced and corrected copies are only needed for OCR and the model call
+    for temp_file in &temp_files {
+        let _ = std::fs::remove_file(temp_file);
+    }
+    let (used_model, mut raw_response, parsed, attempts) = generated?;
+
+    let mut parsed = parsed.unwrap_or_else(|| ParsedResponse {
+        confidence: 0.3,
+        ..Default::default()
+    });
+
+    if handwritten {
+        parsed.confidence = parsed.confidence.min(HANDWRITING_MAX_CONFIDENCE);
+    }
+
+    // Fall back to OCR line parsing when the model drops the rows
+    if document_kind == DocumentKind::Inventory && parsed.rows.is_empty() {
+        parsed.rows = doctypes::parse_inventory_text(&ocr_text);
+    }
+    // Whiteboards the model couldn't transcribe keep whatever OCR read
+    if let Some(notes) = parsed.data.shop_notes.as_mut().filter(|n| n.notes.trim().is_empty()) {
+        notes.items = doctypes::parse_line_items(&ocr_text);
+        notes.notes = ocr_text.clone();
+    }
+
+    // Vision models misread printed VINs; when the model's fails its check digit, OCR's wins
+    let model_vin = parsed.data.vin.clone();
+    // Without any OCR text (no Tesseract, or a blank page) a second pass won't do better
+    let ocr_read = !ocr_text.trim().is_empty();
+    let mut fallback_vin = None;
+    if ocr_read && image_kind == ImageKind::Document && document_kind != DocumentKind::Inventory {
+        let tuned = ocr_fallback_vin(original_path, model_vin.as_deref(), &ocr_vins).await;
+        fallback_vin = tuned.unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "VIN-tuned OCR pass failed");
+            None
+        });
+        if let Some(found) = fallback_vin.clone() {
+            tracing::debug!(vin = %found, model_vin = ?model_vin, "VIN taken from OCR");
+            raw_response.push_str(&format!(
+                "\n\n[VIN {} read by OCR; the model read {}]",
+                found,
+                model_vin.as_deref().unwrap_or("none")
+            ));
+            parsed.data.vin = Some(found);
+        }
+    }
+
+    // Cross-check every VIN reader we have for this image
+    let mut vin_sources = Vec::new();
+    for code in ocr::read_barcodes(original_path).await.unwrap_or_default() {
+        // Code 39 VIN labels on imports carry a leading "I"
+        let code = if code.len() == 18 && code.starts_with('I') { &code[1..] } else { &code[..] };
+        if let Some(found) = vin::find_vins(code).first() {
+            vin_sources.push(VinSource::new(VinSourceKind::Barcode, found));
+        }
+    }
+    // The VIN-tuned pass is an OCR read too, when it found one the general pass didn't
+    let tuned_vin = fallback_vin.filter(|found| !ocr_vins.contains(found));
+    for found in ocr_vins.iter().chain(&tuned_vin) {
+        vin_sources.push(VinSource::new(VinSourceKind::Ocr, found));
+    }
+    // Etched glass is too faint for the general OCR pass; read it again tuned for etching
+    if let Some(detail) = parsed.data.detail.as_mut().filter(|d| d.kind == DetailKind::GlassEtch) {
+        let etched = ocr::read_etched_vins(original_path).await;
+        for found in &etched {
+            vin_sources.push(VinSource::new(VinSourceKind::Ocr, found));
+        }
+        if detail.etched_vin.is_none() {
+            detail.etched_vin = etched.into_iter().next();
+        }
+    }
+    // On the text route the model only saw OCR output, so it isn't an independent read
+    if let (ProcessingRoute::Vision, Some(model_vin)) = (route, &model_vin) {
+        vin_sources.push(VinSource::new(VinSourceKind::Vision, model_vin));
+    }
+
+    // A sheet's VINs belong to its rows, not to the document
+    let vin_verification = if vin_sources.is_empty() || document_kind == DocumentKind::Inventory {
+        None
+    } else {
+        Some(vin::cross_check_vins(vin_sources))
+    };
+    if let Some(verification) = &vin_verification {
+        if parsed.data.vin.is_none() {
+            parsed.data.vin = verification.vin.clone();
+        }
+    }
+
+    Ok(ExtractionResult {
+        path,
+        model: used_model,
+        route,
+        image_kind,
+        collage,
+        enhanced,
+        corrections: corrected.corrections,
+        handwritten,
+        document_kind,
+        data: parsed.data,
+        inventory: parsed.rows,
+        confidence: parsed.confidence,
+        raw_response,
+        vin_verification,
+        order_index: None,
+        condition: None,
+        ocr: None,
+        duplicate_scans: Vec::new(),
+        stitched_sections: Vec::new(),
+        stitch_needs_review: false,
+        aspect_ratio,
+        cached: false,
+        attempts,
+    })
+}
+
+/// A check-digit-valid VIN from OCR when the model's is missing or fails its check digit: from
+/// the general OCR pass if it has one, else from a second pass tuned for VINs. Fails when
+/// that second pass can't run, e.g. without Tesseract's language data.
+pub async fn ocr_fallback_vin(
+    path: &Path,
+    model_vin: Option<&str>,
+    ocr_vins: &[String],
+) -> Result<Option<String>, String> {
+    if model_vin.is_some_and(vin::validate_vin) {
+        return Ok(None);
+    }
+    match ocr_vins.iter().find(|v| vin::validate_vin(v)) {
+        Some(found) => Ok(Some(found.clone())),
+        None => Ok(ocr::read_printed_vins(path).await?.into_iter().next()),
+    }
+}
+
+/// Appended to the prompt when the previous answer had no JSON in it
+fn strict_json_suffix(failed_attempts: u32) -> String {
+    format!(
+        "\n\nYour previous answer could not be parsed (attempt {}). Return ONLY the JSON object: no prose, no markdown fences, no comments.",
+        failed_attempts
+    )
+}
+
+/// Photos and handwriting always need the vision model; strips with OCR text always go to
+/// the text model, and other documents by `choose_route`
+fn route_for(
+    text_model: Option<&str>,
+    image_kind: ImageKind,
+    handwritten: bool,
+    strip: bool,
+    ocr_output: Option<&OcrOutput>,
+    complexity: Option<f32>,
+) -> ProcessingRoute {
+    match (text_model, image_kind) {
+        (Some(_), ImageKind::Document) if strip && !handwritten && ocr_output.is_some() => ProcessingRoute::Text,
+        (Some(_), ImageKind::Document) if !handwritten => choose_route(ocr_output, complexity),
+        _ => ProcessingRoute::Vision,
+    }
+}
+
+/// Prompt for the vision model: a recognized layout's own prompt, else one for handwriting,
+/// car photos or paper documents
+fn vision_prompt(image_kind: ImageKind, handwritten: bool, targeted: Option<&str>) -> &str {
+    match (image_kind, targeted) {
+        (_, Some(prompt)) => prompt,
+        _ if handwritten => HANDWRITING_PROMPT,
+        (ImageKind::Photo, None) => PHOTO_PROMPT,
+        (ImageKind::Document, None) => DOCUMENT_PROMPT,
+    }
+}
+
+/// Text-dense, visually simple images go to the text model; everything else needs vision
+fn choose_route(ocr_output: Option<&ocr::OcrOutput>, complexity: Option<f32>) -> ProcessingRoute {
+    let ocr_output = match ocr_output {
+        Some(o) => o,
+        None => return ProcessingRoute::Vision,
+    };
+
+    let text_dense = ocr_output.word_count >= TEXT_DENSE_MIN_WORDS
+        && ocr_output.mean_confidence >= TEXT_DENSE_MIN_CONFIDENCE;
+    let visually_complex = complexity.map(|c| c > PHOTO_MIN_COMPLEXITY).unwrap_or(false);
+
+    if text_dense && !visually_complex {
+        ProcessingRoute::Text
+    } else {
+        ProcessingRoute::Vision
+    }
+}
+
+/// Parse the JSON object out of a model response
+fn parse_response(raw: &str, kind: DocumentKind) -> Option<ParsedResponse> {
+    // French labels land on the same fields whichever language the model read
+    let (value, french_originals) = bilingual::canonicalize(&modeljson::extract_json(raw)?);
+    let mut data = extracted_from_value(&value);
+    data.french_originals = french_originals;
+    if let Some(detail) = details::parse_detail_shot(&value) {
+        details::apply_detail(detail, &mut data);
+    }
+    doctypes::apply_kind_fields(kind, &value, &mut data);
+    let rows = match kind {
+        DocumentKind::Inventory => doctypes::parse_inventory(&value),
+        _ => Vec::new(),
+    };
+
+    Some(ParsedResponse {
+        data,
+        rows,
+        confidence: value
+            .get("confidence")
+            .and_then(|c| c.as_f64())
+            .map(|c| c as f32)
+            .unwrap_or(0.5),
+    })
+}
+
+/// Build ExtractedData from a model's JSON, tolerating numbers where strings are expected
+pub fn extracted_from_value(value: &serde_json::Value) -> ExtractedData {
+    let plate_number = field(value, "plate_number");
+    // ISO 8601 on the day printed; unparseable dates are kept as read
+    let date = field(value, "date").as_deref().and_then(dates::parse_date);
+    // A VIN with hidden positions is kept apart so it never passes for a full one
+    let vin = field(value, "vin");
+    let partial_vin = vin
+        .as_deref()
+        .filter(|v| v.contains(vin::VIN_PLACEHOLDERS))
+        .and_then(vin::parse_partial_vin);
+    let vin = match partial_vin {
+        Some(_) => None,
+        None => vin.map(|v| v.to_uppercase()),
+    };
+    ExtractedData {
+        vin,
+        partial_vin,
+        year: field(value, "year"),
+        make: field(value, "make"),
+        model: field(value, "model"),
+        color: field(value, "color"),
+        mileage: field(value, "mileage"),
+        owner_name: field(value, "owner_name"),
+        plate_type: plate_number.as_deref().map(plates::classify_plate),
+        plate_number,
+        document_type: field(value, "document_type"),
+        date_precision: date.as_ref().map(|d| d.precision),
+        date: date
+            .map(|d| d.iso)
+            .or_else(|| field(value, "date")),
+        price: field(value, "price"),
+        ..Default::default()
+    }
+}
+
+/// Regex-only extraction for text we already have (DOCX, OCR output)
+pub fn extract_text_fields(text: &str) -> (ExtractedData, f32) {
+    let hint = crate::extract_hints_from_text(text);
+
+    // Prefer a VIN whose check digit validates
+    let vins = vin::find_vins(text);
+    let vin = vins
+        .iter()
+        .find(|v| vin::validate_vin(v))
+        .or_else(|| vins.first())
+        .cloned();
+
+    let capture = |pattern: &str| {
+        Regex::new(pattern)
+            .ok()?
+            .captures(text)
+            .map(|c| c[1].replace(',', ""))
+    };
+
+    let data = ExtractedData {
+        vin: vin.clone(),
+        year: hint.as_ref().and_then(|h| h.year.clone()),
+        make: hint.as_ref().and_then(|h| h.make.clone()),
+        model: hint.as_ref().and_then(|h| h.model.clone()),
+        mileage: capture(r"(?i)(?:odometer|mileage|miles)\W{0,3}([\d,]{3,9})"),
+        price: capture(r"(?i)(?:sale price|purchase price|price|amount)\W{0,3}\$?\s*([\d,]+(?:\.\d{2})?)"),
+        ..Default::default()
+    };
+
+    let mut confidence = hint.map(|h| h.confidence).unwrap_or(0.0);
+    if vin.is_some() {
+        confidence += 0.3;
+    }
+    (data, confidence.min(1.0))
+}
+
+/// Read a field as a non-empty string, accepting numbers and skipping null-like values
+pub fn field(value: &serde_json::Value, key: &str) -> Option<String> {
+    let s = match value.get(key)? {
+        serde_json::Value::String(s) => s.trim().to_string(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::Bool(b) => b.to_string(),
+        _ => return None,
+    };
+
+    match s.to_lowercase().as_str() {
+        "" | "null" | "none" | "unknown" | "n/a" => None,
+        _ => Some(s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    /// A white page with rows of dark "words"
+    fn text_page() -> GrayImage {
+        GrayImage::from_fn(400, 500, |x, y| {
+            let in_line = (40..460).contains(&y) && y % 20 < 5;
+            let in_word = x % 60 < 50 && (20..380).contains(&x);
+            Luma([if in_line && in_word { 20 } else { 240 }])
+        })
+    }
+
+    /// Busy, noisy tones like a car photo
+    fn photo() -> GrayImage {
+        let mut seed: u32 = 7;
+        GrayImage::from_fn(400, 300, |x, y| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            Luma([((x + y) / 3 % 128) as u8 + (seed >> 25) as u8])
+        })
+    }
+
+    fn ocr(word_count: usize, mean_confidence: f32) -> OcrOutput {
+        OcrOutput {
+            text: "word ".repeat(word_count),
+            word_count,
+            mean_confidence,
+            min_confidence: mean_confidence,
+            low_confidence_words: 0,
+        }
+    }
+
+    /// The decision `extract_image` makes for an image and its OCR output
+    fn route_image(
+        img: &GrayImage,
+        ocr_output: &OcrOutput,
+        text_model: Option<&str>,
+    ) -> ProcessingRoute {
+        let kind = imaging::classify_image(Some(img), ocr_output.word_count);
+        let handwritten = imaging::is_handwritten(Some(img), Some(ocr_output));
+        let complexity = imaging::visual_complexity(img);
+        route_for(
+            text_model,
+            kind,
+            handwritten,
+            false,
+            Some(ocr_output),
+            Some(complexity),
+        )
+    }
+
+    #[test]
+    fn text_heavy_document_goes_to_the_text_model() {
+        let page = text_page();
+        assert_eq!(
+            imaging::classify_image(Some(&page), 120),
+            ImageKind::Document
+        );
+        assert_eq!(
+            route_image(&page, &ocr(120, 88.0), Some("llama3.1")),
+            ProcessingRoute::Text
+        );
+    }
+
+    #[test]
+    fn photo_goes_to_the_vision_model() {
+        let photo = photo();
+        assert_eq!(imaging::classify_image(Some(&photo), 2), ImageKind::Photo);
+        assert_eq!(
+            route_image(&photo, &ocr(2, 40.0), Some("llama3.1")),
+            ProcessingRoute::Vision
+        );
+    }
+
+    #[test]
+    fn documents_need_vision_without_a_text_model_or_readable_text() {
+        let page = text_page();
+        assert_eq!(
+            route_image(&page, &ocr(120, 88.0), None),
+            ProcessingRoute::Vision
+        );
+        // Sparse or poorly read text isn't enough to trust OCR alone
+        assert_eq!(
+            choose_route(Some(&ocr(10, 90.0)), Some(0.1)),
+            ProcessingRoute::Vision
+        );
+        assert_eq!(
+            choose_route(Some(&ocr(120, 45.0)), Some(0.1)),
+            ProcessingRoute::Vision
+        );
+        assert_eq!(
+            choose_route(Some(&ocr(120, 88.0)), Some(0.8)),
+            ProcessingRoute::Vision
+        );
+        assert_eq!(choose_route(None, Some(0.1)), ProcessingRoute::Vision);
+    }
+
+    #[test]
+    fn handwriting_and_strips_override_the_text_heuristic() {
+        let text = ocr(120, 88.0);
+        let route = |handwritten, strip, ocr_output| {
+            route_for(
+                Some("llama3.1"),
+                ImageKind::Document,
+                handwritten,
+                strip,
+                ocr_output,
+                Some(0.9),
+            )
+        };
+        assert_eq!(route(true, false, Some(&text)), ProcessingRoute::Vision);
+        assert_eq!(route(false, true, Some(&text)), ProcessingRoute::Text);
+        assert_eq!(route(false, true, None), ProcessingRoute::Vision);
+    }
+
+    #[test]
+    fn unchanged_file_keeps_its_processed_store_key() {
+        let path = std::env::temp_dir().join(format!("nuke-cache-key-{}.jpg", std::process::id()));
+        std::fs::write(&path, b"title scan").unwrap();
+        let path_str = path.to_string_lossy().to_string();
+        let options = OllamaOptions::default();
+        let key = || cache_key(&path_str, "llava", None, &options).unwrap();
+
+        let first = key();
+        assert_eq!(key(), first);
+        assert_ne!(
+            cache_key(&path_str, "llava", Some("llama3.1"), &options).unwrap(),
+            first
+        );
+        let seeded = OllamaOptions {
+            seed: Some(1234),
+            ..OllamaOptions::default()
+        };
+        assert_ne!(cache_key(&path_str, "llava", None, &seeded).unwrap(), first);
+
+        std::fs::write(&path, b"edited scan").unwrap();
+        assert_ne!(key(), first);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn document_and_photo_images_get_different_prompts() {
+        let prompt_for = |img: &GrayImage, words| {
+            vision_prompt(imaging::classify_image(Some(img), words), false, None)
+        };
+        assert_eq!(prompt_for(&text_page(), 120), DOCUMENT_PROMPT);
+        assert_eq!(prompt_for(&photo(), 0), PHOTO_PROMPT);
+        // Undecodable images go on OCR words alone
+        assert_eq!(imaging::classify_image(None, 20), ImageKind::Document);
+        assert_eq!(imaging::classify_image(None, 3), ImageKind::Photo);
+    }
+
+    #[test]
+    fn recognized_layouts_keep_their_own_prompt() {
+        let auction = doctypes::prompt_for(DocumentKind::Auction).unwrap();
+        assert_eq!(
+            vision_prompt(ImageKind::Document, true, Some(&auction)),
+            auction
+        );
+        assert_eq!(
+            vision_prompt(ImageKind::Photo, false, Some(&auction)),
+            auction
+        );
+    }
+
+    /// Wavy pen strokes on paper, which OCR reads as a few low-confidence fragments
+    fn handwritten_page() -> GrayImage {
+        GrayImage::from_fn(400, 500, |x, y| {
+            let baseline = 40 + (y / 40) * 40;
+            let wave = ((x as f32 / 7.0).sin() * 6.0) as i32;
+            let on_stroke = (y as i32 - baseline as i32 - wave).abs() < 2 && (x / 30) % 5 != 4;
+            Luma([if on_stroke { 20 } else { 240 }])
+        })
+    }
+
+    #[test]
+    fn handwriting_is_flagged_and_read_with_the_handwriting_prompt() {
+        let page = handwritten_page();
+        let poor = ocr(3, 31.0);
+        assert!(imaging::is_handwritten(Some(&page), Some(&poor)));
+        // Even with a text model and some OCR text, handwriting goes to vision
+        assert_eq!(
+            route_image(&page, &poor, Some("llama3.1")),
+            ProcessingRoute::Vision
+        );
+        assert_eq!(
+            vision_prompt(ImageKind::Document, true, None),
+            HANDWRITING_PROMPT
+        );
+
+        // The same strokes read cleanly are print, not handwriting
+        assert!(!imaging::is_handwritten(Some(&page), Some(&ocr(80, 88.0))));
+        // Photos and undecodable images are never handwriting
+        assert!(!imaging::is_handwritten(Some(&photo()), Some(&poor)));
+        assert!(!imaging::is_handwritten(None, Some(&poor)));
+    }
+
+    /// A folder of distinct images, and a processed store kept beside it
+    fn resumable_folder(name: &str, count: usize) -> (PathBuf, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = (0..count)
+            .map(|i| {
+                let path = dir.join(format!("scan-{}.jpg", i));
+                std::fs::write(&path, format!("page {}", i)).unwrap();
+                path.to_string_lossy().to_string()
+            })
+            .collect();
+        (dir.join("processed.jsonl"), files)
+    }
+
+    /// One run of the folder loop against the store on disk, as a fresh app launch would see it.
+    /// Returns the run and the files it extracted.
+    async fn run_folder(
+        store: &Path,
+        files: &[String],
+        max_files: Option<usize>,
+        cancel: bool,
+    ) -> (FolderRun, Vec<String>) {
+        let processed = Mutex::new(ProcessedStore::load(store.to_path_buf()));
+        let options = OllamaOptions::default();
+        let mut extracted = Vec::new();
+        let run = resume_folder(
+            files.to_vec(),
+            &processed,
+            &AtomicBool::new(cancel),
+            max_files,
+            |file| cache_key(file, "llava", None, &options),
+            |key, file| {
+                extracted.push(file.clone());
+                let result: ExtractionResult = serde_json::from_value(serde_json::json!({
+                    "path": file,
+                    "model": "llava",
+                    "route": "vision",
+                    "image_kind": "document",
+                    "data": {},
+                    "confidence": 0.9,
+                    "raw_response": "{}",
+                }))
+                .unwrap();
+                let stored = processed
+                    .lock()
+                    .unwrap()
+                    .insert(key, result)
+                    .map_err(NukeError::FileWrite);
+                async move { stored }
+            },
+        )
+        .await;
+        (run, extracted)
+    }
+
+    #[tokio::test]
+    async fn interrupted_folder_resumes_without_reprocessing() {
+        let (store, files) = resumable_folder("resume", 4);
+
+        // Stop halfway through
+        let (run, first) = run_folder(&store, &files, Some(2), false).await;
+        assert_eq!(
+            (run.processed, run.skipped, run.remaining, run.failed),
+            (2, 0, 2, 0)
+        );
+        assert_eq!(first, files[..2]);
+
+        // Resuming picks up only what's left
+        let (run, second) = run_folder(&store, &files, None, false).await;
+        assert_eq!(
+            (run.processed, run.skipped, run.remaining, run.failed),
+            (2, 2, 0, 0)
+        );
+        assert_eq!(second, files[2..]);
+
+        // A finished folder has nothing to do
+        let (run, third) = run_folder(&store, &files, None, false).await;
+        assert_eq!((run.processed, run.skipped, run.remaining), (0, 4, 0));
+        assert!(third.is_empty());
+        std::fs::remove_dir_all(store.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cancelled_folder_leaves_everything_for_the_next_run() {
+        let (store, mut files) = resumable_folder("resume-cancel", 2);
+        files.push("/nonexistent/scan.jpg".to_string());
+
+        let (run, extracted) = run_folder(&store, &files, None, true).await;
+        assert_eq!((run.processed, run.remaining, run.failed), (0, 2, 1));
+        assert!(extracted.is_empty());
+        assert_eq!(run.errors.len(), 1);
+        assert!(run.errors[0].starts_with("/nonexistent/scan.jpg: "));
+        std::fs::remove_dir_all(store.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn receipt_strip_is_read_as_text_rather_than_by_the_vehicle_prompt() {
+        let path = std::env::temp_dir().join(format!("nuke-receipt-{}.png", std::process::id()));
+        GrayImage::from_pixel(200, 1600, Luma([240]))
+            .save(&path)
+            .unwrap();
+        let ratio = imaging::aspect_ratio(&path).unwrap();
+        let strip = !imaging::AspectRange::default().contains(ratio);
+        assert!(strip);
+
+        // A strip reads as text even when it's too sparse for the usual text heuristic
+        let sparse = ocr(12, 85.0);
+        let route = |text_model, ocr_output| {
+            route_for(
+                text_model,
+                ImageKind::Document,
+                false,
+                strip,
+                ocr_output,
+                Some(0.9),
+            )
+        };
+        assert_eq!(
+            route(Some("llama3.1"), Some(&sparse)),
+            ProcessingRoute::Text
+        );
+        // Without a text model or readable text it still needs vision
+        assert_eq!(route(None, Some(&sparse)), ProcessingRoute::Vision);
+        assert_eq!(route(Some("llama3.1"), None), ProcessingRoute::Vision);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn pdf_page(name: &str, confidence: f32) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": name,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": confidence,
+            "raw_response": name,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pdf_pages_merge_around_the_page_with_the_valid_vin() {
+        let mut cover = pdf_page("cover", 0.9);
+        cover.data.make = Some("Ford".to_string());
+        // Misread: the check digit doesn't validate
+        cover.data.vin = Some("1FTEF14N0SLA0000X".to_string());
+        let mut title = pdf_page("title", 0.5);
+        title.data.vin = Some("1HGCM82633A004352".to_string());
+        title.data.year = Some("2003".to_string());
+        let mut bill = pdf_page("bill", 0.7);
+        bill.data.make = Some("Honda".to_string());
+        bill.data.price = Some("5000".to_string());
+
+        let merged = merge_pages(vec![cover, title, bill]).unwrap();
+        assert_eq!(merged.data.vin.as_deref(), Some("1HGCM82633A004352"));
+        assert_eq!(merged.data.year.as_deref(), Some("2003"));
+        // Gaps are filled from the most confident of the other pages
+        assert_eq!(merged.data.make.as_deref(), Some("Ford"));
+        assert_eq!(merged.data.price.as_deref(), Some("5000"));
+        assert_eq!(merged.raw_response, "title\n\ncover\n\nbill");
+        assert!(merge_pages(Vec::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn document_batches_keep_input_order_and_leave_out_failures() {
+        let (slow, fast) = (pdf_page("slow.jpg", 0.9), pdf_page("fast.jpg", 0.9));
+        let tasks = vec![
+            (
+                "slow.jpg".to_string(),
+                tokio::spawn(async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    Ok(slow)
+                }),
+            ),
+            (
+                "missing.jpg".to_string(),
+                tokio::spawn(async { Err(NukeError::FileRead("Failed to read file".to_string())) }),
+            ),
+            (
+                "fast.jpg".to_string(),
+                tokio::spawn(async move { Ok(fast) }),
+            ),
+            (
+                "crashed.jpg".to_string(),
+                tokio::spawn(async { panic!("extraction panicked") }),
+            ),
+        ];
+
+        let results = collect_documents(tasks).await.unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["slow.jpg", "fast.jpg"]);
+    }
+
+    #[tokio::test]
+    async fn a_batch_with_no_results_fails_with_the_first_error() {
+        let tasks = vec![
+            (
+                "missing.jpg".to_string(),
+                tokio::spawn(async { Err(NukeError::FileRead("Failed to read file".to_string())) }),
+            ),
+            (
+                "offline.jpg".to_string(),
+                tokio::spawn(async { Err(NukeError::OllamaUnreachable) }),
+            ),
+        ];
+        assert_eq!(
+            collect_documents(tasks).await.unwrap_err(),
+            NukeError::FileRead("Failed to read file".to_string())
+        );
+        assert!(collect_documents(Vec::new()).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn stitched_sections_are_extracted_once_in_place_of_the_top_section() {
+        let scan = StitchedScan {
+            path: "/tmp/stitch/top_stitched.png".to_string(),
+            sources: vec!["top.png".to_string(), "bottom.png".to_string()],
+            overlaps: Vec::new(),
+            confidence: 0.4,
+            needs_review: true,
+        };
+        let paths = ["cover.jpg", "top.png", "bottom.png", "bill.jpg"]
+            .map(String::from)
+            .to_vec();
+        let inputs = stitch_inputs(paths, vec![scan.clone()]);
+        let files: Vec<&str> = inputs.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            files,
+            ["cover.jpg", "/tmp/stitch/top_stitched.png", "bill.jpg"]
+        );
+        assert!(inputs[0].1.is_none() && inputs[1].1.is_some());
+
+        let result = from_sections(pdf_page("/tmp/stitch/top_stitched.png", 0.8), scan);
+        assert_eq!(result.path, "top.png");
+        assert_eq!(result.stitched_sections, ["top.png", "bottom.png"]);
+        assert!(result.stitch_needs_review);
+    }
+
+    #[tokio::test]
+    async fn ocr_vins_are_used_only_when_the_model_vin_fails_its_check() {
+        let path = Path::new("/nonexistent/scan.png");
+        let ocr_vins = ["ABCDEFGH123456789", "1HGCM82633A004352"].map(String::from);
+        let valid = "1HGCM82633A004352";
+        let failing = "1HGCM82633A004353";
+
+        let kept = ocr_fallback_vin(path, Some(valid), &ocr_vins).await;
+        assert_eq!(kept, Ok(None));
+        let found = Ok(Some(valid.to_string()));
+        assert_eq!(ocr_fallback_vin(path, None, &ocr_vins).await, found);
+        let fixed = ocr_fallback_vin(path, Some(failing), &ocr_vins).await;
+        assert_eq!(fixed, found);
+        // Without a valid VIN from the general pass the tuned pass runs, and a file it can't
+        // read is an error rather than a missing VIN
+        let tuned = ocr_fallback_vin(path, Some(failing), &ocr_vins[..1]).await;
+        assert!(tuned.is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// Word document text extraction
+
+use crate::documents::{self, ExtractionResult, ProcessingRoute};
+use crate::doctypes::DocumentKind;
+use crate::error::NukeError;
+use crate::imaging::ImageKind;
+use quick_xml::events::Event;
+use std::io::Read;
+use std::path::Path;
+
+/// Extract vehicle data from a Word document without involving a model
+#[tauri::command]
+pub async fn parse_docx(path: String) -> Result<ExtractionResult, NukeError> {
+    let extension = Path::new(&path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if extension == "doc" {
+        return Err(NukeError::InvalidInput(
+            "Legacy .doc files aren't supported; convert to .docx and try again".to_string(),
+        ));
+    }
+
+    let text = read_docx_text(Path::new(&path)).map_err(NukeError::FileRead)?;
+    let (data, confidence) = documents::extract_text_fields(&text);
+
+    Ok(ExtractionResult {
+        path,
+        model: "offline".to_string(),
+        route: ProcessingRoute::Offline,
+        image_kind: ImageKind::Document,
+        collage: None,
+        enhanced: false,
+        corrections: Vec::new(),
+        handwritten: false,
+        document_kind: DocumentKind::General,
+        data,
+        inventory: Vec::new(),
+        confidence,
+        raw_response: text,
+        vin_verification: None,
+        order_index: None,
+        condition: None,
+        ocr: None,
+        duplicate_scans: Vec::new(),
+        stitched_sections: Vec::new(),
+        stitch_needs_review: false,
+        aspect_ratio: None,
+        cached: false,
+        attempts: 0,
+    })
+}
+
+/// Pull the body text out of a .docx (word/document.xml inside the zip)
+pub fn read_docx_text(path: &Path) -> Result<String, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Not a valid .docx file: {}", e))?;
+
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .map_err(|e| format!("Not a valid .docx file: {}", e))?
+        .read_to_string(&mut xml)
+        .map_err(|e| format!("Failed to read document body: {}", e))?;
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut text = String::new();
+    let mut in_run = false;
+    let mut in_text_run = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.name().as_ref() {
+                b"w:r" => in_run = true,
+                b"w:t" => in_text_run = true,
+                _ => {}
+            },
+            Ok(Event::End(e)) => match e.name().as_ref() {
+                b"w:r" => in_run = false,
+                b"w:t" => in_text_run = false,
+                b"w:p" => text.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) => match e.name().as_ref() {
+                // Tab stops in paragraph properties are also w:tab; only a run's tab is text
+                b"w:tab" if in_run => text.push('\t'),
+                b"w:br" => text.push('\n'),
+                _ => {}
+            },
+            Ok(Event::Text(t)) if in_text_run => {
+                let unescaped = t
+                    .unescape()
+                    .map_err(|e| format!("Failed to decode document text: {}", e))?;
+                text.push_str(&unescaped);
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Failed to parse document body: {}", e)),
+            _ => {}
+        }
+    }
+
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    /// A .docx holding just `body` as its document.xml body
+    fn write_docx(name: &str, body: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("nuke-{}-{}.docx", name, std::process::id()));
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        zip.start_file("word/document.xml", zip::write::FileOptions::default())
+            .unwrap();
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?><w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{}</w:body></w:document>"#,
+            body
+        )
+        .unwrap();
+        std::fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_paragraphs_runs_tabs_and_breaks() {
+        let path = write_docx(
+            "docx-text",
+            "<w:p><w:r><w:t>Bill of</w:t></w:r><w:r><w:t xml:space=\"preserve\"> Sale</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>Buyer</w:t><w:tab/><w:t>J. Smith &amp; Sons</w:t><w:br/><w:t>Seller</w:t></w:r></w:p>",
+        );
+        let text = read_docx_text(&path).unwrap();
+        assert_eq!(text, "Bill of Sale\nBuyer\tJ. Smith & Sons\nSeller\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn extracts_vin_and_year_from_a_docx() {
+        let path = write_docx(
+            "docx-vin",
+            "<w:p><w:r><w:t>BILL OF SALE</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>1969 Chevrolet Camaro</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>VIN: 1HGCM82633A004352</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>Sale Price: $32,500.00</w:t></w:r></w:p>",
+        );
+        let result = parse_docx(path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.data.vin.as_deref(), Some("1HGCM82633A004352"));
+        assert_eq!(result.data.year.as_deref(), Some("1969"));
+        assert_eq!(result.data.make.as_deref(), Some("Chevrolet"));
+        assert_eq!(result.data.price.as_deref(), Some("32500.00"));
+        assert_eq!(result.route, ProcessingRoute::Offline);
+        assert!(result.confidence > 0.5);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_legacy_doc_and_non_zip_files() {
+        let error = parse_docx("/tmp/old.doc".to_string()).await.unwrap_err();
+        assert!(matches!(error, NukeError::InvalidInput(_)));
+
+        let path = std::env::temp_dir().join(format!("nuke-docx-bad-{}.docx", std::process::id()));
+        std::fs::write(&path, b"not a zip").unwrap();
+        let error = parse_docx(path.to_string_lossy().to_string())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, NukeError::FileRead(_)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tab_stop_definitions_are_not_read_as_tabs() {
+        let path = write_docx(
+            "docx-tabstops",
+            "<w:p><w:pPr><w:tabs><w:tab w:val=\"left\" w:pos=\"2880\"/></w:tabs></w:pPr>\
+             <w:r><w:t>Make</w:t><w:tab/><w:t>Ford</w:t></w:r></w:p>",
+        );
+        let text = read_docx_text(&path).unwrap();
+        assert_eq!(text, "Make\tFord\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+// Command errors - typed so the frontend can pick a message or a retry by variant
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Error returned by every command, serialized as `{"kind": "...", "message": "..."}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum NukeError {
+    /// Ollama isn't running or refused the connection; worth retrying once it's started
+    OllamaUnreachable,
+    /// Ollama took longer than the request timeout; a large model may still be loading
+    OllamaTimeout,
+    /// Another workload kept Ollama saturated through every deferral; retry once it's free
+    OllamaBusy(String),
+    /// The chosen model can't take images; `vision_models` are the installed ones that can
+    NonVisionModel {
+        model: String,
+        vision_models: Vec<String>,
+    },
+    /// A file couldn't be read: missing, unreadable, or not what it claims to be
+    FileRead(String),
+    /// An output file or folder couldn't be written
+    FileWrite(String),
+    /// The image crate or an external decoder couldn't decode an image
+    ImageDecode(String),
+    /// Input or a response wasn't the JSON we expected
+    JsonParse(String),
+    /// `configure_supabase` hasn't been called
+    SupabaseNotConfigured,
+    /// A remote request failed or returned an error status
+    Network(String),
+    /// An argument was out of range or unsupported
+    InvalidInput(String),
+    /// Anything else; the message is meant for display
+    Other(String),
+}
+
+impl NukeError {
+    /// A failed Ollama request: a connection failure means the server isn't up
+    pub fn ollama(e: reqwest::Error) -> Self {
+        if e.is_connect() {
+            NukeError::OllamaUnreachable
+        } else if e.is_timeout() {
+            NukeError::OllamaTimeout
+        } else if e.is_decode() {
+            NukeError::JsonParse(format!("Failed to parse response: {}", e))
+        } else {
+            NukeError::Network(format!("Ollama request failed: {}", e))
+        }
+    }
+}
+
+impl fmt::Display for NukeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NukeError::OllamaUnreachable => {
+                write!(f, "Ollama is not reachable; is it running?")
+            }
+            NukeError::OllamaTimeout => write!(
+                f,
+                "Ollama didn't answer in time; the model may still be loading"
+            ),
+            NukeError::NonVisionModel {
+                model,
+                vision_models,
+            } if vision_models.is_empty() => write!(
+                f,
+                "{} can't read images; install a vision model such as llava",
+                model
+            ),
+            NukeError::NonVisionModel {
+                model,
+                vision_models,
+            } => write!(
+                f,
+                "{} can't read images; switch to {}",
+                model,
+                vision_models.join(", ")
+            ),
+            NukeError::OllamaBusy(reason) => write!(f, "Ollama stayed busy: {}", reason),
+            NukeError::SupabaseNotConfigured => write!(f, "Supabase is not configured"),
+            NukeError::FileRead(message)
+            | NukeError::FileWrite(message)
+            | NukeError::ImageDecode(message)
+            | NukeError::JsonParse(message)
+            | NukeError::Network(message)
+            | NukeError::InvalidInput(message)
+            | NukeError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for NukeError {}
+
+impl From<serde_json::Error> for NukeError {
+    fn from(e: serde_json::Error) -> Self {
+        NukeError::JsonParse(e.to_string())
+    }
+}
+
+impl From<image::ImageError> for NukeError {
+    fn from(e: image::ImageError) -> Self {
+        NukeError::ImageDecode(e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for NukeError {
+    fn from(e: reqwest::Error) -> Self {
+        NukeError::Network(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn errors_serialize_by_kind_with_their_message() {
+        assert_eq!(
+            serde_json::to_value(NukeError::OllamaUnreachable).unwrap(),
+            json!({ "kind": "ollama_unreachable" })
+        );
+        assert_eq!(
+            serde_json::to_value(NukeError::FileRead("missing".to_string())).unwrap(),
+            json!({ "kind": "file_read", "message": "missing" })
+        );
+        assert_eq!(
+            serde_json::to_value(NukeError::NonVisionModel {
+                model: "llama3.1".to_string(),
+                vision_models: vec!["llava".to_string()],
+            })
+            .unwrap(),
+            json!({
+                "kind": "non_vision_model",
+                "message": { "model": "llama3.1", "vision_models": ["llava"] },
+            })
+        );
+    }
+
+    #[test]
+    fn display_gives_the_message_or_a_suggestion() {
+        assert_eq!(
+            NukeError::FileWrite("disk full".to_string()).to_string(),
+            "disk full"
+        );
+        let no_vision = NukeError::NonVisionModel {
+            model: "llama3.1".to_string(),
+            vision_models: Vec::new(),
+        };
+        assert!(no_vision.to_string().contains("install a vision model"));
+    }
+}
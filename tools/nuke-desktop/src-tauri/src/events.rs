@@ -0,0 +1,276 @@
+// App events - one typed stream on the `app-event` channel for everything the UI reacts to
+
+use crate::error::NukeError;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+pub const APP_EVENT: &str = "app-event";
+
+// Events kept for `get_app_events`, so a UI that attaches late can catch up
+const EVENT_HISTORY: usize = 1000;
+
+/// Everything the frontend listens for; serialized with a `type` discriminant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AppEvent {
+    ScanStarted {
+        roots: Vec<String>,
+    },
+    ScanFinished {
+        files: usize,
+        truncated: bool,
+        cancelled: bool,
+    },
+    FileProcessed {
+        path: String,
+        cached: bool,
+    },
+    SyncBatchDone {
+        target: String,
+        synced: usize,
+        failed: usize,
+    },
+    Error {
+        operation: String,
+        path: Option<String>,
+        message: String,
+    },
+}
+
+/// An event with its position in the stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub event: AppEvent,
+}
+
+/// Recent events in emission order; lives in AppState
+pub struct EventLog {
+    inner: Mutex<(u64, VecDeque<LoggedEvent>)>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        EventLog {
+            inner: Mutex::new((0, VecDeque::new())),
+        }
+    }
+
+    /// Append an event, returning it with its sequence number
+    pub fn record(&self, event: AppEvent) -> LoggedEvent {
+        let mut inner = self.inner.lock().unwrap();
+        inner.0 += 1;
+        let logged = LoggedEvent {
+            seq: inner.0,
+            event,
+        };
+        inner.1.push_back(logged.clone());
+        if inner.1.len() > EVENT_HISTORY {
+            inner.1.pop_front();
+        }
+        logged
+    }
+
+    /// Events after `since` (all retained events when None)
+    pub fn since(&self, since: Option<u64>) -> Vec<LoggedEvent> {
+        let since = since.unwrap_or(0);
+        self.inner
+            .lock()
+            .unwrap()
+            .1
+            .iter()
+            .filter(|e| e.seq > since)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Record an event and send it on the `app-event` channel
+pub fn emit(app: Option<&tauri::AppHandle>, log: &EventLog, event: AppEvent) {
+    let logged = log.record(event);
+    if let Some(app) = app {
+        let _ = app.emit(APP_EVENT, logged);
+    }
+}
+
+/// Events emitted after sequence number `since`
+#[tauri::command]
+pub async fn get_app_events(
+    state: tauri::State<'_, AppState>,
+    since: Option<u64>,
+) -> Result<Vec<LoggedEvent>, NukeError> {
+    Ok(state.events.since(since))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supabase::SyncTally;
+
+    fn test_state(name: &str) -> AppState {
+        let dir = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        AppState::new(
+            dir.clone(),
+            dir.clone(),
+            crate::logging::Logging::init(&dir),
+        )
+    }
+
+    /// A scan, a streamed cloud import with one rejected batch, and a Supabase sync with
+    /// one failed row, through the same calls the commands make
+    async fn intake_run(state: &AppState) {
+        let config = crate::ScanConfig {
+            paths: vec!["/intake".to_string()],
+            ..Default::default()
+        };
+        crate::start_scan(None, state, &config);
+        crate::finish_scan(
+            None,
+            state,
+            &crate::ScanWalk {
+                results: Vec::new(),
+                truncated: false,
+                cancelled: false,
+                skipped: Default::default(),
+                merged_roots: Vec::new(),
+            },
+        );
+
+        let csv = "vin,year,make\n1HGCM82633A004352,2003,Honda\n,1969,Chevrolet\n";
+        let mut batches = 0;
+        crate::csvimport::stream_import(
+            csv.as_bytes(),
+            "inventory.csv",
+            None,
+            &Default::default(),
+            1,
+            |_| {
+                batches += 1;
+                let sent = if batches == 1 {
+                    Ok(())
+                } else {
+                    Err("Batch failed: 500".to_string())
+                };
+                async move { sent }
+            },
+            |stage, count| crate::csvimport::report_progress(None, state, stage, count),
+        )
+        .await
+        .unwrap();
+
+        let mut tally = SyncTally::default();
+        tally.record(None, state, "sync_to_supabase", "/intake/title.jpg", None);
+        tally.record(
+            None,
+            state,
+            "sync_to_supabase",
+            "/intake/blurry.jpg",
+            Some("500 Internal Server Error".to_string()),
+        );
+        tally.finish(None, state);
+    }
+
+    fn types(events: &[LoggedEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| {
+                serde_json::to_value(e).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn an_intake_run_streams_its_events_in_order() {
+        let state = test_state("events-order");
+        intake_run(&state).await;
+
+        let events = state.events.since(None);
+        assert_eq!(
+            types(&events),
+            [
+                "scan_started",
+                "scan_finished",
+                "sync_batch_done",
+                "sync_batch_done",
+                "error",
+                "sync_batch_done"
+            ]
+        );
+        let seqs: Vec<u64> = events.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [1, 2, 3, 4, 5, 6]);
+
+        assert_eq!(
+            events[2].event,
+            AppEvent::SyncBatchDone {
+                target: "cloud".to_string(),
+                synced: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(
+            events[3].event,
+            AppEvent::SyncBatchDone {
+                target: "cloud".to_string(),
+                synced: 0,
+                failed: 1
+            }
+        );
+        assert_eq!(
+            events[5].event,
+            AppEvent::SyncBatchDone {
+                target: "supabase".to_string(),
+                synced: 1,
+                failed: 1
+            }
+        );
+
+        // The payload sits beside the discriminant, ready to dispatch on
+        let error = serde_json::to_value(&events[4]).unwrap();
+        assert_eq!(
+            error,
+            serde_json::json!({
+                "seq": 5,
+                "type": "error",
+                "operation": "sync_to_supabase",
+                "path": "/intake/blurry.jpg",
+                "message": "500 Internal Server Error"
+            })
+        );
+        let round_trip: LoggedEvent = serde_json::from_value(error).unwrap();
+        assert_eq!(round_trip.event, events[4].event);
+    }
+
+    #[tokio::test]
+    async fn late_subscribers_catch_up_from_a_sequence_number() {
+        let state = test_state("events-catch-up");
+        intake_run(&state).await;
+        let missed = state.events.since(Some(4));
+        assert_eq!(types(&missed), ["error", "sync_batch_done"]);
+        assert!(state.events.since(Some(6)).is_empty());
+    }
+
+    #[test]
+    fn history_keeps_only_the_latest_events() {
+        let log = EventLog::new();
+        for files in 0..EVENT_HISTORY + 10 {
+            log.record(AppEvent::ScanFinished {
+                files,
+                truncated: false,
+                cancelled: false,
+            });
+        }
+        let kept = log.since(None);
+        assert_eq!(kept.len(), EVENT_HISTORY);
+        assert_eq!(kept[0].seq, 11);
+        assert_eq!(kept.last().unwrap().seq, (EVENT_HISTORY + 10) as u64);
+    }
+}
@@ -0,0 +1,241 @@
+// Confidence floors - how sure an extraction must be, by document type, before it may sync
+
+use crate::doctypes::DocumentKind;
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::imaging::ImageKind;
+use crate::{AppState, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Floor for types the settings don't list
+const DEFAULT_FLOOR: f32 = 0.5;
+
+// High-stakes paperwork needs a surer read than a casual photo
+const BUILT_IN_FLOORS: &[(&str, f32)] = &[
+    ("title", 0.8),
+    ("registration", 0.75),
+    ("finance", 0.7),
+    ("bill_of_sale", 0.7),
+    ("inspection", 0.6),
+    ("document", 0.5),
+    ("photo", 0.3),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfidenceFloors {
+    /// Minimum confidence by document type: "title", "registration", "photo"...
+    pub floors: BTreeMap<String, f32>,
+    pub default_floor: f32,
+}
+
+impl Default for ConfidenceFloors {
+    fn default() -> Self {
+        ConfidenceFloors {
+            floors: BUILT_IN_FLOORS
+                .iter()
+                .map(|(kind, floor)| (kind.to_string(), *floor))
+                .collect(),
+            default_floor: DEFAULT_FLOOR,
+        }
+    }
+}
+
+/// Why a record isn't eligible to sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BelowFloor {
+    pub document_type: String,
+    pub confidence: f32,
+    pub floor: f32,
+}
+
+impl BelowFloor {
+    pub fn message(&self) -> String {
+        format!(
+            "{} confidence {:.2} is below its {:.2} floor",
+            self.document_type, self.confidence, self.floor
+        )
+    }
+}
+
+impl ConfidenceFloors {
+    pub fn floor(&self, document_type: &str) -> f32 {
+        self.floors
+            .get(document_type)
+            .copied()
+            .unwrap_or(self.default_floor)
+    }
+
+    /// The floor key a result is judged under: its document kind, the type the model named, or photo/document
+    pub fn document_type(&self, result: &ExtractionResult) -> String {
+        if result.document_kind != DocumentKind::General {
+            if let Ok(serde_json::Value::String(kind)) = serde_json::to_value(result.document_kind)
+            {
+                return kind;
+            }
+        }
+        if let Some(named) = result
+            .data
+            .document_type
+            .as_deref()
+            .and_then(|t| self.named_type(t))
+        {
+            return named;
+        }
+        match result.image_kind {
+            ImageKind::Photo => "photo".to_string(),
+            ImageKind::Document => "document".to_string(),
+        }
+    }
+
+    /// A scan result only has its path to go on: "1972 C10 title.jpg" is a title
+    pub fn scan_document_type(&self, result: &ScanResult) -> String {
+        if let Some(named) = self.named_type(&result.filename) {
+            return named;
+        }
+        match result.category.as_str() {
+            "image" => "photo".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    /// None when the result may sync
+    pub fn check(&self, result: &ExtractionResult) -> Option<BelowFloor> {
+        self.check_confidence(self.document_type(result), result.confidence)
+    }
+
+    /// None when the scan result's hint may sync; results without a hint have nothing to send
+    pub fn check_scan(&self, result: &ScanResult) -> Option<BelowFloor> {
+        let confidence = result.potential_vehicle.as_ref()?.confidence;
+        self.check_confidence(self.scan_document_type(result), confidence)
+    }
+
+    fn check_confidence(&self, document_type: String, confidence: f32) -> Option<BelowFloor> {
+        let floor = self.floor(&document_type);
+        (confidence < floor).then_some(BelowFloor {
+            document_type,
+            confidence,
+            floor,
+        })
+    }
+
+    /// A floor key named as a whole word in free text ("Certificate of Title" -> "title");
+    /// when several are named, the strictest wins
+    fn named_type(&self, text: &str) -> Option<String> {
+        let words: Vec<String> = text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_string())
+            .collect();
+        let text = format!(" {} ", words.join(" "));
+        self.floors
+            .iter()
+            .filter(|(key, _)| text.contains(&format!(" {} ", key.replace('_', " "))))
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(key, _)| key.clone())
+    }
+}
+
+/// Set the per-document-type confidence floors that gate sync
+#[tauri::command]
+pub async fn configure_confidence_floors(
+    state: tauri::State<'_, AppState>,
+    floors: ConfidenceFloors,
+) -> Result<(), NukeError> {
+    *state.confidence_floors.lock().unwrap() = floors;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(image_kind: &str, document_kind: &str, confidence: f32) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": "scan.jpg",
+            "model": "llava",
+            "route": "vision",
+            "image_kind": image_kind,
+            "document_kind": document_kind,
+            "data": {},
+            "confidence": confidence,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    fn scan_result(filename: &str, confidence: f32) -> ScanResult {
+        serde_json::from_value(json!({
+            "path": filename,
+            "filename": filename,
+            "file_type": "jpg",
+            "category": "image",
+            "size": 1,
+            "modified": "",
+            "potential_vehicle": {
+                "year": "1972",
+                "make": "Chevrolet",
+                "model": "C10",
+                "vin": null,
+                "source": "filename",
+                "confidence": confidence,
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn a_title_is_held_back_where_a_photo_at_the_same_confidence_passes() {
+        let floors = ConfidenceFloors::default();
+        let below = floors.check(&result("document", "title", 0.4)).unwrap();
+        assert_eq!(below.document_type, "title");
+        assert_eq!(below.floor, 0.8);
+        assert!(floors.check(&result("photo", "general", 0.4)).is_none());
+        assert!(floors.check(&result("document", "title", 0.85)).is_none());
+    }
+
+    #[test]
+    fn the_type_the_model_names_picks_the_floor() {
+        let floors = ConfidenceFloors::default();
+        let mut registration = result("document", "general", 0.7);
+        registration.data.document_type = Some("Vehicle Registration Card".to_string());
+        assert_eq!(
+            floors.check(&registration).unwrap().document_type,
+            "registration"
+        );
+
+        // Whole words only, and the strictest of several
+        assert_eq!(
+            floors.named_type("Bill of Sale"),
+            Some("bill_of_sale".to_string())
+        );
+        assert_eq!(
+            floors.named_type("title and registration"),
+            Some("title".to_string())
+        );
+        assert_eq!(floors.named_type("entitled"), None);
+    }
+
+    #[test]
+    fn scan_results_are_typed_by_filename() {
+        let floors = ConfidenceFloors::default();
+        let below = floors
+            .check_scan(&scan_result("1972 chevy c10 title.jpg", 0.4))
+            .unwrap();
+        assert_eq!(below.document_type, "title");
+        assert!(floors
+            .check_scan(&scan_result("1972 chevy c10.jpg", 0.4))
+            .is_none());
+    }
+
+    #[test]
+    fn configured_types_fall_back_to_the_default_floor() {
+        let floors: ConfidenceFloors =
+            serde_json::from_value(json!({ "floors": { "title": 0.9 } })).unwrap();
+        assert_eq!(floors.floor("title"), 0.9);
+        assert_eq!(floors.floor("registration"), DEFAULT_FLOOR);
+    }
+}
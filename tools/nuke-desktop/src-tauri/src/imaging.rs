@@ -0,0 +1,1416 @@
+// Image analysis heuristics used before sending files to a model
+
+use crate::error::NukeError;
+use crate::ocr::{self, OcrOutput};
+use image::GrayImage;
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// Images are analyzed at this size; heuristics don't need full resolution
+const ANALYSIS_SIZE: u32 = 512;
+// Enough for a model to read small print on a letter-size page
+const PDF_RENDER_DPI: u32 = 150;
+// Pages rendered from one PDF; long contracts rarely carry vehicle details past this
+const MAX_PDF_PAGES: usize = 20;
+// Bundled pdfium library folder; see `set_pdfium_dir`
+static PDFIUM_DIR: OnceLock<PathBuf> = OnceLock::new();
+// Pdfium's library setup and teardown mustn't overlap, so renders take turns
+static PDF_RENDER_LOCK: Mutex<()> = Mutex::new(());
+// Extensions decoded through `convert_heif`
+const HEIF_EXTENSIONS: &[&str] = &["heic", "heif", "hif"];
+// Transcoded HEIC copies are re-encoded again for the model, so keep this one close to lossless
+const HEIF_JPEG_QUALITY: u8 = 95;
+// Longest edge sent to the model; vision models tile or downscale anything bigger anyway
+pub const DEFAULT_MAX_DIMENSION: u32 = 1536;
+// Re-encode quality for model payloads; small print stays legible
+const MODEL_JPEG_QUALITY: u8 = 85;
+
+/// Load an image as a downscaled grayscale copy for analysis
+pub fn load_grayscale(path: &Path) -> Option<GrayImage> {
+    let img = image::open(path).ok()?;
+    Some(img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8())
+}
+
+/// Fraction of pixels that don't belong to the dominant tone (0 = flat page, 1 = busy photo)
+pub fn visual_complexity(img: &GrayImage) -> f32 {
+    let total = (img.width() * img.height()) as f32;
+    if total == 0.0 {
+        return 0.0;
+    }
+
+    let mut histogram = [0u32; 256];
+    for pixel in img.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    // Documents are mostly paper: one tone band dominates the histogram
+    let mode = histogram
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .map(|(value, _)| value)
+        .unwrap_or(0);
+    let low = mode.saturating_sub(24);
+    let high = (mode + 24).min(255);
+    let background: u32 = histogram[low..=high].iter().sum();
+
+    1.0 - background as f32 / total
+}
+
+/// Whether an image is a paper document or a photograph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageKind {
+    Document,
+    Photo,
+}
+
+/// Fraction of rows that are long horizontal edges (form lines, table rules, page edges)
+pub fn horizontal_line_ratio(img: &GrayImage) -> f32 {
+    let (width, height) = img.dimensions();
+    if width == 0 || height < 2 {
+        return 0.0;
+    }
+
+    let mut line_rows = 0;
+    for y in 1..height {
+        let strong = (0..width)
+            .filter(|&x| {
+                let above = img.get_pixel(x, y - 1)[0] as i16;
+                let here = img.get_pixel(x, y)[0] as i16;
+                (above - here).abs() > 40
+            })
+            .count();
+        if strong as f32 > width as f32 * 0.5 {
+            line_rows += 1;
+        }
+    }
+
+    line_rows as f32 / height as f32
+}
+
+/// Width:height range a vision prompt handles; receipt strips and panoramas fall outside
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AspectRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for AspectRange {
+    fn default() -> Self {
+        AspectRange {
+            min: 0.33,
+            max: 3.0,
+        }
+    }
+}
+
+impl AspectRange {
+    pub fn contains(&self, ratio: f32) -> bool {
+        ratio >= self.min && ratio <= self.max
+    }
+}
+
+/// Width / height from the image header, without decoding the pixels
+pub fn aspect_ratio(path: &Path) -> Option<f32> {
+    let (width, height) = image::image_dimensions(path).ok()?;
+    (height > 0).then(|| width as f32 / height as f32)
+}
+
+/// Quick document-vs-photo call from OCR word count plus tone and line structure
+pub fn classify_image(img: Option<&GrayImage>, ocr_words: usize) -> ImageKind {
+    let mut document_signals = 0;
+    if ocr_words >= 15 {
+        document_signals += 1;
+    }
+
+    let required = match img {
+        Some(img) => {
+            if visual_complexity(img) < 0.5 {
+                document_signals += 1;
+            }
+            if horizontal_line_ratio(img) >= 0.01 {
+                document_signals += 1;
+            }
+            2
+        }
+        // Can't decode it (HEIC, PDF); go on text alone
+        None => 1,
+    };
+
+    if document_signals >= required {
+        ImageKind::Document
+    } else {
+        ImageKind::Photo
+    }
+}
+
+// Tesseract reads clean print well above this; handwriting rarely reaches it
+const HANDWRITING_MAX_OCR_CONFIDENCE: f32 = 55.0;
+
+/// Whether a page is mostly handwriting: paper-like, carries real ink, but OCR can't read it
+pub fn is_handwritten(img: Option<&GrayImage>, ocr_output: Option<&OcrOutput>) -> bool {
+    let img = match img {
+        Some(img) => img,
+        None => return false,
+    };
+    let total = (img.width() * img.height()) as f32;
+    if total == 0.0 || visual_complexity(img) >= 0.5 {
+        return false;
+    }
+
+    let ink = img.pixels().filter(|p| p[0] < 128).count() as f32 / total;
+    let unreadable = ocr_output
+        .map(|o| o.word_count == 0 || o.mean_confidence < HANDWRITING_MAX_OCR_CONFIDENCE)
+        .unwrap_or(false);
+    ink >= 0.02 && unreadable
+}
+
+/// Grid layout of an image made of several photos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollageLayout {
+    pub rows: usize,
+    pub cols: usize,
+    /// Divider positions in original image pixels
+    pub row_splits: Vec<u32>,
+    pub col_splits: Vec<u32>,
+}
+
+/// Detect a collage: straight dividers (gutters or hard seams) running edge to edge
+pub fn detect_collage(path: &Path) -> Option<CollageLayout> {
+    let img = image::open(path).ok()?;
+    let (width, height) = (img.width(), img.height());
+    let small = img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+    let layout = collage_layout(&small)?;
+
+    // Scale divider positions back up to the original image
+    let scale_rows = height as f32 / small.height() as f32;
+    let scale_cols = width as f32 / small.width() as f32;
+    Some(CollageLayout {
+        row_splits: layout
+            .row_splits
+            .iter()
+            .map(|y| (*y as f32 * scale_rows) as u32)
+            .collect(),
+        col_splits: layout
+            .col_splits
+            .iter()
+            .map(|x| (*x as f32 * scale_cols) as u32)
+            .collect(),
+        ..layout
+    })
+}
+
+/// Grid layout of a (downscaled) grayscale image, or None for a single photo
+pub fn collage_layout(img: &GrayImage) -> Option<CollageLayout> {
+    let (width, height) = img.dimensions();
+    if width < 16 || height < 16 {
+        return None;
+    }
+
+    let row_splits = divider_positions(height, width, |along, across| {
+        img.get_pixel(across, along)[0]
+    });
+    let col_splits = divider_positions(width, height, |along, across| {
+        img.get_pixel(along, across)[0]
+    });
+    if row_splits.is_empty() && col_splits.is_empty() {
+        return None;
+    }
+
+    Some(CollageLayout {
+        rows: row_splits.len() + 1,
+        cols: col_splits.len() + 1,
+        row_splits,
+        col_splits,
+    })
+}
+
+/// Positions along one axis where a divider line spans the whole other axis
+///
+/// `pixel(along, across)` reads the image with `along` as the axis being searched.
+fn divider_positions(length: u32, span: u32, pixel: impl Fn(u32, u32) -> u8) -> Vec<u32> {
+    // Dividers near the border are frames, not splits
+    let start = length / 10;
+    let end = length - length / 10;
+
+    let line_mean = |at: u32| (0..span).map(|i| pixel(at, i) as f32).sum::<f32>() / span as f32;
+    let is_gutter = |at: u32| {
+        let mean = line_mean(at);
+        (0..span).all(|i| (pixel(at, i) as f32 - mean).abs() < 12.0)
+    };
+    let is_seam = |at: u32| {
+        let strong = (0..span)
+            .filter(|&i| (pixel(at, i) as i16 - pixel(at - 1, i) as i16).abs() > 30)
+            .count();
+        strong as f32 >= span as f32 * 0.9
+    };
+
+    let mut candidates: Vec<u32> = Vec::new();
+    let mut gutter_run: Vec<u32> = Vec::new();
+    for at in start.max(1)..end {
+        if is_gutter(at) {
+            gutter_run.push(at);
+            continue;
+        }
+        // Thin flat bands are gutters; wide ones are sky, walls or paper
+        if !gutter_run.is_empty() && gutter_run.len() as u32 <= length / 20 {
+            candidates.push(gutter_run[gutter_run.len() / 2]);
+        }
+        gutter_run.clear();
+        if is_seam(at) {
+            candidates.push(at);
+        }
+    }
+
+    // Merge neighbouring hits and keep sub-regions a sensible size
+    let min_gap = length * 15 / 100;
+    let mut splits: Vec<u32> = Vec::new();
+    for at in candidates {
+        match splits.last() {
+            Some(last) if at - last < min_gap => {}
+            _ if at < min_gap || length - at < min_gap => {}
+            _ => splits.push(at),
+        }
+    }
+    splits
+}
+
+/// Split a collage into one image per cell so each photo can be analyzed on its own
+#[tauri::command]
+pub async fn split_collage(path: String, output_dir: String) -> Result<Vec<String>, NukeError> {
+    let layout = detect_collage(Path::new(&path))
+        .ok_or_else(|| NukeError::InvalidInput("Image doesn't look like a collage".to_string()))?;
+    write_collage_cells(Path::new(&path), &layout, Path::new(&output_dir))
+}
+
+/// Crop each cell of a collage into its own image in `output_dir`
+pub fn write_collage_cells(
+    path: &Path,
+    layout: &CollageLayout,
+    output_dir: &Path,
+) -> Result<Vec<String>, NukeError> {
+    let img = image::open(path)
+        .map_err(|e| NukeError::ImageDecode(format!("Failed to open image: {}", e)))?;
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to create output dir: {}", e)))?;
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "collage".to_string());
+
+    let edges = |splits: &[u32], length: u32| {
+        let mut edges = vec![0];
+        edges.extend_from_slice(splits);
+        edges.push(length);
+        edges
+    };
+    let row_edges = edges(&layout.row_splits, img.height());
+    let col_edges = edges(&layout.col_splits, img.width());
+
+    let mut written = Vec::new();
+    for (row, ys) in row_edges.windows(2).enumerate() {
+        for (col, xs) in col_edges.windows(2).enumerate() {
+            let cell = img.crop_imm(xs[0], ys[0], xs[1] - xs[0], ys[1] - ys[0]);
+            let out = output_dir.join(format!("{}_r{}c{}.png", stem, row + 1, col + 1));
+            cell.save(&out).map_err(|e| {
+                NukeError::FileWrite(format!("Failed to write {}: {}", out.display(), e))
+            })?;
+            written.push(out.to_string_lossy().to_string());
+        }
+    }
+    Ok(written)
+}
+
+// Pairs matching below this aren't treated as sections of one document
+const SECTION_MATCH_MIN_CONFIDENCE: f32 = 0.35;
+// Stitches with any seam below this are flagged for review
+const STITCH_REVIEW_CONFIDENCE: f32 = 0.6;
+// Mean edge strength an overlap needs; blank margins match anything
+const MIN_OVERLAP_TEXTURE: f32 = 2.0;
+
+/// Where one section of a split scan overlaps the next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionOverlap {
+    /// Rows at the bottom of the upper section repeated at the top of the lower one
+    pub rows: u32,
+    /// How much better this overlap matched than other offsets (0-1)
+    pub confidence: f32,
+}
+
+/// One document reassembled from scans of its sections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StitchedScan {
+    /// The stitched image, ready for `process_document`
+    pub path: String,
+    /// Section files, top to bottom
+    pub sources: Vec<String>,
+    pub overlaps: Vec<SectionOverlap>,
+    /// The weakest seam's confidence
+    pub confidence: f32,
+    /// Some seam matched weakly; check the stitched image before trusting it
+    pub needs_review: bool,
+}
+
+/// Find consecutive files that are overlapping sections of one document and stitch each
+/// run into a single image in `output_dir`. Files that stand alone are left out.
+#[tauri::command]
+pub async fn stitch_split_scans(
+    paths: Vec<String>,
+    output_dir: String,
+) -> Result<Vec<StitchedScan>, NukeError> {
+    tokio::task::spawn_blocking(move || {
+        let open = |path: &str| {
+            image::open(path)
+                .map(Some)
+                .map_err(|e| NukeError::ImageDecode(format!("Failed to open {}: {}", path, e)))
+        };
+        stitch_sections(&paths, open, Path::new(&output_dir))
+    })
+    .await
+    .map_err(|e| NukeError::Other(format!("Stitch task failed: {}", e)))?
+}
+
+/// Like `stitch_split_scans`, for a batch about to be extracted: files that aren't images
+/// (PDFs, HEIC) don't fail it, they just can't be sections
+pub async fn stitch_batch_sections(
+    paths: Vec<String>,
+    output_dir: PathBuf,
+) -> Result<Vec<StitchedScan>, NukeError> {
+    tokio::task::spawn_blocking(move || {
+        stitch_sections(&paths, |path| Ok(image::open(path).ok()), &output_dir)
+    })
+    .await
+    .map_err(|e| NukeError::Other(format!("Stitch task failed: {}", e)))?
+}
+
+/// Stitch each run of consecutive overlapping sections, decoding one file at a time so only
+/// the current run is held in memory; a file `open` can't read ends a run
+fn stitch_sections(
+    paths: &[String],
+    open: impl Fn(&str) -> Result<Option<image::DynamicImage>, NukeError>,
+    output_dir: &Path,
+) -> Result<Vec<StitchedScan>, NukeError> {
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to create output dir: {}", e)))?;
+    let mut stitched = Vec::new();
+    // The current run: indices into `paths` with their images, and the overlap after each
+    // but the last
+    let mut run: Vec<(usize, image::DynamicImage)> = Vec::new();
+    let mut overlaps: Vec<SectionOverlap> = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        let image = open(path)?;
+        let overlap = match (run.last(), &image) {
+            (Some((_, upper)), Some(lower)) => section_overlap(upper, lower),
+            _ => None,
+        };
+        match overlap {
+            Some(overlap) if overlap.confidence >= SECTION_MATCH_MIN_CONFIDENCE => {
+                overlaps.push(overlap);
+            }
+            _ => {
+                let ended = std::mem::take(&mut run);
+                let ended_overlaps = std::mem::take(&mut overlaps);
+                stitched.extend(stitch_run(paths, &ended, ended_overlaps, output_dir)?);
+            }
+        }
+        if let Some(image) = image {
+            run.push((i, image));
+        }
+    }
+    stitched.extend(stitch_run(paths, &run, overlaps, output_dir)?);
+    Ok(stitched)
+}
+
+/// Write one run of sections as a stitched image; None for a file that stands alone
+fn stitch_run(
+    paths: &[String],
+    run: &[(usize, image::DynamicImage)],
+    overlaps: Vec<SectionOverlap>,
+    output_dir: &Path,
+) -> Result<Option<StitchedScan>, NukeError> {
+    if run.len() < 2 {
+        return Ok(None);
+    }
+    let sections: Vec<&image::DynamicImage> = run.iter().map(|(_, image)| image).collect();
+    let rows: Vec<u32> = overlaps.iter().map(|o| o.rows).collect();
+    let first = Path::new(&paths[run[0].0]);
+    let stem = first
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "scan".to_string());
+    let out = output_dir.join(format!("{}_stitched.png", stem));
+    stitch_vertical(&sections, &rows)
+        .save(&out)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to write {}: {}", out.display(), e)))?;
+
+    let confidence = overlaps.iter().map(|o| o.confidence).fold(1.0, f32::min);
+    Ok(Some(StitchedScan {
+        path: out.to_string_lossy().to_string(),
+        sources: run.iter().map(|(i, _)| paths[*i].clone()).collect(),
+        overlaps,
+        confidence,
+        needs_review: confidence < STITCH_REVIEW_CONFIDENCE,
+    }))
+}
+
+/// How the bottom of `upper` overlaps the top of `lower`, in `upper`'s full-resolution rows
+pub fn section_overlap(
+    upper: &image::DynamicImage,
+    lower: &image::DynamicImage,
+) -> Option<SectionOverlap> {
+    // Sections of one scan share a width; allow for slightly different crops
+    let width_ratio = upper.width() as f32 / lower.width().max(1) as f32;
+    if !(0.85..=1.15).contains(&width_ratio) {
+        return None;
+    }
+
+    // Compare at analysis size, with the lower section scaled to the upper's width
+    let width = upper.width().min(ANALYSIS_SIZE);
+    let scaled = |img: &image::DynamicImage| {
+        let height = (img.height() as f32 * width as f32 / img.width() as f32).round() as u32;
+        image::imageops::resize(
+            &img.to_luma8(),
+            width,
+            height.max(1),
+            image::imageops::FilterType::Triangle,
+        )
+    };
+    let (small_upper, small_lower) = (scaled(upper), scaled(lower));
+    let (rows, confidence) = vertical_overlap(&small_upper, &small_lower)?;
+
+    let scale = upper.height() as f32 / small_upper.height() as f32;
+    Some(SectionOverlap {
+        rows: (rows as f32 * scale).round() as u32,
+        confidence,
+    })
+}
+
+/// Overlap in rows between the bottom of `upper` and the top of `lower` (same width),
+/// matched on edge maps so text and rules line up rather than paper tone, with its confidence
+pub fn vertical_overlap(upper: &GrayImage, lower: &GrayImage) -> Option<(u32, f32)> {
+    let width = upper.width().min(lower.width());
+    let shorter = upper.height().min(lower.height());
+    let min_rows = (shorter / 25).max(4);
+    let max_rows = shorter * 3 / 5;
+    if width < 8 || max_rows <= min_rows {
+        return None;
+    }
+
+    // A light blur keeps sub-pixel misregistration between the two scans from dominating
+    let edges = |img: &GrayImage| edge_map(&image::imageops::blur(img, 1.0));
+    let (upper_edges, lower_edges) = (edges(upper), edges(lower));
+    let edge = |img: &[Vec<f32>], x: u32, y: u32| img[y as usize][x as usize];
+    let top = upper.height();
+
+    // Mean edge difference for each candidate overlap
+    let mut scores: Vec<(u32, f32)> = Vec::new();
+    for rows in min_rows..=max_rows {
+        let (mut diff, mut count) = (0.0, 0);
+        for y in 0..rows {
+            for x in (0..width).step_by(2) {
+                diff += (edge(&upper_edges, x, top - rows + y) - edge(&lower_edges, x, y)).abs();
+                count += 1;
+            }
+        }
+        scores.push((rows, diff / count as f32));
+    }
+
+    let &(rows, best) = scores.iter().min_by(|a, b| a.1.total_cmp(&b.1))?;
+    let texture = (0..rows)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| edge(&lower_edges, x, y))
+        .sum::<f32>()
+        / (rows * width) as f32;
+    if texture < MIN_OVERLAP_TEXTURE {
+        return None;
+    }
+
+    // A true overlap matches far better than a typical offset
+    let mut diffs: Vec<f32> = scores.iter().map(|(_, d)| *d).collect();
+    diffs.sort_by(|a, b| a.total_cmp(b));
+    let median = diffs[diffs.len() / 2];
+    let confidence = if median > 0.0 {
+        (1.0 - best / median).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Some((rows, confidence))
+}
+
+/// Gradient magnitude per pixel (right and down neighbours)
+fn edge_map(img: &GrayImage) -> Vec<Vec<f32>> {
+    let (width, height) = img.dimensions();
+    let pixel = |x: u32, y: u32| img.get_pixel(x.min(width - 1), y.min(height - 1))[0] as f32;
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    let here = pixel(x, y);
+                    (pixel(x + 1, y) - here).abs() + (pixel(x, y + 1) - here).abs()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Stack sections top to bottom, dropping each seam's overlapping rows from the lower section
+pub fn stitch_vertical(sections: &[&image::DynamicImage], overlaps: &[u32]) -> image::RgbImage {
+    let width = sections.first().map(|s| s.width()).unwrap_or(0);
+    let resized: Vec<image::RgbImage> = sections
+        .iter()
+        .map(|s| {
+            let rgb = s.to_rgb8();
+            if s.width() == width {
+                return rgb;
+            }
+            let height = (s.height() as f32 * width as f32 / s.width() as f32).round() as u32;
+            image::imageops::resize(
+                &rgb,
+                width,
+                height.max(1),
+                image::imageops::FilterType::Triangle,
+            )
+        })
+        .collect();
+
+    let total: u32 = resized.iter().map(|s| s.height()).sum();
+    let height = total.saturating_sub(overlaps.iter().sum());
+    let mut out = image::RgbImage::new(width, height);
+    let mut y = 0i64;
+    for (i, section) in resized.iter().enumerate() {
+        if i > 0 {
+            y -= overlaps.get(i - 1).copied().unwrap_or(0) as i64;
+        }
+        image::imageops::overlay(&mut out, section, 0, y);
+        y += section.height() as i64;
+    }
+    out
+}
+
+// A letter page under ~200 DPI fits within this many pixels on its long side
+const LOW_DPI_MAX_LONG_SIDE: u32 = 2300;
+
+/// Whether an image looks like a fax or low-DPI scan: small, near-bilevel, and speckled
+pub fn is_low_dpi_scan(img: &GrayImage) -> bool {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 || width.max(height) > LOW_DPI_MAX_LONG_SIDE {
+        return false;
+    }
+
+    let total = (width * height) as f32;
+    let bilevel = img.pixels().filter(|p| p[0] < 48 || p[0] > 207).count() as f32 / total;
+    bilevel >= 0.9 && speck_ratio(img) >= 0.002
+}
+
+/// Fraction of pixels that are isolated specks: dark on an all-light neighbourhood or vice versa
+pub fn speck_ratio(img: &GrayImage) -> f32 {
+    let (width, height) = img.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let mut specks = 0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let dark = img.get_pixel(x, y)[0] < 128;
+            let isolated =
+                neighbours(x, y).all(|(nx, ny)| (img.get_pixel(nx, ny)[0] < 128) != dark);
+            if isolated {
+                specks += 1;
+            }
+        }
+    }
+    specks as f32 / (width * height) as f32
+}
+
+fn neighbours(x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> {
+    [
+        (-1i32, -1i32),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ]
+    .into_iter()
+    .map(move |(dx, dy)| ((x as i32 + dx) as u32, (y as i32 + dy) as u32))
+}
+
+/// Clean up a fax-quality scan: despeckle, upscale 2x with light sharpening, binarize
+pub fn enhance_scan(img: &GrayImage) -> GrayImage {
+    let despeckled = median_filter(img);
+    let (width, height) = despeckled.dimensions();
+    let upscaled = image::imageops::resize(
+        &despeckled,
+        width * 2,
+        height * 2,
+        image::imageops::FilterType::CatmullRom,
+    );
+    let sharpened = image::imageops::unsharpen(&upscaled, 1.0, 10);
+
+    let threshold = otsu_threshold(&sharpened);
+    GrayImage::from_fn(sharpened.width(), sharpened.height(), |x, y| {
+        let value = if sharpened.get_pixel(x, y)[0] > threshold {
+            255
+        } else {
+            0
+        };
+        image::Luma([value])
+    })
+}
+
+/// Bring up low-contrast etched glass: upscale, stretch the mid-tones across the full range,
+/// then sharpen hard so frosted strokes separate from what's behind the glass
+pub fn enhance_etching(img: &GrayImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    let upscaled = image::imageops::resize(
+        img,
+        width * 2,
+        height * 2,
+        image::imageops::FilterType::CatmullRom,
+    );
+
+    let mut values: Vec<u8> = upscaled.pixels().map(|p| p[0]).collect();
+    values.sort_unstable();
+    let percentile = |p: usize| values.get(values.len() * p / 100).copied().unwrap_or(0);
+    let (low, high) = (percentile(2) as f32, percentile(98) as f32);
+    let range = (high - low).max(1.0);
+    let stretched = GrayImage::from_fn(upscaled.width(), upscaled.height(), |x, y| {
+        let value = (upscaled.get_pixel(x, y)[0] as f32 - low) / range * 255.0;
+        image::Luma([value.clamp(0.0, 255.0) as u8])
+    });
+    image::imageops::unsharpen(&stretched, 2.0, 5)
+}
+
+/// Enhanced and inverted copies of a glass-etch photo as temporary PNGs; etching reads
+/// light-on-dark or dark-on-light depending on what's behind the glass
+pub fn etching_variants(path: &Path) -> Vec<PathBuf> {
+    let img = match image::open(path) {
+        Ok(img) => img.to_luma8(),
+        Err(_) => return Vec::new(),
+    };
+    let enhanced = enhance_etching(&img);
+    let mut inverted = enhanced.clone();
+    image::imageops::invert(&mut inverted);
+    [(enhanced, "etch"), (inverted, "etch-inverted")]
+        .iter()
+        .filter_map(|(variant, name)| save_temp_variant(variant, name))
+        .collect()
+}
+
+/// Shrink image bytes for a model request: downscale so the longest edge fits
+/// `max_dimension` and re-encode as JPEG. Bytes the `image` crate can't decode, and
+/// JPEGs already within bounds, are returned unchanged.
+pub fn prepare_for_model(bytes: &[u8], max_dimension: u32) -> Vec<u8> {
+    let Ok(img) = image::load_from_memory(bytes) else {
+        return bytes.to_vec();
+    };
+    let oversized = img.width().max(img.height()) > max_dimension;
+    let is_jpeg = image::guess_format(bytes).ok() == Some(image::ImageFormat::Jpeg);
+    if !oversized && is_jpeg {
+        return bytes.to_vec();
+    }
+
+    let img = if oversized {
+        img.resize(
+            max_dimension,
+            max_dimension,
+            image::imageops::FilterType::Triangle,
+        )
+    } else {
+        img
+    };
+    let mut encoded = Vec::new();
+    let encoder =
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut encoded, MODEL_JPEG_QUALITY);
+    if img.to_rgb8().write_with_encoder(encoder).is_err() {
+        return bytes.to_vec();
+    }
+    // A small PNG of flat text can beat JPEG; keep whichever is smaller
+    if !oversized && encoded.len() >= bytes.len() {
+        return bytes.to_vec();
+    }
+    encoded
+}
+
+/// HEIC/HEIF, the iPhone photo format, which nothing downstream can decode directly
+pub fn is_heif(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| HEIF_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Transcode a HEIC/HEIF image to JPEG bytes with libheif
+pub async fn heif_to_jpeg(path: &Path) -> Result<Vec<u8>, String> {
+    let source = path.to_path_buf();
+    tokio::task::spawn_blocking(move || decode_heif_to_jpeg(&source))
+        .await
+        .map_err(|e| format!("HEIC decode task failed: {}", e))?
+        .map_err(|e| format!("Failed to decode HEIC {}: {}", path.display(), e))
+}
+
+/// Transcode a HEIC/HEIF image to a new temporary JPEG, for steps that read from a file
+pub async fn convert_heif(path: &Path) -> Result<PathBuf, String> {
+    let jpeg = heif_to_jpeg(path).await?;
+    let mut file = tempfile::Builder::new()
+        .prefix("nuke-heic-")
+        .suffix(".jpg")
+        .tempfile()
+        .map_err(|e| format!("Failed to create JPEG: {}", e))?;
+    file.write_all(&jpeg)
+        .map_err(|e| format!("Failed to write JPEG: {}", e))?;
+    file.into_temp_path()
+        .keep()
+        .map_err(|e| format!("Failed to keep JPEG: {}", e))
+}
+
+fn decode_heif_to_jpeg(path: &Path) -> Result<Vec<u8>, String> {
+    let lib_heif = LibHeif::new();
+    let context =
+        HeifContext::read_from_file(&path.to_string_lossy()).map_err(|e| e.to_string())?;
+    let handle = context.primary_image_handle().map_err(|e| e.to_string())?;
+    // Decoding applies the file's rotation and mirroring, so the JPEG needs no EXIF orientation
+    let decoded = lib_heif
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| e.to_string())?;
+    let plane = decoded
+        .planes()
+        .interleaved
+        .ok_or("Decoded image has no RGB plane")?;
+
+    // Rows can be padded past the pixels they hold
+    let row_bytes = plane.width as usize * 3;
+    let pixels: Vec<u8> = plane
+        .data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|row| &row[..row_bytes])
+        .copied()
+        .collect();
+    let rgb = image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .ok_or("Decoded image is smaller than its dimensions")?;
+
+    let mut jpeg = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, HEIF_JPEG_QUALITY);
+    rgb.write_with_encoder(encoder).map_err(|e| e.to_string())?;
+    Ok(jpeg)
+}
+
+/// Where the bundled pdfium library lives; set once at startup from the app's resources
+pub fn set_pdfium_dir(dir: PathBuf) {
+    let _ = PDFIUM_DIR.set(dir);
+}
+
+/// Bind the pdfium library bundled with the app, falling back to one installed on the system
+fn bind_pdfium() -> Result<Pdfium, String> {
+    let bundled = PDFIUM_DIR
+        .get()
+        .map(|dir| Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(dir)));
+    let bindings = match bundled {
+        Some(Ok(bindings)) => bindings,
+        _ => Pdfium::bind_to_system_library()
+            .map_err(|e| format!("PDF rendering needs the pdfium library: {}", e))?,
+    };
+    Ok(Pdfium::new(bindings))
+}
+
+/// Render each page of a PDF to a PNG in a fresh temp directory, in page order
+pub async fn render_pdf_pages(path: &Path) -> Result<Vec<PathBuf>, String> {
+    // A fresh folder per call, so extractions of the same PDF can't clear each other's pages
+    let dir = tempfile::Builder::new()
+        .prefix("nuke-pdf-")
+        .tempdir()
+        .map_err(|e| format!("Failed to create page folder: {}", e))?
+        .keep();
+
+    let (source, out) = (path.to_path_buf(), dir.clone());
+    let rendered = tokio::task::spawn_blocking(move || render_pages_into(&source, &out))
+        .await
+        .map_err(|e| format!("PDF render task failed: {}", e))?;
+    match rendered {
+        Ok(pages) if !pages.is_empty() => Ok(pages),
+        Ok(_) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            Err(format!("{}: PDF has no pages", path.display()))
+        }
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&dir);
+            Err(e)
+        }
+    }
+}
+
+fn render_pages_into(path: &Path, dir: &Path) -> Result<Vec<PathBuf>, String> {
+    // A poisoned lock only means another render panicked; pdfium itself is torn down on drop
+    let _turn = PDF_RENDER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let pdfium = bind_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|e| format!("Failed to open PDF: {}", e))?;
+
+    // Page sizes are in points, 72 to the inch
+    let config = PdfRenderConfig::new().scale_page_by_factor(PDF_RENDER_DPI as f32 / 72.0);
+    let mut pages = Vec::new();
+    for (index, page) in document.pages().iter().take(MAX_PDF_PAGES).enumerate() {
+        let image = page
+            .render_with_config(&config)
+            .map_err(|e| format!("Failed to render page {}: {}", index + 1, e))?
+            .as_image();
+        // Zero-padded so the names sort in page order
+        let out = dir.join(format!("page-{:02}.png", index + 1));
+        image
+            .save(&out)
+            .map_err(|e| format!("Failed to save page {}: {}", index + 1, e))?;
+        pages.push(out);
+    }
+    Ok(pages)
+}
+
+/// The embedded text of a PDF's pages, in page order, one page per paragraph
+pub async fn pdf_text_layer(path: &Path) -> Result<String, String> {
+    let source = path.to_path_buf();
+    tokio::task::spawn_blocking(move || read_pdf_text(&source))
+        .await
+        .map_err(|e| format!("PDF text task failed: {}", e))?
+}
+
+fn read_pdf_text(path: &Path) -> Result<String, String> {
+    let _turn = PDF_RENDER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let pdfium = bind_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|e| format!("Failed to open PDF: {}", e))?;
+
+    let mut pages = Vec::new();
+    for (index, page) in document.pages().iter().enumerate() {
+        let text = page
+            .text()
+            .map_err(|e| format!("Failed to read text of page {}: {}", index + 1, e))?;
+        pages.push(text.all());
+    }
+    Ok(pages.join("\n\n"))
+}
+
+/// Enhance a low-DPI scan into a temporary PNG, returning its path; None if the image is fine
+pub fn enhance_if_low_dpi(path: &Path) -> Option<PathBuf> {
+    let img = image::open(path).ok()?.to_luma8();
+    if !is_low_dpi_scan(&img) {
+        return None;
+    }
+
+    save_temp_variant(&enhance_scan(&img), "enhanced")
+}
+
+/// Save a processed copy of an image as a new PNG in the temp directory, named for its variant
+fn save_temp_variant(img: &GrayImage, variant: &str) -> Option<PathBuf> {
+    let (file, out) = tempfile::Builder::new()
+        .prefix(&format!("nuke-{}-", variant))
+        .suffix(".png")
+        .tempfile()
+        .ok()?
+        .keep()
+        .ok()?;
+    let written = img.write_to(&mut std::io::BufWriter::new(file), image::ImageFormat::Png);
+    if written.is_err() {
+        let _ = std::fs::remove_file(&out);
+        return None;
+    }
+    Some(out)
+}
+
+/// 3x3 median filter; removes salt-and-pepper noise without smearing strokes much
+fn median_filter(img: &GrayImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    GrayImage::from_fn(width, height, |x, y| {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            return *img.get_pixel(x, y);
+        }
+        let mut window: Vec<u8> = neighbours(x, y)
+            .map(|(nx, ny)| img.get_pixel(nx, ny)[0])
+            .collect();
+        window.push(img.get_pixel(x, y)[0]);
+        window.sort_unstable();
+        image::Luma([window[4]])
+    })
+}
+
+/// Threshold that best separates ink from paper (Otsu's method)
+fn otsu_threshold(img: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let weighted_total: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(v, c)| v as f64 * *c as f64)
+        .sum();
+
+    let mut best = (0u8, 0.0f64);
+    let mut background = 0u64;
+    let mut weighted_background = 0.0;
+    for (value, count) in histogram.iter().enumerate() {
+        background += count;
+        if background == 0 || background == total {
+            continue;
+        }
+        weighted_background += value as f64 * *count as f64;
+        let foreground = total - background;
+        let mean_background = weighted_background / background as f64;
+        let mean_foreground = (weighted_total - weighted_background) / foreground as f64;
+        let variance =
+            background as f64 * foreground as f64 * (mean_background - mean_foreground).powi(2);
+        if variance > best.1 {
+            best = (value as u8, variance);
+        }
+    }
+    best.0
+}
+
+/// A fix applied to a scan before extraction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanCorrection {
+    /// Color negative: light text on a dark page
+    Inverted,
+    /// Horizontally mirrored text
+    Mirrored,
+}
+
+/// A corrected copy of a scan, if any correction applied, with the OCR of the final image
+pub struct CorrectedScan {
+    pub path: Option<PathBuf>,
+    pub corrections: Vec<ScanCorrection>,
+    pub ocr: Option<OcrOutput>,
+}
+
+/// Whether a page looks color-inverted: a flat, dark dominant tone where paper should be
+pub fn looks_negative(img: &GrayImage) -> bool {
+    let total = (img.width() * img.height()) as f32;
+    if total == 0.0 {
+        return false;
+    }
+    let dark = img.pixels().filter(|p| p[0] < 96).count() as f32 / total;
+    // A night photo is dark too, but busy rather than flat
+    dark >= 0.6 && visual_complexity(img) < 0.5
+}
+
+// OCR this far below a readable page is worth retrying mirrored
+const MIRROR_CHECK_MAX_CONFIDENCE: f32 = 70.0;
+
+/// Undo negative and mirrored scans; `ocr_output` is the OCR already run on `path`
+pub async fn correct_scan(path: &Path, ocr_output: Option<OcrOutput>) -> CorrectedScan {
+    let unchanged = |ocr| CorrectedScan {
+        path: None,
+        corrections: Vec::new(),
+        ocr,
+    };
+    let mut img = match image::open(path) {
+        Ok(img) => img.to_luma8(),
+        Err(_) => return unchanged(ocr_output),
+    };
+
+    let mut corrections = Vec::new();
+    let mut best_ocr = ocr_output;
+
+    let small = image::imageops::thumbnail(&img, ANALYSIS_SIZE, ANALYSIS_SIZE);
+    if looks_negative(&small) {
+        image::imageops::invert(&mut img);
+        corrections.push(ScanCorrection::Inverted);
+        best_ocr = match save_temp_variant(&img, "inverted") {
+            Some(inverted) => {
+                let ocr = ocr::run_ocr(&inverted).await.ok();
+                let _ = std::fs::remove_file(&inverted);
+                ocr
+            }
+            None => None,
+        };
+    }
+
+    // Mirrored text reads as a few low-confidence fragments; compare against a flipped copy
+    let needs_mirror_check = best_ocr
+        .as_ref()
+        .map(|o| o.word_count >= 3 && o.mean_confidence < MIRROR_CHECK_MAX_CONFIDENCE)
+        .unwrap_or(false);
+    if needs_mirror_check {
+        let flipped = image::imageops::flip_horizontal(&img);
+        if let Some(flipped_path) = save_temp_variant(&flipped, "mirrored") {
+            let flipped_ocr = ocr::run_ocr(&flipped_path).await.ok();
+            let _ = std::fs::remove_file(&flipped_path);
+            if let Some(flipped_ocr) = flipped_ocr {
+                if mirror_reads_better(best_ocr.as_ref(), &flipped_ocr) {
+                    img = flipped;
+                    corrections.push(ScanCorrection::Mirrored);
+                    best_ocr = Some(flipped_ocr);
+                }
+            }
+        }
+    }
+
+    if corrections.is_empty() {
+        return unchanged(best_ocr);
+    }
+    CorrectedScan {
+        path: save_temp_variant(&img, "corrected"),
+        corrections,
+        ocr: best_ocr,
+    }
+}
+
+/// The flipped copy wins only by a clear margin, so normal text never gets flipped
+pub fn mirror_reads_better(original: Option<&OcrOutput>, flipped: &OcrOutput) -> bool {
+    let score = |o: &OcrOutput| o.word_count as f32 * o.mean_confidence;
+    let original_score = original.map(score).unwrap_or(0.0);
+    flipped.mean_confidence >= 50.0 && score(flipped) > original_score * 1.5
+}
+
+/// Pixel count and 64-bit perceptual hash of an image file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScanFingerprint {
+    pub hash: u64,
+    pub pixels: u64,
+}
+
+/// Fingerprint an image for matching re-scans of the same page
+pub fn scan_fingerprint(path: &Path) -> Option<ScanFingerprint> {
+    let img = image::open(path).ok()?;
+    let pixels = img.width() as u64 * img.height() as u64;
+    let gray = img.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+    Some(ScanFingerprint {
+        hash: perceptual_hash(&gray),
+        pixels,
+    })
+}
+
+/// Difference hash over a 9x8 thumbnail; survives rescaling, recompression and exposure shifts
+pub fn perceptual_hash(img: &GrayImage) -> u64 {
+    let small = image::imageops::resize(img, 9, 8, image::imageops::FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let brighter = small.get_pixel(x + 1, y)[0] > small.get_pixel(x, y)[0];
+            hash = (hash << 1) | brighter as u64;
+        }
+    }
+    hash
+}
+
+/// Number of differing bits between two perceptual hashes
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// Busy, photo-like texture with no straight dividers; `seed` varies it per photo
+    fn photo(width: u32, height: u32, seed: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| {
+            Luma([((x * 7 + y * 13 + seed * 50 + (x * y) % 37) % 200 + 20) as u8])
+        })
+    }
+
+    /// Four photos on a white background with 8px gutters between them
+    fn collage() -> GrayImage {
+        let mut collage = GrayImage::from_pixel(400, 300, Luma([255]));
+        for (i, (x, y)) in [(0, 0), (204, 0), (0, 154), (204, 154)]
+            .into_iter()
+            .enumerate()
+        {
+            image::imageops::replace(&mut collage, &photo(196, 146, i as u32 + 1), x, y);
+        }
+        collage
+    }
+
+    #[test]
+    fn single_photo_is_not_a_collage() {
+        assert_eq!(collage_layout(&photo(400, 300, 1)), None);
+        assert_eq!(
+            collage_layout(&GrayImage::from_pixel(400, 300, Luma([128]))),
+            None
+        );
+        assert_eq!(collage_layout(&photo(8, 8, 1)), None);
+    }
+
+    #[test]
+    fn two_by_two_collage_is_detected_with_its_dividers() {
+        let layout = collage_layout(&collage()).unwrap();
+        assert_eq!((layout.rows, layout.cols), (2, 2), "{:?}", layout);
+        assert!((146..154).contains(&layout.row_splits[0]), "{:?}", layout);
+        assert!((196..204).contains(&layout.col_splits[0]), "{:?}", layout);
+    }
+
+    #[test]
+    fn collage_cells_are_written_at_original_scale() {
+        let dir = std::env::temp_dir().join(format!("nuke-collage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("grid.png");
+        image::imageops::resize(&collage(), 800, 600, image::imageops::FilterType::Nearest)
+            .save(&path)
+            .unwrap();
+
+        let layout = detect_collage(&path).unwrap();
+        assert_eq!((layout.rows, layout.cols), (2, 2));
+        assert!((292..308).contains(&layout.row_splits[0]), "{:?}", layout);
+
+        let cells = write_collage_cells(&path, &layout, &dir.join("cells")).unwrap();
+        assert_eq!(cells.len(), 4);
+        assert!(cells[0].ends_with("grid_r1c1.png"));
+        assert!(cells[3].ends_with("grid_r2c2.png"));
+        let first = image::open(&cells[0]).unwrap();
+        assert_eq!(
+            (first.width(), first.height()),
+            (layout.col_splits[0], layout.row_splits[0])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A fax-like page: bars of "text" on white, sprinkled with salt-and-pepper noise
+    fn fax() -> GrayImage {
+        GrayImage::from_fn(800, 1000, |x, y| {
+            let ink = (y / 12) % 3 == 0 && (x / 8) % 2 == 0 && x > 50 && x < 750;
+            let noise = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)) % 97 == 0;
+            Luma([if ink ^ noise { 0 } else { 255 }])
+        })
+    }
+
+    fn ink_ratio(img: &GrayImage) -> f32 {
+        img.pixels().filter(|p| p[0] < 128).count() as f32 / (img.width() * img.height()) as f32
+    }
+
+    #[test]
+    fn fax_scans_are_detected_and_photos_are_not() {
+        let page = fax();
+        assert!(is_low_dpi_scan(&page), "specks {}", speck_ratio(&page));
+        // Smooth gradients aren't bilevel
+        assert!(!is_low_dpi_scan(&photo(800, 600, 1)));
+        // A clean bilevel page has no specks to speak of
+        let clean = GrayImage::from_fn(800, 1000, |x, y| {
+            Luma([if (y / 12) % 3 == 0 && (x / 8) % 2 == 0 {
+                0
+            } else {
+                255
+            }])
+        });
+        assert!(!is_low_dpi_scan(&clean));
+        // Big enough to be a high-DPI scan
+        assert!(!is_low_dpi_scan(&GrayImage::from_pixel(
+            2550,
+            3300,
+            Luma([255])
+        )));
+    }
+
+    #[test]
+    fn enhancement_removes_specks_and_keeps_the_text() {
+        let page = fax();
+        let enhanced = enhance_scan(&page);
+        assert_eq!(enhanced.dimensions(), (1600, 2000));
+        assert!(enhanced.pixels().all(|p| p[0] == 0 || p[0] == 255));
+        assert!(
+            speck_ratio(&enhanced) < speck_ratio(&page) / 4.0,
+            "{} vs {}",
+            speck_ratio(&enhanced),
+            speck_ratio(&page)
+        );
+        // Text bars cover about a sixth of the page either way
+        assert!((ink_ratio(&enhanced) - ink_ratio(&page)).abs() < 0.03);
+    }
+
+    #[test]
+    fn only_low_dpi_files_get_an_enhanced_copy() {
+        let dir = std::env::temp_dir().join(format!("nuke-lowdpi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fax_path = dir.join("fax.png");
+        fax().save(&fax_path).unwrap();
+        let photo_path = dir.join("photo.png");
+        photo(400, 300, 1).save(&photo_path).unwrap();
+
+        let enhanced = enhance_if_low_dpi(&fax_path).unwrap();
+        assert_eq!(image::image_dimensions(&enhanced).unwrap(), (1600, 2000));
+        assert_eq!(enhance_if_low_dpi(&photo_path), None);
+        std::fs::remove_file(&enhanced).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn ocr(word_count: usize, mean_confidence: f32) -> OcrOutput {
+        OcrOutput {
+            text: "word ".repeat(word_count),
+            word_count,
+            mean_confidence,
+            min_confidence: mean_confidence,
+            low_confidence_words: 0,
+        }
+    }
+
+    #[test]
+    fn negative_pages_are_detected_but_dark_photos_are_not() {
+        let page = fax();
+        let mut negative = page.clone();
+        image::imageops::invert(&mut negative);
+        assert!(looks_negative(&negative));
+        assert!(!looks_negative(&page));
+        // Dark, but busy like a night photo
+        let night = GrayImage::from_fn(400, 300, |x, y| Luma([((x * 31 + y * 17) % 80) as u8]));
+        assert!(!looks_negative(&night));
+        assert!(!looks_negative(&GrayImage::new(0, 0)));
+    }
+
+    #[tokio::test]
+    async fn correcting_a_negative_scan_restores_the_page() {
+        let dir = std::env::temp_dir().join(format!("nuke-negative-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = fax();
+        let mut negative = page.clone();
+        image::imageops::invert(&mut negative);
+        let negative_path = dir.join("negative.png");
+        negative.save(&negative_path).unwrap();
+        let page_path = dir.join("page.png");
+        page.save(&page_path).unwrap();
+
+        let corrected = correct_scan(&negative_path, None).await;
+        assert_eq!(corrected.corrections, vec![ScanCorrection::Inverted]);
+        let corrected_path = corrected.path.unwrap();
+        assert_eq!(image::open(&corrected_path).unwrap().to_luma8(), page);
+        assert!(!looks_negative(
+            &image::open(&corrected_path).unwrap().to_luma8()
+        ));
+        std::fs::remove_file(&corrected_path).unwrap();
+
+        // A normal page, with readable OCR, is left alone
+        let unchanged = correct_scan(&page_path, Some(ocr(40, 90.0))).await;
+        assert!(unchanged.corrections.is_empty());
+        assert!(unchanged.path.is_none());
+        assert_eq!(unchanged.ocr.unwrap().word_count, 40);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn mirrored_text_is_flipped_only_when_the_flip_reads_clearly_better() {
+        // Mirrored glyphs come out as a few low-confidence fragments
+        assert!(mirror_reads_better(Some(&ocr(6, 30.0)), &ocr(40, 85.0)));
+        assert!(mirror_reads_better(None, &ocr(10, 60.0)));
+        // Normal text stays put: the flipped copy is no better, or isn't readable itself
+        assert!(!mirror_reads_better(Some(&ocr(40, 85.0)), &ocr(6, 30.0)));
+        assert!(!mirror_reads_better(Some(&ocr(30, 60.0)), &ocr(32, 62.0)));
+        assert!(!mirror_reads_better(None, &ocr(50, 40.0)));
+    }
+
+    #[test]
+    fn heic_files_are_recognized_by_extension() {
+        assert!(is_heif(Path::new("/photos/IMG_0001.HEIC")));
+        assert!(is_heif(Path::new("scan.heif")));
+        assert!(!is_heif(Path::new("scan.jpg")));
+        assert!(!is_heif(Path::new("heic")));
+    }
+
+    #[tokio::test]
+    async fn unreadable_heic_is_an_error() {
+        let missing = Path::new("/nonexistent/IMG_0001.heic");
+        let error = heif_to_jpeg(missing).await.unwrap_err();
+        assert!(error.contains("Failed to decode HEIC"));
+        let error = convert_heif(missing).await.unwrap_err();
+        assert!(error.contains("Failed to decode HEIC"));
+    }
+
+    #[test]
+    fn temp_variants_never_share_a_file() {
+        let img = GrayImage::from_pixel(8, 8, image::Luma([200]));
+        let first = save_temp_variant(&img, "enhanced").unwrap();
+        let second = save_temp_variant(&img, "enhanced").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(image::open(&first).unwrap().to_luma8(), img);
+        std::fs::remove_file(&first).unwrap();
+        assert!(second.exists());
+        std::fs::remove_file(&second).unwrap();
+    }
+
+    /// A page of text-like lines: dark word blocks of varying length on light paper
+    fn text_page(width: u32, height: u32) -> image::DynamicImage {
+        let page = GrayImage::from_fn(width, height, |x, y| {
+            let line = y / 24;
+            let word = (x + line * 37) / 45;
+            let in_gap = (x + line * 37) % 45 > 30 + (word * 7 + line * 3) % 12;
+            let inked = y % 24 < 12 && !in_gap && (word * 13 + line * 5) % 7 != 0;
+            image::Luma([if inked { 20 } else { 240 }])
+        });
+        image::DynamicImage::ImageLuma8(page)
+    }
+
+    #[test]
+    fn overlapping_halves_stitch_back_into_the_page() {
+        let page = text_page(1000, 1400);
+        let top = page.crop_imm(0, 0, 1000, 800);
+        let bottom = page.crop_imm(0, 600, 1000, 800);
+
+        let overlap = section_overlap(&top, &bottom).unwrap();
+        assert!(overlap.rows.abs_diff(200) <= 3, "rows {}", overlap.rows);
+        assert!(overlap.confidence > STITCH_REVIEW_CONFIDENCE);
+
+        let stitched = stitch_vertical(&[&top, &bottom], &[overlap.rows]);
+        assert!(stitched.height().abs_diff(1400) <= 3);
+        // Above the seam rows come from the top half, below it from the bottom half, each
+        // the page's own rows
+        let page = page.to_rgb8();
+        let seam = 800 - overlap.rows;
+        for y in (0..stitched.height()).step_by(7) {
+            let source = if y < seam { y } else { 600 + y - seam };
+            for x in (0..1000).step_by(13) {
+                assert_eq!(stitched.get_pixel(x, y), page.get_pixel(x, source));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn only_overlapping_sections_are_stitched() {
+        let dir = std::env::temp_dir().join(format!("nuke-stitch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let page = text_page(1000, 1400);
+        let unrelated = GrayImage::from_fn(1000, 800, |x, y| {
+            image::Luma([if (x / 7 + y / 11) % 3 == 0 { 30 } else { 230 }])
+        });
+        let files = [
+            ("top.png", page.crop_imm(0, 0, 1000, 800)),
+            ("bottom.png", page.crop_imm(0, 600, 1000, 800)),
+            ("other.png", image::DynamicImage::ImageLuma8(unrelated)),
+        ];
+        let mut paths = Vec::new();
+        for (name, image) in &files {
+            image.save(dir.join(name)).unwrap();
+            paths.push(dir.join(name).to_string_lossy().to_string());
+        }
+
+        let overlap = section_overlap(&files[1].1, &files[2].1);
+        assert!(overlap.is_none_or(|o| o.confidence < SECTION_MATCH_MIN_CONFIDENCE));
+        let output_dir = dir.join("out").to_string_lossy().to_string();
+        let stitched = stitch_split_scans(paths.clone(), output_dir).await.unwrap();
+        assert_eq!(stitched.len(), 1);
+        assert_eq!(stitched[0].sources, paths[..2]);
+        assert!(!stitched[0].needs_review);
+        assert!(Path::new(&stitched[0].path).exists());
+
+        // A batch doesn't fail on files that aren't images; they end a run instead
+        let mut batch = paths.clone();
+        batch.insert(1, dir.join("title.pdf").to_string_lossy().to_string());
+        let stitched = stitch_batch_sections(batch, dir.join("batch"))
+            .await
+            .unwrap();
+        assert!(stitched.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+// Logging - structured diagnostics in a daily rolling file under the app data dir
+
+use crate::error::NukeError;
+use crate::AppState;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::INFO;
+
+// Days of log files kept before the oldest is deleted
+const LOG_FILES_KEPT: usize = 7;
+
+/// The installed subscriber: where it writes and a handle to change its level
+pub struct Logging {
+    dir: PathBuf,
+    filter: reload::Handle<EnvFilter, Registry>,
+    // Flushes buffered lines when the app exits
+    _guard: WorkerGuard,
+}
+
+impl Logging {
+    /// Install the global subscriber, writing `nuke.<date>.log` files into `dir`
+    pub fn init(dir: &Path) -> Self {
+        let (filter, handle) = reload::Layer::new(level_filter(DEFAULT_LOG_LEVEL));
+        let (writer, guard) = match open_appender(dir) {
+            Ok(appender) => tracing_appender::non_blocking(appender),
+            // The app works without a log file; it just can't be diagnosed from one
+            Err(_) => tracing_appender::non_blocking(std::io::sink()),
+        };
+        // Fails only if a subscriber is already installed, which then keeps logging
+        let _ = tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_writer(writer)
+                    .with_ansi(false),
+            )
+            .try_init();
+
+        Logging {
+            dir: dir.to_path_buf(),
+            filter: handle,
+            _guard: guard,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_level(&self, level: LevelFilter) -> Result<(), String> {
+        self.filter
+            .reload(level_filter(level))
+            .map_err(|e| format!("Failed to change log level: {}", e))
+    }
+}
+
+/// Change how much is logged: trace, debug, info, warn, error or off
+#[tauri::command]
+pub async fn set_log_level(
+    state: tauri::State<'_, AppState>,
+    level: String,
+) -> Result<(), NukeError> {
+    let level = parse_level(&level)?;
+    state.logging.set_level(level).map_err(NukeError::Other)?;
+    tracing::info!(%level, "log level changed");
+    Ok(())
+}
+
+/// Folder holding the log files, one per day, for users to attach to support requests
+#[tauri::command]
+pub async fn get_log_path(state: tauri::State<'_, AppState>) -> Result<String, NukeError> {
+    Ok(state.logging.dir().to_string_lossy().to_string())
+}
+
+pub fn parse_level(level: &str) -> Result<LevelFilter, NukeError> {
+    LevelFilter::from_str(&level.trim().to_lowercase())
+        .map_err(|_| NukeError::InvalidInput(format!("Unknown log level: {}", level)))
+}
+
+/// Our own events at `level`; dependencies never log below warn, or HTTP internals drown them out
+fn level_filter(level: LevelFilter) -> EnvFilter {
+    let dependencies = level.min(LevelFilter::WARN);
+    EnvFilter::new(format!(
+        "{},{}={}",
+        dependencies,
+        env!("CARGO_CRATE_NAME"),
+        level
+    ))
+}
+
+fn open_appender(dir: &Path) -> Result<RollingFileAppender, String> {
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("nuke")
+        .filename_suffix("log")
+        .max_log_files(LOG_FILES_KEPT)
+        .build(dir)
+        .map_err(|e| format!("Failed to open log file: {}", e))
+}
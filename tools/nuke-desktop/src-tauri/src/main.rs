@@ -4,16 +4,174 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use walkdir::WalkDir;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+use error::NukeError;
 use regex::Regex;
 
+mod bilingual;
+mod bundle;
+mod cache;
+mod condition;
+mod consistency;
+mod csvimport;
+mod dates;
+mod dedupe;
+mod details;
+mod dictionary;
+mod doctypes;
+mod docx;
+mod error;
+mod events;
+mod documents;
+mod floors;
+mod imaging;
+mod logging;
+mod manifest;
+mod modeljson;
+mod ocr;
+mod ollama;
+mod parties;
+mod partition;
+mod photos;
+mod plates;
+mod privacy;
+mod progress;
+mod report;
+mod review;
+mod scanconfig;
+mod spreadsheet;
+mod standard;
+mod supabase;
+mod syncqueue;
+mod vin;
+mod vinexport;
+mod vpic;
+mod watch;
+
 // File types we scan for
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "heic", "heif", "webp"];
 const DOCUMENT_EXTENSIONS: &[&str] = &["pdf", "doc", "docx", "txt", "rtf"];
 const SPREADSHEET_EXTENSIONS: &[&str] = &["csv", "xlsx", "xls", "numbers"];
 
-#[derive(Debug, Serialize, Deserialize)]
+// Gitignore-syntax file read from each scanned directory
+const NUKE_IGNORE_FILENAME: &str = ".nukeignore";
+
+// Files between streamed scan events when the config doesn't say
+const DEFAULT_PROGRESS_INTERVAL: usize = 500;
+
+const SCAN_PROGRESS_EVENT: &str = "scan-progress";
+// Emitted once with the file count when a scan runs with `precount`
+const SCAN_TOTAL_EVENT: &str = "scan-total";
+
+const CLOUD_API_URL: &str = "https://qkgaybvrernstplzjaam.supabase.co/functions/v1";
+
+// Waits before each retry of a failed sync batch
+const SYNC_RETRY_DELAYS: &[Duration] = &[
+    Duration::from_millis(500),
+    Duration::from_secs(2),
+    Duration::from_secs(8),
+];
+// A 429 asking for a longer wait than this fails the batch instead
+const MAX_SYNC_RETRY_AFTER: Duration = Duration::from_secs(60);
+// A host that hasn't accepted the connection by now isn't there; Ollama is reported unreachable
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// State shared across commands
+pub struct AppState {
+    pub processed: Mutex<cache::ProcessedStore>,
+    pub responses: cache::ResponseCache,
+    pub ocr_cache: cache::OcrCache,
+    pub supabase: Mutex<Option<supabase::SupabaseConfig>>,
+    pub last_scan: Mutex<ScanSummary>,
+    /// Restored from the config dir on launch; updated by `save_scan_config`
+    pub scan_config: Mutex<scanconfig::SavedScanConfig>,
+    pub vpic: vpic::VpicLookup,
+    pub progress: progress::PipelineProgress,
+    pub cancel_processing: AtomicBool,
+    pub cancel_scan: AtomicBool,
+    pub privacy: Mutex<privacy::PrivacySettings>,
+    /// Minimum confidence by document type for a result to sync
+    pub confidence_floors: Mutex<floors::ConfidenceFloors>,
+    /// Longest image edge sent to the model, set with `set_max_image_dimension`
+    pub max_image_dimension: AtomicU32,
+    /// Re-prompts for JSON after an unparseable answer, set with `set_json_retries`
+    pub json_retries: AtomicU32,
+    /// Longest an Ollama request may run, set with `set_ollama_timeout`
+    pub ollama_timeout_secs: AtomicU64,
+    /// Shared by Ollama, cloud, Supabase and vPIC requests so they keep one connection pool;
+    /// Ollama requests carry their own timeout through `ollama_client`
+    pub http: reqwest::Client,
+    pub events: events::EventLog,
+    /// Supabase rows not yet accepted, kept across restarts
+    pub sync_queue: syncqueue::SyncQueue,
+    /// Folder watch started by `start_watching`
+    pub watch: Mutex<Option<watch::WatchSession>>,
+    /// Makes and models recognized in paths; extend with vehicle_dictionary.json
+    pub dictionary: dictionary::VehicleDictionary,
+    /// Log files under the data dir, level set with `set_log_level`
+    pub logging: logging::Logging,
+}
+
+impl AppState {
+    fn new(data_dir: PathBuf, config_dir: PathBuf, logging: logging::Logging) -> Self {
+        let events = events::EventLog::new();
+        let sync_queue = open_sync_queue(&data_dir.join("sync_queue.sqlite"), &events);
+        AppState {
+            processed: Mutex::new(cache::ProcessedStore::load(data_dir.join("processed.jsonl"))),
+            responses: cache::ResponseCache::new(data_dir.join("responses")),
+            ocr_cache: cache::OcrCache::new(data_dir.join("ocr")),
+            supabase: Mutex::new(None),
+            last_scan: Mutex::new(ScanSummary::default()),
+            scan_config: Mutex::new(scanconfig::SavedScanConfig::load(config_dir.join("scan_config.json"))),
+            vpic: vpic::VpicLookup::load(data_dir.join("vpic.json")),
+            progress: progress::PipelineProgress::new(),
+            cancel_processing: AtomicBool::new(false),
+            cancel_scan: AtomicBool::new(false),
+            privacy: Mutex::new(privacy::PrivacySettings::default()),
+            confidence_floors: Mutex::new(floors::ConfidenceFloors::default()),
+            max_image_dimension: AtomicU32::new(imaging::DEFAULT_MAX_DIMENSION),
+            json_retries: AtomicU32::new(documents::DEFAULT_JSON_RETRIES),
+            ollama_timeout_secs: AtomicU64::new(ollama::DEFAULT_TIMEOUT.as_secs()),
+            http: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .unwrap_or_else(|_| reqwest::Client::new()),
+            events,
+            sync_queue,
+            watch: Mutex::new(None),
+            dictionary: dictionary::VehicleDictionary::load(&data_dir.join("vehicle_dictionary.json")),
+            logging,
+        }
+    }
+
+    /// The shared client, with the current Ollama timeout on each request
+    pub fn ollama_client(&self) -> ollama::OllamaClient {
+        let timeout = Duration::from_secs(self.ollama_timeout_secs.load(Ordering::Relaxed));
+        ollama::OllamaClient::new(self.http.clone(), timeout)
+    }
+}
+
+/// Open the on-disk sync queue, or fall back to one in memory and say so: queued syncs
+/// then won't survive a restart
+fn open_sync_queue(path: &Path, events: &events::EventLog) -> syncqueue::SyncQueue {
+    syncqueue::SyncQueue::open(path).unwrap_or_else(|e| {
+        tracing::error!(path = %path.display(), error = %e, "sync queue kept in memory");
+        // Recorded for the UI to pick up with get_app_events once it's listening
+        events::emit(None, events, events::AppEvent::Error {
+            operation: "open_sync_queue".to_string(),
+            path: Some(path.to_string_lossy().to_string()),
+            message: format!("{}; queued syncs won't survive a restart", e),
+        });
+        syncqueue::SyncQueue::in_memory()
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResult {
     pub path: String,
     pub filename: String,
@@ -22,19 +180,56 @@ pub struct ScanResult {
     pub size: u64,
     pub modified: String,
     pub potential_vehicle: Option<VehicleHint>,
+    /// Canonical capture date and its sources, set by `reconcile_dates`
+    #[serde(default)]
+    pub dates: Option<dates::FileDates>,
+    /// Width / height, for images whose header we can read
+    #[serde(default)]
+    pub aspect_ratio: Option<f32>,
+    /// Outside `ScanConfig.aspect_range`; goes to OCR rather than a vehicle prompt
+    #[serde(default)]
+    pub unusual_aspect: bool,
+    /// `cache::quick_hash` of the file, set when `ScanConfig.dedupe_by_content` is on
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// EXIF capture time (ISO 8601), for images that recorded one
+    #[serde(default)]
+    pub captured_at: Option<String>,
+    /// EXIF GPS position in decimal degrees, for images that recorded one
+    #[serde(default)]
+    pub gps_lat: Option<f64>,
+    #[serde(default)]
+    pub gps_lon: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VehicleHint {
     pub year: Option<String>,
     pub make: Option<String>,
     pub model: Option<String>,
+    /// Only set when the check digit validates
     pub vin: Option<String>,
+    /// A VIN-shaped string whose check digit doesn't validate, shown but not trusted
+    #[serde(default)]
+    pub vin_unverified: Option<String>,
+    /// The VIN's year code doesn't fit any year in the path or text
+    #[serde(default)]
+    pub year_conflict: bool,
+    /// Every plausible year found, in order; more than one means `year` is a guess the user
+    /// may want to correct
+    #[serde(default)]
+    pub all_years: Vec<String>,
+    /// Trim named right after the model, e.g. "SS"
+    #[serde(default)]
+    pub trim: Option<String>,
+    /// Engine named right after the model: a displacement ("5.7L", "396") or code ("LS3")
+    #[serde(default)]
+    pub engine: Option<String>,
     pub confidence: f32,
     pub source: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanConfig {
     pub paths: Vec<String>,
     pub include_hidden: bool,
@@ -42,118 +237,750 @@ pub struct ScanConfig {
     pub include_images: bool,
     pub include_documents: bool,
     pub include_spreadsheets: bool,
+    /// Stop once this many results are collected, keeping the first ones by path. A capped
+    /// scan walks on a single thread so the cut falls in the same place every time.
+    pub max_results: Option<usize>,
+    /// Images outside this width:height range are flagged as strips or panoramas
+    #[serde(default)]
+    pub aspect_range: imaging::AspectRange,
+    /// Files between `scan_directories_streamed` events; defaults to DEFAULT_PROGRESS_INTERVAL
+    #[serde(default)]
+    pub progress_interval: Option<usize>,
+    /// Hash each file and skip ones whose content already appeared in this scan
+    #[serde(default)]
+    pub dedupe_by_content: bool,
+    /// Also skip what `.gitignore` files exclude; `.nukeignore` always applies
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    /// Worker threads walking and reading files; defaults to one per CPU
+    #[serde(default)]
+    pub scan_threads: Option<usize>,
+    /// Skip files larger than this (video, RAW dumps); empty files are always skipped
+    #[serde(default)]
+    pub max_file_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub min_file_size_bytes: Option<u64>,
+    /// Also accept makes and models misspelled by a letter or two ("corvete"), at lower confidence
+    #[serde(default)]
+    pub fuzzy_match: bool,
+    /// Count the files before scanning, for a percentage and ETA; an extra pass over
+    /// the directories, which is slow on network and spinning drives
+    #[serde(default)]
+    pub precount: bool,
+}
+
+impl Default for ScanConfig {
+    /// Every supported file type, no limits
+    fn default() -> Self {
+        ScanConfig {
+            paths: Vec::new(),
+            include_hidden: false,
+            max_depth: None,
+            include_images: true,
+            include_documents: true,
+            include_spreadsheets: true,
+            max_results: None,
+            aspect_range: imaging::AspectRange::default(),
+            progress_interval: None,
+            dedupe_by_content: false,
+            respect_gitignore: false,
+            scan_threads: None,
+            max_file_size_bytes: None,
+            min_file_size_bytes: None,
+            fuzzy_match: false,
+            precount: false,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanProgress {
     pub scanned: usize,
     pub found: usize,
     pub current_path: String,
     pub complete: bool,
+    /// Files visited per second, over the last few seconds
+    pub files_per_second: f64,
+    pub elapsed_ms: u64,
+    /// Files the scan will visit, from the `precount` pass
+    pub total: Option<usize>,
+    /// `scanned` out of `total`, 0-100; only with `precount`
+    pub percent: Option<f32>,
+    /// At the current rate; needs `precount`, and None until there's a rate to go on
+    pub estimated_remaining_ms: Option<u64>,
+}
+
+/// Counts, rate and total behind a scan's `scan-progress` events
+struct ScanReporter {
+    interval: usize,
+    scanned: usize,
+    found: usize,
+    rate: progress::Throughput,
+    total: Option<usize>,
+}
+
+impl ScanReporter {
+    /// Runs the count pass first when the config asks for `precount`
+    fn start(config: &ScanConfig, cancel: &AtomicBool) -> Self {
+        let total = if config.precount {
+            count_scan_files(config, cancel)
+        } else {
+            None
+        };
+        ScanReporter {
+            interval: config.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL).max(1),
+            scanned: 0,
+            found: 0,
+            rate: progress::Throughput::start(Instant::now()),
+            total,
+        }
+    }
+
+    /// Count a visited file; every `interval` files, the progress to emit
+    fn visited(&mut self, path: &Path) -> Option<ScanProgress> {
+        self.scanned += 1;
+        if !self.scanned.is_multiple_of(self.interval) {
+            return None;
+        }
+        self.rate.sample(Instant::now(), self.scanned);
+        Some(self.progress(Some(path)))
+    }
+
+    /// Progress at `current_path`, or the final report when that's None
+    fn progress(&self, current_path: Option<&Path>) -> ScanProgress {
+        let remaining = match (current_path, self.total) {
+            (None, _) => Some(Duration::ZERO),
+            (Some(_), Some(total)) => self.rate.remaining(self.scanned, total),
+            (Some(_), None) => None,
+        };
+        // Files added since the count can take a scan past its total
+        let percent = self.total.map(|total| match current_path {
+            None => 100.0,
+            Some(_) if total == 0 => 0.0,
+            Some(_) => (self.scanned as f32 / total as f32 * 100.0).min(100.0),
+        });
+        ScanProgress {
+            scanned: self.scanned,
+            found: self.found,
+            current_path: current_path
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            complete: current_path.is_none(),
+            files_per_second: self.rate.rate(),
+            elapsed_ms: self.rate.elapsed(Instant::now()).as_millis() as u64,
+            total: self.total,
+            percent,
+            estimated_remaining_ms: remaining.map(|r| r.as_millis() as u64),
+        }
+    }
+}
+
+/// Summary of the most recent scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanSummary {
+    pub total_results: usize,
+    /// The scan hit `max_results` and stopped early
+    pub truncated: bool,
+    /// `cancel_scan` stopped the scan; the results are what was found before that
+    #[serde(default)]
+    pub cancelled: bool,
+    /// Matching files left out for their size
+    #[serde(default)]
+    pub skipped: SizeSkips,
+    /// Roots dropped because another root already covers them
+    #[serde(default)]
+    pub merged_roots: Vec<MergedRoot>,
 }
 
-/// Scan directories for vehicle-related files
+/// A scan root that wasn't walked on its own
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergedRoot {
+    pub path: String,
+    /// The root walked instead; the same folder, or one containing it
+    pub covered_by: String,
+}
+
+/// Why a file of a wanted type was left out of a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeSkip {
+    /// Zero bytes; placeholders from sync clients and failed copies
+    Empty,
+    TooSmall,
+    TooLarge,
+}
+
+/// Counts of files skipped for their size
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SizeSkips {
+    pub empty: usize,
+    pub too_small: usize,
+    pub too_large: usize,
+}
+
+impl SizeSkips {
+    fn add(&mut self, skip: SizeSkip) {
+        match skip {
+            SizeSkip::Empty => self.empty += 1,
+            SizeSkip::TooSmall => self.too_small += 1,
+            SizeSkip::TooLarge => self.too_large += 1,
+        }
+    }
+}
+
+/// Scan directories for vehicle-related files. With `precount`, the files are counted first
+/// (`scan-total`) and `scan-progress` reports a percentage every `progress_interval` files.
 #[tauri::command]
-async fn scan_directories(config: ScanConfig) -> Result<Vec<ScanResult>, String> {
+async fn scan_directories(
+    app: tauri::AppHandle,
+    config: ScanConfig,
+) -> Result<Vec<ScanResult>, NukeError> {
+    // The walk (and precount) block on the filesystem for as long as the tree takes;
+    // keep them off the async runtime
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        start_scan(Some(&app), &state, &config);
+        let mut reporter = ScanReporter::start(&config, &state.cancel_scan);
+        if let Some(total) = reporter.total {
+            let _ = app.emit(SCAN_TOTAL_EVENT, total);
+        }
+        let walk = walk_scan(&config, &state.dictionary, &state.cancel_scan, |step| match step {
+            // Without a total there's no percentage, and this command never streamed progress
+            ScanStep::Visited(path) if config.precount => {
+                if let Some(progress) = reporter.visited(path) {
+                    let _ = app.emit(SCAN_PROGRESS_EVENT, progress);
+                }
+            }
+            ScanStep::Visited(_) => {}
+            ScanStep::Found(_) => {
+                reporter.found += 1;
+                progress::advance(Some(&app), &state.progress, progress::Stage::Discovered, 1);
+            }
+        });
+        if config.precount {
+            let _ = app.emit(SCAN_PROGRESS_EVENT, reporter.progress(None));
+        }
+        finish_scan(Some(&app), &state, &walk);
+
+        walk.results
+    })
+    .await
+    .map_err(|e| NukeError::Other(format!("Scan stopped unexpectedly: {}", e)))
+}
+
+/// `scan_directories`, emitting `scan-progress` every `progress_interval` files visited and
+/// `scan-result` with each batch of that many results, so the UI can render as it goes.
+/// With `precount`, progress carries a percentage and time left.
+#[tauri::command]
+async fn scan_directories_streamed(
+    window: tauri::Window,
+    config: ScanConfig,
+) -> Result<Vec<ScanResult>, NukeError> {
+    let app = window.app_handle().clone();
+    // Off the async runtime, as in `scan_directories`
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let mut batch: Vec<ScanResult> = Vec::new();
+
+        start_scan(Some(&app), &state, &config);
+        let mut reporter = ScanReporter::start(&config, &state.cancel_scan);
+        if let Some(total) = reporter.total {
+            let _ = window.emit(SCAN_TOTAL_EVENT, total);
+        }
+        let walk = walk_scan(&config, &state.dictionary, &state.cancel_scan, |step| match step {
+            ScanStep::Visited(path) => {
+                if let Some(progress) = reporter.visited(path) {
+                    let _ = window.emit(SCAN_PROGRESS_EVENT, progress);
+                }
+            }
+            ScanStep::Found(result) => {
+                reporter.found += 1;
+                progress::advance(Some(&app), &state.progress, progress::Stage::Discovered, 1);
+                batch.push(result.clone());
+                if batch.len() >= reporter.interval {
+                    let _ = window.emit("scan-result", std::mem::take(&mut batch));
+                }
+            }
+        });
+
+        if !batch.is_empty() {
+            let _ = window.emit("scan-result", batch);
+        }
+        let _ = window.emit(SCAN_PROGRESS_EVENT, reporter.progress(None));
+        finish_scan(Some(&app), &state, &walk);
+
+        walk.results
+    })
+    .await
+    .map_err(|e| NukeError::Other(format!("Scan stopped unexpectedly: {}", e)))
+}
+
+/// Ask a running scan to stop; it returns what it has found so far
+#[tauri::command]
+async fn cancel_scan(state: tauri::State<'_, AppState>) -> Result<(), NukeError> {
+    state.cancel_scan.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// What the walk reports as it goes
+enum ScanStep<'a> {
+    /// A file of an included type was looked at, whether or not it gave a result
+    Visited(&'a Path),
+    Found(&'a ScanResult),
+}
+
+struct ScanWalk {
+    results: Vec<ScanResult>,
+    /// Stopped at `max_results`
+    truncated: bool,
+    /// Stopped by `cancel_scan`
+    cancelled: bool,
+    skipped: SizeSkips,
+    merged_roots: Vec<MergedRoot>,
+}
+
+/// What scan workers send back to the thread collecting results
+enum ScanMessage {
+    Visited(PathBuf),
+    Found(Box<ScanResult>),
+    Skipped(SizeSkip),
+}
+
+/// What came of looking at one file
+pub enum FileScan {
+    Found(Box<ScanResult>),
+    Skipped(SizeSkip),
+    /// Not a type the config asks for, or unreadable
+    Excluded,
+}
+
+/// Walk the configured roots on `scan_threads` workers, collecting matching files until done,
+/// cut off or cancelled. Results come back sorted by path.
+#[tracing::instrument(skip_all, fields(roots = config.paths.len()))]
+fn walk_scan(
+    config: &ScanConfig,
+    dictionary: &dictionary::VehicleDictionary,
+    cancel: &AtomicBool,
+    mut on_step: impl FnMut(ScanStep),
+) -> ScanWalk {
+    let started = std::time::Instant::now();
+    let (roots, merged_roots) = normalize_roots(&config.paths);
+    for merged in &merged_roots {
+        tracing::debug!(path = %merged.path, covered_by = %merged.covered_by, "root already covered");
+    }
+    let config = &ScanConfig {
+        paths: roots,
+        ..config.clone()
+    };
     let mut results = Vec::new();
+    let mut truncated = false;
+    let mut skipped = SizeSkips::default();
+    let mut seen_hashes = HashSet::new();
+    // Set once we have `max_results`, so the workers wind down
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = std::sync::mpsc::channel();
 
-    for base_path in &config.paths {
-        let walker = WalkDir::new(base_path)
-            .max_depth(config.max_depth.unwrap_or(10))
-            .follow_links(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| run_scan_workers(config, dictionary, cancel, &stop, sender));
 
-        for entry in walker.into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
+        for message in receiver {
+            let result = match message {
+                ScanMessage::Visited(path) => {
+                    on_step(ScanStep::Visited(&path));
+                    continue;
+                }
+                ScanMessage::Found(result) => *result,
+                ScanMessage::Skipped(skip) => {
+                    skipped.add(skip);
+                    continue;
+                }
+            };
 
-            // Skip hidden files unless explicitly included
-            if !config.include_hidden {
-                if let Some(name) = path.file_name() {
-                    if name.to_string_lossy().starts_with('.') {
-                        continue;
-                    }
+            // Overlapping roots or a re-scan hand us the same file more than once;
+            // whichever copy a worker reaches first is kept
+            if let Some(hash) = &result.content_hash {
+                if !seen_hashes.insert(hash.clone()) {
+                    continue;
                 }
             }
 
-            // Only process files
-            if !path.is_file() {
-                continue;
+            if let Some(max) = config.max_results {
+                if results.len() >= max {
+                    truncated = true;
+                    stop.store(true, Ordering::SeqCst);
+                    break;
+                }
+            }
+
+            results.push(result);
+            if let Some(result) = results.last() {
+                on_step(ScanStep::Found(result));
             }
+        }
+    });
+
+    // Parallel workers finish in any order
+    results.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let cancelled = !truncated && cancel.load(Ordering::SeqCst);
+    tracing::info!(
+        found = results.len(),
+        truncated,
+        cancelled,
+        empty = skipped.empty,
+        too_small = skipped.too_small,
+        too_large = skipped.too_large,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "scan finished"
+    );
+    if results.is_empty() && !cancelled {
+        tracing::warn!(roots = ?config.paths, "scan found no matching files");
+    }
+
+    ScanWalk {
+        results,
+        truncated,
+        cancelled,
+        skipped,
+        merged_roots,
+    }
+}
+
+/// Files a scan of `config` will visit, counted without reading them or extracting hints.
+/// None if the scan is cancelled while counting.
+fn count_scan_files(config: &ScanConfig, cancel: &AtomicBool) -> Option<usize> {
+    let (roots, _) = normalize_roots(&config.paths);
+    let Some((first, rest)) = roots.split_first() else {
+        return Some(0);
+    };
+    let mut builder = scan_builder(Path::new(first), config, config.max_depth.unwrap_or(10));
+    for root in rest {
+        builder.add(root);
+    }
 
-            let extension = path
-                .extension()
-                .map(|e| e.to_string_lossy().to_lowercase())
-                .unwrap_or_default();
-
-            // Determine category and whether to include
-            let (category, include) = if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
-                ("image", config.include_images)
-            } else if DOCUMENT_EXTENSIONS.contains(&extension.as_str()) {
-                ("document", config.include_documents)
-            } else if SPREADSHEET_EXTENSIONS.contains(&extension.as_str()) {
-                ("spreadsheet", config.include_spreadsheets)
-            } else {
-                ("unknown", false)
+    let count = AtomicUsize::new(0);
+    builder.threads(scan_threads(config)).build_parallel().run(|| {
+        Box::new(|entry| {
+            if cancel.load(Ordering::SeqCst) {
+                return ignore::WalkState::Quit;
+            }
+            let Ok(entry) = entry else {
+                return ignore::WalkState::Continue;
             };
+            if is_scan_candidate(entry.path(), config) {
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+            ignore::WalkState::Continue
+        })
+    });
+    (!cancel.load(Ordering::SeqCst)).then(|| count.into_inner())
+}
+
+/// Canonicalize scan roots and drop repeats and roots inside another root, so no folder
+/// is walked twice. Trailing slashes, `..` and symlinks all resolve to one path; a root
+/// that can't be resolved (it doesn't exist) is kept as given.
+fn normalize_roots(paths: &[String]) -> (Vec<String>, Vec<MergedRoot>) {
+    let mut resolved: Vec<(String, PathBuf)> = paths
+        .iter()
+        .map(|path| {
+            let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+            (path.clone(), canonical)
+        })
+        .collect();
+    // Parents sort before their children, so each root only needs checking against kept ones
+    resolved.sort_by_key(|(_, canonical)| canonical.components().count());
+
+    let mut kept: Vec<(String, PathBuf)> = Vec::new();
+    let mut merged = Vec::new();
+    for (path, canonical) in resolved {
+        match kept.iter().find(|(_, root)| canonical.starts_with(root)) {
+            Some((covering, _)) => merged.push(MergedRoot {
+                path,
+                covered_by: covering.clone(),
+            }),
+            None => kept.push((path, canonical)),
+        }
+    }
 
-            if !include {
-                continue;
+    // Walk in the order the user listed the roots
+    kept.sort_by_key(|(path, _)| paths.iter().position(|p| p == path));
+    let roots = kept
+        .into_iter()
+        .map(|(_, canonical)| canonical.to_string_lossy().to_string())
+        .collect();
+    (roots, merged)
+}
+
+/// Walk every root in parallel, sending each file visited and each match to `sender`
+fn run_scan_workers(
+    config: &ScanConfig,
+    dictionary: &dictionary::VehicleDictionary,
+    cancel: &AtomicBool,
+    stop: &AtomicBool,
+    sender: std::sync::mpsc::Sender<ScanMessage>,
+) {
+    let Some((first, rest)) = config.paths.split_first() else {
+        return;
+    };
+    let mut builder = scan_builder(Path::new(first), config, config.max_depth.unwrap_or(10));
+    for root in rest {
+        builder.add(root);
+    }
+
+    // Which files a cap keeps can't depend on thread timing, so a capped scan walks one
+    // thread in path order and keeps the first `max_results` files by path
+    if config.max_results.is_some() {
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+        for entry in builder.build() {
+            if cancel.load(Ordering::SeqCst) || stop.load(Ordering::SeqCst) {
+                return;
             }
+            if let Ok(entry) = entry {
+                scan_entry(&entry, config, dictionary, &sender);
+            }
+        }
+        return;
+    }
 
-            // Get file metadata
-            let metadata = match std::fs::metadata(path) {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
+    builder.threads(scan_threads(config)).build_parallel().run(|| {
+        let sender = sender.clone();
+        Box::new(move |entry| {
+            if cancel.load(Ordering::SeqCst) || stop.load(Ordering::SeqCst) {
+                return ignore::WalkState::Quit;
+            }
+            if let Ok(entry) = entry {
+                scan_entry(&entry, config, dictionary, &sender);
+            }
+            ignore::WalkState::Continue
+        })
+    });
+}
 
-            let modified = metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs().to_string())
-                .unwrap_or_default();
-
-            // Try to extract vehicle hints from filename/path
-            let potential_vehicle = extract_vehicle_hints(path);
-
-            results.push(ScanResult {
-                path: path.to_string_lossy().to_string(),
-                filename: path.file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_default(),
-                file_type: extension,
-                category: category.to_string(),
-                size: metadata.len(),
-                modified,
-                potential_vehicle,
-            });
+/// Scan one walked entry, sending what the walk found to `sender`
+fn scan_entry(
+    entry: &ignore::DirEntry,
+    config: &ScanConfig,
+    dictionary: &dictionary::VehicleDictionary,
+    sender: &std::sync::mpsc::Sender<ScanMessage>,
+) {
+    let path = entry.path();
+    if !is_scan_candidate(path, config) {
+        return;
+    }
+    let _ = sender.send(ScanMessage::Visited(path.to_path_buf()));
+
+    match scan_file(path, config, dictionary) {
+        FileScan::Found(result) => {
+            let _ = sender.send(ScanMessage::Found(result));
+        }
+        FileScan::Skipped(skip) => {
+            let _ = sender.send(ScanMessage::Skipped(skip));
+        }
+        FileScan::Excluded => {}
+    }
+}
+
+/// A file the scan looks at: of a type the config includes, and not hidden unless asked.
+/// `count_scan_files` uses the same test, so precount totals match the files visited.
+fn is_scan_candidate(path: &Path, config: &ScanConfig) -> bool {
+    let hidden = path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    if hidden && !config.include_hidden {
+        return false;
+    }
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    // Checked last; it follows symlinks, which costs a stat
+    categorize(&extension, config).1 && path.is_file()
+}
+
+/// Worker threads for a scan; defaults to one per CPU
+fn scan_threads(config: &ScanConfig) -> usize {
+    config
+        .scan_threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1))
+        .max(1)
+}
+
+/// The scan result for one file, or why the config leaves it out
+fn scan_file(
+    path: &Path,
+    config: &ScanConfig,
+    dictionary: &dictionary::VehicleDictionary,
+) -> FileScan {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    // Determine category and whether to include
+    let (category, include) = categorize(&extension, config);
+    if !include {
+        return FileScan::Excluded;
+    }
+
+    // Get file metadata
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            tracing::debug!(path = %path.display(), error = %e, "can't read file metadata");
+            return FileScan::Excluded;
+        }
+    };
+    // Checked before hashing so a huge file is never read
+    if let Some(skip) = size_skip(metadata.len(), config) {
+        return FileScan::Skipped(skip);
+    }
+
+    let content_hash = if config.dedupe_by_content {
+        match cache::quick_hash(path, metadata.len()) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                tracing::debug!(path = %path.display(), error = %e, "can't hash file");
+                return FileScan::Excluded;
+            }
         }
+    } else {
+        None
+    };
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_default();
+
+    // Try to extract vehicle hints from filename/path
+    let potential_vehicle = extract_vehicle_hints(path, dictionary, config.fuzzy_match);
+
+    let (aspect_ratio, exif) = match category {
+        "image" => (imaging::aspect_ratio(path), photos::read_photo_exif(path)),
+        _ => (None, photos::PhotoExif::default()),
+    };
+    let unusual_aspect = aspect_ratio
+        .map(|r| !config.aspect_range.contains(r))
+        .unwrap_or(false);
+
+    FileScan::Found(Box::new(ScanResult {
+        path: path.to_string_lossy().to_string(),
+        filename: path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        file_type: extension,
+        category: category.to_string(),
+        size: metadata.len(),
+        modified,
+        potential_vehicle,
+        dates: None,
+        aspect_ratio,
+        unusual_aspect,
+        content_hash,
+        captured_at: exif.captured_at,
+        gps_lat: exif.gps.map(|(lat, _)| lat),
+        gps_lon: exif.gps.map(|(_, lon)| lon),
+    }))
+}
+
+/// Whether a file's size puts it outside the scan's limits
+fn size_skip(size: u64, config: &ScanConfig) -> Option<SizeSkip> {
+    if size == 0 {
+        Some(SizeSkip::Empty)
+    } else if config.min_file_size_bytes.is_some_and(|min| size < min) {
+        Some(SizeSkip::TooSmall)
+    } else if config.max_file_size_bytes.is_some_and(|max| size > max) {
+        Some(SizeSkip::TooLarge)
+    } else {
+        None
     }
+}
+
+/// Walker under `root` that skips what `.nukeignore` (and `.gitignore`, when asked) excludes.
+/// Hidden files are left to the caller so the check stays per file.
+fn scan_builder(root: &Path, config: &ScanConfig, max_depth: usize) -> ignore::WalkBuilder {
+    let mut builder = ignore::WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .add_custom_ignore_filename(NUKE_IGNORE_FILENAME)
+        .git_ignore(config.respect_gitignore)
+        .git_exclude(config.respect_gitignore)
+        .git_global(config.respect_gitignore)
+        // Work units from `partition_scan` root below the original, so read ignores above too
+        .parents(true)
+        // Project folders aren't always git checkouts
+        .require_git(false)
+        .max_depth(Some(max_depth))
+        .follow_links(false);
+    builder
+}
+
+fn start_scan(app: Option<&tauri::AppHandle>, state: &AppState, config: &ScanConfig) {
+    state.progress.reset();
+    state.cancel_scan.store(false, Ordering::SeqCst);
+    events::emit(app, &state.events, events::AppEvent::ScanStarted {
+        roots: config.paths.clone(),
+    });
+}
+
+fn finish_scan(app: Option<&tauri::AppHandle>, state: &AppState, walk: &ScanWalk) {
+    *state.last_scan.lock().unwrap() = ScanSummary {
+        total_results: walk.results.len(),
+        truncated: walk.truncated,
+        cancelled: walk.cancelled,
+        skipped: walk.skipped.clone(),
+        merged_roots: walk.merged_roots.clone(),
+    };
+    events::emit(app, &state.events, events::AppEvent::ScanFinished {
+        files: walk.results.len(),
+        truncated: walk.truncated,
+        cancelled: walk.cancelled,
+    });
+}
 
-    Ok(results)
+/// Get the summary of the most recent scan
+#[tauri::command]
+async fn get_scan_summary(state: tauri::State<'_, AppState>) -> Result<ScanSummary, NukeError> {
+    Ok(state.last_scan.lock().unwrap().clone())
+}
+
+/// Category for a file extension, and whether the config includes that category
+fn categorize(extension: &str, config: &ScanConfig) -> (&'static str, bool) {
+    if IMAGE_EXTENSIONS.contains(&extension) {
+        ("image", config.include_images)
+    } else if DOCUMENT_EXTENSIONS.contains(&extension) {
+        ("document", config.include_documents)
+    } else if SPREADSHEET_EXTENSIONS.contains(&extension) {
+        ("spreadsheet", config.include_spreadsheets)
+    } else {
+        ("unknown", false)
+    }
 }
 
 /// Extract vehicle hints from filename and path
-fn extract_vehicle_hints(path: &std::path::Path) -> Option<VehicleHint> {
-    let full_path = path.to_string_lossy().to_lowercase();
+fn extract_vehicle_hints(
+    path: &std::path::Path,
+    dictionary: &dictionary::VehicleDictionary,
+    fuzzy: bool,
+) -> Option<VehicleHint> {
+    extract_hints_with(&path.to_string_lossy(), dictionary, fuzzy)
+}
+
+/// Extract vehicle hints from arbitrary text (a path, or a document's contents)
+pub fn extract_hints_from_text(text: &str) -> Option<VehicleHint> {
+    extract_hints_with(text, dictionary::built_in(), false)
+}
+
+/// `extract_hints_from_text` with the user's makes and models, optionally matched fuzzily
+pub fn extract_hints_with(
+    text: &str,
+    dictionary: &dictionary::VehicleDictionary,
+    fuzzy: bool,
+) -> Option<VehicleHint> {
+    let full_path = text.to_lowercase();
 
     // Common vehicle year patterns (1900-2030)
     let year_regex = Regex::new(r"\b(19[0-9]{2}|20[0-3][0-9])\b").ok()?;
 
-    // Common makes
-    let makes = vec![
-        "chevrolet", "chevy", "ford", "dodge", "gmc", "toyota", "honda",
-        "bmw", "mercedes", "porsche", "ferrari", "lamborghini", "audi",
-        "volkswagen", "vw", "jeep", "ram", "nissan", "mazda", "subaru",
-    ];
-
-    // Common models
-    let models = vec![
-        "c10", "c20", "k10", "k20", "k5", "blazer", "suburban", "silverado",
-        "mustang", "f150", "f-150", "camaro", "corvette", "challenger",
-        "charger", "911", "944", "carrera", "civic", "accord", "tacoma",
-        "4runner", "wrangler", "bronco",
-    ];
-
     // VIN pattern (17 alphanumeric, no I/O/Q)
     let vin_regex = Regex::new(r"\b[A-HJ-NPR-Z0-9]{17}\b").ok()?;
 
@@ -162,99 +989,93 @@ fn extract_vehicle_hints(path: &std::path::Path) -> Option<VehicleHint> {
         make: None,
         model: None,
         vin: None,
+        vin_unverified: None,
+        year_conflict: false,
+        all_years: Vec::new(),
+        trim: None,
+        engine: None,
         confidence: 0.0,
         source: "filename".to_string(),
     };
 
-    // Extract year
-    if let Some(cap) = year_regex.captures(&full_path) {
-        hint.year = Some(cap[1].to_string());
+    // Extract years; one past the current year is the newest model year on sale
+    let latest = chrono::Datelike::year(&chrono::Utc::now()) as u32 + 1;
+    for cap in year_regex.captures_iter(&full_path) {
+        let plausible = cap[1].parse::<u32>().is_ok_and(|year| year <= latest);
+        if plausible && !hint.all_years.iter().any(|y| y == &cap[1]) {
+            hint.all_years.push(cap[1].to_string());
+        }
+    }
+    // With several, the first is a guess until a VIN below settles it; counted once either way
+    if let Some(year) = hint.all_years.first() {
+        hint.year = Some(year.clone());
         hint.confidence += 0.3;
     }
 
-    // Extract make
-    for make in &makes {
-        if full_path.contains(make) {
-            hint.make = Some(normalize_make(make));
-            hint.confidence += 0.3;
-            break;
-        }
+    // Extract make and model; a near miss counts for half
+    let found = dictionary.find_with(text, fuzzy);
+    if let Some(make) = found.make {
+        hint.make = Some(make.to_string());
+        hint.confidence += if found.fuzzy_make { 0.15 } else { 0.3 };
     }
-
-    // Extract model
-    for model in &models {
-        if full_path.contains(model) {
-            hint.model = Some(model.to_uppercase());
-            hint.confidence += 0.3;
-            break;
-        }
+    if let Some(model) = found.model {
+        // Only after a model: "gt" or "350" alone could be anything
+        (hint.trim, hint.engine) = dictionary.find_designators(text, &model);
+        hint.model = Some(model);
+        hint.confidence += if found.fuzzy_model { 0.15 } else { 0.3 };
     }
 
-    // Extract VIN
-    if let Some(cap) = vin_regex.captures(&full_path) {
-        hint.vin = Some(cap[0].to_string());
+    // Extract VIN; long filenames and hashes match the pattern, so require the check digit
+    let upper = text.to_uppercase();
+    let candidates: Vec<&str> = vin_regex.find_iter(&upper).map(|m| m.as_str()).collect();
+    if let Some(valid) = candidates.iter().find(|c| vin::validate_vin(c)) {
+        hint.vin = Some(valid.to_string());
         hint.confidence += 0.5;
+
+        // Two independent reads of the year agreeing is worth more than either alone, and
+        // picks the right one when the path has several
+        let decode = vin::decode_vin(valid);
+        if decode.model_year.is_some() && !hint.all_years.is_empty() {
+            let agreeing = hint
+                .all_years
+                .iter()
+                .find(|y| y.parse().is_ok_and(|year| vin::year_matches(&decode, year)))
+                .cloned();
+            match agreeing {
+                Some(year) => {
+                    hint.year = Some(year);
+                    hint.confidence += 0.2;
+                }
+                None => hint.year_conflict = true,
+            }
+        }
+    } else if let Some(candidate) = candidates.first() {
+        hint.vin_unverified = Some(candidate.to_string());
     }
 
     // Only return if we found something
-    if hint.confidence > 0.0 {
+    if hint.confidence > 0.0 || hint.vin_unverified.is_some() {
         Some(hint)
     } else {
         None
     }
 }
 
-/// Normalize make names
-fn normalize_make(make: &str) -> String {
-    match make {
-        "chevy" => "Chevrolet".to_string(),
-        "vw" => "Volkswagen".to_string(),
-        _ => {
-            let mut chars = make.chars();
-            match chars.next() {
-                None => String::new(),
-                Some(first) => first.to_uppercase().chain(chars).collect(),
-            }
-        }
-    }
-}
-
-/// Parse CSV file for vehicle data
+/// Parse CSV file for vehicle data; `delimiter` overrides the sniffed one
 #[tauri::command]
-async fn parse_csv(path: String) -> Result<Vec<serde_json::Value>, String> {
-    let file = std::fs::File::open(&path)
-        .map_err(|e| format!("Failed to open file: {}", e))?;
-
-    let mut reader = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(file);
-
-    let headers = reader.headers()
-        .map_err(|e| format!("Failed to read headers: {}", e))?
-        .clone();
-
-    let mut results = Vec::new();
-    for result in reader.records() {
-        let record = result.map_err(|e| format!("Failed to read record: {}", e))?;
-
-        let mut obj = serde_json::Map::new();
-        for (i, header) in headers.iter().enumerate() {
-            if let Some(value) = record.get(i) {
-                obj.insert(header.to_string(), serde_json::Value::String(value.to_string()));
-            }
-        }
-        results.push(serde_json::Value::Object(obj));
-    }
-
-    Ok(results)
+async fn parse_csv(
+    path: String,
+    delimiter: Option<String>,
+    options: Option<spreadsheet::CsvParseOptions>,
+) -> Result<Vec<serde_json::Value>, NukeError> {
+    let delimiter = spreadsheet::parse_delimiter(delimiter.as_deref())?;
+    spreadsheet::read_csv(Path::new(&path), delimiter, &options.unwrap_or_default())
 }
 
 /// Check if Ollama is running locally
 #[tauri::command]
-async fn check_ollama() -> Result<bool, String> {
-    let client = reqwest::Client::new();
-    match client.get("http://localhost:11434/api/tags").send().await {
+async fn check_ollama(state: tauri::State<'_, AppState>) -> Result<bool, NukeError> {
+    match state.ollama_client().get("http://localhost:11434/api/tags").send().await {
         Ok(response) => Ok(response.status().is_success()),
         Err(_) => Ok(false),
     }
@@ -262,19 +1083,34 @@ async fn check_ollama() -> Result<bool, String> {
 
 /// Process image with local Ollama for vehicle detection
 #[tauri::command]
-async fn analyze_image_local(image_path: String) -> Result<serde_json::Value, String> {
-    // Read image and convert to base64
-    let image_data = std::fs::read(&image_path)
-        .map_err(|e| format!("Failed to read image: {}", e))?;
-    let base64_image = base64::encode(&image_data);
+async fn analyze_image_local(
+    state: tauri::State<'_, AppState>,
+    image_path: String,
+    options: Option<ollama::OllamaOptions>,
+) -> Result<serde_json::Value, NukeError> {
+    // Read image (HEIC through a JPEG copy), shrink it for the model and convert to base64
+    let image_data = if imaging::is_heif(Path::new(&image_path)) {
+        imaging::heif_to_jpeg(Path::new(&image_path))
+            .await
+            .map_err(NukeError::ImageDecode)?
+    } else {
+        std::fs::read(&image_path)
+            .map_err(|e| NukeError::FileRead(format!("Failed to read image: {}", e)))?
+    };
+    let image_data = imaging::prepare_for_model(
+        &image_data,
+        state.max_image_dimension.load(Ordering::Relaxed),
+    );
+    let base64_image = ollama::encode_image_b64(&image_data);
 
-    let client = reqwest::Client::new();
+    let client = state.ollama_client();
 
     let request = serde_json::json!({
         "model": "llava",
         "prompt": "Analyze this image. If it shows a vehicle, identify the year, make, model, and any visible modifications. If it's a document (receipt, title, etc.), extract relevant vehicle information. Return JSON with fields: is_vehicle, year, make, model, vin, modifications, document_type, extracted_text.",
         "images": [base64_image],
-        "stream": false
+        "stream": false,
+        "options": options.unwrap_or_default()
     });
 
     let response = client
@@ -282,85 +1118,222 @@ async fn analyze_image_local(image_path: String) -> Result<serde_json::Value, St
         .json(&request)
         .send()
         .await
-        .map_err(|e| format!("Ollama request failed: {}", e))?;
+        .map_err(NukeError::ollama)?;
 
-    let result: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse response: {}", e))?;
+    let result: serde_json::Value = response.json().await.map_err(NukeError::ollama)?;
 
     Ok(result)
 }
 
-/// Sync files to Nuke cloud
+/// What the batch API does when a vehicle already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDuplicate {
+    #[default]
+    Skip,
+    Update,
+    CreateVersion,
+}
+
+/// How `sync_to_cloud` filters and sends vehicles
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncOptions {
+    pub on_duplicate: OnDuplicate,
+    /// Build the batch requests and return them instead of posting
+    pub dry_run: bool,
+    /// Filename hints below this confidence stay local; manifest entries were reviewed
+    /// and always go
+    pub min_confidence: f32,
+}
+
+/// Body for one `api-v1-batch` request
+fn batch_request(vehicles: &[serde_json::Value], on_duplicate: OnDuplicate) -> serde_json::Value {
+    serde_json::json!({
+        "vehicles": vehicles,
+        "options": {
+            "on_duplicate": on_duplicate,
+            // Older API versions only understand the boolean
+            "skip_duplicates": on_duplicate == OnDuplicate::Skip,
+            "match_by": "vin"
+        }
+    })
+}
+
+/// How one batch POST went, after any retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchOutcome {
+    pub size: usize,
+    pub retries: u32,
+    pub error: Option<String>,
+}
+
+/// POST one batch, retrying transport errors, 5xx and 429 with backoff; other 4xx fail at once
+async fn post_batch_at(
+    client: &reqwest::Client,
+    url: &str,
+    api_key: &str,
+    request: &serde_json::Value,
+    delays: &[Duration],
+) -> (u32, Option<String>) {
+    let mut retries = 0;
+    loop {
+        let response = client
+            .post(url)
+            .header("X-API-Key", api_key)
+            .json(request)
+            .send()
+            .await;
+
+        let (error, wait) = match response {
+            Ok(resp) if resp.status().is_success() => return (retries, None),
+            Ok(resp) if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => (
+                format!("Batch failed: {}", resp.status()),
+                vpic::retry_after(resp.headers()),
+            ),
+            Ok(resp) if resp.status().is_server_error() => {
+                (format!("Batch failed: {}", resp.status()), None)
+            }
+            Ok(resp) => return (retries, Some(format!("Batch failed: {}", resp.status()))),
+            Err(e) => (format!("Request error: {}", e), None),
+        };
+
+        let Some(backoff) = delays.get(retries as usize) else {
+            return (retries, Some(error));
+        };
+        let wait = wait.unwrap_or(*backoff);
+        if wait > MAX_SYNC_RETRY_AFTER {
+            return (retries, Some(error));
+        }
+        tracing::debug!(%error, retries, wait_ms = wait.as_millis() as u64, "retrying sync batch");
+        tokio::time::sleep(wait).await;
+        retries += 1;
+    }
+}
+
+/// Sync files to Nuke cloud, or the reviewed vehicles of a manifest from `build_sync_manifest`.
+/// With `dry_run` nothing is posted; the batch requests come back for review instead.
 #[tauri::command]
+#[tracing::instrument(skip_all, fields(files = files.as_ref().map(|f| f.len()), batch_size = batch_size))]
 async fn sync_to_cloud(
-    files: Vec<ScanResult>,
+    app: tauri::AppHandle,
+    files: Option<Vec<ScanResult>>,
+    manifest: Option<manifest::SyncManifest>,
     api_key: String,
     batch_size: usize,
-) -> Result<serde_json::Value, String> {
-    let client = reqwest::Client::new();
-    let base_url = "https://qkgaybvrernstplzjaam.supabase.co/functions/v1";
+    options: Option<SyncOptions>,
+) -> Result<serde_json::Value, NukeError> {
+    let state = app.state::<AppState>();
+    let SyncOptions {
+        on_duplicate,
+        dry_run,
+        min_confidence,
+    } = options.unwrap_or_default();
+    let client = state.http.clone();
+    let started = std::time::Instant::now();
 
     let mut synced = 0;
     let mut failed = 0;
     let mut errors: Vec<String> = Vec::new();
+    let mut batches: Vec<BatchOutcome> = Vec::new();
 
-    // Process in batches
-    for batch in files.chunks(batch_size) {
-        let vehicles: Vec<serde_json::Value> = batch
+    // Hints below their document type's floor stay local
+    let floors = state.confidence_floors.lock().unwrap().clone();
+    let files = files.unwrap_or_default();
+    let below_floor = files.iter().filter(|f| floors.check_scan(f).is_some()).count();
+    let mut low_confidence = 0;
+
+    // A manifest already holds the exact payloads; raw files only carry filename hints
+    let vehicles: Vec<serde_json::Value> = match manifest {
+        Some(manifest) => manifest.entries.into_iter().map(|e| e.payload).collect(),
+        None => files
             .iter()
+            .filter(|f| floors.check_scan(f).is_none())
             .filter_map(|f| {
-                f.potential_vehicle.as_ref().map(|v| {
-                    serde_json::json!({
-                        "year": v.year,
-                        "make": v.make,
-                        "model": v.model,
-                        "vin": v.vin,
-                        "description": format!("Imported from {}", f.filename)
-                    })
-                })
+                let v = f.potential_vehicle.as_ref()?;
+                if v.confidence < min_confidence {
+                    low_confidence += 1;
+                    return None;
+                }
+                Some(serde_json::json!({
+                    "year": v.year,
+                    "make": v.make,
+                    "model": v.model,
+                    "vin": v.vin,
+                    "description": format!("Imported from {}", f.filename)
+                }))
             })
-            .collect();
+            .collect(),
+    };
+    // Sent all the same, but the batch API can only dedupe by VIN
+    let without_vin = vehicles
+        .iter()
+        .filter(|v| v["vin"].as_str().is_none_or(|vin| vin.trim().is_empty()))
+        .count();
 
-        if vehicles.is_empty() {
-            continue;
-        }
+    if dry_run {
+        let payloads: Vec<serde_json::Value> = vehicles
+            .chunks(batch_size.max(1))
+            .map(|batch| batch_request(batch, on_duplicate))
+            .collect();
+        return Ok(serde_json::json!({
+            "dry_run": true,
+            "would_sync": vehicles.len(),
+            "without_vin": without_vin,
+            "below_floor": below_floor,
+            "low_confidence": low_confidence,
+            "payloads": payloads
+        }));
+    }
 
-        let request = serde_json::json!({
-            "vehicles": vehicles,
-            "options": {
-                "skip_duplicates": true,
-                "match_by": "vin"
-            }
+    // Process in batches
+    for batch in vehicles.chunks(batch_size.max(1)) {
+        let request = batch_request(batch, on_duplicate);
+        let url = format!("{}/api-v1-batch", CLOUD_API_URL);
+        let (retries, error) =
+            post_batch_at(&client, &url, &api_key, &request, SYNC_RETRY_DELAYS).await;
+        batches.push(BatchOutcome {
+            size: batch.len(),
+            retries,
+            error: error.clone(),
         });
 
-        let response = client
-            .post(format!("{}/api-v1-batch", base_url))
-            .header("X-API-Key", &api_key)
-            .json(&request)
-            .send()
-            .await;
-
-        match response {
-            Ok(resp) => {
-                if resp.status().is_success() {
-                    synced += vehicles.len();
-                } else {
-                    failed += vehicles.len();
-                    errors.push(format!("Batch failed: {}", resp.status()));
-                }
-            }
-            Err(e) => {
-                failed += vehicles.len();
-                errors.push(format!("Request error: {}", e));
-            }
+        let batch_synced = if error.is_some() { 0 } else { batch.len() };
+        synced += batch_synced;
+        failed += batch.len() - batch_synced;
+        events::emit(Some(&app), &state.events, events::AppEvent::SyncBatchDone {
+            target: "cloud".to_string(),
+            synced: batch_synced,
+            failed: batch.len() - batch_synced,
+        });
+        if let Some(error) = error {
+            tracing::warn!(size = batch.len(), retries, %error, "sync batch failed");
+            events::emit(Some(&app), &state.events, events::AppEvent::Error {
+                operation: "sync_to_cloud".to_string(),
+                path: None,
+                message: error.clone(),
+            });
+            errors.push(error);
         }
     }
 
+    tracing::info!(
+        synced,
+        failed,
+        below_floor,
+        low_confidence,
+        without_vin,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "cloud sync finished"
+    );
+
     Ok(serde_json::json!({
         "synced": synced,
         "failed": failed,
+        "below_floor": below_floor,
+        "low_confidence": low_confidence,
+        "without_vin": without_vin,
+        "batches": batches,
         "errors": errors
     }))
 }
@@ -371,13 +1344,314 @@ fn main() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            // First, so everything set up after it can log
+            let logging = logging::Logging::init(&data_dir.join("logs"));
+            let config_dir = app.path().app_config_dir()?;
+            std::fs::create_dir_all(&config_dir)?;
+            imaging::set_pdfium_dir(app.path().resource_dir()?.join("pdfium"));
+            app.manage(AppState::new(data_dir, config_dir, logging));
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             scan_directories,
+            scan_directories_streamed,
+            cancel_scan,
+            watch::start_watching,
+            watch::stop_watching,
+            get_scan_summary,
+            scanconfig::save_scan_config,
+            scanconfig::load_scan_config,
             parse_csv,
+            spreadsheet::parse_spreadsheet,
             check_ollama,
+            ollama::list_ollama_models,
             analyze_image_local,
             sync_to_cloud,
+            csvimport::stream_csv_import,
+            csvimport::map_csv_to_vehicles,
+            report::generate_import_report,
+            report::coverage_report,
+            documents::process_document,
+            documents::process_documents,
+            documents::extract_inventory,
+            ocr::attach_ocr,
+            documents::process_folder_resumable,
+            documents::cancel_processing,
+            documents::set_json_retries,
+            condition::assess_condition,
+            ollama::ollama_load,
+            ollama::set_max_image_dimension,
+            ollama::set_ollama_timeout,
+            imaging::split_collage,
+            imaging::stitch_split_scans,
+            cache::clear_response_cache,
+            supabase::configure_supabase,
+            supabase::verify_supabase_schema,
+            supabase::test_supabase_connection,
+            privacy::configure_privacy,
+            floors::configure_confidence_floors,
+            supabase::sync_to_supabase,
+            supabase::retry_pending_sync,
+            dedupe::find_vin_duplicates,
+            dedupe::vehicle_similarity,
+            dedupe::dedupe_scans,
+            consistency::consistency_score,
+            review::review_queue,
+            parties::merge_parties,
+            manifest::build_sync_manifest,
+            manifest::export_organized,
+            photos::order_photo_set,
+            dates::reconcile_dates,
+            doctypes::extract_shop_notes,
+            details::check_glass_etch,
+            bundle::validate_bundle,
+            standard::export_standard,
+            vinexport::export_vins,
+            vpic::decode_vins_batch,
+            vpic::get_external_api_quota,
+            vin::resolve_vin_year,
+            vin::complete_partial_vin,
+            progress::get_pipeline_progress,
+            progress::set_progress_throttle,
+            events::get_app_events,
+            logging::set_log_level,
+            logging::get_log_path,
+            docx::parse_docx,
+            partition::partition_scan,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the temp dir holding `files`, each with a little content
+    fn temp_tree(name: &str, files: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for file in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, b"scan me").unwrap();
+        }
+        root
+    }
+
+    fn scan_config(root: &Path) -> ScanConfig {
+        ScanConfig {
+            paths: vec![root.to_string_lossy().to_string()],
+            ..ScanConfig::default()
+        }
+    }
+
+    fn scan(config: &ScanConfig) -> ScanWalk {
+        let dictionary = dictionary::VehicleDictionary::default();
+        walk_scan(config, &dictionary, &AtomicBool::new(false), |_| {})
+    }
+
+    /// Result paths relative to `root`
+    fn relative_paths(walk: &ScanWalk, root: &Path) -> Vec<String> {
+        let root = std::fs::canonicalize(root).unwrap();
+        walk.results
+            .iter()
+            .map(|r| {
+                Path::new(&r.path)
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn max_results_stops_at_the_cap_and_keeps_the_first_files_by_path() {
+        let root = temp_tree(
+            "cap",
+            &["b/2.jpg", "a/1.jpg", "a/3.jpg", "c.jpg", "d/e/4.jpg"],
+        );
+        let config = ScanConfig {
+            max_results: Some(2),
+            scan_threads: Some(4),
+            ..scan_config(&root)
+        };
+        for _ in 0..5 {
+            let walk = scan(&config);
+            assert!(walk.truncated);
+            assert_eq!(relative_paths(&walk, &root), vec!["a/1.jpg", "a/3.jpg"]);
+        }
+
+        let walk = scan(&scan_config(&root));
+        assert!(!walk.truncated);
+        assert_eq!(walk.results.len(), 5);
+
+        // Exactly at the cap isn't a truncation
+        let walk = scan(&ScanConfig {
+            max_results: Some(5),
+            ..scan_config(&root)
+        });
+        assert!(!walk.truncated);
+        assert_eq!(walk.results.len(), 5);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn on_duplicate_option_reaches_the_batch_request() {
+        let vehicles = [serde_json::json!({ "vin": "1HGCM82633A004352" })];
+        for (on_duplicate, name, skip) in [
+            (OnDuplicate::Skip, "skip", true),
+            (OnDuplicate::Update, "update", false),
+            (OnDuplicate::CreateVersion, "create_version", false),
+        ] {
+            let request = batch_request(&vehicles, on_duplicate);
+            assert_eq!(request["options"]["on_duplicate"], name);
+            assert_eq!(request["options"]["skip_duplicates"], skip);
+            assert_eq!(request["options"]["match_by"], "vin");
+            assert_eq!(request["vehicles"][0]["vin"], "1HGCM82633A004352");
+        }
+        assert_eq!(OnDuplicate::default(), OnDuplicate::Skip);
+        let parsed: OnDuplicate =
+            serde_json::from_value(serde_json::json!("create_version")).unwrap();
+        assert_eq!(parsed, OnDuplicate::CreateVersion);
+    }
+
+    #[test]
+    fn receipt_strips_are_flagged_with_their_aspect_ratio() {
+        let root = temp_tree("aspect", &[]);
+        std::fs::create_dir_all(&root).unwrap();
+        image::GrayImage::from_pixel(200, 1600, image::Luma([240]))
+            .save(root.join("receipt.png"))
+            .unwrap();
+        image::GrayImage::from_pixel(800, 600, image::Luma([120]))
+            .save(root.join("car.png"))
+            .unwrap();
+
+        let flagged = |config: &ScanConfig| -> Vec<(String, bool)> {
+            let mut results: Vec<(String, bool)> = scan(config)
+                .results
+                .into_iter()
+                .map(|r| (r.filename, r.unusual_aspect))
+                .collect();
+            results.sort();
+            results
+        };
+        let walk = scan(&scan_config(&root));
+        let receipt = walk
+            .results
+            .iter()
+            .find(|r| r.filename == "receipt.png")
+            .unwrap();
+        assert!((receipt.aspect_ratio.unwrap() - 0.125).abs() < 1e-4);
+        assert_eq!(
+            flagged(&scan_config(&root)),
+            vec![
+                ("car.png".to_string(), false),
+                ("receipt.png".to_string(), true)
+            ]
+        );
+
+        // A wider range lets the strip through
+        let lenient = ScanConfig {
+            aspect_range: imaging::AspectRange {
+                min: 0.1,
+                max: 10.0,
+            },
+            ..scan_config(&root)
+        };
+        assert!(flagged(&lenient).iter().all(|(_, unusual)| !unusual));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn only_check_digit_valid_vins_become_hints() {
+        let hint = extract_hints_from_text("/photos/1HGCM82633A004352.jpg").unwrap();
+        assert_eq!(hint.vin.as_deref(), Some("1HGCM82633A004352"));
+        assert_eq!(hint.vin_unverified, None);
+        assert_eq!(hint.confidence, 0.5);
+
+        // A 17-character hash or misread VIN is kept aside, and adds no confidence
+        let hint = extract_hints_from_text("/tmp/1HGCM82633A004353.jpg").unwrap();
+        assert_eq!(hint.vin, None);
+        assert_eq!(hint.vin_unverified.as_deref(), Some("1HGCM82633A004353"));
+        assert_eq!(hint.confidence, 0.0);
+
+        // A valid VIN later in the path wins over an earlier invalid one
+        let hint = extract_hints_from_text("/ABCDEFGH123456789/1M8GDM9AXKP042788.jpg").unwrap();
+        assert_eq!(hint.vin.as_deref(), Some("1M8GDM9AXKP042788"));
+    }
+
+    #[test]
+    fn precount_matches_the_files_the_scan_visits() {
+        let root = temp_tree(
+            "precount",
+            &[
+                "x.jpg",
+                "y.txt",
+                ".dot.jpg",
+                "a/z.pdf",
+                "a/b/unknown.bin",
+                ".hidden/v.jpg",
+                "skip.me.jpg",
+            ],
+        );
+        std::fs::write(root.join(".nukeignore"), "skip.me.jpg\n").unwrap();
+        // An overlapping second root is merged rather than counted twice
+        let config = ScanConfig {
+            paths: vec![
+                root.to_string_lossy().to_string(),
+                root.join("a").to_string_lossy().to_string(),
+            ],
+            ..ScanConfig::default()
+        };
+
+        let mut visited = 0;
+        let dictionary = dictionary::VehicleDictionary::default();
+        walk_scan(&config, &dictionary, &AtomicBool::new(false), |step| {
+            if let ScanStep::Visited(_) = step {
+                visited += 1;
+            }
+        });
+        // x.jpg, y.txt, a/z.pdf and .hidden/v.jpg: no dotfiles, unknown types or ignored files
+        assert_eq!(visited, 4);
+        assert_eq!(
+            count_scan_files(&config, &AtomicBool::new(false)),
+            Some(visited)
+        );
+
+        let images_only = ScanConfig {
+            include_documents: false,
+            ..config.clone()
+        };
+        assert_eq!(
+            count_scan_files(&images_only, &AtomicBool::new(false)),
+            Some(2)
+        );
+        assert_eq!(count_scan_files(&config, &AtomicBool::new(true)), None);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn an_unopenable_sync_queue_falls_back_to_memory_and_says_so() {
+        let events = events::EventLog::new();
+        let path = Path::new("/nonexistent/dir/sync_queue.sqlite");
+        let queue = open_sync_queue(path, &events);
+        assert_eq!(queue.pending_count(), 0);
+
+        let reported = events.since(None);
+        assert_eq!(reported.len(), 1);
+        match &reported[0].event {
+            events::AppEvent::Error {
+                operation, path, ..
+            } => {
+                assert_eq!(operation, "open_sync_queue");
+                assert_eq!(path.as_deref(), Some("/nonexistent/dir/sync_queue.sqlite"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
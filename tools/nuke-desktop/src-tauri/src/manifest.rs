@@ -0,0 +1,510 @@
+// Sync manifest - one merged record per vehicle, built and reviewed before anything is sent
+
+use crate::dedupe::{self, SimilarityWeights};
+use crate::documents::{ExtractedData, ExtractionResult};
+use crate::error::NukeError;
+use crate::imaging::ImageKind;
+use crate::{photos, vin};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+type FieldAccess = fn(&mut ExtractedData) -> &mut Option<String>;
+
+// Fields merged across a vehicle's source files, best-confidence source first
+const MERGED_FIELDS: &[(&str, FieldAccess)] = &[
+    ("vin", |d| &mut d.vin),
+    ("year", |d| &mut d.year),
+    ("make", |d| &mut d.make),
+    ("model", |d| &mut d.model),
+    ("color", |d| &mut d.color),
+    ("mileage", |d| &mut d.mileage),
+    ("plate_number", |d| &mut d.plate_number),
+    ("owner_name", |d| &mut d.owner_name),
+    ("price", |d| &mut d.price),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub vin: Option<String>,
+    pub vehicle: ExtractedData,
+    /// Which source file each merged field came from
+    pub provenance: BTreeMap<String, String>,
+    pub sources: Vec<String>,
+    /// Photo paths in capture order
+    pub photos: Vec<String>,
+    /// Exactly what `sync_to_cloud` sends for this vehicle
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncManifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// One source record: a result, or one row of an inventory sheet
+struct Item<'a> {
+    data: &'a ExtractedData,
+    result: &'a ExtractionResult,
+}
+
+/// Consolidate results into one record per vehicle, ready to review and sync
+#[tauri::command]
+pub async fn build_sync_manifest(
+    results: Vec<ExtractionResult>,
+    weights: Option<SimilarityWeights>,
+) -> Result<SyncManifest, NukeError> {
+    Ok(build_manifest(&results, &weights.unwrap_or_default()))
+}
+
+pub fn build_manifest(results: &[ExtractionResult], weights: &SimilarityWeights) -> SyncManifest {
+    let mut items: Vec<Item> = Vec::new();
+    for result in results {
+        if result.inventory.is_empty() {
+            items.push(Item {
+                data: &result.data,
+                result,
+            });
+        } else {
+            items.extend(
+                result
+                    .inventory
+                    .iter()
+                    .map(|row| Item { data: row, result }),
+            );
+        }
+    }
+
+    // VIN clusters first, then VIN-less records join the most similar vehicle
+    let mut clusters: Vec<(Option<String>, Vec<Item>)> = Vec::new();
+    let mut vinless: Vec<Item> = Vec::new();
+    for item in items {
+        match item.data.vin.as_deref().and_then(vin::normalize_vin) {
+            Some(normalized) => {
+                let key = normalized.match_key();
+                match clusters
+                    .iter_mut()
+                    .find(|(k, _)| k.as_deref() == Some(key.as_str()))
+                {
+                    Some((_, members)) => members.push(item),
+                    None => clusters.push((Some(key), vec![item])),
+                }
+            }
+            None => vinless.push(item),
+        }
+    }
+    for item in vinless {
+        let best = clusters
+            .iter()
+            .enumerate()
+            .map(|(i, (_, members))| {
+                let score = dedupe::score_similarity(&merge(members).0, item.data, weights);
+                (i, score)
+            })
+            .filter(|(_, score)| score.is_match)
+            .max_by(|a, b| a.1.score.total_cmp(&b.1.score))
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => clusters[i].1.push(item),
+            None => clusters.push((None, vec![item])),
+        }
+    }
+
+    SyncManifest {
+        entries: clusters
+            .iter()
+            .map(|(_, members)| manifest_entry(members))
+            .collect(),
+    }
+}
+
+fn manifest_entry(members: &[Item]) -> ManifestEntry {
+    let (mut vehicle, provenance) = merge(members);
+    // Send the VIN without the spaces or labels it was printed with
+    if let Some(normalized) = vehicle.vin.as_deref().and_then(vin::normalize_vin) {
+        vehicle.vin = Some(normalized.vin);
+    }
+    // Concours paperwork is provenance collectors pay for; carry the most confident record
+    vehicle.concours = members
+        .iter()
+        .filter(|item| item.data.concours.is_some())
+        .max_by(|a, b| a.result.confidence.total_cmp(&b.result.confidence))
+        .and_then(|item| item.data.concours.clone());
+
+    let mut sources: Vec<String> = Vec::new();
+    for item in members {
+        if !sources.contains(&item.result.path) {
+            sources.push(item.result.path.clone());
+        }
+    }
+
+    let photo_results: Vec<ExtractionResult> = members
+        .iter()
+        .filter(|item| item.result.image_kind == ImageKind::Photo)
+        .map(|item| item.result.clone())
+        .collect();
+    let photos: Vec<String> = photos::order_photos(photo_results)
+        .results
+        .into_iter()
+        .map(|r| r.path)
+        .collect();
+
+    let payload = serde_json::json!({
+        "vin": vehicle.vin,
+        "year": vehicle.year,
+        "make": vehicle.make,
+        "model": vehicle.model,
+        "color": vehicle.color,
+        "mileage": vehicle.mileage,
+        "photos": photos,
+        "concours": vehicle.concours,
+        "description": format!("Imported from {} file(s)", sources.len()),
+    });
+
+    ManifestEntry {
+        vin: vehicle.vin.clone(),
+        vehicle,
+        provenance,
+        sources,
+        photos,
+        payload,
+    }
+}
+
+/// Merge a cluster's fields, taking each from the most confident source that has it
+fn merge(members: &[Item]) -> (ExtractedData, BTreeMap<String, String>) {
+    let mut ranked: Vec<&Item> = members.iter().collect();
+    ranked.sort_by(|a, b| b.result.confidence.total_cmp(&a.result.confidence));
+
+    // Owned copies so the same accessor can read sources and write the merge
+    let mut candidates: Vec<(ExtractedData, &String)> = ranked
+        .iter()
+        .map(|item| (item.data.clone(), &item.result.path))
+        .collect();
+
+    let mut merged = ExtractedData::default();
+    let mut provenance = BTreeMap::new();
+    for (name, access) in MERGED_FIELDS {
+        let found = candidates
+            .iter_mut()
+            .find_map(|(data, path)| Some((access(data).clone()?, *path)));
+        if let Some((value, path)) = found {
+            *access(&mut merged) = Some(value);
+            provenance.insert(name.to_string(), path.clone());
+        }
+    }
+    (merged, provenance)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OrganizedExport {
+    pub folders: Vec<String>,
+    pub copied: usize,
+    /// Source files that no longer exist; the vehicle is still exported without them
+    pub missing: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Write one folder per vehicle under `dest_root`, with its source files and a vehicle.json
+#[tauri::command]
+pub async fn export_organized(
+    manifest: SyncManifest,
+    dest_root: String,
+) -> Result<OrganizedExport, NukeError> {
+    export_manifest(&manifest, Path::new(&dest_root)).map_err(NukeError::FileWrite)
+}
+
+pub fn export_manifest(
+    manifest: &SyncManifest,
+    dest_root: &Path,
+) -> Result<OrganizedExport, String> {
+    std::fs::create_dir_all(dest_root)
+        .map_err(|e| format!("Failed to create export folder: {}", e))?;
+
+    let mut export = OrganizedExport::default();
+    for entry in &manifest.entries {
+        let folder = unique_path(&dest_root.join(folder_name(entry)));
+        if let Err(e) = std::fs::create_dir_all(&folder) {
+            export.errors.push(format!("{}: {}", folder.display(), e));
+            continue;
+        }
+
+        // Original path -> file name inside the vehicle folder
+        let mut files: BTreeMap<String, String> = BTreeMap::new();
+        for source in &entry.sources {
+            let source_path = Path::new(source);
+            if !source_path.is_file() {
+                export.missing.push(source.clone());
+                continue;
+            }
+            let file_name = source_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "file".to_string());
+            let target = unique_path(&folder.join(file_name));
+            match std::fs::copy(source_path, &target) {
+                Ok(_) => {
+                    export.copied += 1;
+                    let copied_name = target
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    files.insert(source.clone(), copied_name);
+                }
+                Err(e) => export.errors.push(format!("{}: {}", source, e)),
+            }
+        }
+
+        let vehicle_json = serde_json::json!({
+            "vin": entry.vin,
+            "vehicle": entry.vehicle,
+            "provenance": entry.provenance,
+            "photos": entry.photos,
+            "files": files,
+        });
+        let written = serde_json::to_string_pretty(&vehicle_json)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                std::fs::write(folder.join("vehicle.json"), json).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            export.errors.push(format!(
+                "{}: Failed to write vehicle.json: {}",
+                folder.display(),
+                e
+            ));
+        }
+
+        export.folders.push(folder.to_string_lossy().to_string());
+    }
+
+    Ok(export)
+}
+
+/// "1972 Chevrolet C10 - CE142S123456", with characters file systems reject removed
+fn folder_name(entry: &ManifestEntry) -> String {
+    let vehicle = &entry.vehicle;
+    let mut name = [&vehicle.year, &vehicle.make, &vehicle.model]
+        .iter()
+        .filter_map(|part| part.as_deref())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if let Some(vin) = &entry.vin {
+        name = if name.is_empty() {
+            vin.clone()
+        } else {
+            format!("{} - {}", name, vin)
+        };
+    }
+
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || " -_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').to_string();
+    if cleaned.is_empty() {
+        "Unidentified vehicle".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// `path`, or "name (2).ext", "name (3).ext", ... if it already exists
+fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let parent = path.parent().unwrap_or(Path::new(""));
+    (2..)
+        .map(|n| parent.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(
+        path: &str,
+        image_kind: &str,
+        confidence: f32,
+        data: serde_json::Value,
+    ) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": image_kind,
+            "data": data,
+            "confidence": confidence,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    fn honda_and_ford() -> Vec<ExtractionResult> {
+        vec![
+            result(
+                "/title.jpg",
+                "document",
+                0.9,
+                json!({ "vin": "1HGCM82633A004352", "year": "2003", "make": "Honda", "owner_name": "J Smith" }),
+            ),
+            result(
+                "/a.jpg",
+                "photo",
+                0.5,
+                json!({ "vin": "1HGCM 82633A004352", "color": "Blue", "year": "2004" }),
+            ),
+            result(
+                "/b.jpg",
+                "photo",
+                0.6,
+                json!({ "year": "2003", "make": "Honda", "model": "Accord", "color": "blue" }),
+            ),
+            result(
+                "/c.jpg",
+                "photo",
+                0.6,
+                json!({ "vin": "1FMEU15H5FLA12345", "year": "1985", "make": "Ford" }),
+            ),
+            result(
+                "/d.jpg",
+                "photo",
+                0.6,
+                json!({ "year": "1960", "make": "Porsche", "model": "356" }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn one_entry_per_unique_vin() {
+        let manifest = build_manifest(&honda_and_ford(), &SimilarityWeights::default());
+        let vins: Vec<Option<&str>> = manifest.entries.iter().map(|e| e.vin.as_deref()).collect();
+        // The spaced VIN joins its cluster; the VIN-less Porsche matches nothing
+        assert_eq!(
+            vins,
+            vec![Some("1HGCM82633A004352"), Some("1FMEU15H5FLA12345"), None]
+        );
+    }
+
+    #[test]
+    fn merges_fields_from_the_most_confident_source_with_provenance() {
+        let manifest = build_manifest(&honda_and_ford(), &SimilarityWeights::default());
+        let honda = &manifest.entries[0];
+        assert_eq!(honda.sources, vec!["/title.jpg", "/a.jpg", "/b.jpg"]);
+        assert_eq!(honda.vehicle.year.as_deref(), Some("2003"));
+        assert_eq!(honda.provenance["year"], "/title.jpg");
+        assert_eq!(honda.provenance["color"], "/b.jpg");
+        assert_eq!(honda.provenance["model"], "/b.jpg");
+        assert_eq!(honda.vehicle.owner_name.as_deref(), Some("J Smith"));
+
+        // Only photos are listed as photos
+        let mut photos = honda.photos.clone();
+        photos.sort();
+        assert_eq!(photos, vec!["/a.jpg", "/b.jpg"]);
+
+        assert_eq!(honda.payload["vin"], "1HGCM82633A004352");
+        assert_eq!(honda.payload["model"], "Accord");
+        assert_eq!(honda.payload["description"], "Imported from 3 file(s)");
+        // Owner names stay local
+        assert!(honda.payload.get("owner_name").is_none());
+    }
+
+    #[test]
+    fn inventory_rows_are_separate_vehicles() {
+        let mut sheet = result(
+            "/inventory.jpg",
+            "document",
+            0.8,
+            json!({ "document_type": "inventory" }),
+        );
+        sheet.inventory = vec![
+            ExtractedData {
+                vin: Some("1FMEU15H5FLA12345".to_string()),
+                ..Default::default()
+            },
+            ExtractedData {
+                vin: Some("1HGCM82633A004352".to_string()),
+                ..Default::default()
+            },
+        ];
+        let manifest = build_manifest(&[sheet], &SimilarityWeights::default());
+        assert_eq!(manifest.entries.len(), 2);
+        assert!(manifest
+            .entries
+            .iter()
+            .all(|e| e.sources == vec!["/inventory.jpg"]));
+    }
+
+    #[test]
+    fn organized_export_writes_a_folder_per_vehicle() {
+        let root = std::env::temp_dir().join(format!("nuke-organized-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let source = root.join("intake");
+        std::fs::create_dir_all(source.join("sub")).unwrap();
+        std::fs::write(source.join("a.jpg"), b"front").unwrap();
+        std::fs::write(source.join("sub/a.jpg"), b"rear").unwrap();
+
+        let path = |name: &str| source.join(name).to_string_lossy().to_string();
+        let honda = json!({ "vin": "1HGCM82633A004352", "year": "2003", "make": "Honda", "model": "Accord" });
+        let results = vec![
+            result(&path("a.jpg"), "photo", 0.9, honda.clone()),
+            result(&path("sub/a.jpg"), "photo", 0.8, honda),
+            result(
+                &path("gone.jpg"),
+                "photo",
+                0.9,
+                json!({ "vin": "1FMEU15H5FLA12345", "make": "Ford/Mercury" }),
+            ),
+        ];
+        let manifest = build_manifest(&results, &SimilarityWeights::default());
+        let dest = root.join("export");
+        let export = export_manifest(&manifest, &dest).unwrap();
+
+        assert_eq!(export.copied, 2);
+        assert_eq!(export.missing, vec![path("gone.jpg")]);
+        assert!(export.errors.is_empty());
+        assert_eq!(export.folders.len(), 2);
+
+        // Same-named files from different folders both land, the second renamed
+        let honda_folder = dest.join("2003 Honda Accord - 1HGCM82633A004352");
+        assert_eq!(std::fs::read(honda_folder.join("a.jpg")).unwrap(), b"front");
+        assert_eq!(
+            std::fs::read(honda_folder.join("a (2).jpg")).unwrap(),
+            b"rear"
+        );
+        let vehicle: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(honda_folder.join("vehicle.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(vehicle["vin"], "1HGCM82633A004352");
+        assert_eq!(vehicle["vehicle"]["model"], "Accord");
+        assert_eq!(vehicle["files"][path("sub/a.jpg")], "a (2).jpg");
+
+        // A vehicle whose files are all gone still gets its folder and manifest
+        let ford_folder = dest.join("Ford_Mercury - 1FMEU15H5FLA12345");
+        assert!(ford_folder.join("vehicle.json").is_file());
+
+        // Exporting again beside the first run doesn't overwrite it
+        let again = export_manifest(&manifest, &dest).unwrap();
+        assert!(again.folders[0].ends_with("1HGCM82633A004352 (2)"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,184 @@
+// Model JSON - pull the JSON object out of a model's free-form response
+
+use serde_json::Value;
+
+/// The JSON object in a model response. Fenced ```json blocks are tried first, then the whole
+/// text; among the balanced `{...}` spans that parse, the largest wins, so prose braces and
+/// trailing commentary don't get in the way. Trailing commas are forgiven.
+pub fn extract_json(raw: &str) -> Option<Value> {
+    fenced_blocks(raw)
+        .into_iter()
+        .chain(std::iter::once(raw))
+        .find_map(largest_object)
+}
+
+/// Contents of each ``` fenced block, language tag dropped
+fn fenced_blocks(raw: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut rest = raw;
+    while let Some(open) = rest.find("```") {
+        let after = &rest[open + 3..];
+        // The rest of the opening line is the language tag ("json", "JSON", ...)
+        let body_start = after.find('\n').map(|i| i + 1).unwrap_or(after.len());
+        let body = &after[body_start..];
+        let Some(close) = body.find("```") else {
+            // An unclosed fence still holds the answer when the model stopped early
+            blocks.push(body);
+            break;
+        };
+        blocks.push(&body[..close]);
+        rest = &body[close + 3..];
+    }
+    blocks
+}
+
+/// The longest balanced `{...}` span in `text` that parses as an object
+fn largest_object(text: &str) -> Option<Value> {
+    let mut best: Option<(usize, Value)> = None;
+    let mut start = 0;
+    while let Some(offset) = text[start..].find('{') {
+        let open = start + offset;
+        match matching_brace(&text[open..]) {
+            Some(len) => {
+                let span = &text[open..open + len];
+                if let Some(value) = parse_object(span) {
+                    if best.as_ref().map(|(l, _)| len > *l).unwrap_or(true) {
+                        best = Some((len, value));
+                    }
+                    // Anything nested inside is smaller
+                    start = open + len;
+                    continue;
+                }
+                start = open + 1;
+            }
+            None => start = open + 1,
+        }
+    }
+    best.map(|(_, value)| value)
+}
+
+/// Byte length of the object opening at the start of `text`, through its closing brace
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_object(span: &str) -> Option<Value> {
+    serde_json::from_str::<Value>(span)
+        .ok()
+        .or_else(|| serde_json::from_str(&strip_trailing_commas(span)).ok())
+        .filter(|v| v.is_object())
+}
+
+/// `{"a": 1,}` -> `{"a": 1}`, leaving commas inside strings alone
+fn strip_trailing_commas(span: &str) -> String {
+    let mut out = String::with_capacity(span.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = span.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let mut lookahead = chars.clone();
+            while lookahead.next_if(|c| c.is_whitespace()).is_some() {}
+            if matches!(lookahead.peek(), Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fenced_block_wins_over_the_prose_around_it() {
+        let raw = "Here you go:\n```json\n{\"vin\": \"1GCEK14K8HZ123456\", \"year\": 1987}\n```\nLet me know {if} you need more.";
+        assert_eq!(
+            extract_json(raw),
+            Some(json!({ "vin": "1GCEK14K8HZ123456", "year": 1987 }))
+        );
+    }
+
+    #[test]
+    fn prose_braces_before_the_object_are_skipped() {
+        let raw =
+            "The document {a title} shows: {\"make\": \"Ford\", \"notes\": \"brace } in string\"}";
+        assert_eq!(
+            extract_json(raw),
+            Some(json!({ "make": "Ford", "notes": "brace } in string" }))
+        );
+    }
+
+    #[test]
+    fn the_largest_object_wins_over_trailing_commentary() {
+        let raw = "{\"a\": 1} and the full answer {\"make\": \"Chevrolet\", \"model\": \"C10\", \"year\": \"1972\"} hope that helps {ok}";
+        assert_eq!(
+            extract_json(raw),
+            Some(json!({ "make": "Chevrolet", "model": "C10", "year": "1972" }))
+        );
+        // Nested objects stay inside the outer one
+        assert_eq!(
+            extract_json("x {\"a\": {\"b\": 1}} y"),
+            Some(json!({ "a": { "b": 1 } }))
+        );
+    }
+
+    #[test]
+    fn trailing_commas_and_an_unclosed_fence_are_forgiven() {
+        let raw = "```JSON\n{\"make\": \"Dodge\", \"tags\": [\"a\", \"b\",],}\n";
+        assert_eq!(
+            extract_json(raw),
+            Some(json!({ "make": "Dodge", "tags": ["a", "b"] }))
+        );
+        // Commas inside strings are left alone
+        assert_eq!(
+            extract_json("{\"s\": \"x,}\"}"),
+            Some(json!({ "s": "x,}" }))
+        );
+    }
+
+    #[test]
+    fn responses_without_an_object_give_nothing() {
+        assert_eq!(extract_json("no json here"), None);
+        assert_eq!(extract_json("{not json}"), None);
+        assert_eq!(extract_json("[1, 2]"), None);
+        assert_eq!(extract_json("{\"unterminated\": "), None);
+    }
+}
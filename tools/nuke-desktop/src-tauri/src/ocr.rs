@@ -0,0 +1,443 @@
+// OCR through the Tesseract library, and barcode reading via zbarimg
+
+use crate::cache::{self, OcrCache};
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::{vin, AppState};
+use leptess::{LepTess, Variable};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Words Tesseract is less sure of than this are counted as low confidence
+const LOW_WORD_CONFIDENCE: f32 = 60.0;
+// A VIN never contains I, O or Q
+const VIN_CHARSET: &str = "ABCDEFGHJKLMNPRSTUVWXYZ0123456789";
+// Etched VINs are read as a single line of VIN characters
+const ETCHED_VIN_SETTINGS: &[(Variable, &str)] = &[
+    (Variable::TesseditPagesegMode, "7"),
+    (Variable::TesseditCharWhitelist, VIN_CHARSET),
+];
+// Printed VINs are looked for as sparse text of VIN characters
+const PRINTED_VIN_SETTINGS: &[(Variable, &str)] = &[
+    (Variable::TesseditPagesegMode, "11"),
+    (Variable::TesseditCharWhitelist, VIN_CHARSET),
+];
+
+// Tesseract processes at once when the caller doesn't say
+const DEFAULT_OCR_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OcrOutput {
+    pub text: String,
+    pub word_count: usize,
+    pub mean_confidence: f32,
+    #[serde(default)]
+    pub min_confidence: f32,
+    #[serde(default)]
+    pub low_confidence_words: usize,
+}
+
+/// Results of `attach_ocr`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OcrBatch {
+    pub results: Vec<ExtractionResult>,
+    /// How many came from the OCR cache
+    pub cached: usize,
+    pub errors: Vec<String>,
+}
+
+/// Run OCR over results' files and attach the text, reusing earlier output for unchanged files
+#[tauri::command]
+pub async fn attach_ocr(
+    state: tauri::State<'_, AppState>,
+    results: Vec<ExtractionResult>,
+    concurrency: Option<usize>,
+) -> Result<OcrBatch, NukeError> {
+    Ok(attach_ocr_with(
+        results,
+        state.ocr_cache.clone(),
+        concurrency.unwrap_or(DEFAULT_OCR_CONCURRENCY),
+        |path| async move { ocr_file(&path).await },
+    )
+    .await)
+}
+
+/// `attach_ocr` with the OCR engine passed in; at most `concurrency` files run at once
+pub async fn attach_ocr_with<F, Fut>(
+    mut results: Vec<ExtractionResult>,
+    cache: OcrCache,
+    concurrency: usize,
+    run: F,
+) -> OcrBatch
+where
+    F: Fn(PathBuf) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<OcrOutput, String>> + Send,
+{
+    let permits = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, result) in results.iter().enumerate() {
+        let (path, cache, run, permits) = (
+            PathBuf::from(&result.path),
+            cache.clone(),
+            run.clone(),
+            permits.clone(),
+        );
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let hash = cache::hash_file(&path)?;
+            if let Some(hit) = cache.get(&hash) {
+                return Ok((index, hit, true));
+            }
+            let output = run(path).await?;
+            // A failed cache write only costs a re-run next time
+            let _ = cache.insert(&hash, &output);
+            Ok::<_, String>((index, output, false))
+        });
+    }
+
+    let mut cached = 0;
+    let mut errors = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined.map_err(|e| format!("OCR task failed: {}", e)) {
+            Ok(Ok((index, output, hit))) => {
+                cached += hit as usize;
+                results[index].ocr = Some(output);
+            }
+            Ok(Err(e)) | Err(e) => errors.push(e),
+        }
+    }
+
+    OcrBatch {
+        results,
+        cached,
+        errors,
+    }
+}
+
+/// OCR an image with Tesseract, or pull the text layer out of a PDF
+pub async fn ocr_file(path: &Path) -> Result<OcrOutput, String> {
+    let is_pdf = path
+        .extension()
+        .map(|e| e.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+    if !is_pdf {
+        return run_ocr(path).await;
+    }
+
+    let text = crate::imaging::pdf_text_layer(path)
+        .await?
+        .trim()
+        .to_string();
+    if text.is_empty() {
+        return Err(format!("{}: PDF has no text layer", path.display()));
+    }
+    // Embedded text is exact, so there's no recognition confidence to report
+    Ok(OcrOutput {
+        word_count: text.split_whitespace().count(),
+        mean_confidence: 100.0,
+        min_confidence: 100.0,
+        low_confidence_words: 0,
+        text,
+    })
+}
+
+/// Run Tesseract over an image and collect recognized words with confidences
+pub async fn run_ocr(path: &Path) -> Result<OcrOutput, String> {
+    run_tesseract(path, &[]).await
+}
+
+/// VINs read from a glass-etch photo: contrast-enhanced and inverted copies, read as a
+/// single line restricted to VIN characters. Check-digit-valid reads come first.
+pub async fn read_etched_vins(path: &Path) -> Vec<String> {
+    let mut vins: Vec<String> = Vec::new();
+    for variant in crate::imaging::etching_variants(path) {
+        let output = run_tesseract(&variant, ETCHED_VIN_SETTINGS).await;
+        let _ = std::fs::remove_file(&variant);
+        if let Ok(output) = output {
+            for found in etched_vins_from_text(&output.text) {
+                if !vins.contains(&found) {
+                    vins.push(found);
+                }
+            }
+        }
+    }
+    vins.sort_by_key(|v| !vin::validate_vin(v));
+    vins
+}
+
+/// Check-digit-valid VINs on a printed document, from a pass tuned for them: sparse text,
+/// VIN characters only. For when the general pass and the model both missed the VIN.
+pub async fn read_printed_vins(path: &Path) -> Result<Vec<String>, String> {
+    let output = run_tesseract(path, PRINTED_VIN_SETTINGS).await?;
+    Ok(etched_vins_from_text(&output.text)
+        .into_iter()
+        .filter(|v| vin::validate_vin(v))
+        .collect())
+}
+
+/// VINs in OCR text from etched glass. Etched characters are often read with stray spaces
+/// between them, so a line without a clean VIN is searched again with the spaces dropped.
+pub fn etched_vins_from_text(text: &str) -> Vec<String> {
+    let mut vins: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let mut found = vin::find_vins(line);
+        if found.is_empty() {
+            let compact: Vec<char> = line
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .map(|c| c.to_ascii_uppercase())
+                .collect();
+            found = compact
+                .windows(17)
+                .map(|w| w.iter().collect::<String>())
+                .filter(|candidate| vin::validate_vin(candidate))
+                .collect();
+        }
+        for candidate in found {
+            if !vins.contains(&candidate) {
+                vins.push(candidate);
+            }
+        }
+    }
+    vins
+}
+
+async fn run_tesseract(
+    path: &Path,
+    settings: &'static [(Variable, &'static str)],
+) -> Result<OcrOutput, String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || recognize(&path, settings))
+        .await
+        .map_err(|e| format!("OCR task failed: {}", e))?
+}
+
+/// Read an image with Tesseract's English model, with `settings` applied first
+fn recognize(path: &Path, settings: &[(Variable, &str)]) -> Result<OcrOutput, String> {
+    let mut engine = LepTess::new(None, "eng").map_err(|e| {
+        format!(
+            "OCR needs Tesseract and its English language data (eng.traineddata), which could not be loaded: {}",
+            e
+        )
+    })?;
+    for (variable, value) in settings {
+        engine
+            .set_variable(*variable, value)
+            .map_err(|e| format!("Failed to set Tesseract's {:?}: {}", variable, e))?;
+    }
+    engine
+        .set_image(path)
+        .map_err(|e| format!("Failed to load {} for OCR: {}", path.display(), e))?;
+    let tsv = engine
+        .get_tsv_text(0)
+        .map_err(|e| format!("Tesseract returned invalid text: {}", e))?;
+    Ok(parse_tsv(&tsv))
+}
+
+/// Parse Tesseract TSV output into text and word statistics
+fn parse_tsv(tsv: &str) -> OcrOutput {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_line: Option<(String, String, String)> = None;
+    let mut word_count = 0;
+    let mut confidence_sum = 0.0;
+    let mut min_confidence: Option<f32> = None;
+    let mut low_confidence_words = 0;
+
+    // Columns: level page block par line word left top width height conf text. Only word
+    // rows (level 5) are read, which also passes over the CLI's header row.
+    for row in tsv.lines() {
+        let cols: Vec<&str> = row.split('\t').collect();
+        if cols.len() < 12 || cols[0] != "5" {
+            continue;
+        }
+
+        let conf: f32 = cols[10].parse().unwrap_or(-1.0);
+        let word = cols[11].trim();
+        if conf < 0.0 || word.is_empty() {
+            continue;
+        }
+
+        let line_key = (
+            cols[2].to_string(),
+            cols[3].to_string(),
+            cols[4].to_string(),
+        );
+        if current_line.as_ref() != Some(&line_key) {
+            lines.push(String::new());
+            current_line = Some(line_key);
+        }
+        if let Some(line) = lines.last_mut() {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+
+        word_count += 1;
+        confidence_sum += conf;
+        min_confidence = Some(min_confidence.map_or(conf, |m| m.min(conf)));
+        if conf < LOW_WORD_CONFIDENCE {
+            low_confidence_words += 1;
+        }
+    }
+
+    OcrOutput {
+        text: lines.join("\n"),
+        word_count,
+        mean_confidence: if word_count > 0 {
+            confidence_sum / word_count as f32
+        } else {
+            0.0
+        },
+        min_confidence: min_confidence.unwrap_or(0.0),
+        low_confidence_words,
+    }
+}
+
+/// Decode barcodes in an image with zbarimg, returning each symbol's raw data
+pub async fn read_barcodes(path: &Path) -> Result<Vec<String>, String> {
+    let output = tokio::process::Command::new("zbarimg")
+        .arg("--quiet")
+        .arg("--raw")
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run zbarimg: {}", e))?;
+
+    // zbarimg exits 4 when the image simply has no barcodes
+    if !output.status.success() && output.status.code() != Some(4) {
+        return Err(format!(
+            "zbarimg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn result(path: &Path) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path.to_string_lossy(),
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": 0.8,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    /// An OCR engine that reads the file as its text and counts how often it ran
+    fn counting_engine(
+        runs: Arc<AtomicUsize>,
+    ) -> impl Fn(PathBuf) -> std::future::Ready<Result<OcrOutput, String>> + Clone {
+        move |path| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            std::future::ready(Ok(OcrOutput {
+                text: std::fs::read_to_string(path).unwrap(),
+                word_count: 1,
+                mean_confidence: 91.0,
+                min_confidence: 91.0,
+                low_confidence_words: 0,
+            }))
+        }
+    }
+
+    #[tokio::test]
+    async fn ocr_text_is_attached_and_cached_across_runs() {
+        let dir = std::env::temp_dir().join(format!("nuke-attach-ocr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let results: Vec<ExtractionResult> = (0..4)
+            .map(|i| {
+                let path = dir.join(format!("{}.jpg", i));
+                std::fs::write(&path, format!("page {}", i)).unwrap();
+                result(&path)
+            })
+            .collect();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let batch = attach_ocr_with(
+            results.clone(),
+            OcrCache::new(dir.join("cache")),
+            2,
+            counting_engine(runs.clone()),
+        )
+        .await;
+        assert!(batch.errors.is_empty());
+        assert_eq!(batch.cached, 0);
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        // Attached to the right result, in input order
+        for (i, result) in batch.results.iter().enumerate() {
+            assert_eq!(result.ocr.as_ref().unwrap().text, format!("page {}", i));
+        }
+
+        // A later run over the same files reads everything from the cache
+        let batch = attach_ocr_with(
+            results,
+            OcrCache::new(dir.join("cache")),
+            2,
+            counting_engine(runs.clone()),
+        )
+        .await;
+        assert_eq!(batch.cached, 4);
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert_eq!(batch.results[3].ocr.as_ref().unwrap().mean_confidence, 91.0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreadable_files_are_reported_and_left_without_text() {
+        let missing = result(Path::new("/nonexistent/scan.jpg"));
+        let runs = Arc::new(AtomicUsize::new(0));
+        let cache_dir =
+            std::env::temp_dir().join(format!("nuke-attach-ocr-missing-{}", std::process::id()));
+        let batch = attach_ocr_with(
+            vec![missing],
+            OcrCache::new(cache_dir),
+            2,
+            counting_engine(runs.clone()),
+        )
+        .await;
+        assert_eq!(batch.errors.len(), 1);
+        assert!(batch.results[0].ocr.is_none());
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn tesseract_tsv_gives_lines_and_a_confidence_summary() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+5\t1\t1\t1\t1\t1\t0\t0\t10\t10\t96\tCERTIFICATE
+5\t1\t1\t1\t1\t2\t0\t0\t10\t10\t90\tOF
+5\t1\t1\t1\t1\t3\t0\t0\t10\t10\t93\tTITLE
+4\t1\t1\t1\t2\t0\t0\t0\t10\t10\t-1\t
+5\t1\t1\t1\t2\t1\t0\t0\t10\t10\t41\tVIN
+5\t1\t1\t1\t2\t2\t0\t0\t10\t10\t-1\t ";
+        let output = parse_tsv(tsv);
+        assert_eq!(output.text, "CERTIFICATE OF TITLE\nVIN");
+        assert_eq!(output.word_count, 4);
+        assert_eq!(output.mean_confidence, 80.0);
+        assert_eq!(output.min_confidence, 41.0);
+        assert_eq!(output.low_confidence_words, 1);
+        assert_eq!(parse_tsv("").word_count, 0);
+    }
+
+    #[test]
+    fn etched_glass_reads_are_joined_across_stray_spaces() {
+        let text = "~ ,. \n1H GCM8 2633 A00 4352\n";
+        assert_eq!(etched_vins_from_text(text), vec!["1HGCM82633A004352"]);
+        assert!(etched_vins_from_text("~ ,.\n").is_empty());
+    }
+}
@@ -0,0 +1,862 @@
+// Local Ollama client helpers
+
+use crate::cache::ResponseCache;
+use crate::error::NukeError;
+use crate::AppState;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub const OLLAMA_URL: &str = "http://localhost:11434";
+
+// A /api/ps call slower than this means the server is struggling
+const BUSY_RESPONSE_MS: u64 = 2000;
+
+/// Longest an Ollama request may take, start to finish, unless `set_ollama_timeout` changes it
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Seed for `OllamaOptions::default`; any fixed value makes runs repeatable
+const DEFAULT_SEED: i64 = 42;
+
+// Model families that take images. llama3.2-vision reports "mllama"; llava-style models
+// list "clip" for their image encoder.
+const VISION_FAMILIES: &[&str] = &["llava", "bakllava", "mllama", "moondream", "clip"];
+
+// Name fragments that mark a vision model when the family doesn't
+const VISION_NAME_HINTS: &[&str] = &["vision", "llava", "moondream"];
+
+/// Base64-encode image bytes for a model request. Every image payload goes through here, so
+/// an endpoint that needs another alphabet only has to change this one engine.
+pub fn encode_image_b64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Ollama requests on the app's shared HTTP client, each limited to the Ollama timeout
+#[derive(Clone)]
+pub struct OllamaClient {
+    http: reqwest::Client,
+    timeout: Duration,
+}
+
+impl OllamaClient {
+    pub fn new(http: reqwest::Client, timeout: Duration) -> Self {
+        OllamaClient { http, timeout }
+    }
+
+    pub fn get<U: reqwest::IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.http.get(url).timeout(self.timeout)
+    }
+
+    pub fn post<U: reqwest::IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.http.post(url).timeout(self.timeout)
+    }
+}
+
+/// Set how long an Ollama request may run before failing with `OllamaTimeout`
+#[tauri::command]
+pub async fn set_ollama_timeout(
+    state: tauri::State<'_, AppState>,
+    seconds: u64,
+) -> Result<(), NukeError> {
+    if seconds == 0 {
+        return Err(NukeError::InvalidInput(
+            "seconds must be at least 1".to_string(),
+        ));
+    }
+    state.ollama_timeout_secs.store(seconds, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Set the longest image edge sent to vision models; larger images are downscaled first
+#[tauri::command]
+pub async fn set_max_image_dimension(
+    state: tauri::State<'_, AppState>,
+    max_dimension: u32,
+) -> Result<(), NukeError> {
+    if max_dimension == 0 {
+        return Err(NukeError::InvalidInput(
+            "max_dimension must be at least 1".to_string(),
+        ));
+    }
+    state
+        .max_image_dimension
+        .store(max_dimension, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Receives each piece of text as a streamed generation produces it
+pub type TokenSink<'a> = dyn Fn(&str) + Sync + 'a;
+
+/// Payload of the `ollama-token` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenEvent {
+    /// The file being extracted
+    pub path: String,
+    pub token: String,
+}
+
+/// A sink that emits `ollama-token` for each token generated while extracting `path`
+pub fn token_emitter<'a>(app: &'a tauri::AppHandle, path: &'a str) -> impl Fn(&str) + Sync + 'a {
+    move |token| {
+        let _ = app.emit(
+            "ollama-token",
+            TokenEvent {
+                path: path.to_string(),
+                token: token.to_string(),
+            },
+        );
+    }
+}
+
+/// Sampling parameters sent as a generation's `options`; unset fields use the model's own
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Most tokens to generate; -1 means no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Context window in tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+}
+
+impl Default for OllamaOptions {
+    /// Greedy sampling with a fixed seed, so the same document extracts the same way twice
+    fn default() -> Self {
+        OllamaOptions {
+            temperature: Some(0.0),
+            top_p: None,
+            num_predict: None,
+            seed: Some(DEFAULT_SEED),
+            num_ctx: None,
+        }
+    }
+}
+
+/// One generation: which model, what to ask it, and how to sample
+pub struct Generation<'a> {
+    pub model: &'a str,
+    pub prompt: &'a str,
+    pub images: Vec<String>,
+    pub options: &'a OllamaOptions,
+}
+
+impl Generation<'_> {
+    fn request(&self, stream: bool) -> serde_json::Value {
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "prompt": self.prompt,
+            "stream": stream,
+            "options": self.options,
+        });
+        if !self.images.is_empty() {
+            request["images"] = serde_json::json!(self.images);
+        }
+        request
+    }
+}
+
+/// Run a generation, answering from the response cache when these exact inputs were seen
+/// before. With `on_token` the response is streamed and each token passed on as it arrives.
+pub async fn generate_cached(
+    client: &OllamaClient,
+    cache: &ResponseCache,
+    generation: Generation<'_>,
+    on_token: Option<&TokenSink<'_>>,
+) -> Result<String, NukeError> {
+    generate_cached_at(client, OLLAMA_URL, cache, generation, on_token).await
+}
+
+pub async fn generate_cached_at(
+    client: &OllamaClient,
+    base_url: &str,
+    cache: &ResponseCache,
+    generation: Generation<'_>,
+    on_token: Option<&TokenSink<'_>>,
+) -> Result<String, NukeError> {
+    // Different sampling can give a different answer, so options are part of the key
+    let options = serde_json::to_string(generation.options)
+        .map_err(|e| NukeError::InvalidInput(format!("Invalid model options: {}", e)))?;
+    let key = ResponseCache::key(
+        generation.model,
+        generation.prompt,
+        &options,
+        &generation.images,
+    );
+    if let Some(response) = cache.get(&key) {
+        return Ok(response);
+    }
+    let response = match on_token {
+        Some(on_token) => generate_streamed_at(client, base_url, &generation, on_token).await?,
+        None => generate_at(client, base_url, &generation).await?,
+    };
+    // Empty answers are usually a model hiccup; let the next run try again
+    if !response.trim().is_empty() {
+        // A cache write failure shouldn't lose a good response
+        let _ = cache.insert(&key, generation.model, &response);
+    }
+    Ok(response)
+}
+
+/// Run a non-streaming generation and return the model's response text
+async fn generate_at(
+    client: &OllamaClient,
+    base_url: &str,
+    generation: &Generation<'_>,
+) -> Result<String, NukeError> {
+    let response = client
+        .post(format!("{}/api/generate", base_url))
+        .json(&generation.request(false))
+        .send()
+        .await
+        .map_err(NukeError::ollama)?;
+
+    if !response.status().is_success() {
+        return Err(NukeError::Network(format!(
+            "Ollama returned {}",
+            response.status()
+        )));
+    }
+
+    let result: serde_json::Value = response.json().await.map_err(NukeError::ollama)?;
+
+    Ok(result
+        .get("response")
+        .and_then(|r| r.as_str())
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// Run a streaming generation, reading Ollama's NDJSON chunks as they arrive; returns the
+/// full response text once the model is done
+async fn generate_streamed_at(
+    client: &OllamaClient,
+    base_url: &str,
+    generation: &Generation<'_>,
+    on_token: &TokenSink<'_>,
+) -> Result<String, NukeError> {
+    let mut response = client
+        .post(format!("{}/api/generate", base_url))
+        .json(&generation.request(true))
+        .send()
+        .await
+        .map_err(NukeError::ollama)?;
+
+    if !response.status().is_success() {
+        return Err(NukeError::Network(format!(
+            "Ollama returned {}",
+            response.status()
+        )));
+    }
+
+    let mut text = String::new();
+    // Network chunks don't line up with NDJSON lines
+    let mut pending: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(NukeError::ollama)? {
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            if let Some(token) = stream_token(&line)? {
+                on_token(&token);
+                text.push_str(&token);
+            }
+        }
+    }
+    if let Some(token) = stream_token(&pending)? {
+        on_token(&token);
+        text.push_str(&token);
+    }
+    Ok(text)
+}
+
+/// The response text carried by one line of a streamed generation
+fn stream_token(line: &[u8]) -> Result<Option<String>, NukeError> {
+    if line.trim_ascii().is_empty() {
+        return Ok(None);
+    }
+    let chunk: serde_json::Value = serde_json::from_slice(line)
+        .map_err(|e| NukeError::JsonParse(format!("Failed to parse response chunk: {}", e)))?;
+    if let Some(error) = chunk.get("error").and_then(|e| e.as_str()) {
+        return Err(NukeError::Network(format!("Ollama error: {}", error)));
+    }
+    Ok(chunk
+        .get("response")
+        .and_then(|r| r.as_str())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string()))
+}
+
+/// An installed model, as `/api/tags` describes it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModelInfo {
+    pub name: String,
+    /// Bytes on disk
+    pub size: u64,
+    pub family: Option<String>,
+    /// e.g. "7B"
+    pub parameter_size: Option<String>,
+    /// e.g. "Q4_0"
+    pub quantization_level: Option<String>,
+    /// Whether the model accepts images; text-only models can't read documents
+    pub supports_vision: bool,
+}
+
+/// Models installed in the local Ollama, with their size and family
+#[tauri::command]
+pub async fn list_ollama_models(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<OllamaModelInfo>, NukeError> {
+    list_models(&state.ollama_client(), OLLAMA_URL).await
+}
+
+pub async fn list_models(
+    client: &OllamaClient,
+    base_url: &str,
+) -> Result<Vec<OllamaModelInfo>, NukeError> {
+    let response: serde_json::Value = client
+        .get(format!("{}/api/tags", base_url))
+        .send()
+        .await
+        .map_err(NukeError::ollama)?
+        .json()
+        .await
+        .map_err(NukeError::ollama)?;
+
+    Ok(response
+        .get("models")
+        .and_then(|m| m.as_array())
+        .map(|models| models.iter().filter_map(model_info).collect())
+        .unwrap_or_default())
+}
+
+/// Fail with `NonVisionModel` when `model` is installed but can't take images. A model
+/// that isn't installed is left for the generate call to report.
+pub async fn require_vision(
+    client: &OllamaClient,
+    base_url: &str,
+    model: &str,
+) -> Result<(), NukeError> {
+    let models = list_models(client, base_url).await?;
+    match models.iter().find(|m| same_model(&m.name, model)) {
+        Some(info) if !info.supports_vision => Err(NukeError::NonVisionModel {
+            model: model.to_string(),
+            vision_models: models
+                .iter()
+                .filter(|m| m.supports_vision)
+                .map(|m| m.name.clone())
+                .collect(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// One entry of `/api/tags`
+pub fn model_info(model: &serde_json::Value) -> Option<OllamaModelInfo> {
+    let name = model.get("name").and_then(|n| n.as_str())?.to_string();
+    let details = model.get("details");
+    let detail = |key: &str| {
+        details
+            .and_then(|d| d.get(key))
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+    };
+    let family = detail("family");
+
+    let families: Vec<String> = details
+        .and_then(|d| d.get("families"))
+        .and_then(|f| f.as_array())
+        .map(|f| {
+            f.iter()
+                .filter_map(|v| v.as_str())
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .unwrap_or_default();
+    let lower_name = name.to_lowercase();
+    let supports_vision = family
+        .iter()
+        .map(|f| f.to_lowercase())
+        .chain(families)
+        .any(|f| VISION_FAMILIES.contains(&f.as_str()))
+        || VISION_NAME_HINTS
+            .iter()
+            .any(|hint| lower_name.contains(hint));
+
+    Some(OllamaModelInfo {
+        size: model.get("size").and_then(|s| s.as_u64()).unwrap_or(0),
+        family,
+        parameter_size: detail("parameter_size"),
+        quantization_level: detail("quantization_level"),
+        supports_vision,
+        name,
+    })
+}
+
+/// What Ollama has loaded right now, and whether it looks busy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaLoad {
+    pub loaded_models: Vec<String>,
+    pub vram_bytes: u64,
+    pub response_ms: u64,
+    pub busy: bool,
+    pub reason: Option<String>,
+}
+
+/// Payload of the `ollama-deferral` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferralEvent {
+    /// False once the server has capacity again and dispatch resumes
+    pub deferred: bool,
+    pub attempt: u32,
+    pub retry_in_ms: u64,
+    pub reason: Option<String>,
+}
+
+/// How long to back off while the server is saturated
+#[derive(Debug, Clone, Copy)]
+pub struct DeferralPolicy {
+    pub initial: Duration,
+    pub max: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for DeferralPolicy {
+    fn default() -> Self {
+        DeferralPolicy {
+            initial: Duration::from_secs(2),
+            max: Duration::from_secs(60),
+            max_attempts: 10,
+        }
+    }
+}
+
+/// Report what the local Ollama server is running, given the vision and text models we use
+#[tauri::command]
+pub async fn ollama_load(
+    state: tauri::State<'_, AppState>,
+    model: String,
+    text_model: Option<String>,
+) -> Result<OllamaLoad, NukeError> {
+    let models: Vec<&str> = std::iter::once(model.as_str())
+        .chain(text_model.as_deref())
+        .collect();
+    check_load(&state.ollama_client(), OLLAMA_URL, &models).await
+}
+
+/// Check `/api/ps`: busy when it answers slowly, or another workload holds the GPU
+/// and none of `models` (every model the app uses) is loaded
+pub async fn check_load(
+    client: &OllamaClient,
+    base_url: &str,
+    models: &[&str],
+) -> Result<OllamaLoad, NukeError> {
+    let started = Instant::now();
+    let response: serde_json::Value = client
+        .get(format!("{}/api/ps", base_url))
+        .send()
+        .await
+        .map_err(NukeError::ollama)?
+        .json()
+        .await
+        .map_err(NukeError::ollama)?;
+    let response_ms = started.elapsed().as_millis() as u64;
+
+    let running = response
+        .get("models")
+        .and_then(|m| m.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let loaded_models: Vec<String> = running
+        .iter()
+        .filter_map(|m| m.get("name").and_then(|n| n.as_str()))
+        .map(|n| n.to_string())
+        .collect();
+    let vram_bytes = running
+        .iter()
+        .filter_map(|m| m.get("size_vram").and_then(|v| v.as_u64()))
+        .sum();
+
+    let is_ours = |loaded: &String| models.iter().any(|model| same_model(loaded, model));
+    let ours_loaded = loaded_models.iter().any(is_ours);
+    let foreign: Vec<&str> = loaded_models
+        .iter()
+        .filter(|m| !is_ours(m))
+        .map(|m| m.as_str())
+        .collect();
+    let reason = if response_ms > BUSY_RESPONSE_MS {
+        Some(format!("Ollama took {} ms to respond", response_ms))
+    } else if !foreign.is_empty() && !ours_loaded {
+        Some(format!("Another workload is using {}", foreign.join(", ")))
+    } else {
+        None
+    };
+
+    Ok(OllamaLoad {
+        loaded_models,
+        vram_bytes,
+        response_ms,
+        busy: reason.is_some(),
+        reason,
+    })
+}
+
+/// Wait until Ollama has capacity for `models`, emitting `ollama-deferral` while we hold back
+pub async fn wait_for_capacity(
+    app: &tauri::AppHandle,
+    client: &OllamaClient,
+    models: &[&str],
+) -> Result<(), NukeError> {
+    wait_for_capacity_with(
+        client,
+        OLLAMA_URL,
+        models,
+        DeferralPolicy::default(),
+        |event| {
+            let _ = app.emit("ollama-deferral", event.clone());
+        },
+    )
+    .await
+}
+
+/// Back off exponentially while the server is saturated; gives up after `max_attempts`
+pub async fn wait_for_capacity_with(
+    client: &OllamaClient,
+    base_url: &str,
+    models: &[&str],
+    policy: DeferralPolicy,
+    on_event: impl Fn(&DeferralEvent),
+) -> Result<(), NukeError> {
+    let mut delay = policy.initial;
+    let mut attempt = 0;
+
+    loop {
+        // If /api/ps itself fails, let the real request surface the error
+        let load = match check_load(client, base_url, models).await {
+            Ok(load) => load,
+            Err(_) => return Ok(()),
+        };
+        if !load.busy {
+            if attempt > 0 {
+                on_event(&DeferralEvent {
+                    deferred: false,
+                    attempt,
+                    retry_in_ms: 0,
+                    reason: None,
+                });
+            }
+            return Ok(());
+        }
+
+        attempt += 1;
+        if attempt > policy.max_attempts {
+            return Err(NukeError::OllamaBusy(load.reason.unwrap_or_default()));
+        }
+
+        on_event(&DeferralEvent {
+            deferred: true,
+            attempt,
+            retry_in_ms: delay.as_millis() as u64,
+            reason: load.reason,
+        });
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(policy.max);
+    }
+}
+
+/// "llava" and "llava:latest" are the same model
+fn same_model(loaded: &str, requested: &str) -> bool {
+    let tagged = |name: &str| {
+        if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("{}:latest", name)
+        }
+    };
+    tagged(loaded) == tagged(requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn image_b64_round_trips_known_bytes() {
+        // A JPEG header, with bytes that land on '+' and '/' in the standard alphabet
+        let bytes = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10, 0x4a, 0x46, 0xfb, 0xef, 0xbe,
+        ];
+        let encoded = encode_image_b64(&bytes);
+        assert_eq!(encoded, "/9j/4AAQSkb7774=");
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD
+                .decode(&encoded)
+                .unwrap(),
+            bytes
+        );
+        assert_eq!(encode_image_b64(&[]), "");
+    }
+
+    fn test_client() -> OllamaClient {
+        OllamaClient::new(reqwest::Client::new(), DEFAULT_TIMEOUT)
+    }
+
+    /// A local HTTP server answering the nth request with `respond(n)`; returns its base URL
+    async fn serve(respond: fn(usize) -> String) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for n in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 8192];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket.write_all(respond(n).as_bytes()).await;
+            }
+        });
+        url
+    }
+
+    fn json_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn quick_policy(max_attempts: u32) -> DeferralPolicy {
+        DeferralPolicy {
+            initial: Duration::from_millis(5),
+            max: Duration::from_millis(10),
+            max_attempts,
+        }
+    }
+
+    const FOREIGN_LOAD: &str = r#"{"models":[{"name":"qwen2:72b","size_vram":40000000000}]}"#;
+
+    #[tokio::test]
+    async fn another_workload_on_the_gpu_defers_dispatch() {
+        let url = serve(|_| json_response(FOREIGN_LOAD)).await;
+        let client = test_client();
+        let load = check_load(&client, &url, &["llava"]).await.unwrap();
+        assert!(load.busy);
+        assert_eq!(load.loaded_models, vec!["qwen2:72b"]);
+        assert_eq!(load.vram_bytes, 40_000_000_000);
+        assert_eq!(
+            load.reason.as_deref(),
+            Some("Another workload is using qwen2:72b")
+        );
+
+        let events = Mutex::new(Vec::new());
+        let waited = wait_for_capacity_with(&client, &url, &["llava"], quick_policy(2), |event| {
+            events.lock().unwrap().push(event.clone())
+        })
+        .await;
+        assert!(matches!(waited, Err(NukeError::OllamaBusy(_))));
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.deferred));
+        assert_eq!(events[1].attempt, 2);
+    }
+
+    #[tokio::test]
+    async fn our_own_models_are_not_another_workload() {
+        let client = test_client();
+        // Only the text model is loaded, from a text-route extraction
+        let url =
+            serve(|_| json_response(r#"{"models":[{"name":"llama3:latest","size_vram":4000}]}"#))
+                .await;
+        let load = check_load(&client, &url, &["llava", "llama3"])
+            .await
+            .unwrap();
+        assert!(!load.busy, "{:?}", load.reason);
+        let waited =
+            wait_for_capacity_with(&client, &url, &["llava", "llama3"], quick_policy(1), |_| {
+                panic!("should not defer")
+            })
+            .await;
+        assert_eq!(waited, Ok(()));
+
+        // "llava" is "llava:latest"
+        let url =
+            serve(|_| json_response(r#"{"models":[{"name":"llava:latest","size_vram":4000}]}"#))
+                .await;
+        assert!(!check_load(&client, &url, &["llava"]).await.unwrap().busy);
+
+        let url = serve(|_| json_response(r#"{"models":[]}"#)).await;
+        assert!(!check_load(&client, &url, &["llava"]).await.unwrap().busy);
+    }
+
+    #[tokio::test]
+    async fn dispatch_resumes_once_the_server_frees_up() {
+        let url = serve(|n| match n {
+            0 => json_response(FOREIGN_LOAD),
+            _ => json_response(r#"{"models":[]}"#),
+        })
+        .await;
+        let events = Mutex::new(Vec::new());
+        let waited =
+            wait_for_capacity_with(&test_client(), &url, &["llava"], quick_policy(3), |event| {
+                events.lock().unwrap().push(event.clone())
+            })
+            .await;
+        assert_eq!(waited, Ok(()));
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].deferred);
+        assert!(!events[1].deferred);
+    }
+
+    #[tokio::test]
+    async fn an_unreachable_server_does_not_hold_back_dispatch() {
+        let waited = wait_for_capacity_with(
+            &test_client(),
+            "http://127.0.0.1:9",
+            &["llava"],
+            quick_policy(1),
+            |_| panic!("should not defer"),
+        )
+        .await;
+        assert_eq!(waited, Ok(()));
+    }
+
+    /// A generate reply naming which request it answered, so cache hits are visible
+    fn numbered_generation(n: usize) -> String {
+        json_response(&serde_json::json!({ "response": format!("{{\"n\":{}}}", n) }).to_string())
+    }
+
+    fn generation<'a>(prompt: &'a str, options: &'a OllamaOptions) -> Generation<'a> {
+        Generation {
+            model: "llava",
+            prompt,
+            images: vec![encode_image_b64(b"title scan")],
+            options,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_response_cached_in_one_session_is_reused_in_the_next() {
+        let dir = std::env::temp_dir().join(format!("nuke-response-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let url = serve(numbered_generation).await;
+        let client = test_client();
+        let options = OllamaOptions::default();
+
+        let first = generate_cached_at(
+            &client,
+            &url,
+            &ResponseCache::new(dir.clone()),
+            generation("read the title", &options),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(first, r#"{"n":0}"#);
+
+        // A fresh cache over the same directory, as after a restart, answers without a request
+        let restarted = ResponseCache::new(dir.clone());
+        let again = generate_cached_at(
+            &client,
+            &url,
+            &restarted,
+            generation("read the title", &options),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(again, first);
+
+        // Any change to the inputs goes back to the server, which has only seen one request
+        let other_prompt = generate_cached_at(
+            &client,
+            &url,
+            &restarted,
+            generation("read the bill", &options),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(other_prompt, r#"{"n":1}"#);
+        let hot = OllamaOptions {
+            temperature: Some(0.8),
+            ..OllamaOptions::default()
+        };
+        let other_options = generate_cached_at(
+            &client,
+            &url,
+            &restarted,
+            generation("read the title", &hot),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(other_options, r#"{"n":2}"#);
+
+        // Invalidation is per model
+        assert_eq!(restarted.clear(Some("llama3")).unwrap(), 0);
+        assert_eq!(restarted.clear(Some("llava")).unwrap(), 3);
+        let after_clear = generate_cached_at(
+            &client,
+            &url,
+            &restarted,
+            generation("read the title", &options),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(after_clear, r#"{"n":3}"#);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_refused_connection_is_reported_as_unreachable() {
+        let dir = std::env::temp_dir().join(format!("nuke-unreachable-{}", std::process::id()));
+        let options = OllamaOptions::default();
+        let error = generate_cached_at(
+            &test_client(),
+            "http://127.0.0.1:9",
+            &ResponseCache::new(dir),
+            generation("read the title", &options),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, NukeError::OllamaUnreachable);
+    }
+
+    #[tokio::test]
+    async fn a_slow_server_fails_with_the_ollama_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 8192];
+            let _ = socket.read(&mut request).await;
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let client = OllamaClient::new(reqwest::Client::new(), Duration::from_millis(200));
+        let dir = std::env::temp_dir().join(format!("nuke-timeout-{}", std::process::id()));
+        let options = OllamaOptions::default();
+        let error = generate_cached_at(
+            &client,
+            &url,
+            &ResponseCache::new(dir),
+            generation("read the title", &options),
+            None,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, NukeError::OllamaTimeout);
+    }
+}
@@ -0,0 +1,300 @@
+// Party records - cluster owner name variants into one canonical party before sync
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use serde::{Deserialize, Serialize};
+
+const HONORIFICS: &[&str] = &["mr", "mrs", "ms", "miss", "dr"];
+const SUFFIXES: &[&str] = &["jr", "sr", "ii", "iii", "iv"];
+const BUSINESS_WORDS: &[&str] = &[
+    "llc",
+    "inc",
+    "corp",
+    "co",
+    "ltd",
+    "motors",
+    "auto",
+    "dealership",
+    "trust",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Party {
+    pub canonical: String,
+    /// Every spelling seen, as extracted
+    pub variants: Vec<String>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartyMerge {
+    /// Results with `owner_name` replaced by the canonical party name
+    pub results: Vec<ExtractionResult>,
+    pub parties: Vec<Party>,
+}
+
+/// A person's name split into comparable parts
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartyName {
+    pub first: String,
+    pub last: String,
+    /// Businesses and single-word names only match exactly
+    pub whole: Option<String>,
+}
+
+/// Cluster owner name variants across results and rewrite them to one canonical spelling
+#[tauri::command]
+pub async fn merge_parties(mut results: Vec<ExtractionResult>) -> Result<PartyMerge, NukeError> {
+    let names: Vec<(String, String)> = results
+        .iter()
+        .filter_map(|r| Some((r.data.owner_name.clone()?, r.path.clone())))
+        .collect();
+    let parties = cluster_parties(&names);
+
+    for result in &mut results {
+        let owner = match &result.data.owner_name {
+            Some(owner) => owner.clone(),
+            None => continue,
+        };
+        if let Some(party) = parties.iter().find(|p| p.variants.contains(&owner)) {
+            result.data.owner_name = Some(party.canonical.clone());
+        }
+    }
+
+    Ok(PartyMerge { results, parties })
+}
+
+/// Group (name, path) pairs into parties
+pub fn cluster_parties(names: &[(String, String)]) -> Vec<Party> {
+    let mut clusters: Vec<(PartyName, Party)> = Vec::new();
+
+    // Full names first, so an initial ("J. Smith") can tell whether it's ambiguous
+    let mut ordered: Vec<(PartyName, &String, &String)> = names
+        .iter()
+        .filter_map(|(name, path)| Some((parse_party_name(name)?, name, path)))
+        .collect();
+    ordered.sort_by_key(|(parsed, _, _)| parsed.first.chars().count() <= 1);
+
+    for (parsed, raw, path) in ordered {
+        let matches: Vec<usize> = clusters
+            .iter()
+            .enumerate()
+            .filter(|(_, (key, _))| same_party(key, &parsed))
+            .map(|(i, _)| i)
+            .collect();
+
+        // An initial that fits two different people stays on its own
+        let target = match matches.as_slice() {
+            [only] => Some(*only),
+            _ => None,
+        };
+        match target {
+            Some(i) => {
+                let party = &mut clusters[i].1;
+                if !party.variants.contains(raw) {
+                    party.variants.push(raw.clone());
+                }
+                if !party.paths.contains(path) {
+                    party.paths.push(path.clone());
+                }
+            }
+            None => clusters.push((
+                parsed.clone(),
+                Party {
+                    canonical: display_name(&parsed),
+                    variants: vec![raw.clone()],
+                    paths: vec![path.clone()],
+                },
+            )),
+        }
+    }
+
+    clusters.into_iter().map(|(_, party)| party).collect()
+}
+
+/// Parse "John Smith", "J. Smith", "SMITH, JOHN A", "Mr. John Smith Jr."
+pub fn parse_party_name(raw: &str) -> Option<PartyName> {
+    let clean = |s: &str| -> Vec<String> {
+        s.split(|c: char| c.is_whitespace() || c == '.')
+            .map(|t| {
+                t.chars()
+                    .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '\'')
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .filter(|t| {
+                !t.is_empty()
+                    && !HONORIFICS.contains(&t.as_str())
+                    && !SUFFIXES.contains(&t.as_str())
+            })
+            .collect()
+    };
+
+    // "Last, First" puts the surname first
+    let tokens = match raw.split_once(',') {
+        Some((last, first)) if !clean(first).is_empty() => {
+            let mut tokens = clean(first);
+            tokens.extend(clean(last));
+            tokens
+        }
+        _ => clean(raw),
+    };
+
+    let is_business = tokens.iter().any(|t| BUSINESS_WORDS.contains(&t.as_str()));
+    if tokens.len() < 2 || is_business {
+        let whole = tokens.join(" ");
+        if whole.is_empty() {
+            return None;
+        }
+        return Some(PartyName {
+            first: String::new(),
+            last: String::new(),
+            whole: Some(whole),
+        });
+    }
+
+    Some(PartyName {
+        first: tokens[0].clone(),
+        last: tokens[tokens.len() - 1].clone(),
+        whole: None,
+    })
+}
+
+/// Same surname (allowing a typo) and a compatible first name or initial
+pub fn same_party(a: &PartyName, b: &PartyName) -> bool {
+    match (&a.whole, &b.whole) {
+        (Some(x), Some(y)) => return x == y,
+        (None, None) => {}
+        _ => return false,
+    }
+
+    if strsim::jaro_winkler(&a.last, &b.last) < 0.93 {
+        return false;
+    }
+
+    let initial_only = |s: &str| s.chars().count() == 1;
+    if initial_only(&a.first) || initial_only(&b.first) {
+        return a.first.chars().next() == b.first.chars().next();
+    }
+    strsim::jaro_winkler(&a.first, &b.first) >= 0.9
+}
+
+/// "John Smith" in title case
+fn display_name(name: &PartyName) -> String {
+    let title = |s: &str| {
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().collect::<String>() + chars.as_str(),
+            None => String::new(),
+        }
+    };
+
+    match &name.whole {
+        Some(whole) => whole.split(' ').map(title).collect::<Vec<_>>().join(" "),
+        None => format!("{} {}", title(&name.first), title(&name.last)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(raw: &[&str]) -> Vec<(String, String)> {
+        raw.iter()
+            .map(|name| (name.to_string(), format!("/scans/{}.jpg", name)))
+            .collect()
+    }
+
+    #[test]
+    fn clusters_variants_of_one_person() {
+        let parties = cluster_parties(&names(&[
+            "John Smith",
+            "J. Smith",
+            "SMITH, JOHN",
+            "Mr. John Smith Jr.",
+            "Jon Smith",
+        ]));
+        assert_eq!(parties.len(), 1, "{:?}", parties);
+        assert_eq!(parties[0].canonical, "John Smith");
+        assert_eq!(parties[0].variants.len(), 5);
+        assert_eq!(parties[0].paths.len(), 5);
+    }
+
+    #[test]
+    fn keeps_different_people_and_businesses_apart() {
+        let parties = cluster_parties(&names(&[
+            "John Smith",
+            "Jane Doe",
+            "Robert Smith",
+            "Smith Motors LLC",
+            "SMITH MOTORS LLC",
+        ]));
+        let mut canonical: Vec<&str> = parties.iter().map(|p| p.canonical.as_str()).collect();
+        canonical.sort();
+        assert_eq!(
+            canonical,
+            vec!["Jane Doe", "John Smith", "Robert Smith", "Smith Motors Llc"]
+        );
+        let business = parties
+            .iter()
+            .find(|p| p.canonical == "Smith Motors Llc")
+            .unwrap();
+        assert_eq!(business.variants.len(), 2);
+    }
+
+    #[test]
+    fn an_initial_that_fits_two_people_stays_on_its_own() {
+        let parties = cluster_parties(&names(&["J. Smith", "John Smith", "Jane Smith"]));
+        assert_eq!(parties.len(), 3, "{:?}", parties);
+    }
+
+    #[test]
+    fn parses_last_first_ordering_and_drops_titles() {
+        let expected = PartyName {
+            first: "john".to_string(),
+            last: "smith".to_string(),
+            whole: None,
+        };
+        assert_eq!(parse_party_name("SMITH, JOHN A").unwrap().first, "john");
+        assert_eq!(parse_party_name("SMITH, JOHN").unwrap(), expected);
+        assert_eq!(parse_party_name("Dr. John Smith III").unwrap(), expected);
+        assert_eq!(
+            parse_party_name("Cher").unwrap().whole.as_deref(),
+            Some("cher")
+        );
+        assert_eq!(parse_party_name(" Mr. "), None);
+    }
+
+    #[tokio::test]
+    async fn merge_rewrites_owner_names_to_the_canonical_party() {
+        let result = |path: &str, owner: Option<&str>| -> ExtractionResult {
+            serde_json::from_value(serde_json::json!({
+                "path": path,
+                "model": "llava",
+                "route": "vision",
+                "image_kind": "document",
+                "data": { "owner_name": owner },
+                "confidence": 0.9,
+                "raw_response": "",
+            }))
+            .unwrap()
+        };
+        let merged = merge_parties(vec![
+            result("/a.jpg", Some("SMITH, JOHN")),
+            result("/b.jpg", Some("John Smith")),
+            result("/c.jpg", None),
+        ])
+        .await
+        .unwrap();
+        assert_eq!(merged.parties.len(), 1);
+        assert_eq!(
+            merged.results[0].data.owner_name.as_deref(),
+            Some("John Smith")
+        );
+        assert_eq!(
+            merged.results[1].data.owner_name.as_deref(),
+            Some("John Smith")
+        );
+        assert_eq!(merged.results[2].data.owner_name, None);
+    }
+}
@@ -0,0 +1,263 @@
+// Split a scan into independent work units for parallel or distributed intake
+
+use crate::error::NukeError;
+use crate::{categorize, scan_builder, ScanConfig};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkUnit {
+    /// A full scan config; run it with scan_directories and merge the results
+    pub config: ScanConfig,
+    pub estimated_files: usize,
+    pub estimated_bytes: u64,
+}
+
+/// A subtree (or single file) and its estimated size
+struct PartitionItem {
+    path: PathBuf,
+    files: usize,
+    bytes: u64,
+}
+
+/// Divide the scan roots into `n` work units balanced by estimated file count
+#[tauri::command]
+pub async fn partition_scan(config: ScanConfig, n: usize) -> Result<Vec<WorkUnit>, NukeError> {
+    if n == 0 {
+        return Err(NukeError::InvalidInput(
+            "Need at least one work unit".to_string(),
+        ));
+    }
+
+    let max_depth = config.max_depth.unwrap_or(10);
+    if max_depth == 0 {
+        // Nothing below the roots to split up
+        return Ok(vec![WorkUnit {
+            config,
+            estimated_files: 0,
+            estimated_bytes: 0,
+        }]);
+    }
+
+    // Each root's immediate children become the items we distribute
+    let mut items = Vec::new();
+    for root in &config.paths {
+        let root = PathBuf::from(root);
+        if !root.is_dir() {
+            items.push(estimate(&root, &config, 0));
+            continue;
+        }
+        std::fs::read_dir(&root).map_err(|e| {
+            NukeError::FileRead(format!("Failed to read {}: {}", root.display(), e))
+        })?;
+        // A child walked as its own root wouldn't apply the root's ignore rules to itself
+        for child in scan_builder(&root, &config, 1)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.depth() == 1)
+        {
+            items.push(estimate(child.path(), &config, max_depth - 1));
+        }
+    }
+
+    // Largest first onto the lightest unit keeps the split close to even
+    items.sort_by(|a, b| b.files.cmp(&a.files).then(b.bytes.cmp(&a.bytes)));
+    let mut units: Vec<(Vec<PathBuf>, usize, u64)> = (0..n).map(|_| (Vec::new(), 0, 0)).collect();
+    for item in items {
+        if let Some(unit) = units.iter_mut().min_by_key(|u| (u.1, u.2)) {
+            unit.0.push(item.path);
+            unit.1 += item.files;
+            unit.2 += item.bytes;
+        }
+    }
+
+    Ok(units
+        .into_iter()
+        .filter(|(paths, _, _)| !paths.is_empty())
+        .map(|(paths, files, bytes)| WorkUnit {
+            config: ScanConfig {
+                paths: paths
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect(),
+                // Children sit one level below the original roots
+                max_depth: Some(max_depth - 1),
+                ..config.clone()
+            },
+            estimated_files: files,
+            estimated_bytes: bytes,
+        })
+        .collect())
+}
+
+/// Count the files a scan would pick up under `path`, without extracting hints
+fn estimate(path: &std::path::Path, config: &ScanConfig, max_depth: usize) -> PartitionItem {
+    let mut files = 0;
+    let mut bytes = 0;
+
+    for entry in scan_builder(path, config, max_depth)
+        .build()
+        .filter_map(|e| e.ok())
+    {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if !config.include_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let extension = entry
+            .path()
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if categorize(&extension, config).1 {
+            files += 1;
+            bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        }
+    }
+
+    PartitionItem {
+        path: path.to_path_buf(),
+        files,
+        bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::sync::atomic::AtomicBool;
+
+    /// A fresh directory under the temp dir holding `files`
+    fn temp_tree(name: &str, files: &[&str]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for file in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, b"scan me").unwrap();
+        }
+        root
+    }
+
+    fn scan_config(root: &Path) -> ScanConfig {
+        ScanConfig {
+            paths: vec![root.to_string_lossy().to_string()],
+            ..ScanConfig::default()
+        }
+    }
+
+    fn scanned_paths(config: &ScanConfig) -> Vec<String> {
+        let dictionary = crate::dictionary::VehicleDictionary::default();
+        let mut paths: Vec<String> =
+            crate::walk_scan(config, &dictionary, &AtomicBool::new(false), |_| {})
+                .results
+                .into_iter()
+                .map(|r| r.path)
+                .collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn splits_children_into_balanced_units_that_cover_the_scan_once() {
+        let root = temp_tree(
+            "partition",
+            &[
+                "a/1.jpg",
+                "a/2.jpg",
+                "a/3.jpg",
+                "b/1.jpg",
+                "b/2.jpg",
+                "c.jpg",
+                "notes.xyz",
+                ".hidden.jpg",
+            ],
+        );
+        let config = scan_config(&root);
+        let units = partition_scan(config.clone(), 2).await.unwrap();
+
+        assert_eq!(units.len(), 2);
+        let mut counts: Vec<usize> = units.iter().map(|u| u.estimated_files).collect();
+        counts.sort();
+        assert_eq!(counts, vec![3, 3]);
+        assert!(units
+            .iter()
+            .all(|u| u.estimated_bytes == 7 * u.estimated_files as u64));
+
+        // The biggest child goes alone; the other two share the second unit. Children with
+        // nothing to scan (notes.xyz, .hidden.jpg) ride along on whichever unit is lightest.
+        let names = |unit: &WorkUnit| -> Vec<String> {
+            let mut names: Vec<String> = unit
+                .config
+                .paths
+                .iter()
+                .map(|p| {
+                    Path::new(p)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .filter(|name| !matches!(name.as_str(), "notes.xyz" | ".hidden.jpg"))
+                .collect();
+            names.sort();
+            names
+        };
+        let mut layout: Vec<Vec<String>> = units.iter().map(names).collect();
+        layout.sort();
+        assert_eq!(layout, vec![vec!["a"], vec!["b", "c.jpg"]]);
+        assert!(units.iter().all(|u| u.config.max_depth == Some(9)));
+
+        // Scanning every unit finds each file of the full scan exactly once
+        let mut merged: Vec<String> = units
+            .iter()
+            .flat_map(|u| scanned_paths(&u.config))
+            .collect();
+        merged.sort();
+        for unit in &units {
+            assert_eq!(scanned_paths(&unit.config).len(), unit.estimated_files);
+        }
+        assert_eq!(merged, scanned_paths(&config));
+        assert_eq!(merged.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn more_units_than_children_drops_the_empty_ones() {
+        let root = temp_tree("partition-few", &["a/1.jpg", "b/1.jpg"]);
+        let units = partition_scan(scan_config(&root), 5).await.unwrap();
+        assert_eq!(units.len(), 2);
+        assert!(units
+            .iter()
+            .all(|u| u.config.paths.len() == 1 && u.estimated_files == 1));
+    }
+
+    #[tokio::test]
+    async fn zero_depth_keeps_the_roots_as_one_unit() {
+        let root = temp_tree("partition-flat", &["a/1.jpg"]);
+        let config = ScanConfig {
+            max_depth: Some(0),
+            ..scan_config(&root)
+        };
+        let units = partition_scan(config.clone(), 3).await.unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].config.paths, config.paths);
+        assert_eq!(units[0].config.max_depth, Some(0));
+    }
+
+    #[tokio::test]
+    async fn rejects_zero_units_and_unreadable_roots() {
+        let root = temp_tree("partition-bad", &["a/1.jpg"]);
+        assert!(matches!(
+            partition_scan(scan_config(&root), 0).await,
+            Err(NukeError::InvalidInput(_))
+        ));
+
+        // A root that is a single file is its own item
+        let file = root.join("a/1.jpg");
+        let units = partition_scan(scan_config(&file), 2).await.unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].estimated_files, 1);
+    }
+}
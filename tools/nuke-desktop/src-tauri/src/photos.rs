@@ -0,0 +1,319 @@
+// Photo metadata (EXIF) and photo-set ordering
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderSource {
+    Exif,
+    Filename,
+    Mtime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderedPhotoSet {
+    pub results: Vec<ExtractionResult>,
+    pub ordered_by: OrderSource,
+}
+
+/// Order a vehicle's photos by capture time, assigning each an explicit `order_index`
+///
+/// EXIF capture time is used when every photo has it, then a sequence number in the
+/// filename (IMG_0042), then filesystem mtime. The whole set uses one source so the
+/// ordering stays consistent.
+#[tauri::command]
+pub async fn order_photo_set(results: Vec<ExtractionResult>) -> Result<OrderedPhotoSet, NukeError> {
+    Ok(order_photos(results))
+}
+
+pub fn order_photos(mut results: Vec<ExtractionResult>) -> OrderedPhotoSet {
+    let capture_times: Vec<Option<String>> = results
+        .iter()
+        .map(|r| read_capture_time(Path::new(&r.path)))
+        .collect();
+    let sequences: Vec<Option<u64>> = results.iter().map(|r| filename_sequence(&r.path)).collect();
+
+    let ordered_by = if capture_times.iter().all(|t| t.is_some()) {
+        OrderSource::Exif
+    } else if sequences.iter().all(|s| s.is_some()) {
+        OrderSource::Filename
+    } else {
+        OrderSource::Mtime
+    };
+
+    let mut keyed: Vec<(String, u64, ExtractionResult)> = results
+        .drain(..)
+        .enumerate()
+        .map(|(i, r)| {
+            let sequence = sequences[i].unwrap_or(0);
+            let primary = match ordered_by {
+                OrderSource::Exif => capture_times[i].clone().unwrap_or_default(),
+                OrderSource::Filename => String::new(),
+                OrderSource::Mtime => format!("{:020}", mtime_secs(&r.path)),
+            };
+            (primary, sequence, r)
+        })
+        .collect();
+    keyed.sort_by(|a, b| (&a.0, a.1, &a.2.path).cmp(&(&b.0, b.1, &b.2.path)));
+
+    let results = keyed
+        .into_iter()
+        .enumerate()
+        .map(|(index, (_, _, mut r))| {
+            r.order_index = Some(index);
+            r
+        })
+        .collect();
+
+    OrderedPhotoSet { results, ordered_by }
+}
+
+/// Trailing number in a filename stem ("IMG_0042.jpg" -> 42)
+fn filename_sequence(path: &str) -> Option<u64> {
+    let stem = Path::new(path).file_stem()?.to_string_lossy().to_string();
+    let digits: String = stem
+        .chars()
+        .rev()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    digits.parse().ok()
+}
+
+fn mtime_secs(path: &str) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Capture time and location from a photo's EXIF; fields are None when it has no EXIF
+/// or the camera didn't record them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhotoExif {
+    pub captured_at: Option<String>,
+    /// Decimal degrees, south and west negative
+    pub gps: Option<(f64, f64)>,
+}
+
+/// Read capture time and GPS position in one pass over the file
+pub fn read_photo_exif(path: &Path) -> PhotoExif {
+    match read_exif(path) {
+        Some(exif) => PhotoExif {
+            captured_at: capture_time(&exif),
+            gps: gps_position(&exif),
+        },
+        None => PhotoExif::default(),
+    }
+}
+
+/// Read the capture timestamp (DateTimeOriginal, falling back to DateTime) as ISO 8601,
+/// with the camera's UTC offset when it recorded one (OffsetTimeOriginal / OffsetTime)
+pub fn read_capture_time(path: &Path) -> Option<String> {
+    capture_time(&read_exif(path)?)
+}
+
+fn read_exif(path: &Path) -> Option<exif::Exif> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut reader = std::io::BufReader::new(file);
+    exif::Reader::new().read_from_container(&mut reader).ok()
+}
+
+fn exif_ascii(exif: &exif::Exif, tag: exif::Tag) -> Option<Vec<u8>> {
+    match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+        exif::Value::Ascii(values) => values.first().cloned(),
+        _ => None,
+    }
+}
+
+fn capture_time(exif: &exif::Exif) -> Option<String> {
+    let ascii = |tag| exif_ascii(exif, tag);
+
+    [
+        (exif::Tag::DateTimeOriginal, exif::Tag::OffsetTimeOriginal),
+        (exif::Tag::DateTime, exif::Tag::OffsetTime),
+    ]
+    .iter()
+    .find_map(|(tag, offset_tag)| {
+        let mut dt = exif::DateTime::from_ascii(&ascii(*tag)?).ok()?;
+        if let Some(offset) = ascii(*offset_tag) {
+            let _ = dt.parse_offset(&offset);
+        }
+        let local = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+        );
+        Some(match dt.offset {
+            Some(minutes) => format!(
+                "{}{}{:02}:{:02}",
+                local,
+                if minutes < 0 { '-' } else { '+' },
+                minutes.abs() / 60,
+                minutes.abs() % 60
+            ),
+            None => local,
+        })
+    })
+}
+
+/// GPSLatitude / GPSLongitude (degrees, minutes, seconds) as signed decimal degrees
+fn gps_position(exif: &exif::Exif) -> Option<(f64, f64)> {
+    let coordinate = |tag: exif::Tag, ref_tag: exif::Tag, negative: u8| {
+        let degrees = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Rational(parts) if !parts.is_empty() => parts
+                .iter()
+                .zip([1.0, 60.0, 3600.0])
+                .map(|(part, scale)| part.to_f64() / scale)
+                .sum::<f64>(),
+            _ => return None,
+        };
+        let sign = match exif_ascii(exif, ref_tag)?.first() {
+            Some(r) if r.eq_ignore_ascii_case(&negative) => -1.0,
+            _ => 1.0,
+        };
+        Some(sign * degrees).filter(|d| d.is_finite())
+    };
+
+    let lat = coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S')?;
+    let lon = coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W')?;
+    // Some phones write 0/0 when they never got a fix
+    if lat.abs() > 90.0 || lon.abs() > 180.0 || (lat == 0.0 && lon == 0.0) {
+        return None;
+    }
+    Some((lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Tag, Value};
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nuke-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A TIFF holding only an EXIF capture time, or no EXIF at all
+    fn write_photo(path: &Path, captured: Option<&str>) {
+        let Some(captured) = captured else {
+            std::fs::write(path, b"no exif").unwrap();
+            return;
+        };
+        let field = Field {
+            tag: Tag::DateTimeOriginal,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![captured.as_bytes().to_vec()]),
+        };
+        let mut writer = Writer::new();
+        writer.push_field(&field);
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        writer.write(&mut buffer, false).unwrap();
+        std::fs::write(path, buffer.into_inner()).unwrap();
+    }
+
+    fn result(path: &Path) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path.to_string_lossy(),
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "photo",
+            "data": {},
+            "confidence": 0.9,
+            "raw_response": "{}",
+        }))
+        .unwrap()
+    }
+
+    fn names(set: &OrderedPhotoSet) -> Vec<String> {
+        set.results
+            .iter()
+            .map(|r| {
+                Path::new(&r.path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn exif_capture_time_beats_filename_order() {
+        let dir = temp_dir("order-exif");
+        let photos = [
+            ("IMG_0003.tif", "2021:05:01 09:00:00"),
+            ("IMG_0001.tif", "2021:05:01 09:05:00"),
+            ("IMG_0002.tif", "2021:05:01 09:10:00"),
+        ];
+        let mut results = Vec::new();
+        for (name, captured) in photos {
+            write_photo(&dir.join(name), Some(captured));
+            results.push(result(&dir.join(name)));
+        }
+        results.reverse();
+
+        let ordered = order_photos(results);
+        assert_eq!(ordered.ordered_by, OrderSource::Exif);
+        assert_eq!(
+            names(&ordered),
+            vec!["IMG_0003.tif", "IMG_0001.tif", "IMG_0002.tif"]
+        );
+        let indexes: Vec<_> = ordered.results.iter().map(|r| r.order_index).collect();
+        assert_eq!(indexes, vec![Some(0), Some(1), Some(2)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn one_photo_without_exif_falls_back_to_filename_sequence() {
+        let dir = temp_dir("order-filename");
+        write_photo(&dir.join("IMG_0010.tif"), Some("2021:05:01 09:00:00"));
+        write_photo(&dir.join("IMG_0009.tif"), None);
+        write_photo(&dir.join("IMG_0100.tif"), Some("2020:01:01 00:00:00"));
+        let results = ["IMG_0100.tif", "IMG_0010.tif", "IMG_0009.tif"]
+            .iter()
+            .map(|name| result(&dir.join(name)))
+            .collect();
+
+        let ordered = order_photos(results);
+        assert_eq!(ordered.ordered_by, OrderSource::Filename);
+        // Numerically, not as text
+        assert_eq!(
+            names(&ordered),
+            vec!["IMG_0009.tif", "IMG_0010.tif", "IMG_0100.tif"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn without_exif_or_sequences_orders_by_mtime() {
+        let dir = temp_dir("order-mtime");
+        write_photo(&dir.join("front.tif"), None);
+        write_photo(&dir.join("IMG_0001.tif"), None);
+        let results = vec![
+            result(&dir.join("front.tif")),
+            result(&dir.join("IMG_0001.tif")),
+        ];
+        assert_eq!(order_photos(results).ordered_by, OrderSource::Mtime);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_the_trailing_filename_sequence() {
+        assert_eq!(filename_sequence("/a/IMG_0042.jpg"), Some(42));
+        assert_eq!(filename_sequence("DSC00042-edit.jpg"), Some(42));
+        assert_eq!(filename_sequence("2019 mustang 7.jpg"), Some(7));
+        assert_eq!(filename_sequence("front.jpg"), None);
+    }
+}
@@ -0,0 +1,119 @@
+// License plate classification
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlateType {
+    Standard,
+    Vanity,
+    Dealer,
+    Temporary,
+    Paper,
+}
+
+/// Classify a plate string by keywords and character layout
+pub fn classify_plate(plate: &str) -> PlateType {
+    let upper = plate.trim().to_uppercase();
+
+    // Keywords printed on the plate or tag win over layout
+    if upper.contains("DEALER") || upper.starts_with("DLR") || upper.ends_with("DLR") {
+        return PlateType::Dealer;
+    }
+    if upper.contains("TEMP")
+        || upper.contains("TMP")
+        || upper.contains("TRANSIT")
+        || upper.contains("DAY PERMIT")
+    {
+        return PlateType::Temporary;
+    }
+    if upper.contains("PAPER") || upper.contains("EXPIRES") {
+        return PlateType::Paper;
+    }
+
+    // Paper tags carry long serials or a printed expiration date
+    if let Ok(date_regex) = Regex::new(r"\b\d{1,2}[/-]\d{1,2}[/-]\d{2,4}\b") {
+        if date_regex.is_match(&upper) {
+            return PlateType::Paper;
+        }
+    }
+
+    let compact: String = upper.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    let digits = compact.chars().filter(|c| c.is_ascii_digit()).count();
+    let letters = compact.len() - digits;
+
+    if digits >= 8 && letters <= 1 {
+        return PlateType::Paper;
+    }
+
+    // Standard issue mixes letters and digits in a few runs (ABC1234, 1ABC234, 123ABC)
+    let runs = count_runs(&compact);
+    if (5..=8).contains(&compact.len()) && letters > 0 && digits > 0 && runs <= 3 {
+        return PlateType::Standard;
+    }
+
+    // All letters, or an unusual mix, reads as a personalized plate
+    if !compact.is_empty() && compact.len() <= 8 {
+        return PlateType::Vanity;
+    }
+
+    PlateType::Standard
+}
+
+/// Number of alternating letter/digit runs ("ABC1234" has 2)
+fn count_runs(s: &str) -> usize {
+    let mut runs = 0;
+    let mut last_is_digit = None;
+    for c in s.chars() {
+        let is_digit = c.is_ascii_digit();
+        if last_is_digit != Some(is_digit) {
+            runs += 1;
+            last_is_digit = Some(is_digit);
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_letter_digit_plates_are_standard() {
+        for plate in ["ABC 1234", "7ABC123", "123-ABC", "abc1234"] {
+            assert_eq!(classify_plate(plate), PlateType::Standard, "{}", plate);
+        }
+    }
+
+    #[test]
+    fn all_letters_or_unusual_mixes_are_vanity() {
+        for plate in ["SMOKIN", "GR8 C4R 2", "Z"] {
+            assert_eq!(classify_plate(plate), PlateType::Vanity, "{}", plate);
+        }
+    }
+
+    #[test]
+    fn keywords_win_over_layout() {
+        assert_eq!(classify_plate("DLR 4821"), PlateType::Dealer);
+        assert_eq!(classify_plate("4821 DLR"), PlateType::Dealer);
+        assert_eq!(classify_plate("Dealer ABC123"), PlateType::Dealer);
+        assert_eq!(classify_plate("TEMP 88213"), PlateType::Temporary);
+        assert_eq!(classify_plate("In Transit"), PlateType::Temporary);
+        assert_eq!(classify_plate("PAPER TAG"), PlateType::Paper);
+    }
+
+    #[test]
+    fn long_serials_and_printed_dates_are_paper_tags() {
+        assert_eq!(classify_plate("12345678 EXP 10/21/2024"), PlateType::Paper);
+        assert_eq!(classify_plate("A7 3-15-25"), PlateType::Paper);
+        assert_eq!(classify_plate("8812345678"), PlateType::Paper);
+    }
+
+    #[test]
+    fn counts_letter_digit_runs() {
+        assert_eq!(count_runs("ABC1234"), 2);
+        assert_eq!(count_runs("1ABC234"), 3);
+        assert_eq!(count_runs(""), 0);
+    }
+}
@@ -0,0 +1,136 @@
+// Privacy settings - what personal data is masked before results leave the app's own store
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Mask loan/account numbers down to their last four characters
+    pub redact_account_numbers: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        PrivacySettings {
+            redact_account_numbers: true,
+        }
+    }
+}
+
+/// Update PII redaction settings for subsequent results
+#[tauri::command]
+pub async fn configure_privacy(
+    state: tauri::State<'_, AppState>,
+    settings: PrivacySettings,
+) -> Result<(), NukeError> {
+    *state.privacy.lock().unwrap() = settings;
+    Ok(())
+}
+
+/// Apply redaction settings to a result in place
+pub fn redact_result(settings: &PrivacySettings, result: &mut ExtractionResult) {
+    if !settings.redact_account_numbers {
+        return;
+    }
+    if let Some(finance) = &mut result.data.finance {
+        if let Some(account) = &finance.account_number {
+            if !finance.account_redacted {
+                finance.account_number = Some(redact_account_number(account));
+                finance.account_redacted = true;
+            }
+        }
+    }
+}
+
+/// "123456789" -> "*****6789"; very short values are masked entirely
+pub fn redact_account_number(account: &str) -> String {
+    let chars: Vec<char> = account.chars().filter(|c| c.is_alphanumeric()).collect();
+    if chars.len() <= 4 {
+        return "*".repeat(chars.len().max(4));
+    }
+    let visible: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", "*".repeat(chars.len() - 4), visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctypes::{self, DocumentKind};
+    use crate::documents::extracted_from_value;
+    use serde_json::json;
+
+    /// A finance document's result, parsed the way extraction does it
+    fn finance_result(response: serde_json::Value) -> ExtractionResult {
+        let mut result: ExtractionResult = serde_json::from_value(json!({
+            "path": "/payoff.jpg",
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": 0.8,
+            "raw_response": response.to_string(),
+        }))
+        .unwrap();
+        result.data = extracted_from_value(&response);
+        doctypes::apply_kind_fields(DocumentKind::Finance, &response, &mut result.data);
+        result
+    }
+
+    fn payoff_letter() -> serde_json::Value {
+        json!({
+            "vin": "1FMEU15H5FLA12345",
+            "lienholder": "Ally Financial",
+            "loan_balance": "$12,345.67",
+            "account_number": "0012-3456-789",
+        })
+    }
+
+    #[test]
+    fn finance_response_is_parsed_with_the_account_number_masked() {
+        let mut result = finance_result(payoff_letter());
+        redact_result(&PrivacySettings::default(), &mut result);
+
+        assert_eq!(result.data.vin.as_deref(), Some("1FMEU15H5FLA12345"));
+        let finance = result.data.finance.unwrap();
+        assert_eq!(finance.lienholder.as_deref(), Some("Ally Financial"));
+        assert_eq!(finance.loan_balance, Some(12345.67));
+        assert_eq!(finance.account_number.as_deref(), Some("*******6789"));
+        assert!(finance.account_redacted);
+    }
+
+    #[test]
+    fn redaction_can_be_turned_off_and_never_masks_twice() {
+        let mut result = finance_result(payoff_letter());
+        let off = PrivacySettings {
+            redact_account_numbers: false,
+        };
+        redact_result(&off, &mut result);
+        let finance = result.data.finance.as_ref().unwrap();
+        assert_eq!(finance.account_number.as_deref(), Some("0012-3456-789"));
+        assert!(!finance.account_redacted);
+
+        redact_result(&PrivacySettings::default(), &mut result);
+        redact_result(&PrivacySettings::default(), &mut result);
+        let finance = result.data.finance.unwrap();
+        assert_eq!(finance.account_number.as_deref(), Some("*******6789"));
+    }
+
+    #[test]
+    fn short_account_numbers_are_masked_entirely() {
+        assert_eq!(redact_account_number("123456789"), "*****6789");
+        assert_eq!(redact_account_number("12"), "****");
+        assert_eq!(redact_account_number("AB-123"), "*B123");
+    }
+
+    #[test]
+    fn payoff_letters_are_detected_as_finance_paperwork() {
+        assert_eq!(
+            doctypes::detect_document_kind("PAYOFF LETTER\nLoan balance: $1"),
+            DocumentKind::Finance
+        );
+        assert!(doctypes::parse_finance(&json!({ "vin": "1FMEU15H5FLA12345" })).is_none());
+    }
+}
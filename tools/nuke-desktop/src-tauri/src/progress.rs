@@ -0,0 +1,275 @@
+// Pipeline progress - one merged, throttled progress model across scan, hash, process and sync
+
+use crate::error::NukeError;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+const DEFAULT_THROTTLE: Duration = Duration::from_millis(250);
+
+// Recent stretch a rate is measured over, so it follows slow and fast parts of a scan
+const RATE_WINDOW: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Discovered,
+    Hashed,
+    Processed,
+    Synced,
+    Failed,
+}
+
+/// Counts for every stage, taken together so they're always consistent with each other
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProgressSnapshot {
+    pub discovered: usize,
+    pub hashed: usize,
+    pub processed: usize,
+    pub synced: usize,
+    pub failed: usize,
+}
+
+struct ProgressInner {
+    snapshot: ProgressSnapshot,
+    throttle: Duration,
+    last_emit: Option<Instant>,
+}
+
+/// Merges per-stage updates from concurrent tasks; lives in AppState
+pub struct PipelineProgress {
+    // One lock for counts and throttle state, so a snapshot never mixes two updates
+    inner: Mutex<ProgressInner>,
+}
+
+impl PipelineProgress {
+    pub fn new() -> Self {
+        PipelineProgress {
+            inner: Mutex::new(ProgressInner {
+                snapshot: ProgressSnapshot::default(),
+                throttle: DEFAULT_THROTTLE,
+                last_emit: None,
+            }),
+        }
+    }
+
+    /// Add `count` to a stage; returns a snapshot when one is due for emitting
+    pub fn record(&self, stage: Stage, count: usize) -> Option<ProgressSnapshot> {
+        let mut inner = self.inner.lock().unwrap();
+        let snapshot = &mut inner.snapshot;
+        match stage {
+            Stage::Discovered => snapshot.discovered += count,
+            Stage::Hashed => snapshot.hashed += count,
+            Stage::Processed => snapshot.processed += count,
+            Stage::Synced => snapshot.synced += count,
+            Stage::Failed => snapshot.failed += count,
+        }
+
+        let due = inner
+            .last_emit
+            .map(|last| last.elapsed() >= inner.throttle)
+            .unwrap_or(true);
+        if !due {
+            return None;
+        }
+        inner.last_emit = Some(Instant::now());
+        Some(inner.snapshot.clone())
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        self.inner.lock().unwrap().snapshot.clone()
+    }
+
+    pub fn set_throttle(&self, throttle: Duration) {
+        self.inner.lock().unwrap().throttle = throttle;
+    }
+
+    /// Start a new run from zero
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.snapshot = ProgressSnapshot::default();
+        inner.last_emit = None;
+    }
+}
+
+/// Rolling rate of a count, for showing speed and time left
+pub struct Throughput {
+    started: Instant,
+    /// (when, count done by then); the oldest sample is at most one past RATE_WINDOW
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl Throughput {
+    pub fn start(now: Instant) -> Self {
+        Throughput {
+            started: now,
+            samples: VecDeque::from([(now, 0)]),
+        }
+    }
+
+    pub fn sample(&mut self, now: Instant, done: usize) {
+        self.samples.push_back((now, done));
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= RATE_WINDOW {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        now.duration_since(self.started)
+    }
+
+    /// Per second over the last RATE_WINDOW
+    pub fn rate(&self) -> f64 {
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return 0.0;
+        };
+        let seconds = last.0.duration_since(first.0).as_secs_f64();
+        if seconds <= 0.0 {
+            return 0.0;
+        }
+        last.1.saturating_sub(first.1) as f64 / seconds
+    }
+
+    /// Time to reach `total` at the current rate; None until something has been done
+    pub fn remaining(&self, done: usize, total: usize) -> Option<Duration> {
+        let rate = self.rate();
+        if rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(total.saturating_sub(done) as f64 / rate))
+    }
+}
+
+/// Record progress and emit `pipeline-progress` when the throttle allows
+pub fn advance(
+    app: Option<&tauri::AppHandle>,
+    progress: &PipelineProgress,
+    stage: Stage,
+    count: usize,
+) {
+    if let (Some(snapshot), Some(app)) = (progress.record(stage, count), app) {
+        let _ = app.emit("pipeline-progress", snapshot);
+    }
+}
+
+/// Current merged progress across all stages
+#[tauri::command]
+pub async fn get_pipeline_progress(
+    state: tauri::State<'_, AppState>,
+) -> Result<ProgressSnapshot, NukeError> {
+    Ok(state.progress.snapshot())
+}
+
+/// Set how often `pipeline-progress` events may fire
+#[tauri::command]
+pub async fn set_progress_throttle(
+    state: tauri::State<'_, AppState>,
+    throttle_ms: u64,
+) -> Result<(), NukeError> {
+    state
+        .progress
+        .set_throttle(Duration::from_millis(throttle_ms));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn concurrent_stage_updates_merge_into_one_snapshot() {
+        let progress = Arc::new(PipelineProgress::new());
+        let stages = [
+            Stage::Discovered,
+            Stage::Hashed,
+            Stage::Processed,
+            Stage::Synced,
+            Stage::Failed,
+        ];
+        let workers: Vec<_> = stages
+            .into_iter()
+            .flat_map(|stage| [stage, stage])
+            .map(|stage| {
+                let progress = progress.clone();
+                std::thread::spawn(move || {
+                    let mut emitted = Vec::new();
+                    for _ in 0..1000 {
+                        emitted.extend(progress.record(stage, 1));
+                    }
+                    emitted
+                })
+            })
+            .collect();
+        let emitted: Vec<ProgressSnapshot> = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .collect();
+
+        let snapshot = progress.snapshot();
+        assert_eq!(
+            (
+                snapshot.discovered,
+                snapshot.hashed,
+                snapshot.processed,
+                snapshot.synced,
+                snapshot.failed
+            ),
+            (2000, 2000, 2000, 2000, 2000)
+        );
+        // Throttled: the first update emits, and no emitted snapshot overshoots the totals
+        assert!(
+            !emitted.is_empty() && emitted.len() < 100,
+            "{}",
+            emitted.len()
+        );
+        assert!(emitted
+            .iter()
+            .all(|s| s.discovered <= 2000 && s.failed <= 2000));
+    }
+
+    #[test]
+    fn throttle_and_reset_decide_when_snapshots_emit() {
+        let progress = PipelineProgress::new();
+        assert!(progress.record(Stage::Hashed, 1).is_some());
+        assert!(progress.record(Stage::Hashed, 1).is_none());
+
+        progress.set_throttle(Duration::ZERO);
+        let snapshot = progress.record(Stage::Hashed, 3).unwrap();
+        assert_eq!(snapshot.hashed, 5);
+
+        progress.set_throttle(Duration::from_secs(60));
+        progress.reset();
+        assert_eq!(progress.snapshot().hashed, 0);
+        // A new run emits its first update straight away
+        assert_eq!(progress.record(Stage::Discovered, 2).unwrap().discovered, 2);
+        assert!(progress.record(Stage::Discovered, 1).is_none());
+    }
+
+    #[test]
+    fn rate_follows_recent_samples_and_gives_time_left() {
+        let started = Instant::now();
+        let mut throughput = Throughput::start(started);
+        assert_eq!(throughput.remaining(0, 100), None);
+
+        throughput.sample(started + Duration::from_secs(1), 10);
+        assert!((throughput.rate() - 10.0).abs() < 1e-9);
+        assert_eq!(throughput.remaining(10, 100), Some(Duration::from_secs(9)));
+
+        // Samples older than the window drop out: the rate is now the recent 100/s
+        for second in 2..=20 {
+            throughput.sample(
+                started + Duration::from_secs(second),
+                10 + (second as usize - 1) * 100,
+            );
+        }
+        assert!((throughput.rate() - 100.0).abs() < 1e-6);
+        assert_eq!(
+            throughput.elapsed(started + Duration::from_secs(20)),
+            Duration::from_secs(20)
+        );
+    }
+}
@@ -0,0 +1,482 @@
+// Import reports - shareable summaries of an intake session
+
+use crate::error::NukeError;
+use crate::floors::ConfidenceFloors;
+use crate::{AppState, ScanResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Hints below this confidence are listed under "Needs Review"
+const LOW_CONFIDENCE_THRESHOLD: f32 = 0.6;
+
+/// A vehicle in the report, grouped from one or more scan results
+struct ReportVehicle {
+    year: String,
+    make: String,
+    model: String,
+    vin: String,
+    files: Vec<String>,
+    best_confidence: f32,
+}
+
+/// Everything a report needs, independent of output format
+struct ReportData {
+    total_files: usize,
+    with_hints: usize,
+    synced: Option<u64>,
+    failed: Option<u64>,
+    vehicles: Vec<ReportVehicle>,
+    needs_review: Vec<(String, String)>,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldCoverage {
+    pub filled: usize,
+    pub percent: f32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoverageReport {
+    pub total: usize,
+    pub fields: BTreeMap<String, FieldCoverage>,
+    /// Records the backend will accept: a VIN, or year + make + model
+    pub meeting_minimum: usize,
+    /// Of those, the ones confident enough for their document type's floor
+    pub sync_eligible: usize,
+}
+
+/// Report how complete the sync payload would be, field by field
+#[tauri::command]
+pub async fn coverage_report(
+    state: tauri::State<'_, AppState>,
+    results: Vec<ScanResult>,
+) -> Result<CoverageReport, NukeError> {
+    let floors = state.confidence_floors.lock().unwrap().clone();
+    Ok(compute_coverage(&results, &floors))
+}
+
+pub fn compute_coverage(results: &[ScanResult], floors: &ConfidenceFloors) -> CoverageReport {
+    let hints: Vec<_> = results.iter().map(|r| r.potential_vehicle.as_ref()).collect();
+    let total = results.len();
+
+    let coverage = |has_field: &dyn Fn(&crate::VehicleHint) -> bool| {
+        let filled = hints.iter().filter(|h| h.map(has_field).unwrap_or(false)).count();
+        FieldCoverage {
+            filled,
+            percent: if total > 0 {
+                filled as f32 * 100.0 / total as f32
+            } else {
+                0.0
+            },
+        }
+    };
+
+    let mut fields = BTreeMap::new();
+    fields.insert("vin".to_string(), coverage(&|h| h.vin.is_some()));
+    fields.insert("year".to_string(), coverage(&|h| h.year.is_some()));
+    fields.insert("make".to_string(), coverage(&|h| h.make.is_some()));
+    fields.insert("model".to_string(), coverage(&|h| h.model.is_some()));
+
+    let meets_minimum = |h: &crate::VehicleHint| {
+        h.vin.is_some() || (h.year.is_some() && h.make.is_some() && h.model.is_some())
+    };
+    let meeting_minimum = hints.iter().flatten().filter(|h| meets_minimum(h)).count();
+    let sync_eligible = results
+        .iter()
+        .filter(|r| r.potential_vehicle.as_ref().map(meets_minimum).unwrap_or(false))
+        .filter(|r| floors.check_scan(r).is_none())
+        .count();
+
+    CoverageReport {
+        total,
+        fields,
+        meeting_minimum,
+        sync_eligible,
+    }
+}
+
+/// Generate an HTML or Markdown import report and write it to `output_path`
+#[tauri::command]
+pub async fn generate_import_report(
+    results: Vec<ScanResult>,
+    format: String,
+    output_path: String,
+    sync_summary: Option<serde_json::Value>,
+) -> Result<String, NukeError> {
+    let data = collect_report_data(&results, sync_summary.as_ref());
+
+    let contents = match format.to_lowercase().as_str() {
+        "html" => render_html(&data),
+        "markdown" | "md" => render_markdown(&data),
+        other => return Err(NukeError::InvalidInput(format!("Unsupported report format: {}", other))),
+    };
+
+    std::fs::write(&output_path, contents)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to write report: {}", e)))?;
+
+    Ok(output_path)
+}
+
+/// Group results into vehicles and pull counts/errors from the sync summary
+fn collect_report_data(results: &[ScanResult], sync_summary: Option<&serde_json::Value>) -> ReportData {
+    let mut vehicles: BTreeMap<String, ReportVehicle> = BTreeMap::new();
+    let mut needs_review = Vec::new();
+
+    for result in results {
+        let hint = match &result.potential_vehicle {
+            Some(h) => h,
+            None => continue,
+        };
+
+        if hint.confidence < LOW_CONFIDENCE_THRESHOLD {
+            needs_review.push((
+                result.path.clone(),
+                format!("Low confidence ({:.2})", hint.confidence),
+            ));
+        }
+
+        // Group by VIN when we have one, otherwise by year/make/model
+        let key = match &hint.vin {
+            Some(vin) => vin.to_uppercase(),
+            None => format!(
+                "{}|{}|{}",
+                hint.year.as_deref().unwrap_or(""),
+                hint.make.as_deref().unwrap_or(""),
+                hint.model.as_deref().unwrap_or("")
+            ),
+        };
+
+        let vehicle = vehicles.entry(key).or_insert_with(|| ReportVehicle {
+            year: String::new(),
+            make: String::new(),
+            model: String::new(),
+            vin: String::new(),
+            files: Vec::new(),
+            best_confidence: 0.0,
+        });
+
+        fill_if_empty(&mut vehicle.year, hint.year.as_deref());
+        fill_if_empty(&mut vehicle.make, hint.make.as_deref());
+        fill_if_empty(&mut vehicle.model, hint.model.as_deref());
+        fill_if_empty(&mut vehicle.vin, hint.vin.as_deref());
+        vehicle.files.push(result.filename.clone());
+        vehicle.best_confidence = vehicle.best_confidence.max(hint.confidence);
+    }
+
+    let count = |key: &str| sync_summary.and_then(|s| s.get(key)).and_then(|v| v.as_u64());
+    let errors = sync_summary
+        .and_then(|s| s.get("errors"))
+        .and_then(|e| e.as_array())
+        .map(|errors| {
+            errors
+                .iter()
+                .filter_map(|e| e.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ReportData {
+        total_files: results.len(),
+        with_hints: results.iter().filter(|r| r.potential_vehicle.is_some()).count(),
+        synced: count("synced"),
+        failed: count("failed"),
+        vehicles: vehicles.into_values().collect(),
+        needs_review,
+        errors,
+    }
+}
+
+fn fill_if_empty(field: &mut String, value: Option<&str>) {
+    if field.is_empty() {
+        if let Some(v) = value {
+            *field = v.to_string();
+        }
+    }
+}
+
+fn optional_count(count: Option<u64>) -> String {
+    count.map(|c| c.to_string()).unwrap_or_else(|| "n/a".to_string())
+}
+
+fn render_markdown(data: &ReportData) -> String {
+    let mut out = String::from("# Nuke Import Report\n\n## Summary\n\n");
+    out.push_str(&format!("- Files scanned: {}\n", data.total_files));
+    out.push_str(&format!("- Files with vehicle hints: {}\n", data.with_hints));
+    out.push_str(&format!("- Vehicles found: {}\n", data.vehicles.len()));
+    out.push_str(&format!("- Synced: {}\n", optional_count(data.synced)));
+    out.push_str(&format!("- Failed: {}\n", optional_count(data.failed)));
+
+    out.push_str("\n## Vehicles\n\n");
+    out.push_str("| Year | Make | Model | VIN | Files | Confidence |\n");
+    out.push_str("|------|------|-------|-----|-------|------------|\n");
+    for v in &data.vehicles {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {:.2} |\n",
+            escape_markdown(&v.year),
+            escape_markdown(&v.make),
+            escape_markdown(&v.model),
+            escape_markdown(&v.vin),
+            v.files.len(),
+            v.best_confidence
+        ));
+    }
+
+    out.push_str("\n## Needs Review\n\n");
+    if data.needs_review.is_empty() {
+        out.push_str("Nothing flagged.\n");
+    }
+    for (path, reason) in &data.needs_review {
+        out.push_str(&format!("- `{}`: {}\n", path, reason));
+    }
+
+    out.push_str("\n## Errors\n\n");
+    if data.errors.is_empty() {
+        out.push_str("No errors.\n");
+    }
+    for error in &data.errors {
+        out.push_str(&format!("- {}\n", error));
+    }
+
+    out
+}
+
+fn render_html(data: &ReportData) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Nuke Import Report</title>\n</head>\n<body>\n<h1>Nuke Import Report</h1>\n",
+    );
+
+    out.push_str("<h2>Summary</h2>\n<ul>\n");
+    out.push_str(&format!("<li>Files scanned: {}</li>\n", data.total_files));
+    out.push_str(&format!("<li>Files with vehicle hints: {}</li>\n", data.with_hints));
+    out.push_str(&format!("<li>Vehicles found: {}</li>\n", data.vehicles.len()));
+    out.push_str(&format!("<li>Synced: {}</li>\n", optional_count(data.synced)));
+    out.push_str(&format!("<li>Failed: {}</li>\n", optional_count(data.failed)));
+    out.push_str("</ul>\n");
+
+    out.push_str("<h2>Vehicles</h2>\n<table>\n");
+    out.push_str("<tr><th>Year</th><th>Make</th><th>Model</th><th>VIN</th><th>Files</th><th>Confidence</th></tr>\n");
+    for v in &data.vehicles {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td></tr>\n",
+            escape_html(&v.year),
+            escape_html(&v.make),
+            escape_html(&v.model),
+            escape_html(&v.vin),
+            v.files.len(),
+            v.best_confidence
+        ));
+    }
+    out.push_str("</table>\n");
+
+    out.push_str("<h2>Needs Review</h2>\n");
+    if data.needs_review.is_empty() {
+        out.push_str("<p>Nothing flagged.</p>\n");
+    } else {
+        out.push_str("<ul>\n");
+        for (path, reason) in &data.needs_review {
+            out.push_str(&format!(
+                "<li><code>{}</code>: {}</li>\n",
+                escape_html(path),
+                escape_html(reason)
+            ));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("<h2>Errors</h2>\n");
+    if data.errors.is_empty() {
+        out.push_str("<p>No errors.</p>\n");
+    } else {
+        out.push_str("<ul>\n");
+        for error in &data.errors {
+            out.push_str(&format!("<li>{}</li>\n", escape_html(error)));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_markdown(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan_result(path: &str, hint: serde_json::Value) -> ScanResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "filename": path.rsplit('/').next().unwrap(),
+            "file_type": "jpg",
+            "category": "image",
+            "size": 1,
+            "modified": "",
+            "potential_vehicle": hint,
+        }))
+        .unwrap()
+    }
+
+    fn hint(
+        year: &str,
+        make: &str,
+        model: &str,
+        vin: Option<&str>,
+        confidence: f32,
+    ) -> serde_json::Value {
+        serde_json::json!({
+            "year": year,
+            "make": make,
+            "model": model,
+            "vin": vin,
+            "confidence": confidence,
+            "source": "path",
+        })
+    }
+
+    fn fixture() -> Vec<ScanResult> {
+        vec![
+            scan_result(
+                "/cars/c10/front.jpg",
+                hint("1972", "Chevrolet", "C10", None, 0.9),
+            ),
+            scan_result(
+                "/cars/c10/rear.jpg",
+                hint("1972", "Chevrolet", "C10", None, 0.5),
+            ),
+            scan_result(
+                "/cars/title.pdf",
+                hint("1967", "Ford", "Mustang", Some("7r02c123456"), 1.2),
+            ),
+            scan_result("/cars/notes.txt", serde_json::Value::Null),
+        ]
+    }
+
+    #[test]
+    fn groups_results_into_vehicles() {
+        let data = collect_report_data(&fixture(), None);
+        assert_eq!((data.total_files, data.with_hints), (4, 3));
+        assert_eq!(data.vehicles.len(), 2);
+        let mustang = data.vehicles.iter().find(|v| v.model == "Mustang").unwrap();
+        assert_eq!(mustang.vin, "7r02c123456");
+        let c10 = data.vehicles.iter().find(|v| v.model == "C10").unwrap();
+        assert_eq!(c10.files, vec!["front.jpg", "rear.jpg"]);
+        assert_eq!(c10.best_confidence, 0.9);
+    }
+
+    #[test]
+    fn flags_hints_below_the_low_confidence_threshold() {
+        let results = vec![
+            scan_result("/a.jpg", hint("1972", "Chevrolet", "C10", None, 0.59)),
+            scan_result(
+                "/b.jpg",
+                hint("1972", "Chevrolet", "C10", None, LOW_CONFIDENCE_THRESHOLD),
+            ),
+        ];
+        let data = collect_report_data(&results, None);
+        assert_eq!(data.needs_review.len(), 1);
+        assert_eq!(data.needs_review[0].0, "/a.jpg");
+        assert_eq!(data.needs_review[0].1, "Low confidence (0.59)");
+    }
+
+    #[test]
+    fn markdown_has_every_section_and_a_row_per_vehicle() {
+        let markdown = render_markdown(&collect_report_data(&fixture(), None));
+        for section in [
+            "# Nuke Import Report",
+            "## Summary",
+            "## Vehicles",
+            "## Needs Review",
+            "## Errors",
+        ] {
+            assert!(markdown.contains(section), "missing {}", section);
+        }
+        assert!(markdown.contains("| 1972 | Chevrolet | C10 |  | 2 | 0.90 |"));
+        assert!(markdown.contains("| 1967 | Ford | Mustang | 7r02c123456 | 1 | 1.20 |"));
+        assert!(markdown.contains("- `/cars/c10/rear.jpg`: Low confidence (0.50)"));
+        assert!(markdown.contains("- Synced: n/a"));
+        assert!(markdown.contains("No errors."));
+    }
+
+    #[test]
+    fn sync_summary_fills_counts_and_errors() {
+        let summary = serde_json::json!({
+            "synced": 2,
+            "failed": 1,
+            "errors": ["title.pdf: 500 Internal Server Error"],
+        });
+        let data = collect_report_data(&fixture(), Some(&summary));
+        assert_eq!((data.synced, data.failed), (Some(2), Some(1)));
+
+        let markdown = render_markdown(&data);
+        assert!(markdown.contains("- Synced: 2\n- Failed: 1\n"));
+        assert!(markdown.contains("## Errors\n\n- title.pdf: 500 Internal Server Error\n"));
+        let html = render_html(&data);
+        assert!(html.contains("<li>Synced: 2</li>"));
+        assert!(html.contains("<li>title.pdf: 500 Internal Server Error</li>"));
+    }
+
+    #[test]
+    fn html_escapes_and_markdown_escapes_pipes() {
+        let results = vec![scan_result(
+            "/<a>.jpg",
+            hint("1972", "A|B", "<C10>", None, 0.3),
+        )];
+        let data = collect_report_data(&results, None);
+        assert!(render_html(&data).contains("<td>&lt;C10&gt;</td>"));
+        assert!(render_html(&data).contains("<code>/&lt;a&gt;.jpg</code>"));
+        assert!(render_markdown(&data).contains("| A\\|B |"));
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_formats() {
+        let error = generate_import_report(fixture(), "pdf".to_string(), String::new(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, NukeError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn coverage_counts_fill_rates_on_a_mixed_fixture() {
+        let mut results = fixture();
+        results.push(scan_result(
+            "/cars/title scan.jpg",
+            serde_json::json!({ "vin": "1HGCM82633A004352", "confidence": 0.5, "source": "path" }),
+        ));
+        results.push(scan_result(
+            "/cars/ford.jpg",
+            serde_json::json!({ "make": "Ford", "confidence": 0.4, "source": "path" }),
+        ));
+        let coverage = compute_coverage(&results, &ConfidenceFloors::default());
+
+        assert_eq!(coverage.total, 6);
+        let filled = |field: &str| {
+            let f = &coverage.fields[field];
+            (f.filled, f.percent)
+        };
+        assert_eq!(filled("vin"), (2, 2.0 * 100.0 / 6.0));
+        assert_eq!(filled("make").0, 4);
+        assert_eq!(filled("year").0, 3);
+        // Year + make + model, or a VIN; the make-only hint and the file without one miss out
+        assert_eq!(coverage.meeting_minimum, 4);
+        // A title needs 0.8, so the VIN-only title scan at 0.5 can't sync
+        assert_eq!(coverage.sync_eligible, 3);
+    }
+
+    #[test]
+    fn coverage_of_nothing_is_zero_percent() {
+        let coverage = compute_coverage(&[], &ConfidenceFloors::default());
+        assert_eq!(coverage.total, 0);
+        assert_eq!(coverage.fields["vin"].percent, 0.0);
+    }
+}
@@ -0,0 +1,186 @@
+// Review queue - the results that need a person, quickest fixes first
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::vin::VinStatus;
+use serde::{Deserialize, Serialize};
+
+// Below this confidence a result goes to review
+const DEFAULT_REVIEW_THRESHOLD: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewReason {
+    /// VIN read but make or model missing; a VIN decode usually fills it in
+    MissingModel,
+    /// Some VIN characters were hidden; only those need checking
+    PartialVin,
+    /// Barcode, OCR and model read different VINs
+    VinConflict,
+    /// The title's brand area was missing or didn't match a known brand
+    TitleBrandUnreadable,
+    /// The VIN's check digit doesn't validate
+    VinUnverified,
+    /// Several photos in one image; split it and re-run
+    Collage,
+    /// Handwriting, read with capped confidence
+    Handwritten,
+    LowConfidence,
+}
+
+impl ReviewReason {
+    /// How likely a quick look is to resolve this (0-1)
+    fn quick_fix(self) -> f32 {
+        match self {
+            ReviewReason::MissingModel => 0.9,
+            ReviewReason::PartialVin => 0.8,
+            ReviewReason::VinConflict => 0.7,
+            ReviewReason::TitleBrandUnreadable => 0.6,
+            ReviewReason::VinUnverified => 0.5,
+            ReviewReason::Collage => 0.4,
+            ReviewReason::Handwritten => 0.3,
+            ReviewReason::LowConfidence => 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub path: String,
+    pub confidence: f32,
+    pub reasons: Vec<ReviewReason>,
+    /// Higher sorts first: quick fixes ahead of unreadable files
+    pub priority: f32,
+    pub result: ExtractionResult,
+}
+
+/// Results below the confidence threshold or with conflicts and flags, quickest fixes first
+#[tauri::command]
+pub async fn review_queue(
+    results: Vec<ExtractionResult>,
+    threshold: Option<f32>,
+) -> Result<Vec<ReviewItem>, NukeError> {
+    Ok(build_review_queue(
+        results,
+        threshold.unwrap_or(DEFAULT_REVIEW_THRESHOLD),
+    ))
+}
+
+pub fn build_review_queue(results: Vec<ExtractionResult>, threshold: f32) -> Vec<ReviewItem> {
+    let mut queue: Vec<ReviewItem> = results
+        .into_iter()
+        .filter_map(|result| {
+            let reasons = review_reasons(&result, threshold);
+            let priority = reasons.iter().map(|r| r.quick_fix()).fold(0.0, f32::max);
+            (!reasons.is_empty()).then(|| ReviewItem {
+                path: result.path.clone(),
+                confidence: result.confidence,
+                reasons,
+                priority,
+                result,
+            })
+        })
+        .collect();
+
+    // Among equals, the result closest to passing is the quicker fix
+    queue.sort_by(|a, b| {
+        b.priority
+            .total_cmp(&a.priority)
+            .then(b.confidence.total_cmp(&a.confidence))
+    });
+    queue
+}
+
+/// Why a result needs review; empty when it doesn't
+fn review_reasons(result: &ExtractionResult, threshold: f32) -> Vec<ReviewReason> {
+    let data = &result.data;
+    let mut flags = Vec::new();
+    if data.partial_vin.is_some() {
+        flags.push(ReviewReason::PartialVin);
+    }
+    match result.vin_verification.as_ref().map(|v| v.status) {
+        Some(VinStatus::Conflict) => flags.push(ReviewReason::VinConflict),
+        Some(VinStatus::Unverified) if data.vin.is_some() => {
+            flags.push(ReviewReason::VinUnverified)
+        }
+        _ => {}
+    }
+    if data.title.as_ref().map(|t| t.needs_review).unwrap_or(false) {
+        flags.push(ReviewReason::TitleBrandUnreadable);
+    }
+    if result.collage.is_some() {
+        flags.push(ReviewReason::Collage);
+    }
+
+    let low_confidence = result.confidence < threshold;
+    if flags.is_empty() && !low_confidence {
+        return flags;
+    }
+
+    // These explain a result already in the queue rather than putting it there
+    if data.vin.is_some() && (data.make.is_none() || data.model.is_none()) {
+        flags.push(ReviewReason::MissingModel);
+    }
+    if result.handwritten {
+        flags.push(ReviewReason::Handwritten);
+    }
+    if low_confidence {
+        flags.push(ReviewReason::LowConfidence);
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(path: &str, confidence: f32) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": confidence,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn only_sub_threshold_and_flagged_results_are_queued_quickest_fix_first() {
+        let passing = result("passing", 0.9);
+        let mut vin_only = result("vin_only", 0.4);
+        vin_only.data.vin = Some("1HGCM82633A004352".to_string());
+        let blurry = result("blurry", 0.2);
+        // Confident, but with hidden VIN characters
+        let mut partial = result("partial", 0.95);
+        partial.data.partial_vin = crate::vin::parse_partial_vin("***CM82633A004352");
+        assert!(partial.data.partial_vin.is_some());
+
+        let queue = build_review_queue(vec![passing, blurry, partial, vin_only], 0.6);
+        let paths: Vec<&str> = queue.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["vin_only", "partial", "blurry"]);
+        assert_eq!(
+            queue[0].reasons,
+            vec![ReviewReason::MissingModel, ReviewReason::LowConfidence]
+        );
+        assert_eq!(queue[1].reasons, vec![ReviewReason::PartialVin]);
+        assert_eq!(queue[2].reasons, vec![ReviewReason::LowConfidence]);
+    }
+
+    #[test]
+    fn equal_priorities_put_the_result_closest_to_passing_first() {
+        let queue = build_review_queue(
+            vec![
+                result("worse", 0.1),
+                result("better", 0.5),
+                result("ok", 0.6),
+            ],
+            0.6,
+        );
+        let paths: Vec<&str> = queue.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["better", "worse"]);
+    }
+}
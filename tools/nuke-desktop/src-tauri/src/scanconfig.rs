@@ -0,0 +1,57 @@
+// Saved scan configuration - the folders and toggles from last time, restored on launch
+
+use crate::error::NukeError;
+use crate::{AppState, ScanConfig};
+use std::path::PathBuf;
+
+/// The last saved `ScanConfig`, kept in memory and mirrored to a JSON file
+pub struct SavedScanConfig {
+    path: PathBuf,
+    config: Option<ScanConfig>,
+}
+
+impl SavedScanConfig {
+    /// Load the saved config, starting with none if the file is missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let config = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok());
+        SavedScanConfig { path, config }
+    }
+
+    pub fn get(&self) -> Option<&ScanConfig> {
+        self.config.as_ref()
+    }
+
+    /// Remember a config and write it back to disk
+    pub fn save(&mut self, config: ScanConfig) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&config)
+            .map_err(|e| format!("Failed to serialize scan config: {}", e))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| format!("Failed to write scan config: {}", e))?;
+        self.config = Some(config);
+        Ok(())
+    }
+}
+
+/// Save the scan form so the next launch starts from it
+#[tauri::command]
+pub async fn save_scan_config(
+    state: tauri::State<'_, AppState>,
+    config: ScanConfig,
+) -> Result<(), NukeError> {
+    state
+        .scan_config
+        .lock()
+        .unwrap()
+        .save(config)
+        .map_err(NukeError::FileWrite)
+}
+
+/// The last saved scan config, or None if nothing has been saved yet
+#[tauri::command]
+pub async fn load_scan_config(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<ScanConfig>, NukeError> {
+    Ok(state.scan_config.lock().unwrap().get().cloned())
+}
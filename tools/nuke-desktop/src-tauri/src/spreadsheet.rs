@@ -0,0 +1,632 @@
+// Spreadsheet parsing - CSV and Excel workbooks into JSON rows, with cell type coercion
+
+use crate::error::NukeError;
+use calamine::{Data, Reader};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+// Workbook formats calamine reads
+const WORKBOOK_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+// Delimiters tried when sniffing, in tie-break order
+const CANDIDATE_DELIMITERS: &[u8] = b",;\t|";
+
+// Lines looked at when sniffing the delimiter
+const SNIFF_LINES: usize = 10;
+
+// Bytes read ahead of a streamed CSV to guess its encoding and delimiter
+const STREAM_SNIFF_BYTES: usize = 16 * 1024;
+
+// Rows sampled per column when inferring its type
+const DEFAULT_SAMPLE_ROWS: usize = 100;
+
+/// How parse_csv turns cell text into JSON values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvParseOptions {
+    /// Coerce numeric and boolean columns; off means every cell stays a string
+    pub infer_types: bool,
+    /// Rows sampled to decide a column's type; a column is only numeric or boolean when
+    /// every non-empty sampled cell is
+    pub sample_rows: usize,
+    /// Keep "01234" (ZIP codes, stock numbers) as a string
+    pub preserve_leading_zeros: bool,
+    /// Digit runs longer than this stay strings (VINs, account numbers)
+    pub max_numeric_length: usize,
+    /// Keep phone-number-shaped values as strings
+    pub preserve_phone_numbers: bool,
+}
+
+impl Default for CsvParseOptions {
+    fn default() -> Self {
+        CsvParseOptions {
+            infer_types: true,
+            sample_rows: DEFAULT_SAMPLE_ROWS,
+            preserve_leading_zeros: true,
+            max_numeric_length: 15,
+            preserve_phone_numbers: true,
+        }
+    }
+}
+
+/// Parse a CSV or Excel file into one JSON object per row, keyed by the header row.
+/// Workbooks read `sheet_name`, or the first sheet with data rows when it's not given.
+#[tauri::command]
+pub async fn parse_spreadsheet(
+    path: String,
+    sheet_name: Option<String>,
+    delimiter: Option<String>,
+    options: Option<CsvParseOptions>,
+) -> Result<Vec<serde_json::Value>, NukeError> {
+    let options = options.unwrap_or_default();
+    let delimiter = parse_delimiter(delimiter.as_deref())?;
+    let extension = Path::new(&path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "numbers" => Err(NukeError::InvalidInput(
+            "Numbers files aren't supported; export to CSV or Excel and try again".to_string(),
+        )),
+        ext if WORKBOOK_EXTENSIONS.contains(&ext) => {
+            read_workbook(Path::new(&path), sheet_name.as_deref(), &options)
+        }
+        _ => read_csv(Path::new(&path), delimiter, &options),
+    }
+}
+
+/// Check a user-supplied delimiter override; it must be a single ASCII character
+pub fn parse_delimiter(delimiter: Option<&str>) -> Result<Option<u8>, NukeError> {
+    let Some(delimiter) = delimiter else {
+        return Ok(None);
+    };
+    let delimiter = if delimiter == "\\t" { "\t" } else { delimiter };
+    match delimiter.as_bytes() {
+        [byte] if byte.is_ascii() => Ok(Some(*byte)),
+        _ => Err(NukeError::InvalidInput(format!(
+            "Delimiter must be a single character, got {:?}",
+            delimiter
+        ))),
+    }
+}
+
+/// Rows of a CSV file as JSON objects keyed by header. The encoding is detected and the
+/// delimiter sniffed unless one is given.
+pub fn read_csv(
+    path: &Path,
+    delimiter: Option<u8>,
+    options: &CsvParseOptions,
+) -> Result<Vec<serde_json::Value>, NukeError> {
+    let (headers, rows) = read_csv_rows(path, delimiter)?;
+    Ok(rows_to_objects(&headers, rows, options))
+}
+
+/// Header row and raw cell text of a CSV file, before any type inference
+pub fn read_csv_rows(
+    path: &Path,
+    delimiter: Option<u8>,
+) -> Result<(Vec<String>, Vec<Vec<String>>), NukeError> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| NukeError::FileRead(format!("Failed to open file: {}", e)))?;
+    let text = decode_text(&bytes);
+    let delimiter = delimiter.unwrap_or_else(|| sniff_delimiter(&text));
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| NukeError::FileRead(format!("Failed to read headers: {}", e)))?
+        .clone();
+
+    let mut rows = Vec::new();
+    for result in reader.records() {
+        let record =
+            result.map_err(|e| NukeError::FileRead(format!("Failed to read record: {}", e)))?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+
+    let headers = headers.iter().map(str::to_string).collect();
+    Ok((headers, rows))
+}
+
+/// Decode file bytes to UTF-8, guessing the encoding when there's no BOM.
+/// Windows-1252 and Latin-1 exports from older dealer systems are common.
+pub fn decode_text(bytes: &[u8]) -> String {
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    // decode() lets a BOM override the guess and strips it
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+/// A CSV stream decoded to UTF-8 as it's read, and its delimiter (`delimiter`, else sniffed).
+/// The encoding and delimiter are guessed from the first STREAM_SNIFF_BYTES.
+pub fn decode_csv_stream<R: Read>(
+    mut reader: R,
+    delimiter: Option<u8>,
+) -> std::io::Result<(impl Read, u8)> {
+    let mut sample = Vec::with_capacity(STREAM_SNIFF_BYTES);
+    (&mut reader)
+        .take(STREAM_SNIFF_BYTES as u64)
+        .read_to_end(&mut sample)?;
+    let complete = sample.len() < STREAM_SNIFF_BYTES;
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(&sample, complete);
+    let encoding = detector.guess(None, true);
+    let delimiter = delimiter.unwrap_or_else(|| {
+        let text = encoding.decode(&sample).0;
+        // A sample cut short ends mid-line, which would skew the column counts
+        let lines = if complete {
+            &text[..]
+        } else {
+            text.rsplit_once('\n').map_or(&text[..], |(lines, _)| lines)
+        };
+        sniff_delimiter(lines)
+    });
+
+    let decoded = DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding))
+        // A BOM overrides the guess, as it does for decode_text
+        .bom_override(true)
+        .strip_bom(true)
+        .build(std::io::Cursor::new(sample).chain(reader));
+    Ok((decoded, delimiter))
+}
+
+/// Pick the delimiter that splits the first lines into the most consistent number of
+/// columns, falling back to a comma
+pub fn sniff_delimiter(text: &str) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+
+    let mut best = (b',', 0, 0);
+    for &delimiter in CANDIDATE_DELIMITERS {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|line| count_unquoted(line, delimiter))
+            .collect();
+        let Some(&header_count) = counts.first() else {
+            break;
+        };
+        if header_count == 0 {
+            continue;
+        }
+        // Lines agreeing with the header matter more than the raw count, so a comma inside
+        // a European decimal doesn't beat the semicolons that actually split the row
+        let agreeing = counts.iter().filter(|&&c| c == header_count).count();
+        if (agreeing, header_count) > (best.1, best.2) {
+            best = (delimiter, agreeing, header_count);
+        }
+    }
+    best.0
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut quoted = false;
+    let mut count = 0;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            quoted = !quoted;
+        } else if byte == delimiter && !quoted {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Rows of one worksheet as JSON objects keyed by its first row
+pub fn read_workbook(
+    path: &Path,
+    sheet_name: Option<&str>,
+    options: &CsvParseOptions,
+) -> Result<Vec<serde_json::Value>, NukeError> {
+    let mut workbook = calamine::open_workbook_auto(path)
+        .map_err(|e| NukeError::FileRead(format!("Failed to open workbook: {}", e)))?;
+    let sheet_names = workbook.sheet_names();
+
+    if let Some(name) = sheet_name {
+        if !sheet_names.iter().any(|n| n == name) {
+            return Err(NukeError::InvalidInput(format!(
+                "No sheet named {}; the workbook has {}",
+                name,
+                sheet_names.join(", ")
+            )));
+        }
+    }
+    let wanted: Vec<String> = match sheet_name {
+        Some(name) => vec![name.to_string()],
+        None => sheet_names,
+    };
+
+    for name in wanted {
+        let range = workbook
+            .worksheet_range(&name)
+            .map_err(|e| NukeError::FileRead(format!("Failed to read sheet {}: {}", name, e)))?;
+        let rows = sheet_rows(&range, options);
+        // Cover sheets and empty tabs come first in plenty of dealer exports
+        if !rows.is_empty() || sheet_name.is_some() {
+            return Ok(rows);
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn sheet_rows(range: &calamine::Range<Data>, options: &CsvParseOptions) -> Vec<serde_json::Value> {
+    let mut rows = range.rows();
+    let Some(header_row) = rows.next() else {
+        return Vec::new();
+    };
+    let headers: Vec<String> = header_row
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| match cell_text(cell).trim() {
+            "" => format!("column_{}", i + 1),
+            header => header.to_string(),
+        })
+        .collect();
+
+    let rows = rows
+        .filter(|row| row.iter().any(|cell| *cell != Data::Empty))
+        .map(|row| row.iter().map(cell_text).collect())
+        .collect();
+    rows_to_objects(&headers, rows, options)
+}
+
+/// A cell as the text a CSV export would hold, so both formats coerce the same way
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::DateTime(value) => match value.as_datetime() {
+            Some(datetime) if datetime.time() == chrono::NaiveTime::MIN => {
+                datetime.format("%Y-%m-%d").to_string()
+            }
+            Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+            None => value.to_string(),
+        },
+        Data::Error(_) => String::new(),
+        cell => cell.to_string(),
+    }
+}
+
+/// What a column holds, decided from a sample of its cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+}
+
+/// Turn parsed rows into JSON objects keyed by header, typing each column as a whole.
+/// Short rows only get the keys they have cells for.
+pub fn rows_to_objects(
+    headers: &[String],
+    rows: Vec<Vec<String>>,
+    options: &CsvParseOptions,
+) -> Vec<serde_json::Value> {
+    let types = infer_column_types(headers.len(), &rows, options);
+    rows.into_iter()
+        .map(|row| {
+            let mut obj = serde_json::Map::new();
+            for ((header, column_type), cell) in headers.iter().zip(&types).zip(row) {
+                obj.insert(header.clone(), coerce_column(cell, *column_type, options));
+            }
+            serde_json::Value::Object(obj)
+        })
+        .collect()
+}
+
+/// Decide each column's type from the first `sample_rows` rows. Mixed columns are text,
+/// so a ZIP column with one "01234" keeps every value a string instead of only some.
+pub fn infer_column_types(
+    columns: usize,
+    rows: &[Vec<String>],
+    options: &CsvParseOptions,
+) -> Vec<ColumnType> {
+    if !options.infer_types {
+        return vec![ColumnType::Text; columns];
+    }
+
+    let sample = &rows[..rows.len().min(options.sample_rows.max(1))];
+    (0..columns)
+        .map(|i| {
+            let mut column_type = None;
+            for cell in sample.iter().filter_map(|row| row.get(i)) {
+                if cell.trim().is_empty() {
+                    continue;
+                }
+                let cell_type = match coerce_cell(cell, options) {
+                    serde_json::Value::Bool(_) => ColumnType::Boolean,
+                    serde_json::Value::Number(n) if n.is_i64() => ColumnType::Integer,
+                    serde_json::Value::Number(_) => ColumnType::Float,
+                    _ => return ColumnType::Text,
+                };
+                column_type = match (column_type, cell_type) {
+                    (None, t) => Some(t),
+                    (Some(a), b) if a == b => Some(a),
+                    (Some(ColumnType::Integer), ColumnType::Float)
+                    | (Some(ColumnType::Float), ColumnType::Integer) => Some(ColumnType::Float),
+                    _ => return ColumnType::Text,
+                };
+            }
+            column_type.unwrap_or(ColumnType::Text)
+        })
+        .collect()
+}
+
+/// Convert a cell in a typed column. Cells past the sample that don't fit the column's
+/// type stay strings, and empty cells in typed columns become null.
+pub fn coerce_column(
+    raw: String,
+    column_type: ColumnType,
+    options: &CsvParseOptions,
+) -> serde_json::Value {
+    if column_type == ColumnType::Text {
+        return serde_json::Value::String(raw);
+    }
+    if raw.trim().is_empty() {
+        return serde_json::Value::Null;
+    }
+    match (column_type, coerce_cell(&raw, options)) {
+        (ColumnType::Boolean, value @ serde_json::Value::Bool(_)) => value,
+        (ColumnType::Integer, serde_json::Value::Number(n)) if n.is_i64() => n.into(),
+        (ColumnType::Float, serde_json::Value::Number(n)) => n
+            .as_f64()
+            .and_then(serde_json::Number::from_f64)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::String(raw)),
+        _ => serde_json::Value::String(raw),
+    }
+}
+
+/// Convert one cell to a JSON value according to the coercion options
+pub fn coerce_cell(raw: &str, options: &CsvParseOptions) -> serde_json::Value {
+    let as_string = || serde_json::Value::String(raw.to_string());
+    if !options.infer_types {
+        return as_string();
+    }
+
+    let value = raw.trim();
+    match value.to_lowercase().as_str() {
+        "true" => return serde_json::Value::Bool(true),
+        "false" => return serde_json::Value::Bool(false),
+        _ => {}
+    }
+
+    if !is_plain_number(value) {
+        return as_string();
+    }
+
+    let digits = value.chars().filter(|c| c.is_ascii_digit()).count();
+    let unsigned = value.trim_start_matches('-');
+    let leading_zero = unsigned.len() > 1 && unsigned.starts_with('0') && !unsigned.starts_with("0.");
+    if options.preserve_leading_zeros && leading_zero {
+        return as_string();
+    }
+    if digits > options.max_numeric_length {
+        return as_string();
+    }
+    if options.preserve_phone_numbers && looks_like_phone(value) {
+        return as_string();
+    }
+
+    if let Ok(i) = value.parse::<i64>() {
+        return serde_json::Value::from(i);
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(serde_json::Number::from_f64)
+        .map(serde_json::Value::Number)
+        .unwrap_or_else(as_string)
+}
+
+/// Plain decimal notation only; "1e5", "inf" and "NaN" stay text
+fn is_plain_number(value: &str) -> bool {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    digits(whole) && fraction.is_none_or(digits)
+}
+
+/// Bare 10-digit (or 11 with a leading 1) numbers are almost always phone numbers
+fn looks_like_phone(value: &str) -> bool {
+    value.chars().all(|c| c.is_ascii_digit())
+        && (value.len() == 10 || (value.len() == 11 && value.starts_with('1')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rows(cells: &[&[&str]]) -> Vec<Vec<String>> {
+        cells
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    fn headers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn coerces_numbers_and_booleans() {
+        let options = CsvParseOptions::default();
+        assert_eq!(coerce_cell("1969", &options), json!(1969));
+        assert_eq!(coerce_cell("-42", &options), json!(-42));
+        assert_eq!(coerce_cell("0", &options), json!(0));
+        assert_eq!(coerce_cell("12.5", &options), json!(12.5));
+        assert_eq!(coerce_cell("0.75", &options), json!(0.75));
+        assert_eq!(coerce_cell(" 7 ", &options), json!(7));
+        assert_eq!(coerce_cell("TRUE", &options), json!(true));
+        assert_eq!(coerce_cell("false", &options), json!(false));
+    }
+
+    #[test]
+    fn keeps_identifiers_and_non_plain_numbers_as_strings() {
+        let options = CsvParseOptions::default();
+        assert_eq!(coerce_cell("01234", &options), json!("01234"));
+        assert_eq!(
+            coerce_cell("12345678901234567", &options),
+            json!("12345678901234567")
+        );
+        assert_eq!(coerce_cell("5551234567", &options), json!("5551234567"));
+        assert_eq!(coerce_cell("15551234567", &options), json!("15551234567"));
+        assert_eq!(coerce_cell("1e5", &options), json!("1e5"));
+        assert_eq!(coerce_cell("NaN", &options), json!("NaN"));
+        assert_eq!(coerce_cell("$12,000", &options), json!("$12,000"));
+
+        let loose = CsvParseOptions {
+            preserve_leading_zeros: false,
+            preserve_phone_numbers: false,
+            ..Default::default()
+        };
+        assert_eq!(coerce_cell("01234", &loose), json!(1234));
+        assert_eq!(coerce_cell("5551234567", &loose), json!(5551234567i64));
+    }
+
+    #[test]
+    fn dates_stay_strings() {
+        let options = CsvParseOptions::default();
+        for date in [
+            "2019-06-01",
+            "06/01/2019",
+            "2019-06-01 14:30:00",
+            "June 1, 1969",
+        ] {
+            assert_eq!(coerce_cell(date, &options), json!(date));
+        }
+
+        let out = rows_to_objects(
+            &headers(&["sold_on"]),
+            rows(&[&["2019-06-01"], &["2020-01-15"]]),
+            &options,
+        );
+        assert_eq!(out[0]["sold_on"], "2019-06-01");
+        assert_eq!(out[1]["sold_on"], "2020-01-15");
+    }
+
+    #[test]
+    fn workbook_dates_read_as_iso_text() {
+        use calamine::{ExcelDateTime, ExcelDateTimeType};
+        let date = Data::DateTime(ExcelDateTime::new(
+            25569.0,
+            ExcelDateTimeType::DateTime,
+            false,
+        ));
+        assert_eq!(cell_text(&date), "1970-01-01");
+        let datetime = Data::DateTime(ExcelDateTime::new(
+            25569.5,
+            ExcelDateTimeType::DateTime,
+            false,
+        ));
+        assert_eq!(cell_text(&datetime), "1970-01-01 12:00:00");
+    }
+
+    #[test]
+    fn empty_cells_are_null_in_typed_columns_and_kept_in_text_columns() {
+        let out = rows_to_objects(
+            &headers(&["year", "notes"]),
+            rows(&[&["1967", "clean"], &["", ""], &["1970"]]),
+            &CsvParseOptions::default(),
+        );
+        assert_eq!(out[0]["year"], 1967);
+        assert_eq!(out[1]["year"], serde_json::Value::Null);
+        assert_eq!(out[1]["notes"], "");
+        assert!(out[2].get("notes").is_none());
+
+        let blank = rows_to_objects(
+            &headers(&["empty"]),
+            rows(&[&[""], &[" "]]),
+            &CsvParseOptions::default(),
+        );
+        assert_eq!(blank[0]["empty"], "");
+        assert_eq!(blank[1]["empty"], " ");
+    }
+
+    #[test]
+    fn mixed_columns_fall_back_to_text() {
+        let out = rows_to_objects(
+            &headers(&["zip", "price", "stock", "sold"]),
+            rows(&[
+                &["12345", "12000", "42", "true"],
+                &["01234", "9500.50", "A-17", "FALSE"],
+            ]),
+            &CsvParseOptions::default(),
+        );
+        // One leading-zero ZIP keeps the whole column as strings
+        assert_eq!(out[0]["zip"], "12345");
+        assert_eq!(out[1]["zip"], "01234");
+        // Integers and floats widen to float
+        assert!(out[0]["price"].is_f64());
+        assert_eq!(out[1]["price"], 9500.5);
+        assert_eq!(out[0]["stock"], "42");
+        assert_eq!(out[1]["sold"], false);
+
+        let types = infer_column_types(
+            2,
+            &rows(&[&["1", "true"], &["2", "7"]]),
+            &Default::default(),
+        );
+        assert_eq!(types, vec![ColumnType::Integer, ColumnType::Text]);
+    }
+
+    #[test]
+    fn values_past_the_sample_that_do_not_fit_stay_strings() {
+        let options = CsvParseOptions {
+            sample_rows: 2,
+            ..Default::default()
+        };
+        let out = rows_to_objects(
+            &headers(&["n"]),
+            rows(&[&["1"], &["2"], &["n/a"]]),
+            &options,
+        );
+        assert_eq!(out[1]["n"], 2);
+        assert_eq!(out[2]["n"], "n/a");
+    }
+
+    #[test]
+    fn inference_off_keeps_every_cell_a_string() {
+        let options = CsvParseOptions {
+            infer_types: false,
+            ..Default::default()
+        };
+        let out = rows_to_objects(
+            &headers(&["year", "sold"]),
+            rows(&[&["1967", "true"]]),
+            &options,
+        );
+        assert_eq!(out[0]["year"], "1967");
+        assert_eq!(out[0]["sold"], "true");
+    }
+
+    #[test]
+    fn streams_longer_than_the_sniff_sample_decode_whole() {
+        let mut csv = "\u{feff}make;model\n".to_string();
+        while csv.len() < STREAM_SNIFF_BYTES * 2 {
+            csv.push_str("Citroën;DS\n");
+        }
+        let (mut decoded, delimiter) = decode_csv_stream(csv.as_bytes(), None).unwrap();
+        assert_eq!(delimiter, b';');
+        let mut text = String::new();
+        decoded.read_to_string(&mut text).unwrap();
+        assert_eq!(text, csv.trim_start_matches('\u{feff}'));
+    }
+}
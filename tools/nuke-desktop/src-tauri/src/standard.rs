@@ -0,0 +1,255 @@
+// Standard vehicle export - extracted data in vPIC-aligned field names for other tools
+
+use crate::documents::{ExtractedData, ExtractionResult};
+use crate::error::NukeError;
+use serde::{Deserialize, Serialize};
+
+pub const STANDARD_SCHEMA: &str = "nuke-standard-vehicle";
+pub const STANDARD_SCHEMA_VERSION: u32 = 1;
+
+// Common spellings -> vPIC Make_Name
+const VPIC_MAKES: &[(&str, &str)] = &[
+    ("chevrolet", "CHEVROLET"),
+    ("chevy", "CHEVROLET"),
+    ("ford", "FORD"),
+    ("dodge", "DODGE"),
+    ("gmc", "GMC"),
+    ("toyota", "TOYOTA"),
+    ("honda", "HONDA"),
+    ("bmw", "BMW"),
+    ("mercedes", "MERCEDES-BENZ"),
+    ("mercedes-benz", "MERCEDES-BENZ"),
+    ("mercedes benz", "MERCEDES-BENZ"),
+    ("porsche", "PORSCHE"),
+    ("ferrari", "FERRARI"),
+    ("lamborghini", "LAMBORGHINI"),
+    ("audi", "AUDI"),
+    ("volkswagen", "VOLKSWAGEN"),
+    ("vw", "VOLKSWAGEN"),
+    ("jeep", "JEEP"),
+    ("ram", "RAM"),
+    ("nissan", "NISSAN"),
+    ("datsun", "DATSUN"),
+    ("mazda", "MAZDA"),
+    ("subaru", "SUBARU"),
+    ("land rover", "LAND ROVER"),
+    ("pontiac", "PONTIAC"),
+    ("oldsmobile", "OLDSMOBILE"),
+    ("buick", "BUICK"),
+    ("cadillac", "CADILLAC"),
+    ("lincoln", "LINCOLN"),
+    ("plymouth", "PLYMOUTH"),
+    ("international", "INTERNATIONAL"),
+];
+
+// Model spellings that differ from vPIC Model_Name
+const VPIC_MODELS: &[(&str, &str)] = &[
+    ("f150", "F-150"),
+    ("f-150", "F-150"),
+    ("f250", "F-250"),
+    ("f-250", "F-250"),
+    ("4runner", "4Runner"),
+    ("c10", "C10"),
+    ("k10", "K10"),
+    ("k5", "K5 Blazer"),
+    ("911", "911"),
+];
+
+/// One vehicle in the standard schema; names follow vPIC's DecodeVinValues variables
+/// where vPIC has one (VIN, ModelYear, Make, Model), and the same style where it doesn't
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct StandardVehicle {
+    #[serde(rename = "VIN")]
+    pub vin: Option<String>,
+    pub model_year: Option<u32>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub exterior_color: Option<String>,
+    /// Odometer reading in the document's units, digits only
+    pub odometer: Option<u64>,
+    pub license_plate: Option<String>,
+    pub document_type: Option<String>,
+    pub document_date: Option<String>,
+    pub price: Option<f64>,
+    pub source_file: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StandardExport {
+    pub schema: String,
+    pub schema_version: u32,
+    pub vehicles: Vec<StandardVehicle>,
+}
+
+/// Write extraction results as JSON in the standard vehicle schema
+#[tauri::command]
+pub async fn export_standard(
+    results: Vec<ExtractionResult>,
+    out_path: String,
+) -> Result<usize, NukeError> {
+    let export = to_standard(&results);
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| NukeError::Other(format!("Failed to serialize export: {}", e)))?;
+    std::fs::write(&out_path, json)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to write export: {}", e)))?;
+    Ok(export.vehicles.len())
+}
+
+/// Map results into the standard schema; inventory sheets contribute one vehicle per row
+pub fn to_standard(results: &[ExtractionResult]) -> StandardExport {
+    let mut vehicles = Vec::new();
+    for result in results {
+        if result.inventory.is_empty() {
+            vehicles.push(standard_vehicle(&result.data, result));
+        } else {
+            for row in &result.inventory {
+                vehicles.push(standard_vehicle(row, result));
+            }
+        }
+    }
+
+    StandardExport {
+        schema: STANDARD_SCHEMA.to_string(),
+        schema_version: STANDARD_SCHEMA_VERSION,
+        vehicles,
+    }
+}
+
+fn standard_vehicle(data: &ExtractedData, result: &ExtractionResult) -> StandardVehicle {
+    let digits = |s: &String| s.chars().filter(|c| c.is_ascii_digit()).collect::<String>();
+    StandardVehicle {
+        vin: data.vin.clone(),
+        model_year: data.year.as_ref().and_then(|y| digits(y).parse().ok()),
+        make: data.make.as_deref().map(vpic_make),
+        model: data.model.as_deref().map(vpic_model),
+        exterior_color: data.color.clone(),
+        odometer: data.mileage.as_ref().and_then(|m| digits(m).parse().ok()),
+        license_plate: data.plate_number.clone(),
+        document_type: data.document_type.clone(),
+        document_date: data.date.clone(),
+        price: data.price.as_deref().and_then(crate::doctypes::parse_money),
+        source_file: result.path.clone(),
+        confidence: result.confidence,
+    }
+}
+
+/// Canonical vPIC make name, or the input uppercased as vPIC stores makes
+pub fn vpic_make(make: &str) -> String {
+    let lower = make.trim().to_lowercase();
+    VPIC_MAKES
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, canonical)| canonical.to_string())
+        .unwrap_or_else(|| make.trim().to_uppercase())
+}
+
+/// Canonical vPIC model name where we know it differs from common spelling
+pub fn vpic_model(model: &str) -> String {
+    let lower = model.trim().to_lowercase();
+    VPIC_MODELS
+        .iter()
+        .find(|(alias, _)| *alias == lower)
+        .map(|(_, canonical)| canonical.to_string())
+        .unwrap_or_else(|| model.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(data: serde_json::Value, inventory: serde_json::Value) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": "/scans/a.jpg",
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "photo",
+            "data": data,
+            "inventory": inventory,
+            "confidence": 0.7,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn uses_vpic_field_names_and_canonical_values() {
+        let data = json!({
+            "make": "Chevy",
+            "model": "f150",
+            "year": "1972",
+            "mileage": "45,120 mi",
+            "price": "$1,200",
+            "color": "Red",
+        });
+        let value = serde_json::to_value(to_standard(&[result(data, json!([]))])).unwrap();
+        assert_eq!(value["schema"], STANDARD_SCHEMA);
+        assert_eq!(value["schema_version"], STANDARD_SCHEMA_VERSION);
+
+        let vehicle = value["vehicles"][0].as_object().unwrap();
+        let mut fields: Vec<&str> = vehicle.keys().map(|k| k.as_str()).collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                "Confidence",
+                "DocumentDate",
+                "DocumentType",
+                "ExteriorColor",
+                "LicensePlate",
+                "Make",
+                "Model",
+                "ModelYear",
+                "Odometer",
+                "Price",
+                "SourceFile",
+                "VIN",
+            ]
+        );
+        assert_eq!(vehicle["Make"], "CHEVROLET");
+        assert_eq!(vehicle["Model"], "F-150");
+        assert_eq!(vehicle["ModelYear"], 1972);
+        assert_eq!(vehicle["Odometer"], 45120);
+        assert_eq!(vehicle["Price"], 1200.0);
+        assert_eq!(vehicle["VIN"], serde_json::Value::Null);
+        assert_eq!(vehicle["SourceFile"], "/scans/a.jpg");
+    }
+
+    #[test]
+    fn unknown_makes_and_models_pass_through() {
+        assert_eq!(vpic_make(" Mercedes Benz "), "MERCEDES-BENZ");
+        assert_eq!(vpic_make("Studebaker"), "STUDEBAKER");
+        assert_eq!(vpic_model("4RUNNER"), "4Runner");
+        assert_eq!(vpic_model(" Bel Air "), "Bel Air");
+    }
+
+    #[test]
+    fn inventory_sheets_give_one_vehicle_per_row() {
+        let inventory =
+            json!([{ "make": "Ford", "year": "1985" }, { "make": "vw", "year": "1967" }]);
+        let export = to_standard(&[result(json!({ "document_type": "inventory" }), inventory)]);
+        assert_eq!(export.vehicles.len(), 2);
+        assert_eq!(export.vehicles[0].make.as_deref(), Some("FORD"));
+        assert_eq!(export.vehicles[1].make.as_deref(), Some("VOLKSWAGEN"));
+        assert!(export
+            .vehicles
+            .iter()
+            .all(|v| v.source_file == "/scans/a.jpg"));
+    }
+
+    #[tokio::test]
+    async fn export_writes_the_vehicles_to_disk() {
+        let path = std::env::temp_dir().join(format!("nuke-standard-{}.json", std::process::id()));
+        let results = vec![result(json!({ "make": "Ford" }), json!([]))];
+        let written = export_standard(results, path.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(written, 1);
+        let export: StandardExport =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(export.vehicles[0].make.as_deref(), Some("FORD"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,561 @@
+// Supabase sync - push document extractions into the import queue
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use crate::events::{self, AppEvent};
+use crate::privacy;
+use crate::progress::{self, Stage};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+// Columns `import_queue_row` writes
+const IMPORT_QUEUE_COLUMNS: &[&str] = &["url", "source", "priority", "metadata"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupabaseConfig {
+    pub url: String,
+    pub anon_key: String,
+}
+
+/// Store the Supabase project URL and key used by sync commands
+#[tauri::command]
+pub async fn configure_supabase(
+    state: tauri::State<'_, AppState>,
+    url: String,
+    anon_key: String,
+) -> Result<(), NukeError> {
+    *state.supabase.lock().unwrap() = Some(SupabaseConfig {
+        url: url.trim_end_matches('/').to_string(),
+        anon_key,
+    });
+    Ok(())
+}
+
+/// Outcome of `test_supabase_connection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    Ok,
+    /// The key was rejected
+    Unauthorized,
+    /// No response: wrong URL, offline, or the project is paused
+    Unreachable,
+    /// Supabase answered with some other error, such as a missing `import_queue` table
+    Unexpected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    pub status: ConnectionStatus,
+    /// HTTP status Supabase answered with, if it answered
+    pub http_status: Option<u16>,
+    pub message: String,
+}
+
+/// Check the stored URL and key with a HEAD on `import_queue`, the table sync writes to
+#[tauri::command]
+pub async fn test_supabase_connection(
+    state: tauri::State<'_, AppState>,
+) -> Result<ConnectionReport, NukeError> {
+    let config = state
+        .supabase
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(NukeError::SupabaseNotConfigured)?;
+
+    let response = state
+        .http
+        .head(format!(
+            "{}/rest/v1/import_queue?select=url&limit=1",
+            config.url
+        ))
+        .header("apikey", &config.anon_key)
+        .header("Authorization", format!("Bearer {}", config.anon_key))
+        .send()
+        .await;
+    let report = match response {
+        Ok(resp) => connection_report(resp.status()),
+        Err(e) => ConnectionReport {
+            status: ConnectionStatus::Unreachable,
+            http_status: None,
+            message: format!("Couldn't reach {}: {}", config.url, e),
+        },
+    };
+    tracing::info!(
+        status = ?report.status,
+        http_status = ?report.http_status,
+        "supabase connection tested"
+    );
+    Ok(report)
+}
+
+/// What a response status says about the configured URL and key
+pub fn connection_report(status: reqwest::StatusCode) -> ConnectionReport {
+    let (connection, message) = match status.as_u16() {
+        _ if status.is_success() => (
+            ConnectionStatus::Ok,
+            "Connected, and the key can read import_queue".to_string(),
+        ),
+        401 | 403 => (
+            ConnectionStatus::Unauthorized,
+            "Supabase rejected the API key".to_string(),
+        ),
+        404 => (
+            ConnectionStatus::Unexpected,
+            "No import_queue table here; check the project URL".to_string(),
+        ),
+        _ => (
+            ConnectionStatus::Unexpected,
+            format!("Supabase returned {}", status),
+        ),
+    };
+    ConnectionReport {
+        status: connection,
+        http_status: Some(status.as_u16()),
+        message,
+    }
+}
+
+/// Whether the configured project has the `import_queue` columns sync writes to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaReport {
+    pub ok: bool,
+    pub table_found: bool,
+    pub missing_columns: Vec<String>,
+    pub message: String,
+}
+
+/// Check the `import_queue` table through PostgREST's OpenAPI description before syncing
+#[tauri::command]
+pub async fn verify_supabase_schema(
+    state: tauri::State<'_, AppState>,
+) -> Result<SchemaReport, NukeError> {
+    let config = state
+        .supabase
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(NukeError::SupabaseNotConfigured)?;
+
+    let response = state
+        .http
+        .get(format!("{}/rest/v1/", config.url))
+        .header("apikey", &config.anon_key)
+        .header("Authorization", format!("Bearer {}", config.anon_key))
+        .header("Accept", "application/openapi+json")
+        .send()
+        .await
+        .map_err(|e| NukeError::Network(format!("Failed to reach Supabase: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(NukeError::Network(format!(
+            "Supabase schema request returned {}",
+            response.status()
+        )));
+    }
+    let openapi: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| NukeError::JsonParse(format!("Failed to parse schema: {}", e)))?;
+
+    Ok(check_schema(&openapi))
+}
+
+/// Compare the OpenAPI `definitions.import_queue` properties against the columns we write
+pub fn check_schema(openapi: &serde_json::Value) -> SchemaReport {
+    let properties = openapi
+        .get("definitions")
+        .and_then(|d| d.get("import_queue"))
+        .and_then(|t| t.get("properties"))
+        .and_then(|p| p.as_object());
+
+    let Some(properties) = properties else {
+        return SchemaReport {
+            ok: false,
+            table_found: false,
+            missing_columns: IMPORT_QUEUE_COLUMNS.iter().map(|c| c.to_string()).collect(),
+            message: "Table import_queue wasn't found, or this key can't see it".to_string(),
+        };
+    };
+
+    let missing_columns: Vec<String> = IMPORT_QUEUE_COLUMNS
+        .iter()
+        .filter(|c| !properties.contains_key(**c))
+        .map(|c| c.to_string())
+        .collect();
+    let message = if missing_columns.is_empty() {
+        "import_queue has every column sync needs".to_string()
+    } else {
+        format!(
+            "import_queue is missing columns: {}",
+            missing_columns.join(", ")
+        )
+    };
+
+    SchemaReport {
+        ok: missing_columns.is_empty(),
+        table_found: true,
+        missing_columns,
+        message,
+    }
+}
+
+/// Queue document extractions in Supabase's `import_queue` table, leaving out results below
+/// `min_confidence`. With `dry_run` the rows that would be inserted come back instead.
+///
+/// Each result goes into the local sync queue before it's sent and stays there until
+/// Supabase accepts it, so `retry_pending_sync` can finish an interrupted sync.
+#[tauri::command]
+#[tracing::instrument(skip_all, fields(results = results.len(), dry_run = ?dry_run))]
+pub async fn sync_to_supabase(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    results: Vec<ExtractionResult>,
+    include_ocr: Option<bool>,
+    dry_run: Option<bool>,
+    min_confidence: Option<f32>,
+) -> Result<serde_json::Value, NukeError> {
+    let include_ocr = include_ocr.unwrap_or(false);
+    let dry_run = dry_run.unwrap_or(false);
+    let min_confidence = min_confidence.unwrap_or(0.0);
+    let config = state
+        .supabase
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(NukeError::SupabaseNotConfigured)?;
+
+    let client = state.http.clone();
+    let mut tally = SyncTally::default();
+
+    let privacy = state.privacy.lock().unwrap().clone();
+    let floors = state.confidence_floors.lock().unwrap().clone();
+    let mut below_floor = Vec::new();
+    let mut payloads = Vec::new();
+    let mut without_vin = 0;
+    let mut low_confidence = 0;
+    for result in &results {
+        if let Some(below) = floors.check(result) {
+            below_floor.push(format!("{}: {}", result.path, below.message()));
+            continue;
+        }
+        if result.confidence < min_confidence {
+            low_confidence += 1;
+            continue;
+        }
+        if result.data.vin.is_none() {
+            without_vin += 1;
+        }
+        // Results may predate a settings change, so redact again on the way out
+        let mut result = result.clone();
+        privacy::redact_result(&privacy, &mut result);
+        let row = import_queue_row(&result, include_ocr);
+        if dry_run {
+            payloads.push(row);
+            continue;
+        }
+
+        // Queued already redacted, so nothing private lands on disk either
+        let queued = state.sync_queue.enqueue(&result, include_ocr);
+        let error = post_row(&client, &config, &row).await;
+        match queued {
+            Ok(id) => mark_queued(&state, id, error.as_deref()),
+            Err(e) => tracing::warn!(path = %result.path, error = %e, "sync not queued for retry"),
+        }
+        tally.record(Some(&app), &state, "sync_to_supabase", &result.path, error);
+    }
+
+    if dry_run {
+        return Ok(serde_json::json!({
+            "dry_run": true,
+            "would_sync": payloads.len(),
+            "without_vin": without_vin,
+            "below_floor": below_floor,
+            "low_confidence": low_confidence,
+            "payloads": payloads
+        }));
+    }
+
+    tally.finish(Some(&app), &state);
+    tracing::info!(
+        synced = tally.synced,
+        failed = tally.failed,
+        below_floor = below_floor.len(),
+        low_confidence,
+        without_vin,
+        "supabase sync finished"
+    );
+
+    Ok(serde_json::json!({
+        "synced": tally.synced,
+        "failed": tally.failed,
+        "below_floor": below_floor,
+        "low_confidence": low_confidence,
+        "without_vin": without_vin,
+        "pending": state.sync_queue.pending_count(),
+        "errors": tally.errors
+    }))
+}
+
+/// Send everything in the local sync queue that Supabase hasn't accepted yet
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn retry_pending_sync(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Value, NukeError> {
+    let config = state
+        .supabase
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or(NukeError::SupabaseNotConfigured)?;
+    let pending = state.sync_queue.pending()?;
+    tracing::info!(pending = pending.len(), "retrying queued sync");
+
+    let client = state.http.clone();
+    let privacy = state.privacy.lock().unwrap().clone();
+    let mut tally = SyncTally::default();
+    for entry in pending {
+        let mut result = entry.result;
+        privacy::redact_result(&privacy, &mut result);
+        let row = import_queue_row(&result, entry.include_ocr);
+        let error = post_row(&client, &config, &row).await;
+        mark_queued(&state, entry.id, error.as_deref());
+        tally.record(
+            Some(&app),
+            &state,
+            "retry_pending_sync",
+            &result.path,
+            error,
+        );
+    }
+    tally.finish(Some(&app), &state);
+    tracing::info!(
+        synced = tally.synced,
+        failed = tally.failed,
+        "queued sync retried"
+    );
+
+    Ok(serde_json::json!({
+        "synced": tally.synced,
+        "failed": tally.failed,
+        "pending": state.sync_queue.pending_count(),
+        "errors": tally.errors
+    }))
+}
+
+/// Insert one `import_queue` row; the error, if Supabase didn't take it
+async fn post_row(
+    client: &reqwest::Client,
+    config: &SupabaseConfig,
+    row: &serde_json::Value,
+) -> Option<String> {
+    let response = client
+        .post(format!("{}/rest/v1/import_queue", config.url))
+        .header("apikey", &config.anon_key)
+        .header("Authorization", format!("Bearer {}", config.anon_key))
+        .header("Prefer", "return=minimal")
+        .json(row)
+        .send()
+        .await;
+
+    match response {
+        Ok(resp) if resp.status().is_success() => None,
+        Ok(resp) => Some(resp.status().to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+/// Record a send in the sync queue; a queue write failing shouldn't fail the sync itself
+fn mark_queued(state: &AppState, id: i64, error: Option<&str>) {
+    let marked = match error {
+        None => state.sync_queue.mark_synced(id),
+        Some(error) => state.sync_queue.mark_failed(id, error),
+    };
+    if let Err(e) = marked {
+        tracing::warn!(id, error = %e, "sync queue not updated");
+    }
+}
+
+/// Running totals of a Supabase sync, with progress and events as it goes
+#[derive(Default)]
+pub struct SyncTally {
+    synced: usize,
+    failed: usize,
+    errors: Vec<String>,
+}
+
+impl SyncTally {
+    pub fn record(
+        &mut self,
+        app: Option<&AppHandle>,
+        state: &AppState,
+        operation: &str,
+        path: &str,
+        error: Option<String>,
+    ) {
+        match error {
+            None => {
+                self.synced += 1;
+                progress::advance(app, &state.progress, Stage::Synced, 1);
+            }
+            Some(error) => {
+                tracing::warn!(operation, path, %error, "row not synced");
+                self.failed += 1;
+                self.errors.push(format!("{}: {}", path, error));
+                progress::advance(app, &state.progress, Stage::Failed, 1);
+                events::emit(
+                    app,
+                    &state.events,
+                    AppEvent::Error {
+                        operation: operation.to_string(),
+                        path: Some(path.to_string()),
+                        message: error,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn finish(&self, app: Option<&AppHandle>, state: &AppState) {
+        events::emit(
+            app,
+            &state.events,
+            AppEvent::SyncBatchDone {
+                target: "supabase".to_string(),
+                synced: self.synced,
+                failed: self.failed,
+            },
+        );
+    }
+}
+
+/// One `import_queue` row; the full extraction rides along in metadata for provenance
+fn import_queue_row(result: &ExtractionResult, include_ocr: bool) -> serde_json::Value {
+    let mut row = serde_json::json!({
+        "url": format!("file://{}", result.path),
+        "source": "nuke-desktop",
+        "priority": 0,
+        "metadata": {
+            "extracted": result.data,
+            "document_kind": result.document_kind,
+            "confidence": result.confidence,
+            "model": result.model,
+            "order_index": result.order_index,
+            "condition": result.condition,
+        }
+    });
+    // OCR text can be large; only send it when asked
+    if let (true, Some(ocr)) = (include_ocr, &result.ocr) {
+        row["metadata"]["ocr_text"] = serde_json::json!(ocr.text);
+        row["metadata"]["ocr_mean_confidence"] = serde_json::json!(ocr.mean_confidence);
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// The shape of PostgREST's OpenAPI description, trimmed to the one table
+    fn introspection(columns: &[&str]) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = columns
+            .iter()
+            .map(|c| (c.to_string(), json!({ "type": "string" })))
+            .collect();
+        json!({
+            "swagger": "2.0",
+            "definitions": { "import_queue": { "type": "object", "properties": properties } },
+        })
+    }
+
+    #[test]
+    fn schema_with_every_column_passes() {
+        let report = check_schema(&introspection(&[
+            "id",
+            "url",
+            "source",
+            "priority",
+            "metadata",
+            "created_at",
+        ]));
+        assert!(report.ok);
+        assert!(report.table_found);
+        assert!(report.missing_columns.is_empty());
+    }
+
+    #[test]
+    fn missing_columns_are_named() {
+        let report = check_schema(&introspection(&["id", "url", "source"]));
+        assert!(!report.ok);
+        assert!(report.table_found);
+        assert_eq!(report.missing_columns, vec!["priority", "metadata"]);
+        assert_eq!(
+            report.message,
+            "import_queue is missing columns: priority, metadata"
+        );
+    }
+
+    #[test]
+    fn a_missing_table_fails_with_every_column() {
+        let report = check_schema(&json!({ "definitions": { "vehicles": { "properties": {} } } }));
+        assert!(!report.ok);
+        assert!(!report.table_found);
+        assert_eq!(report.missing_columns, IMPORT_QUEUE_COLUMNS);
+        assert!(!check_schema(&json!({})).table_found);
+    }
+
+    #[test]
+    fn rows_only_write_the_checked_columns() {
+        let result: ExtractionResult = serde_json::from_value(json!({
+            "path": "/title.jpg",
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": 0.9,
+            "raw_response": "",
+        }))
+        .unwrap();
+        let row = import_queue_row(&result, false);
+        let mut columns: Vec<&str> = row
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        let mut expected = IMPORT_QUEUE_COLUMNS.to_vec();
+        columns.sort();
+        expected.sort();
+        assert_eq!(columns, expected);
+    }
+
+    #[test]
+    fn ocr_text_is_only_synced_when_asked() {
+        let mut result: ExtractionResult = serde_json::from_value(json!({
+            "path": "/title.jpg",
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": {},
+            "confidence": 0.9,
+            "raw_response": "",
+        }))
+        .unwrap();
+        result.ocr = Some(crate::ocr::OcrOutput {
+            text: "CERTIFICATE OF TITLE".to_string(),
+            word_count: 3,
+            mean_confidence: 93.0,
+            ..Default::default()
+        });
+        assert!(import_queue_row(&result, false)["metadata"]
+            .get("ocr_text")
+            .is_none());
+        let row = import_queue_row(&result, true);
+        assert_eq!(row["metadata"]["ocr_text"], "CERTIFICATE OF TITLE");
+        assert_eq!(row["metadata"]["ocr_mean_confidence"], 93.0);
+    }
+}
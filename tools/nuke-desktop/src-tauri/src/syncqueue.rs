@@ -0,0 +1,265 @@
+// Offline sync queue - Supabase rows wait in SQLite until the server accepts them
+
+use crate::documents::ExtractionResult;
+use crate::error::NukeError;
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// One queued extraction and how it should be sent
+#[derive(Debug, Clone)]
+pub struct PendingSync {
+    pub id: i64,
+    pub result: ExtractionResult,
+    pub include_ocr: bool,
+}
+
+/// Extractions waiting to reach Supabase, kept on disk so a dropped connection or a
+/// restart doesn't lose them
+pub struct SyncQueue {
+    conn: Mutex<Connection>,
+}
+
+impl SyncQueue {
+    /// Open the queue database, creating it if it's missing
+    pub fn open(path: &Path) -> Result<Self, NukeError> {
+        let conn = Connection::open(path)
+            .map_err(|e| NukeError::FileWrite(format!("Failed to open sync queue: {}", e)))?;
+        create_schema(&conn)?;
+        Ok(SyncQueue {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// A queue that lasts only as long as the app, for when the database can't be opened
+    pub fn in_memory() -> Self {
+        let conn = Connection::open_in_memory().expect("in-memory SQLite");
+        create_schema(&conn).expect("sync queue schema");
+        SyncQueue {
+            conn: Mutex::new(conn),
+        }
+    }
+
+    /// Queue a result before it's sent; a still-pending entry for the same file is replaced
+    pub fn enqueue(&self, result: &ExtractionResult, include_ocr: bool) -> Result<i64, NukeError> {
+        let json = serde_json::to_string(result).map_err(|e| {
+            NukeError::JsonParse(format!("Failed to serialize queued result: {}", e))
+        })?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM sync_queue WHERE path = ?1 AND synced_at IS NULL",
+            params![result.path],
+        )
+        .map_err(|e| NukeError::FileWrite(format!("Failed to update sync queue: {}", e)))?;
+        conn.execute(
+            "INSERT INTO sync_queue (path, result, include_ocr, queued_at) VALUES (?1, ?2, ?3, ?4)",
+            params![result.path, json, include_ocr, now()],
+        )
+        .map_err(|e| NukeError::FileWrite(format!("Failed to update sync queue: {}", e)))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Entries not yet accepted by the server, oldest first
+    pub fn pending(&self) -> Result<Vec<PendingSync>, NukeError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn
+            .prepare(
+                "SELECT id, result, include_ocr FROM sync_queue
+                 WHERE synced_at IS NULL AND dropped_at IS NULL ORDER BY id",
+            )
+            .map_err(read_error)?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, bool>(2)?,
+                ))
+            })
+            .map_err(read_error)?;
+
+        let mut pending = Vec::new();
+        let mut unreadable = Vec::new();
+        for row in rows {
+            let (id, json, include_ocr) = row.map_err(read_error)?;
+            match serde_json::from_str(&json) {
+                Ok(result) => pending.push(PendingSync {
+                    id,
+                    result,
+                    include_ocr,
+                }),
+                Err(e) => unreadable.push((id, e.to_string())),
+            }
+        }
+        drop(statement);
+
+        // A row written by an older build may no longer deserialize; it can never be sent,
+        // so it's dropped from the queue but kept for inspection
+        for (id, error) in unreadable {
+            tracing::warn!(id, %error, "dropping unreadable sync queue entry");
+            conn.execute(
+                "UPDATE sync_queue SET dropped_at = ?2, last_error = ?3 WHERE id = ?1",
+                params![id, now(), format!("Unreadable entry: {}", error)],
+            )
+            .map_err(|e| NukeError::FileWrite(format!("Failed to update sync queue: {}", e)))?;
+        }
+        Ok(pending)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COUNT(*) FROM sync_queue WHERE synced_at IS NULL AND dropped_at IS NULL",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap_or(0) as usize
+    }
+
+    pub fn mark_synced(&self, id: i64) -> Result<(), NukeError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE sync_queue SET synced_at = ?2, attempts = attempts + 1, last_error = NULL
+                 WHERE id = ?1",
+                params![id, now()],
+            )
+            .map(|_| ())
+            .map_err(|e| NukeError::FileWrite(format!("Failed to update sync queue: {}", e)))
+    }
+
+    /// Leave an entry pending, noting why this attempt failed
+    pub fn mark_failed(&self, id: i64, error: &str) -> Result<(), NukeError> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE sync_queue SET attempts = attempts + 1, last_error = ?2 WHERE id = ?1",
+                params![id, error],
+            )
+            .map(|_| ())
+            .map_err(|e| NukeError::FileWrite(format!("Failed to update sync queue: {}", e)))
+    }
+}
+
+fn read_error(e: rusqlite::Error) -> NukeError {
+    NukeError::FileRead(format!("Failed to read sync queue: {}", e))
+}
+
+fn create_schema(conn: &Connection) -> Result<(), NukeError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sync_queue (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            result TEXT NOT NULL,
+            include_ocr INTEGER NOT NULL DEFAULT 0,
+            queued_at TEXT NOT NULL,
+            synced_at TEXT,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            -- Set when the entry can't be read back and will never be sent
+            dropped_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS sync_queue_pending ON sync_queue (synced_at, dropped_at, id);",
+    )
+    .map_err(|e| NukeError::FileWrite(format!("Failed to create sync queue: {}", e)))
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(path: &str, vin: &str) -> ExtractionResult {
+        serde_json::from_value(serde_json::json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": { "vin": vin },
+            "confidence": 0.9,
+            "raw_response": ""
+        }))
+        .unwrap()
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("nuke-{}-{}.sqlite", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn pending_entries_survive_a_reopen() {
+        let path = temp_path("queue-reopen");
+        {
+            let queue = SyncQueue::open(&path).unwrap();
+            let a = queue.enqueue(&result("/a.jpg", "A"), false).unwrap();
+            let b = queue.enqueue(&result("/b.jpg", "B"), true).unwrap();
+            queue.enqueue(&result("/c.jpg", "C"), false).unwrap();
+            queue.mark_synced(a).unwrap();
+            queue.mark_failed(b, "503 Service Unavailable").unwrap();
+            // Queueing a still-pending file again replaces its entry
+            queue.enqueue(&result("/c.jpg", "C2"), false).unwrap();
+            assert_eq!(queue.pending_count(), 2);
+        }
+
+        let queue = SyncQueue::open(&path).unwrap();
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].result.path, "/b.jpg");
+        assert!(pending[0].include_ocr);
+        assert_eq!(pending[1].result.data.vin.as_deref(), Some("C2"));
+        // A synced file can be queued again
+        queue.enqueue(&result("/a.jpg", "A"), false).unwrap();
+        assert_eq!(queue.pending_count(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unreadable_entries_are_dropped_from_the_pending_count() {
+        let queue = SyncQueue::in_memory();
+        queue.enqueue(&result("/a.jpg", "A"), false).unwrap();
+        let stale = queue.enqueue(&result("/b.jpg", "B"), false).unwrap();
+        queue
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE sync_queue SET result = '{\"written_by\": \"an older build\"}' WHERE id = ?1",
+                params![stale],
+            )
+            .unwrap();
+        assert_eq!(queue.pending_count(), 2);
+
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(queue.pending_count(), 1);
+        let last_error: String = queue
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT last_error FROM sync_queue WHERE id = ?1",
+                params![stale],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(last_error.starts_with("Unreadable entry"));
+    }
+
+    #[test]
+    fn an_unopenable_database_is_an_error() {
+        let error = SyncQueue::open(Path::new("/nonexistent/dir/queue.sqlite"))
+            .err()
+            .unwrap();
+        assert!(matches!(error, NukeError::FileWrite(_)));
+    }
+}
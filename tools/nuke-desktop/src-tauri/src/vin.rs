@@ -0,0 +1,892 @@
+// VIN validation and cross-checking
+
+use crate::error::NukeError;
+use crate::AppState;
+use chrono::Datelike;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// Check digit weights by position (ISO 3779)
+const VIN_WEIGHTS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+
+/// Validate a 17-character VIN's check digit (position 9)
+pub fn validate_vin(vin: &str) -> bool {
+    let vin = vin.trim().to_uppercase();
+    if vin.len() != 17 {
+        return false;
+    }
+
+    let mut sum = 0;
+    for (i, c) in vin.chars().enumerate() {
+        let value = match transliterate(c) {
+            Some(v) => v,
+            None => return false,
+        };
+        sum += value * VIN_WEIGHTS[i];
+    }
+
+    let expected = match sum % 11 {
+        10 => 'X',
+        n => char::from_digit(n, 10).unwrap_or('?'),
+    };
+    vin.chars().nth(8) == Some(expected)
+}
+
+/// Numeric value of a VIN character; I, O and Q are never valid
+fn transliterate(c: char) -> Option<u32> {
+    match c {
+        '0'..='9' => c.to_digit(10),
+        'A' | 'J' => Some(1),
+        'B' | 'K' | 'S' => Some(2),
+        'C' | 'L' | 'T' => Some(3),
+        'D' | 'M' | 'U' => Some(4),
+        'E' | 'N' | 'V' => Some(5),
+        'F' | 'W' => Some(6),
+        'G' | 'P' | 'X' => Some(7),
+        'H' | 'Y' => Some(8),
+        'R' | 'Z' => Some(9),
+        _ => None,
+    }
+}
+
+/// Find 17-character VIN candidates in free text
+pub fn find_vins(text: &str) -> Vec<String> {
+    let vin_regex = match Regex::new(r"\b[A-HJ-NPR-Z0-9]{17}\b") {
+        Ok(r) => r,
+        Err(_) => return Vec::new(),
+    };
+    let upper = text.to_uppercase();
+    let mut vins: Vec<String> = Vec::new();
+    for m in vin_regex.find_iter(&upper) {
+        if !vins.iter().any(|v| v == m.as_str()) {
+            vins.push(m.as_str().to_string());
+        }
+    }
+    vins
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VinSourceKind {
+    Barcode,
+    Ocr,
+    Vision,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinSource {
+    pub kind: VinSourceKind,
+    pub vin: String,
+    pub checksum_valid: bool,
+}
+
+impl VinSource {
+    pub fn new(kind: VinSourceKind, vin: &str) -> Self {
+        let vin = vin.trim().to_uppercase();
+        VinSource {
+            kind,
+            checksum_valid: validate_vin(&vin),
+            vin,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VinStatus {
+    /// Two or more sources agree and the check digit validates
+    Confirmed,
+    /// Sources disagree; needs a human
+    Conflict,
+    /// Only one source produced a VIN
+    Single,
+    /// Sources agree but the check digit fails, or nothing was read
+    Unverified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinVerification {
+    pub status: VinStatus,
+    pub vin: Option<String>,
+    pub sources: Vec<VinSource>,
+}
+
+/// Cross-check VINs read from the same document by different methods
+pub fn cross_check_vins(sources: Vec<VinSource>) -> VinVerification {
+    let mut distinct: Vec<&str> = Vec::new();
+    for source in &sources {
+        if !distinct.contains(&source.vin.as_str()) {
+            distinct.push(&source.vin);
+        }
+    }
+
+    // Barcodes are the most reliable reader, then OCR, then the vision model
+    let best = sources
+        .iter()
+        .filter(|s| s.checksum_valid)
+        .min_by_key(|s| match s.kind {
+            VinSourceKind::Barcode => 0,
+            VinSourceKind::Ocr => 1,
+            VinSourceKind::Vision => 2,
+        })
+        .or_else(|| sources.first())
+        .map(|s| s.vin.clone());
+
+    let status = match distinct.len() {
+        0 => VinStatus::Unverified,
+        1 if sources.len() == 1 => VinStatus::Single,
+        1 if sources.iter().all(|s| s.checksum_valid) => VinStatus::Confirmed,
+        1 => VinStatus::Unverified,
+        _ => VinStatus::Conflict,
+    };
+
+    VinVerification {
+        status,
+        vin: best,
+        sources,
+    }
+}
+
+/// A VIN-like identifier reduced to a comparable form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizedVin {
+    pub vin: String,
+    /// Pre-1981 serials are 5-16 characters with no check digit
+    pub pre_1981: bool,
+    pub checksum_valid: bool,
+}
+
+impl NormalizedVin {
+    /// Key for duplicate matching; leading zeros on old serials are often dropped
+    pub fn match_key(&self) -> String {
+        if self.pre_1981 {
+            self.vin.trim_start_matches('0').to_string()
+        } else {
+            self.vin.clone()
+        }
+    }
+}
+
+/// Normalize a VIN or pre-1981 serial: strip formatting, labels, and barcode prefixes
+pub fn normalize_vin(raw: &str) -> Option<NormalizedVin> {
+    let mut compact: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_uppercase();
+
+    // "VIN: ..." labels survive the strip as a prefix
+    if compact.starts_with("VIN") && compact.len() != 17 {
+        compact = compact[3..].to_string();
+    }
+
+    if compact.len() > 17 {
+        // Code 39 "I" prefixes and other stray leading characters
+        let tail = compact[compact.len() - 17..].to_string();
+        if validate_vin(&tail) || compact.len() == 18 {
+            compact = tail;
+        } else {
+            return None;
+        }
+    }
+
+    match compact.len() {
+        17 => Some(NormalizedVin {
+            checksum_valid: validate_vin(&compact),
+            vin: compact,
+            pre_1981: false,
+        }),
+        5..=16 if compact.chars().any(|c| c.is_ascii_digit()) => Some(NormalizedVin {
+            vin: compact,
+            pre_1981: true,
+            checksum_valid: false,
+        }),
+        _ => None,
+    }
+}
+
+// Position 10 year codes, starting at 1980 (and again at 2010)
+const YEAR_CODES: &str = "ABCDEFGHJKLMNPRSTVWXY123456789";
+
+/// What can be read from a VIN without any network lookup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinDecode {
+    /// World manufacturer identifier (positions 1-3)
+    pub wmi: String,
+    pub region: Option<String>,
+    /// From the built-in WMI table; None for manufacturers it doesn't list
+    #[serde(default)]
+    pub manufacturer: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    pub model_year: Option<u32>,
+}
+
+// Common world manufacturer identifiers: (WMI, make, country of manufacture)
+const WMI_MANUFACTURERS: &[(&str, &str, &str)] = &[
+    ("1B3", "Dodge", "United States"),
+    ("1B7", "Dodge", "United States"),
+    ("1C3", "Chrysler", "United States"),
+    ("1C4", "Jeep", "United States"),
+    ("1C6", "Ram", "United States"),
+    ("1D7", "Dodge", "United States"),
+    ("1FA", "Ford", "United States"),
+    ("1FB", "Ford", "United States"),
+    ("1FD", "Ford", "United States"),
+    ("1FM", "Ford", "United States"),
+    ("1FT", "Ford", "United States"),
+    ("1G1", "Chevrolet", "United States"),
+    ("1G2", "Pontiac", "United States"),
+    ("1G3", "Oldsmobile", "United States"),
+    ("1G4", "Buick", "United States"),
+    ("1G6", "Cadillac", "United States"),
+    ("1GC", "Chevrolet", "United States"),
+    ("1GN", "Chevrolet", "United States"),
+    ("1GT", "GMC", "United States"),
+    ("1HG", "Honda", "United States"),
+    ("1J4", "Jeep", "United States"),
+    ("1J8", "Jeep", "United States"),
+    ("1LN", "Lincoln", "United States"),
+    ("1ME", "Mercury", "United States"),
+    ("1N4", "Nissan", "United States"),
+    ("1YV", "Mazda", "United States"),
+    ("2FA", "Ford", "Canada"),
+    ("2FT", "Ford", "Canada"),
+    ("2G1", "Chevrolet", "Canada"),
+    ("2HG", "Honda", "Canada"),
+    ("2T1", "Toyota", "Canada"),
+    ("3FA", "Ford", "Mexico"),
+    ("3GC", "Chevrolet", "Mexico"),
+    ("3VW", "Volkswagen", "Mexico"),
+    ("4S3", "Subaru", "United States"),
+    ("4T1", "Toyota", "United States"),
+    ("5N1", "Nissan", "United States"),
+    ("5YJ", "Tesla", "United States"),
+    ("JF1", "Subaru", "Japan"),
+    ("JH4", "Acura", "Japan"),
+    ("JHM", "Honda", "Japan"),
+    ("JM1", "Mazda", "Japan"),
+    ("JN1", "Nissan", "Japan"),
+    ("JT2", "Toyota", "Japan"),
+    ("JTD", "Toyota", "Japan"),
+    ("KMH", "Hyundai", "South Korea"),
+    ("KNA", "Kia", "South Korea"),
+    ("SAJ", "Jaguar", "United Kingdom"),
+    ("SAL", "Land Rover", "United Kingdom"),
+    ("SCF", "Aston Martin", "United Kingdom"),
+    ("VF1", "Renault", "France"),
+    ("VF3", "Peugeot", "France"),
+    ("WAU", "Audi", "Germany"),
+    ("WBA", "BMW", "Germany"),
+    ("WBS", "BMW", "Germany"),
+    ("WDB", "Mercedes-Benz", "Germany"),
+    ("WDD", "Mercedes-Benz", "Germany"),
+    ("WP0", "Porsche", "Germany"),
+    ("WP1", "Porsche", "Germany"),
+    ("WVW", "Volkswagen", "Germany"),
+    ("YV1", "Volvo", "Sweden"),
+    ("ZAR", "Alfa Romeo", "Italy"),
+    ("ZFF", "Ferrari", "Italy"),
+    ("ZHW", "Lamborghini", "Italy"),
+];
+
+/// Decode manufacturer, region and model year from a 17-character VIN
+pub fn decode_vin(vin: &str) -> VinDecode {
+    let vin = vin.trim().to_uppercase();
+    let wmi: String = vin.chars().take(3).collect();
+    let known = WMI_MANUFACTURERS.iter().find(|(code, _, _)| *code == wmi);
+    VinDecode {
+        region: vin.chars().next().and_then(region_for),
+        manufacturer: known.map(|(_, make, _)| make.to_string()),
+        country: known.map(|(_, _, country)| country.to_string()),
+        model_year: decode_model_year(&vin),
+        wmi,
+    }
+}
+
+/// Whether a year named elsewhere fits the VIN's year code, in either 30-year cycle
+pub fn year_matches(decode: &VinDecode, year: u32) -> bool {
+    decode
+        .model_year
+        .map(|decoded| decoded == year || decoded.abs_diff(year) == 30)
+        .unwrap_or(false)
+}
+
+fn region_for(c: char) -> Option<String> {
+    let region = match c {
+        '1'..='5' => "North America",
+        '6' | '7' => "Oceania",
+        '8' | '9' => "South America",
+        'A'..='H' => "Africa",
+        'J'..='R' => "Asia",
+        'S'..='Z' => "Europe",
+        _ => return None,
+    };
+    Some(region.to_string())
+}
+
+/// Model year from position 10; a letter in position 7 means the 2010+ cycle
+fn decode_model_year(vin: &str) -> Option<u32> {
+    if vin.len() != 17 {
+        return None;
+    }
+    let code = vin.chars().nth(9)?;
+    let offset = YEAR_CODES.find(code)? as u32;
+    let base = if vin.chars().nth(6)?.is_ascii_alphabetic() {
+        2010
+    } else {
+        1980
+    };
+    Some(base + offset)
+}
+
+/// Model year chosen between the two 30-year cycles position 10 can mean
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearResolution {
+    pub year: u32,
+    /// The other cycle's year, when it wasn't ruled out
+    pub alternative: Option<u32>,
+    pub confidence: f32,
+    /// Which signal decided it
+    pub basis: String,
+}
+
+/// Corroborating years from outside the VIN
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YearEvidence {
+    /// Year named in the filename or extraction ("1972 C10.jpg")
+    pub stated_year: Option<u32>,
+    /// Photo capture year; the vehicle can't be newer than the photo
+    pub captured_year: Option<u32>,
+    /// Document date year; the vehicle existed by then
+    pub document_year: Option<u32>,
+}
+
+/// Pick the model year epoch for a VIN using filename, capture and document dates
+#[tauri::command]
+pub async fn resolve_vin_year(
+    vin: String,
+    filename: Option<String>,
+    captured_at: Option<String>,
+    document_date: Option<String>,
+) -> Result<YearResolution, NukeError> {
+    let evidence = YearEvidence {
+        stated_year: filename.as_deref().and_then(first_year),
+        captured_year: captured_at.as_deref().and_then(first_year),
+        document_year: document_date.as_deref().and_then(first_year),
+    };
+    resolve_model_year(&vin, &evidence)
+        .ok_or_else(|| NukeError::InvalidInput("VIN has no readable model year code".to_string()))
+}
+
+/// Choose between the 1980-cycle and 2010-cycle readings of position 10
+pub fn resolve_model_year(vin: &str, evidence: &YearEvidence) -> Option<YearResolution> {
+    let vin = vin.trim().to_uppercase();
+    if vin.len() != 17 {
+        return None;
+    }
+    let offset = YEAR_CODES.find(vin.chars().nth(9)?)? as u32;
+
+    // A model year can run one ahead of the calendar
+    let latest = chrono::Utc::now().year() as u32 + 1;
+    let mut candidates: Vec<u32> = [1980 + offset, 2010 + offset]
+        .into_iter()
+        .filter(|year| *year <= latest)
+        .collect();
+    let mut basis = "only one cycle is in the past".to_string();
+
+    // Dated photos and paperwork rule out cycles the vehicle can't have been built in yet
+    for (bound, what) in [
+        (evidence.captured_year, "capture date"),
+        (evidence.document_year, "document date"),
+    ] {
+        if let Some(bound) = bound {
+            let before = candidates.len();
+            candidates.retain(|year| *year <= bound + 1);
+            if candidates.len() < before {
+                basis = format!("{} {} rules out the later cycle", what, bound);
+            }
+        }
+    }
+
+    let resolution =
+        |year: u32, alternative: Option<u32>, confidence: f32, basis: String| YearResolution {
+            year,
+            alternative,
+            confidence,
+            basis,
+        };
+    match candidates.as_slice() {
+        [] => None,
+        [only] => Some(resolution(*only, None, 0.9, basis)),
+        [early, late] => {
+            // A stated year near one reading settles it
+            if let Some(stated) = evidence.stated_year {
+                let near = |year: u32| (year as i64 - stated as i64).abs() <= 2;
+                if near(*early) != near(*late) {
+                    let (year, other) = if near(*early) {
+                        (*early, *late)
+                    } else {
+                        (*late, *early)
+                    };
+                    return Some(resolution(
+                        year,
+                        Some(other),
+                        0.85,
+                        format!("stated year {}", stated),
+                    ));
+                }
+            }
+            // Weak prior: North American VINs use a letter at position 7 from 2010
+            let late_cycle = vin
+                .chars()
+                .nth(6)
+                .map(|c| c.is_ascii_alphabetic())
+                .unwrap_or(false);
+            let (year, other) = if late_cycle {
+                (*late, *early)
+            } else {
+                (*early, *late)
+            };
+            Some(resolution(
+                year,
+                Some(other),
+                0.6,
+                "position 7 convention".to_string(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// First four-digit year (1900-2099) in a string
+fn first_year(text: &str) -> Option<u32> {
+    Regex::new(r"(?:^|\D)((?:19|20)\d{2})(?:\D|$)")
+        .ok()?
+        .captures(text)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+/// Part of a VIN, with the positions that were readable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialVin {
+    /// The readable characters, in order
+    pub fragment: String,
+    /// 17 characters with '?' for unknown ones; None when the fragment's position isn't known
+    pub pattern: Option<String>,
+    /// 1-based positions of the readable characters (empty when the pattern is unknown)
+    pub positions: Vec<usize>,
+}
+
+/// Result of matching a partial VIN against known full VINs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinCompletion {
+    pub partial: Option<PartialVin>,
+    /// Every candidate consistent with the partial
+    pub matches: Vec<String>,
+    /// Set only when exactly one candidate matches
+    pub vin: Option<String>,
+    pub ambiguous: bool,
+}
+
+/// Characters models and people use for an unreadable VIN position
+pub const VIN_PLACEHOLDERS: &[char] = &['?', '*', '_', '.', '…'];
+
+/// Parse "?????????12345678", "...12345678" or "1HGCM826****" into a partial VIN.
+/// A bare fragment like "12345678" is taken as the tail, as on dashboard plates.
+pub fn parse_partial_vin(raw: &str) -> Option<PartialVin> {
+    let compact: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || VIN_PLACEHOLDERS.contains(c))
+        .collect::<String>()
+        .to_uppercase();
+    let is_placeholder = |c: char| VIN_PLACEHOLDERS.contains(&c);
+    let fragment: String = compact.chars().filter(|c| !is_placeholder(*c)).collect();
+    if fragment.len() < 4 || fragment.len() >= 17 {
+        return None;
+    }
+
+    let pattern = if compact.chars().count() == 17 {
+        // Padded out to full length: every position is known
+        Some(
+            compact
+                .chars()
+                .map(|c| if is_placeholder(c) { '?' } else { c })
+                .collect::<String>(),
+        )
+    } else {
+        let trimmed_start = compact.trim_start_matches(is_placeholder);
+        let trimmed_end = compact.trim_end_matches(is_placeholder);
+        if trimmed_start.chars().any(is_placeholder) && trimmed_end.chars().any(is_placeholder) {
+            // Gaps in the middle of unknown width
+            None
+        } else if trimmed_end.len() < compact.len() && trimmed_start.len() == compact.len() {
+            // Only trailing placeholders: the fragment is the start
+            Some(format!("{}{}", fragment, "?".repeat(17 - fragment.len())))
+        } else {
+            Some(format!("{}{}", "?".repeat(17 - fragment.len()), fragment))
+        }
+    };
+    let positions = pattern
+        .as_deref()
+        .map(|p| {
+            p.chars()
+                .enumerate()
+                .filter(|(_, c)| *c != '?')
+                .map(|(i, _)| i + 1)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(PartialVin {
+        fragment,
+        pattern,
+        positions,
+    })
+}
+
+/// Whether a full VIN agrees with a partial at every readable position
+pub fn matches_partial(partial: &PartialVin, vin: &str) -> bool {
+    match &partial.pattern {
+        Some(pattern) => {
+            vin.len() == 17
+                && pattern
+                    .chars()
+                    .zip(vin.chars())
+                    .all(|(p, v)| p == '?' || p == v)
+        }
+        None => vin.contains(&partial.fragment),
+    }
+}
+
+/// Match a partial VIN against candidates; only a single match counts as a completion
+pub fn complete_vin(partial: &str, candidates: &[String]) -> VinCompletion {
+    let partial = parse_partial_vin(partial);
+    let mut matches: Vec<String> = Vec::new();
+    if let Some(partial) = &partial {
+        for candidate in candidates {
+            let Some(normalized) = normalize_vin(candidate) else {
+                continue;
+            };
+            if !normalized.pre_1981
+                && matches_partial(partial, &normalized.vin)
+                && !matches.contains(&normalized.vin)
+            {
+                matches.push(normalized.vin);
+            }
+        }
+    }
+
+    VinCompletion {
+        partial,
+        vin: (matches.len() == 1).then(|| matches[0].clone()),
+        ambiguous: matches.len() > 1,
+        matches,
+    }
+}
+
+/// Reconstruct a full VIN from a fragment, against the given VINs or everything processed so far
+#[tauri::command]
+pub async fn complete_partial_vin(
+    state: tauri::State<'_, AppState>,
+    partial: String,
+    candidates: Option<Vec<String>>,
+) -> Result<VinCompletion, NukeError> {
+    let candidates = candidates.unwrap_or_else(|| state.processed.lock().unwrap().known_vins());
+    Ok(complete_vin(&partial, &candidates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = "1HGCM82633A004352";
+    const OTHER_VALID: &str = "WP0AB2A93KS114315";
+    // VALID with a wrong check digit
+    const MISREAD: &str = "1HGCM82643A004352";
+
+    fn source(kind: VinSourceKind, vin: &str) -> VinSource {
+        VinSource::new(kind, vin)
+    }
+
+    #[test]
+    fn agreeing_barcode_and_ocr_confirm_the_vin() {
+        let check = cross_check_vins(vec![
+            source(VinSourceKind::Barcode, VALID),
+            source(VinSourceKind::Ocr, &VALID.to_lowercase()),
+        ]);
+        assert_eq!(check.status, VinStatus::Confirmed);
+        assert_eq!(check.vin.as_deref(), Some(VALID));
+    }
+
+    #[test]
+    fn disagreeing_sources_conflict_and_keep_the_best_reader() {
+        let check = cross_check_vins(vec![
+            source(VinSourceKind::Vision, OTHER_VALID),
+            source(VinSourceKind::Ocr, MISREAD),
+            source(VinSourceKind::Barcode, VALID),
+        ]);
+        assert_eq!(check.status, VinStatus::Conflict);
+        assert_eq!(check.vin.as_deref(), Some(VALID));
+        assert_eq!(check.sources.len(), 3);
+    }
+
+    #[test]
+    fn prefers_barcode_then_ocr_then_vision_among_valid_reads() {
+        let pick = |sources: Vec<VinSource>| cross_check_vins(sources).vin.unwrap();
+        assert_eq!(
+            pick(vec![
+                source(VinSourceKind::Vision, OTHER_VALID),
+                source(VinSourceKind::Ocr, VALID),
+            ]),
+            VALID
+        );
+        // A barcode that fails its check digit loses to a valid OCR read
+        assert_eq!(
+            pick(vec![
+                source(VinSourceKind::Barcode, MISREAD),
+                source(VinSourceKind::Vision, OTHER_VALID),
+                source(VinSourceKind::Ocr, VALID),
+            ]),
+            VALID
+        );
+        // Nothing valid: the first read is all there is
+        assert_eq!(
+            pick(vec![
+                source(VinSourceKind::Vision, MISREAD),
+                source(VinSourceKind::Barcode, "1HGCM82633A00435X"),
+            ]),
+            MISREAD
+        );
+    }
+
+    #[test]
+    fn a_lone_read_is_single() {
+        let check = cross_check_vins(vec![source(VinSourceKind::Vision, VALID)]);
+        assert_eq!(check.status, VinStatus::Single);
+        assert_eq!(check.vin.as_deref(), Some(VALID));
+    }
+
+    #[test]
+    fn agreement_on_a_bad_check_digit_or_no_reads_is_unverified() {
+        let check = cross_check_vins(vec![
+            source(VinSourceKind::Barcode, MISREAD),
+            source(VinSourceKind::Ocr, MISREAD),
+        ]);
+        assert_eq!(check.status, VinStatus::Unverified);
+        assert_eq!(check.vin.as_deref(), Some(MISREAD));
+
+        let check = cross_check_vins(Vec::new());
+        assert_eq!(check.status, VinStatus::Unverified);
+        assert!(check.vin.is_none());
+    }
+
+    #[test]
+    fn normalize_strips_labels_and_formatting() {
+        let normalized = normalize_vin("VIN: 1hgcm-82633 a004352").unwrap();
+        assert_eq!(normalized.vin, VALID);
+        assert!(normalized.checksum_valid && !normalized.pre_1981);
+        // A bare label is nothing
+        assert!(normalize_vin("VIN:").is_none());
+    }
+
+    #[test]
+    fn normalize_drops_a_code_39_prefix_and_stray_characters() {
+        assert_eq!(normalize_vin(&format!("I{}", VALID)).unwrap().vin, VALID);
+        // An 18th character is dropped even when the check digit then fails
+        let eighteen = normalize_vin(&format!("I{}", MISREAD)).unwrap();
+        assert_eq!(eighteen.vin, MISREAD);
+        assert!(!eighteen.checksum_valid);
+        // Longer junk only survives if it ends in a valid VIN
+        assert_eq!(normalize_vin(&format!("XX{}", VALID)).unwrap().vin, VALID);
+        assert!(normalize_vin(&format!("XX{}", MISREAD)).is_none());
+    }
+
+    #[test]
+    fn normalize_keeps_pre_1981_serials() {
+        let serial = normalize_vin("VIN 124379N612345").unwrap();
+        assert_eq!(serial.vin, "124379N612345");
+        assert!(serial.pre_1981 && !serial.checksum_valid);
+        // Too short, or no digits at all, isn't a serial
+        assert!(normalize_vin("1234").is_none());
+        assert!(normalize_vin("MUSTANG").is_none());
+    }
+
+    #[test]
+    fn match_key_strips_leading_zeros_only_from_old_serials() {
+        let serial = normalize_vin("00F8512345").unwrap();
+        assert_eq!(serial.match_key(), "F8512345");
+        assert_eq!(
+            serial.match_key(),
+            normalize_vin("F8512345").unwrap().match_key()
+        );
+        let modern = normalize_vin("0HGCM82633A004352").unwrap();
+        assert_eq!(modern.match_key(), "0HGCM82633A004352");
+    }
+
+    // Position 10 'A' reads as 1980 or 2010; the digit at position 7 leans toward 1980
+    const AMBIGUOUS_YEAR_VIN: &str = "1G1AA1234AA000000";
+
+    #[test]
+    fn capture_and_document_dates_rule_out_the_later_cycle() {
+        let resolved =
+            |evidence: YearEvidence| resolve_model_year(AMBIGUOUS_YEAR_VIN, &evidence).unwrap();
+        let photo = resolved(YearEvidence {
+            captured_year: Some(1995),
+            ..YearEvidence::default()
+        });
+        assert_eq!((photo.year, photo.alternative), (1980, None));
+        assert!(photo.basis.starts_with("capture date 1995"));
+
+        // A dated document outranks a stated year that points the other way
+        let paperwork = resolved(YearEvidence {
+            document_year: Some(2008),
+            stated_year: Some(2010),
+            ..YearEvidence::default()
+        });
+        assert_eq!((paperwork.year, paperwork.alternative), (1980, None));
+    }
+
+    #[test]
+    fn a_stated_year_picks_the_later_cycle() {
+        let evidence = YearEvidence {
+            stated_year: Some(2010),
+            ..YearEvidence::default()
+        };
+        let resolution = resolve_model_year(AMBIGUOUS_YEAR_VIN, &evidence).unwrap();
+        assert_eq!(
+            (resolution.year, resolution.alternative),
+            (2010, Some(1980))
+        );
+        assert_eq!(resolution.basis, "stated year 2010");
+
+        // A letter at position 7 plus a photo from 2012 keeps the 2010 reading
+        let evidence = YearEvidence {
+            captured_year: Some(2012),
+            ..YearEvidence::default()
+        };
+        let resolution = resolve_model_year("1G1AA1B34AA000000", &evidence).unwrap();
+        assert_eq!(
+            (resolution.year, resolution.alternative),
+            (2010, Some(1980))
+        );
+    }
+
+    #[test]
+    fn without_evidence_position_seven_decides_with_low_confidence() {
+        let resolution = resolve_model_year(AMBIGUOUS_YEAR_VIN, &YearEvidence::default()).unwrap();
+        assert_eq!(
+            (resolution.year, resolution.alternative),
+            (1980, Some(2010))
+        );
+        assert!(resolution.confidence < 0.7);
+        assert!(resolve_model_year("1G1AA1234", &YearEvidence::default()).is_none());
+    }
+
+    #[test]
+    fn years_are_read_from_filenames_and_dates() {
+        assert_eq!(first_year("1972 C10 title.jpg"), Some(1972));
+        assert_eq!(first_year("2011-06-03T10:00:00"), Some(2011));
+        assert_eq!(first_year("IMG_123456.jpg"), None);
+    }
+
+    fn known_vins() -> Vec<String> {
+        vec![
+            VALID.to_string(),
+            "1FTFW1EF5FFA12345".to_string(),
+            // Pre-1981 serials are too short to complete against
+            "194377S100001".to_string(),
+        ]
+    }
+
+    #[test]
+    fn last_eight_fragment_completes_to_the_full_vin() {
+        let completion = complete_vin("3A004352", &known_vins());
+        assert_eq!(completion.vin.as_deref(), Some(VALID));
+        assert!(!completion.ambiguous);
+        let partial = completion.partial.unwrap();
+        assert_eq!(partial.pattern.as_deref(), Some("?????????3A004352"));
+        assert_eq!(partial.positions, (10..=17).collect::<Vec<_>>());
+
+        // Padded, leading and dotted forms read the same
+        for raw in [
+            "?????????3A004352",
+            "1HGCM826********",
+            "...3A004352",
+            "1HG??????3A004352",
+        ] {
+            assert_eq!(
+                complete_vin(raw, &known_vins()).vin.as_deref(),
+                Some(VALID),
+                "{}",
+                raw
+            );
+        }
+        let no_match = complete_vin("0000", &known_vins());
+        assert!(no_match.vin.is_none() && no_match.matches.is_empty());
+    }
+
+    #[test]
+    fn several_matching_candidates_are_ambiguous() {
+        let candidates = vec![
+            VALID.to_string(),
+            "1HGCM82633B004352".to_string(),
+            // The same VIN in other formatting counts once
+            "1hgcm 82633a004352".to_string(),
+        ];
+        let completion = complete_vin("004352", &candidates);
+        assert!(completion.ambiguous);
+        assert!(completion.vin.is_none());
+        assert_eq!(completion.matches, vec![VALID, "1HGCM82633B004352"]);
+        // More of the VIN settles it
+        assert_eq!(
+            complete_vin("3A004352", &candidates).vin.as_deref(),
+            Some(VALID)
+        );
+    }
+
+    #[test]
+    fn only_fragments_between_four_and_sixteen_characters_are_partial() {
+        assert!(parse_partial_vin("123").is_none());
+        assert!(parse_partial_vin(VALID).is_none());
+        // Gaps of unknown width leave only the fragment to search on
+        let gapped = parse_partial_vin("1HG...3A0...352").unwrap();
+        assert_eq!(gapped.pattern, None);
+        assert!(gapped.positions.is_empty());
+    }
+
+    #[test]
+    fn check_digit_accepts_known_good_vins() {
+        for vin in [
+            VALID,
+            OTHER_VALID,
+            // Remainder 10 is written as an X check digit
+            "1M8GDM9AXKP042788",
+            "11111111111111111",
+        ] {
+            assert!(validate_vin(vin), "{}", vin);
+        }
+        // Case and surrounding whitespace don't matter
+        assert!(validate_vin(" 1m8gdm9axkp042788 "));
+    }
+
+    #[test]
+    fn check_digit_rejects_known_bad_vins() {
+        assert!(!validate_vin(MISREAD));
+        // X where the sum calls for a digit, and a digit where it calls for X
+        assert!(!validate_vin("1HGCM826X3A004352"));
+        assert!(!validate_vin("1M8GDM9A0KP042788"));
+        // I, O and Q never appear in a VIN, even when the weights would balance
+        for letter in ['I', 'O', 'Q'] {
+            let vin = format!("1HGCM82633A00435{}", letter);
+            assert!(!validate_vin(&vin), "{}", vin);
+        }
+        assert!(!validate_vin("1HGCM82633A00435"));
+        assert!(!validate_vin("1HGCM82633A0043521"));
+        assert!(!validate_vin(""));
+    }
+}
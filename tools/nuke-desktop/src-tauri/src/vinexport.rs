@@ -0,0 +1,194 @@
+// VIN export - one CSV row per unique VIN, with the files it was read from
+
+use crate::documents::{ExtractedData, ExtractionResult};
+use crate::error::NukeError;
+use crate::vin;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// One unique VIN and where it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VinRow {
+    pub vin: String,
+    /// From the VIN's year code, or the extraction when the VIN can't be decoded
+    pub year: Option<String>,
+    /// From the WMI table, or the extraction when the manufacturer isn't listed
+    pub make: Option<String>,
+    pub best_confidence: f32,
+    pub file_count: usize,
+    /// Contributing filenames, separated by "; "
+    pub files: String,
+    /// No read of this VIN passed the check digit (pre-1981 serials have none)
+    pub unverified_only: bool,
+}
+
+/// Everything seen for one VIN while aggregating
+struct Sighting<'a> {
+    vin: String,
+    /// Extractions that named the VIN, for year and make when it can't be decoded
+    data: Vec<&'a ExtractedData>,
+    files: Vec<String>,
+    best_confidence: f32,
+}
+
+/// Write every unique VIN across `results` to a CSV at `out_path`, returning the row count
+#[tauri::command]
+pub async fn export_vins(
+    results: Vec<ExtractionResult>,
+    out_path: String,
+) -> Result<usize, NukeError> {
+    let rows = unique_vins(&results);
+    let mut writer = csv::Writer::from_path(&out_path)
+        .map_err(|e| NukeError::FileWrite(format!("Failed to create CSV: {}", e)))?;
+    for row in &rows {
+        writer
+            .serialize(row)
+            .map_err(|e| NukeError::FileWrite(format!("Failed to write CSV row: {}", e)))?;
+    }
+    writer
+        .flush()
+        .map_err(|e| NukeError::FileWrite(format!("Failed to write CSV: {}", e)))?;
+    Ok(rows.len())
+}
+
+/// Aggregate VINs from extractions, inventory rows and every VIN reader, keyed by normalized VIN
+pub fn unique_vins(results: &[ExtractionResult]) -> Vec<VinRow> {
+    let mut found: BTreeMap<String, Sighting> = BTreeMap::new();
+
+    for result in results {
+        let filename = Path::new(&result.path)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_else(|| result.path.clone());
+
+        let mut reads: Vec<(&str, Option<&ExtractedData>)> = Vec::new();
+        reads.extend(result.data.vin.as_deref().map(|v| (v, Some(&result.data))));
+        reads.extend(
+            result
+                .inventory
+                .iter()
+                .filter_map(|row| row.vin.as_deref().map(|v| (v, Some(row)))),
+        );
+        if let Some(verification) = &result.vin_verification {
+            reads.extend(verification.sources.iter().map(|s| (s.vin.as_str(), None)));
+        }
+
+        for (raw, data) in reads {
+            let Some(normalized) = vin::normalize_vin(raw) else {
+                continue;
+            };
+            let sighting = found
+                .entry(normalized.match_key())
+                .or_insert_with(|| Sighting {
+                    vin: normalized.vin.clone(),
+                    data: Vec::new(),
+                    files: Vec::new(),
+                    best_confidence: 0.0,
+                });
+            sighting.data.extend(data);
+            if !sighting.files.contains(&filename) {
+                sighting.files.push(filename.clone());
+            }
+            sighting.best_confidence = sighting.best_confidence.max(result.confidence);
+        }
+    }
+
+    found
+        .into_values()
+        .map(|sighting| {
+            let Sighting {
+                vin,
+                data,
+                files,
+                best_confidence,
+            } = sighting;
+            let decode = (vin.len() == 17).then(|| vin::decode_vin(&vin));
+            let year = decode
+                .as_ref()
+                .and_then(|d| d.model_year)
+                .map(|y| y.to_string())
+                .or_else(|| data.iter().find_map(|d| d.year.clone()));
+            let make = decode
+                .as_ref()
+                .and_then(|d| d.manufacturer.clone())
+                .or_else(|| data.iter().find_map(|d| d.make.clone()));
+            VinRow {
+                unverified_only: !vin::validate_vin(&vin),
+                vin,
+                year,
+                make,
+                best_confidence,
+                file_count: files.len(),
+                files: files.join("; "),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn result(path: &str, vin: Option<&str>, confidence: f32) -> ExtractionResult {
+        serde_json::from_value(json!({
+            "path": path,
+            "model": "llava",
+            "route": "vision",
+            "image_kind": "document",
+            "data": { "vin": vin, "year": "1999" },
+            "confidence": confidence,
+            "raw_response": "",
+        }))
+        .unwrap()
+    }
+
+    fn results() -> Vec<ExtractionResult> {
+        vec![
+            result("/a/title.jpg", Some("1HGCM82633A004352"), 0.9),
+            result("/b/registration.jpg", Some("1hgcm82633a004352"), 0.95),
+            // One digit off: the check digit fails
+            result("/c/dash.jpg", Some("1HGCM82633A004353"), 0.5),
+            result("/c/interior.jpg", None, 0.5),
+        ]
+    }
+
+    #[test]
+    fn one_row_per_vin_with_its_files_and_best_confidence() {
+        let rows = unique_vins(&results());
+        assert_eq!(rows.len(), 2);
+
+        let verified = rows.iter().find(|r| r.vin == "1HGCM82633A004352").unwrap();
+        assert_eq!(verified.file_count, 2);
+        assert_eq!(verified.files, "title.jpg; registration.jpg");
+        assert_eq!(verified.best_confidence, 0.95);
+        assert!(!verified.unverified_only);
+        // Decoded from the VIN rather than taken from the extraction
+        assert_eq!(verified.year.as_deref(), Some("2003"));
+        assert_eq!(verified.make.as_deref(), Some("Honda"));
+
+        let misread = rows.iter().find(|r| r.vin == "1HGCM82633A004353").unwrap();
+        assert!(misread.unverified_only);
+        assert_eq!(misread.files, "dash.jpg");
+        assert_eq!(misread.best_confidence, 0.5);
+    }
+
+    #[tokio::test]
+    async fn export_writes_a_header_and_a_row_per_vin() {
+        let out = std::env::temp_dir().join(format!("nuke-vins-{}.csv", std::process::id()));
+        let count = export_vins(results(), out.to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let csv = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "vin,year,make,best_confidence,file_count,files,unverified_only"
+        );
+        assert_eq!(lines.len(), 3);
+        std::fs::remove_file(&out).unwrap();
+    }
+}
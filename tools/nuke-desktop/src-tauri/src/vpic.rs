@@ -0,0 +1,492 @@
+// NHTSA vPIC lookups with caching and rate limiting
+
+use crate::error::NukeError;
+use crate::vin::{self, VinDecode};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const VPIC_URL: &str = "https://vpic.nhtsa.dot.gov/api/vehicles/DecodeVinValues";
+// vPIC asks clients to keep request rates modest
+const VPIC_MIN_INTERVAL: Duration = Duration::from_millis(250);
+// 429s in a row before a lookup gives up
+const MAX_RATE_LIMITED_RETRIES: u32 = 3;
+// Wait after a 429 without Retry-After; doubles on each retry
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(2);
+// A longer Retry-After fails the lookup instead of stalling the batch
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Fields we keep from a vPIC decode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VpicDecode {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub model_year: Option<String>,
+    pub trim: Option<String>,
+    pub body_class: Option<String>,
+    pub manufacturer: Option<String>,
+    pub plant_country: Option<String>,
+    pub engine_displacement_l: Option<String>,
+    pub fuel_type: Option<String>,
+}
+
+/// Spaces requests out so consecutive calls are at least `min_interval` apart, and holds
+/// everything back while the server has told us to wait
+pub struct RateLimiter {
+    min_interval: Duration,
+    last_request: tokio::sync::Mutex<Option<Instant>>,
+    blocked_until: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        RateLimiter {
+            min_interval,
+            last_request: tokio::sync::Mutex::new(None),
+            blocked_until: Mutex::new(None),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        let mut last = self.last_request.lock().await;
+        let spacing = last
+            .map(|previous| self.min_interval.saturating_sub(previous.elapsed()))
+            .unwrap_or_default();
+        let wait = spacing.max(self.blocked_for());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        *last = Some(Instant::now());
+    }
+
+    /// Hold every caller back for `wait`, e.g. after a 429
+    pub fn back_off(&self, wait: Duration) {
+        let until = Instant::now() + wait;
+        let mut blocked = self.blocked_until.lock().unwrap();
+        if blocked.map(|b| b < until).unwrap_or(true) {
+            *blocked = Some(until);
+        }
+    }
+
+    /// How much longer the server asked us to wait
+    pub fn blocked_for(&self) -> Duration {
+        self.blocked_until
+            .lock()
+            .unwrap()
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .unwrap_or_default()
+    }
+}
+
+/// Request counts and backoff state for an external enrichment API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiQuota {
+    pub api: String,
+    pub requests: u64,
+    pub cache_hits: u64,
+    /// 429 responses received
+    pub rate_limited: u64,
+    /// Milliseconds until the server said we may call again
+    pub blocked_for_ms: u64,
+    pub cached_entries: usize,
+}
+
+/// How long a 429 asked us to wait: delay-seconds or an HTTP date
+pub fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let millis = (at.timestamp_millis() - chrono::Utc::now().timestamp_millis()).max(0);
+    Some(Duration::from_millis(millis as u64))
+}
+
+/// Cached, rate-limited vPIC client shared through AppState.
+/// Decodes never change, so the cache is kept on disk across runs.
+pub struct VpicLookup {
+    path: PathBuf,
+    cache: Mutex<HashMap<String, VpicDecode>>,
+    limiter: RateLimiter,
+    quota: Mutex<ApiQuota>,
+}
+
+impl VpicLookup {
+    /// Load cached decodes from disk, starting empty if the file is missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let cache = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        VpicLookup {
+            path,
+            cache: Mutex::new(cache),
+            limiter: RateLimiter::new(VPIC_MIN_INTERVAL),
+            quota: Mutex::new(ApiQuota {
+                api: "vpic".to_string(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Decode a VIN through vPIC, serving repeats from the cache
+    pub async fn decode(&self, client: &reqwest::Client, vin: &str) -> Result<VpicDecode, String> {
+        self.decode_at(client, VPIC_URL, vin).await
+    }
+
+    /// `decode` against another vPIC-compatible endpoint
+    pub async fn decode_at(
+        &self,
+        client: &reqwest::Client,
+        base_url: &str,
+        vin: &str,
+    ) -> Result<VpicDecode, String> {
+        if let Some(hit) = self.cache.lock().unwrap().get(vin).cloned() {
+            self.quota.lock().unwrap().cache_hits += 1;
+            return Ok(hit);
+        }
+
+        let url = format!("{}/{}?format=json", base_url, vin);
+        let mut attempt = 0;
+        let response = loop {
+            self.limiter.acquire().await;
+            self.quota.lock().unwrap().requests += 1;
+            let response = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| format!("vPIC request failed: {}", e))?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                break response;
+            }
+
+            self.quota.lock().unwrap().rate_limited += 1;
+            let wait = retry_after(response.headers())
+                .unwrap_or(DEFAULT_BACKOFF * 2u32.pow(attempt));
+            attempt += 1;
+            if attempt > MAX_RATE_LIMITED_RETRIES || wait > MAX_RETRY_AFTER {
+                self.limiter.back_off(wait);
+                return Err(format!(
+                    "vPIC rate limit reached; try again in {}s",
+                    wait.as_secs().max(1)
+                ));
+            }
+            self.limiter.back_off(wait);
+        };
+
+        let response: serde_json::Value = response
+            .error_for_status()
+            .map_err(|e| format!("vPIC request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse vPIC response: {}", e))?;
+
+        let row = response
+            .get("Results")
+            .and_then(|r| r.get(0))
+            .ok_or("vPIC returned no results")?;
+
+        let text = |key: &str| {
+            row.get(key)
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+        };
+
+        let decode = VpicDecode {
+            make: text("Make"),
+            model: text("Model"),
+            model_year: text("ModelYear"),
+            trim: text("Trim"),
+            body_class: text("BodyClass"),
+            manufacturer: text("Manufacturer"),
+            plant_country: text("PlantCountry"),
+            engine_displacement_l: text("DisplacementL"),
+            fuel_type: text("FuelTypePrimary"),
+        };
+
+        let mut cache = self.cache.lock().unwrap();
+        cache.insert(vin.to_string(), decode.clone());
+        // A failed write only costs a repeat lookup next run
+        if let Ok(json) = serde_json::to_string(&*cache) {
+            let _ = std::fs::write(&self.path, json);
+        }
+        Ok(decode)
+    }
+
+    pub fn quota(&self) -> ApiQuota {
+        ApiQuota {
+            blocked_for_ms: self.limiter.blocked_for().as_millis() as u64,
+            cached_entries: self.cache.lock().unwrap().len(),
+            ..self.quota.lock().unwrap().clone()
+        }
+    }
+}
+
+/// Request counts, cache size and any backoff in force for external enrichment APIs
+#[tauri::command]
+pub async fn get_external_api_quota(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ApiQuota>, NukeError> {
+    Ok(vec![state.vpic.quota()])
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchVinResult {
+    pub vin: String,
+    pub valid: bool,
+    pub error: Option<String>,
+    pub decode: Option<VinDecode>,
+    pub vpic: Option<VpicDecode>,
+}
+
+/// Validate and decode a list of VINs, optionally enriching each through vPIC
+#[tauri::command]
+pub async fn decode_vins_batch(
+    state: tauri::State<'_, AppState>,
+    vins: Vec<String>,
+    enrich: bool,
+) -> Result<Vec<BatchVinResult>, NukeError> {
+    let client = state.http.clone();
+    Ok(decode_batch(&state.vpic, &client, VPIC_URL, vins, enrich).await)
+}
+
+/// `decode_vins_batch` against a vPIC-compatible endpoint. VINs are compared after
+/// stripping formatting, and each one is only decoded once.
+async fn decode_batch(
+    lookup: &VpicLookup,
+    client: &reqwest::Client,
+    base_url: &str,
+    vins: Vec<String>,
+    enrich: bool,
+) -> Vec<BatchVinResult> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut results = Vec::new();
+
+    for raw in vins {
+        let candidate: String = raw
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_uppercase();
+        if candidate.is_empty() || seen.contains(&candidate) {
+            continue;
+        }
+        seen.push(candidate.clone());
+
+        if !vin::validate_vin(&candidate) {
+            results.push(BatchVinResult {
+                vin: candidate,
+                valid: false,
+                error: Some("Invalid VIN (length, characters, or check digit)".to_string()),
+                decode: None,
+                vpic: None,
+            });
+            continue;
+        }
+
+        let mut result = BatchVinResult {
+            decode: Some(vin::decode_vin(&candidate)),
+            vin: candidate,
+            valid: true,
+            error: None,
+            vpic: None,
+        };
+
+        if enrich {
+            match lookup.decode_at(client, base_url, &result.vin).await {
+                Ok(vpic) => result.vpic = Some(vpic),
+                Err(e) => result.error = Some(e),
+            }
+        }
+
+        results.push(result);
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A local HTTP server answering the nth request with `respond(n)`; returns its base
+    /// URL and the number of requests it has seen
+    async fn serve(respond: fn(usize) -> String) -> (String, Arc<AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let mut buffer = [0u8; 8192];
+                let _ = socket.read(&mut buffer).await;
+                let _ = socket.write_all(respond(n).as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    fn ok_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn temp_lookup(name: &str) -> (VpicLookup, PathBuf) {
+        let path = std::env::temp_dir().join(format!("nuke-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        (VpicLookup::load(path.clone()), path)
+    }
+
+    #[tokio::test]
+    async fn batch_validates_dedupes_and_enriches_each_vin_once() {
+        let (url, requests) = serve(|_| {
+            ok_response(r#"{"Results":[{"Make":"HONDA","ModelYear":"2003","Trim":" "}]}"#)
+        })
+        .await;
+        let (lookup, path) = temp_lookup("vpic-batch");
+        let vins = vec![
+            "1HGCM82633A004352".to_string(),
+            "1hgcm-82633-a004352".to_string(),
+            "1HGCM82633A004353".to_string(),
+            "  ".to_string(),
+            "WP0AB2A93KS114315".to_string(),
+        ];
+        let results = decode_batch(&lookup, &reqwest::Client::new(), &url, vins, true).await;
+
+        let summary: Vec<_> = results.iter().map(|r| (r.vin.as_str(), r.valid)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("1HGCM82633A004352", true),
+                ("1HGCM82633A004353", false),
+                ("WP0AB2A93KS114315", true),
+            ]
+        );
+        assert!(results[1].error.as_deref().unwrap().contains("check digit"));
+        assert!(results[1].vpic.is_none() && results[1].decode.is_none());
+        let vpic = results[0].vpic.as_ref().unwrap();
+        assert_eq!(vpic.make.as_deref(), Some("HONDA"));
+        assert_eq!(vpic.trim, None);
+        assert!(results[0].decode.is_some());
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+        // Decodes are cached, so a repeat batch makes no requests
+        let again = vec!["1HGCM82633A004352".to_string()];
+        decode_batch(&lookup, &reqwest::Client::new(), &url, again, true).await;
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn batch_without_enrichment_makes_no_requests() {
+        let (url, requests) = serve(|_| ok_response("{}")).await;
+        let (lookup, _) = temp_lookup("vpic-offline");
+        let results = decode_batch(
+            &lookup,
+            &reqwest::Client::new(),
+            &url,
+            vec!["1HGCM82633A004352".to_string()],
+            false,
+        )
+        .await;
+        assert!(results[0].valid && results[0].vpic.is_none() && results[0].error.is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn a_429_waits_for_retry_after_then_retries() {
+        let (url, requests) = serve(|n| match n {
+            0 => "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+            _ => ok_response(r#"{"Results":[{"Make":"FORD","ModelYear":"1995"}]}"#),
+        })
+        .await;
+        let (lookup, path) = temp_lookup("vpic-429");
+        let client = reqwest::Client::new();
+
+        let started = Instant::now();
+        let decode = lookup
+            .decode_at(&client, &url, "1FTEF14N0SLA00001")
+            .await
+            .unwrap();
+        assert!(
+            started.elapsed() >= Duration::from_secs(1),
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(decode.make.as_deref(), Some("FORD"));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        let quota = lookup.quota();
+        assert_eq!(
+            (quota.requests, quota.rate_limited, quota.cached_entries),
+            (2, 1, 1)
+        );
+        assert_eq!(quota.blocked_for_ms, 0);
+
+        // The decode was kept on disk, so the next run answers without the server
+        let reloaded = VpicLookup::load(path.clone());
+        reloaded
+            .decode_at(&client, "http://127.0.0.1:9", "1FTEF14N0SLA00001")
+            .await
+            .unwrap();
+        assert_eq!(reloaded.quota().cache_hits, 1);
+        assert_eq!(reloaded.quota().requests, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn a_retry_after_past_the_limit_fails_and_holds_back_other_calls() {
+        let (url, requests) = serve(|_| {
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 600\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string()
+        })
+        .await;
+        let (lookup, _) = temp_lookup("vpic-429-long");
+        let error = lookup
+            .decode_at(&reqwest::Client::new(), &url, "1FTEF14N0SLA00001")
+            .await
+            .unwrap_err();
+        assert_eq!(error, "vPIC rate limit reached; try again in 600s");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert!(lookup.quota().blocked_for_ms > 590_000);
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_http_dates() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(reqwest::header::RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+        let soon = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        headers.insert(reqwest::header::RETRY_AFTER, soon.parse().unwrap());
+        let wait = retry_after(&headers).unwrap();
+        assert!(
+            wait > Duration::from_secs(28) && wait <= Duration::from_secs(30),
+            "{:?}",
+            wait
+        );
+        // A date in the past means go ahead
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+    }
+}
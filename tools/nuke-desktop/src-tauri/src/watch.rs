@@ -0,0 +1,204 @@
+// Watch mode - pick up files as they're dropped into scanned folders
+
+use crate::error::NukeError;
+use crate::{AppState, FileScan, ScanConfig};
+use notify::event::{EventKind, ModifyKind};
+use notify::{RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+/// Emitted with a `ScanResult` for each new file in a watched folder
+pub const FILE_ADDED_EVENT: &str = "file-added";
+
+// A file has to go this long without events (and keep its size) before it's reported
+const QUIET_PERIOD: Duration = Duration::from_millis(750);
+
+// How often pending files are checked when no events arrive
+const TICK: Duration = Duration::from_millis(250);
+
+// Suffixes of files still being written by browsers, editors and copy tools
+const PARTIAL_SUFFIXES: &[&str] = &[
+    ".tmp",
+    ".temp",
+    ".part",
+    ".partial",
+    ".crdownload",
+    ".download",
+    ".swp",
+    "~",
+];
+
+/// A running watch; dropping it stops the watcher and its worker thread
+pub struct WatchSession {
+    _watcher: notify::RecommendedWatcher,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for WatchSession {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Paths from filesystem events, held until they've been quiet for `QUIET_PERIOD` and their
+/// size hasn't changed between two checks
+pub struct Debouncer {
+    quiet: Duration,
+    pending: HashMap<PathBuf, (Instant, Option<u64>)>,
+}
+
+impl Debouncer {
+    pub fn new(quiet: Duration) -> Self {
+        Debouncer {
+            quiet,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Note an event for `path`, restarting its quiet period
+    pub fn touch(&mut self, path: PathBuf, now: Instant) {
+        let size = self.pending.get(&path).and_then(|(_, size)| *size);
+        self.pending.insert(path, (now, size));
+    }
+
+    /// Paths that have settled; `size_of` returns None for files that are gone
+    pub fn ready(&mut self, now: Instant, size_of: impl Fn(&Path) -> Option<u64>) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        self.pending.retain(|path, (last_event, last_size)| {
+            if now.duration_since(*last_event) < self.quiet {
+                return true;
+            }
+            match size_of(path) {
+                None => false,
+                Some(size) if *last_size == Some(size) => {
+                    ready.push(path.clone());
+                    false
+                }
+                // Still growing, or first look: check again after another quiet period
+                size => {
+                    *last_event = now;
+                    *last_size = size;
+                    true
+                }
+            }
+        });
+        ready.sort();
+        ready
+    }
+}
+
+/// Temp and partially downloaded files, which get renamed when they're done
+pub fn is_partial_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy().to_lowercase()) else {
+        return true;
+    };
+    name.starts_with('.')
+        || name.starts_with("~$")
+        || PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Watch folders for new files, emitting `file-added` with the scanned file for each.
+/// Replaces any watch already running.
+#[tauri::command]
+pub async fn start_watching(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<(), NukeError> {
+    if paths.is_empty() {
+        return Err(NukeError::InvalidInput("No folders to watch".to_string()));
+    }
+    // Stop the old watch first so its worker doesn't report files the new one will
+    state.watch.lock().unwrap().take();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| NukeError::Other(format!("Failed to start watching: {}", e)))?;
+    for path in &paths {
+        watcher
+            .watch(Path::new(path), RecursiveMode::Recursive)
+            .map_err(|e| NukeError::InvalidInput(format!("Can't watch {}: {}", path, e)))?;
+    }
+
+    // The saved scan config decides which file types count; without one, all of them do
+    let mut config = state
+        .scan_config
+        .lock()
+        .unwrap()
+        .get()
+        .cloned()
+        .unwrap_or_default();
+    config.paths = paths;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let worker_stop = stop.clone();
+    std::thread::spawn(move || watch_loop(app, config, rx, worker_stop));
+
+    *state.watch.lock().unwrap() = Some(WatchSession {
+        _watcher: watcher,
+        stop,
+    });
+    Ok(())
+}
+
+/// Stop the running watch, if any
+#[tauri::command]
+pub async fn stop_watching(state: tauri::State<'_, AppState>) -> Result<(), NukeError> {
+    state.watch.lock().unwrap().take();
+    Ok(())
+}
+
+fn watch_loop(
+    app: tauri::AppHandle,
+    config: ScanConfig,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+    stop: Arc<AtomicBool>,
+) {
+    let state = app.state::<AppState>();
+    let mut debouncer = Debouncer::new(QUIET_PERIOD);
+    let mut reported: HashSet<PathBuf> = HashSet::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        match rx.recv_timeout(TICK) {
+            Ok(Ok(event)) => {
+                let relevant = matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(ModifyKind::Data(_))
+                        | EventKind::Modify(ModifyKind::Name(_))
+                        | EventKind::Modify(ModifyKind::Any)
+                );
+                if relevant {
+                    for path in event.paths {
+                        if !is_partial_file(&path) && !reported.contains(&path) {
+                            debouncer.touch(path, Instant::now());
+                        }
+                    }
+                }
+            }
+            Ok(Err(_)) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let settled = debouncer.ready(Instant::now(), |path| {
+            std::fs::metadata(path)
+                .ok()
+                // Empty files are usually mid-creation; their first write touches them again
+                .filter(|m| m.is_file() && m.len() > 0)
+                .map(|m| m.len())
+        });
+        for path in settled {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            reported.insert(path.clone());
+            if let FileScan::Found(result) = crate::scan_file(&path, &config, &state.dictionary) {
+                let _ = app.emit(FILE_ADDED_EVENT, result);
+            }
+        }
+    }
+}